@@ -380,6 +380,14 @@ impl LLMClient for AnthropicClient {
                         "anthropic::cache_hit::{}",
                         message.usage.cache_read_input_tokens
                     );
+                    let _ = sender.send(
+                        LLMClientCompletionResponse::new(
+                            buffered_string.to_owned(),
+                            None,
+                            model_str.to_owned(),
+                        )
+                        .with_cache_hit_tokens(message.usage.cache_read_input_tokens),
+                    );
                 }
                 Err(_e) => {
                     break;
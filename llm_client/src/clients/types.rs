@@ -185,6 +185,107 @@ impl LLMType {
                 | LLMType::DeepSeekCoder33BInstruct
         )
     }
+
+    /// Whether this model can accept images as part of its input, so callers
+    /// know when it is worth attaching image content vs just noting that an
+    /// image was attached but cannot be viewed
+    pub fn supports_vision(&self) -> bool {
+        matches!(
+            self,
+            LLMType::ClaudeOpus
+                | LLMType::ClaudeSonnet
+                | LLMType::ClaudeHaiku
+                | LLMType::Gpt4O
+                | LLMType::Gpt4OMini
+                | LLMType::Gpt4Turbo
+                | LLMType::GeminiPro
+                | LLMType::GeminiProFlash
+        )
+    }
+
+    /// Whether this model supports native structured function calling /
+    /// JSON mode, so callers can skip the free-form XML-ish tool use parser
+    /// and request a structured tool call directly.
+    pub fn supports_native_tool_calling(&self) -> bool {
+        self.is_openai() || self.is_anthropic()
+    }
+
+    /// The documented total context window (input + output) in tokens for
+    /// this model, so context guards and the edit/search prompts have a
+    /// single source of truth instead of hardcoding or guessing per call
+    /// site. Unknown/custom models get a conservative default rather than
+    /// failing outright.
+    pub fn context_window(&self) -> usize {
+        match self {
+            LLMType::Mixtral => 32_000,
+            LLMType::MistralInstruct => 32_000,
+            LLMType::Gpt4 => 8_192,
+            LLMType::GPT3_5_16k => 16_385,
+            LLMType::Gpt4_32k => 32_768,
+            LLMType::Gpt4O | LLMType::Gpt4OMini | LLMType::Gpt4Turbo => 128_000,
+            LLMType::O1Preview | LLMType::O1Mini => 128_000,
+            LLMType::DeepSeekCoder1_3BInstruct
+            | LLMType::DeepSeekCoder6BInstruct
+            | LLMType::DeepSeekCoder33BInstruct => 16_384,
+            LLMType::DeepSeekCoderV2 => 128_000,
+            LLMType::CodeLLama70BInstruct
+            | LLMType::CodeLlama13BInstruct
+            | LLMType::CodeLlama7BInstruct => 16_384,
+            LLMType::Llama3_8bInstruct => 8_192,
+            LLMType::Llama3_1_8bInstruct | LLMType::Llama3_1_70bInstruct => 128_000,
+            LLMType::ClaudeOpus | LLMType::ClaudeSonnet | LLMType::ClaudeHaiku => 200_000,
+            LLMType::PPLXSonnetSmall => 16_384,
+            LLMType::CohereRerankV3 => 4_096,
+            LLMType::GeminiPro | LLMType::GeminiProFlash => 1_000_000,
+            // conservative default for a model we don't have documented
+            // numbers for
+            LLMType::Custom(_) => 8_192,
+        }
+    }
+
+    /// The documented maximum number of tokens this model can produce in a
+    /// single response. Unknown/custom models get a conservative default.
+    pub fn max_output_tokens(&self) -> usize {
+        match self {
+            LLMType::Gpt4O | LLMType::Gpt4OMini => 16_384,
+            LLMType::Gpt4Turbo | LLMType::Gpt4 | LLMType::Gpt4_32k | LLMType::GPT3_5_16k => 4_096,
+            LLMType::O1Preview | LLMType::O1Mini => 32_768,
+            LLMType::ClaudeOpus | LLMType::ClaudeSonnet | LLMType::ClaudeHaiku => 8_192,
+            LLMType::GeminiPro | LLMType::GeminiProFlash => 8_192,
+            LLMType::Llama3_1_8bInstruct | LLMType::Llama3_1_70bInstruct => 4_096,
+            _ => 4_096,
+        }
+    }
+}
+
+/// Rough characters-per-token ratio used for estimating token counts without
+/// a real tokenizer on hand, matching the same heuristic already used for
+/// repomap sizing (https://platform.openai.com/tokenizer suggests ~4 chars
+/// per token for English/code text).
+const ESTIMATED_CHARS_PER_TOKEN: f64 = 4.0;
+
+/// Cheap upper-bound estimate of how many tokens `messages` will cost, used
+/// to check a prompt against a model's context window before sending it
+/// over the wire instead of finding out from an opaque provider error.
+/// Deliberately over-counts a little (padding for message role/formatting
+/// overhead) rather than under-counts, since the whole point is to catch
+/// overflow before the request goes out.
+pub fn estimate_tokens_for_messages(messages: &[LLMClientMessage]) -> usize {
+    messages
+        .iter()
+        .map(|message| {
+            let mut chars = message.content().chars().count();
+            if let Some(function_call) = message.get_function_call() {
+                chars += function_call.name().chars().count();
+                chars += function_call.arguments().chars().count();
+            }
+            if let Some(function_return) = message.get_function_return() {
+                chars += function_return.content().chars().count();
+            }
+            // small fixed overhead per message for role/formatting tokens
+            (chars as f64 / ESTIMATED_CHARS_PER_TOKEN) as usize + 4
+        })
+        .sum()
 }
 
 impl fmt::Display for LLMType {
@@ -294,6 +395,28 @@ impl LLMClientMessageFunctionReturn {
     }
 }
 
+/// An image attached to a message, sent to vision-capable LLMs as inline
+/// base64 data alongside the text content
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct LLMClientMessageImage {
+    media_type: String,
+    data: String,
+}
+
+impl LLMClientMessageImage {
+    pub fn new(media_type: String, data: String) -> Self {
+        Self { media_type, data }
+    }
+
+    pub fn media_type(&self) -> &str {
+        &self.media_type
+    }
+
+    pub fn data(&self) -> &str {
+        &self.data
+    }
+}
+
 #[derive(serde::Serialize, Debug, Clone)]
 pub struct LLMClientMessage {
     role: LLMClientRole,
@@ -302,6 +425,8 @@ pub struct LLMClientMessage {
     function_return: Option<LLMClientMessageFunctionReturn>,
     // if this message marks a caching point in the overall message
     cache_point: bool,
+    // images attached to this message, only meaningful to vision-capable models
+    images: Vec<LLMClientMessageImage>,
 }
 
 impl LLMClientMessage {
@@ -312,6 +437,7 @@ impl LLMClientMessage {
             function_call: None,
             function_return: None,
             cache_point: false,
+            images: vec![],
         }
     }
 
@@ -334,6 +460,7 @@ impl LLMClientMessage {
                 None => self.function_return,
             },
             cache_point: self.cache_point | other.cache_point,
+            images: self.images.into_iter().chain(other.images).collect(),
         }
     }
 
@@ -344,6 +471,7 @@ impl LLMClientMessage {
             function_call: Some(LLMClientMessageFunctionCall { name, arguments }),
             function_return: None,
             cache_point: false,
+            images: vec![],
         }
     }
 
@@ -354,9 +482,19 @@ impl LLMClientMessage {
             function_call: None,
             function_return: Some(LLMClientMessageFunctionReturn { name, content }),
             cache_point: false,
+            images: vec![],
         }
     }
 
+    pub fn attach_images(mut self, images: Vec<LLMClientMessageImage>) -> Self {
+        self.images.extend(images);
+        self
+    }
+
+    pub fn images(&self) -> &[LLMClientMessageImage] {
+        &self.images
+    }
+
     pub fn user(message: String) -> Self {
         Self::new(LLMClientRole::User, message)
     }
@@ -400,6 +538,13 @@ impl LLMClientMessage {
         self
     }
 
+    /// Same as [`Self::cache_point`] but for callers which only have a
+    /// `&mut` reference (e.g. marking an already-built message in place
+    /// inside a `Vec`) instead of owning the message.
+    pub fn set_cache_point(&mut self) {
+        self.cache_point = true;
+    }
+
     pub fn is_cache_point(&self) -> bool {
         self.cache_point
     }
@@ -419,6 +564,70 @@ pub struct LLMClientCompletionRequest {
     max_tokens: Option<usize>,
 }
 
+/// Sampling overrides an individual operation (a code edit, a chat turn, ...)
+/// can ask for instead of the hardcoded defaults most call sites use today -
+/// evals want deterministic `temperature: 0.0`, brainstorm-style chat wants
+/// something higher.
+///
+/// `top_p` is carried here so operations have somewhere to put it, but
+/// `LLMClientCompletionRequest::with_sampling_params` does not copy it onto
+/// the request, so no provider client in this crate sends it; setting it has
+/// no effect on the wire request.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SamplingParams {
+    temperature: f32,
+    top_p: Option<f32>,
+    max_tokens: Option<usize>,
+    stop_sequences: Option<Vec<String>>,
+}
+
+impl SamplingParams {
+    pub fn new(
+        temperature: f32,
+        top_p: Option<f32>,
+        max_tokens: Option<usize>,
+        stop_sequences: Option<Vec<String>>,
+    ) -> Self {
+        Self {
+            temperature,
+            top_p,
+            max_tokens,
+            stop_sequences,
+        }
+    }
+
+    pub fn temperature(&self) -> f32 {
+        self.temperature
+    }
+
+    pub fn top_p(&self) -> Option<f32> {
+        self.top_p
+    }
+
+    pub fn max_tokens(&self) -> Option<usize> {
+        self.max_tokens
+    }
+
+    pub fn stop_sequences(&self) -> Option<&[String]> {
+        self.stop_sequences.as_deref()
+    }
+}
+
+impl Default for SamplingParams {
+    /// Matches the `0.2` temperature and absence of other overrides which
+    /// most call sites in this codebase hardcode today, so plumbing this
+    /// through a call site which doesn't pass an explicit override is a
+    /// no-op.
+    fn default() -> Self {
+        Self {
+            temperature: 0.2,
+            top_p: None,
+            max_tokens: None,
+            stop_sequences: None,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct LLMClientCompletionStringRequest {
     model: LLMType,
@@ -503,6 +712,11 @@ impl LLMClientCompletionRequest {
         self
     }
 
+    pub fn set_messages(mut self, messages: Vec<LLMClientMessage>) -> Self {
+        self.messages = messages;
+        self
+    }
+
     pub fn fix_message_structure(mut self: Self) -> Self {
         // fix here can mean many things, but here we are going to focus on
         // anthropic since there we need alternating human and assistant message
@@ -583,6 +797,27 @@ impl LLMClientCompletionRequest {
     pub fn get_max_tokens(&self) -> Option<usize> {
         self.max_tokens
     }
+
+    pub fn set_stop_words(mut self, stop_words: Vec<String>) -> Self {
+        self.stop_words = Some(stop_words);
+        self
+    }
+
+    /// Applies a caller-provided sampling override on top of this request.
+    /// The temperature is always replaced; max tokens and stop sequences are
+    /// replaced only when the override sets them, and `top_p` is not copied.
+    /// `SamplingParams::default()` carries a temperature of 0.2, so applying
+    /// it leaves a request built at 0.2 unchanged but not one built at 0.0.
+    pub fn with_sampling_params(mut self, sampling_params: &SamplingParams) -> Self {
+        self.temperature = sampling_params.temperature();
+        if let Some(max_tokens) = sampling_params.max_tokens() {
+            self.max_tokens = Some(max_tokens);
+        }
+        if let Some(stop_sequences) = sampling_params.stop_sequences() {
+            self.stop_words = Some(stop_sequences.to_vec());
+        }
+        self
+    }
 }
 
 #[derive(Debug)]
@@ -590,6 +825,10 @@ pub struct LLMClientCompletionResponse {
     answer_up_until_now: String,
     delta: Option<String>,
     model: String,
+    // number of input tokens served from the provider's prompt cache for
+    // this completion, if the provider reports it; `None` for providers
+    // which don't support prompt caching at all
+    cache_hit_tokens: Option<u32>,
 }
 
 impl LLMClientCompletionResponse {
@@ -598,9 +837,15 @@ impl LLMClientCompletionResponse {
             answer_up_until_now,
             delta,
             model,
+            cache_hit_tokens: None,
         }
     }
 
+    pub fn with_cache_hit_tokens(mut self, cache_hit_tokens: u32) -> Self {
+        self.cache_hit_tokens = Some(cache_hit_tokens);
+        self
+    }
+
     pub fn answer_up_until_now(&self) -> &str {
         &self.answer_up_until_now
     }
@@ -612,6 +857,10 @@ impl LLMClientCompletionResponse {
     pub fn model(&self) -> &str {
         &self.model
     }
+
+    pub fn cache_hit_tokens(&self) -> Option<u32> {
+        self.cache_hit_tokens
+    }
 }
 
 #[derive(Error, Debug)]
@@ -694,4 +943,16 @@ mod tests {
         let str_llm_type = serde_json::to_string(&llm_type).expect("to work");
         assert_eq!(str_llm_type, "");
     }
+
+    #[test]
+    fn context_window_returns_documented_limits_for_known_models() {
+        assert_eq!(LLMType::ClaudeSonnet.context_window(), 200_000);
+        assert_eq!(LLMType::Gpt4O.context_window(), 128_000);
+    }
+
+    #[test]
+    fn context_window_falls_back_to_a_conservative_default_for_unknown_models() {
+        let unknown = LLMType::Custom("some-model-nobody-has-heard-of".to_owned());
+        assert_eq!(unknown.context_window(), 8_192);
+    }
 }
@@ -56,6 +56,9 @@ impl PareaLogEvent {
 }
 
 impl PareaLogCompletion {
+    // one field per attribute Parea's trace_log endpoint expects; grouping
+    // these into a builder isn't worth it for a single internal call site
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         messages: Vec<PareaLogMessage>,
         metadata: HashMap<String, String>,
@@ -83,6 +86,12 @@ impl PareaLogCompletion {
     }
 }
 
+impl Default for PareaClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PareaClient {
     pub fn new() -> Self {
         Self {
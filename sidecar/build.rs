@@ -27,15 +27,7 @@ fn main() {
         .iter()
         .flat_map(|dir| read_dir(dir).unwrap())
         .filter_map(Result::ok)
-        .filter_map(|entry| {
-            let path = entry.path();
-            // if Some(OsStr::new("rs")) == path.extension() {
-            //     Some(path)
-            // } else {
-            //     None
-            // }
-            Some(path)
-        })
+        .map(|entry| entry.path())
     {
         hasher.update(read_to_string(&path).unwrap().as_bytes());
         println!("cargo:rerun-if-changed={}", path.to_string_lossy());
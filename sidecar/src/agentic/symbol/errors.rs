@@ -3,7 +3,10 @@ use thiserror::Error;
 use tokio::sync::{mpsc::error::SendError, oneshot::error::RecvError};
 
 use crate::{
-    agentic::tool::{errors::ToolError, lsp::diagnostics::DiagnosticSnippetError},
+    agentic::tool::{
+        errors::ToolError, lsp::diagnostics::DiagnosticSnippetError,
+        session::session::EditConstraint,
+    },
     user_context::types::UserContextError,
 };
 
@@ -44,6 +47,9 @@ pub enum SymbolError {
     #[error("No outline node with name found: {0}")]
     OutlineNodeNotFound(String),
 
+    #[error("No impact summary computed for plan: {0}")]
+    PlanImpactSummaryNotFound(String),
+
     #[error("Snippet not found")]
     SnippetNotFound,
 
@@ -88,4 +94,13 @@ pub enum SymbolError {
 
     #[error("Cancelled Response")]
     CancelledResponseStream,
+
+    #[error("Open exchanges are blocking this request: {0:?}")]
+    OpenExchangesBlockRequest(Vec<String>),
+
+    #[error("{0} changed since the edit was planned, re-read the file and try again")]
+    StaleSymbolContent(String),
+
+    #[error("Edit rejected, it would violate a session constraint: {0}")]
+    ConstraintViolation(EditConstraint),
 }
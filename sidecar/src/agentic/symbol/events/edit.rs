@@ -10,6 +10,15 @@ use crate::{
 
 use super::initial_request::{SymbolEditedItem, SymbolRequestHistoryItem};
 
+/// Hashes file/symbol content so staleness can be detected cheaply without
+/// keeping the whole previous content around for comparison.
+pub(crate) fn content_hash(content: &str) -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct SymbolToEdit {
     outline: bool, // todo(zi): remove this mfer, test case
@@ -37,9 +46,20 @@ pub struct SymbolToEdit {
     previous_user_queries: Vec<String>,
     // the plan-step-id if present for this edit
     plan_step_id: Option<String>,
+    // hash of the symbol's content at the time the edit was planned, so a
+    // caller which read the symbol long before the edit actually executes
+    // can detect the content moved under it in between; `None` means no
+    // expectation was captured and the edit proceeds unconditionally, which
+    // is the behaviour every existing call site relies on
+    expected_content_hash: Option<u64>,
 }
 
 impl SymbolToEdit {
+    /// Kept around so existing call sites don't all need to migrate at once,
+    /// but new call sites should prefer `SymbolToEditBuilder` - fifteen
+    /// positional bool/Option arguments is exactly how `is_new` and
+    /// `is_outline` ended up swapped at a call site in the past.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         symbol_name: String,
         range: Range,
@@ -57,25 +77,18 @@ impl SymbolToEdit {
         previous_user_queries: Vec<String>,
         plan_step_id: Option<String>,
     ) -> Self {
-        Self {
-            symbol_name,
-            range,
-            outline,
-            fs_file_path,
-            instructions,
-            previous_messages: vec![],
-            is_new,
-            is_full_edit,
-            original_user_query,
-            symbol_edited_list,
-            gather_definitions_for_editing,
-            user_provided_context,
-            disable_followups_and_correctness,
-            apply_edits_directly: false,
-            diff_recent_changes,
-            previous_user_queries,
-            plan_step_id,
-        }
+        SymbolToEditBuilder::new(symbol_name, range, fs_file_path, instructions, original_user_query)
+            .outline(outline)
+            .is_new(is_new)
+            .is_full_edit(is_full_edit)
+            .symbol_edited_list(symbol_edited_list)
+            .gather_definitions_for_editing(gather_definitions_for_editing)
+            .user_provided_context(user_provided_context)
+            .disable_followups_and_correctness(disable_followups_and_correctness)
+            .diff_recent_changes(diff_recent_changes)
+            .previous_user_queries(previous_user_queries)
+            .plan_step_id(plan_step_id)
+            .build()
     }
 
     pub fn plan_step_id(&self) -> Option<String> {
@@ -165,6 +178,172 @@ impl SymbolToEdit {
     pub fn previous_message(&self) -> Vec<SessionChatMessage> {
         self.previous_messages.to_vec()
     }
+
+    pub fn expected_content_hash(&self) -> Option<u64> {
+        self.expected_content_hash
+    }
+
+    /// Whether `content` matches the content this edit was planned against.
+    /// Always true when no expectation was captured.
+    pub fn matches_content_hash(&self, content: &str) -> bool {
+        match self.expected_content_hash {
+            Some(expected) => expected == content_hash(content),
+            None => true,
+        }
+    }
+}
+
+/// Builds a `SymbolToEdit` with named setters instead of a long positional
+/// argument list. Only the fields which have no sensible default (the
+/// symbol being edited, where it lives, what to do to it) are required up
+/// front; everything else defaults to its most common value across the
+/// existing call sites and can be overridden with the matching setter.
+pub struct SymbolToEditBuilder {
+    outline: bool,
+    range: Range,
+    fs_file_path: String,
+    symbol_name: String,
+    instructions: Vec<String>,
+    is_new: bool,
+    is_full_edit: bool,
+    original_user_query: String,
+    symbol_edited_list: Option<Vec<SymbolEditedItem>>,
+    gather_definitions_for_editing: bool,
+    user_provided_context: Option<String>,
+    disable_followups_and_correctness: bool,
+    diff_recent_changes: Option<DiffRecentChanges>,
+    previous_user_queries: Vec<String>,
+    plan_step_id: Option<String>,
+    expected_content_hash: Option<u64>,
+}
+
+impl SymbolToEditBuilder {
+    pub fn new(
+        symbol_name: String,
+        range: Range,
+        fs_file_path: String,
+        instructions: Vec<String>,
+        original_user_query: String,
+    ) -> Self {
+        Self {
+            symbol_name,
+            range,
+            fs_file_path,
+            instructions,
+            original_user_query,
+            outline: false,
+            is_new: false,
+            is_full_edit: false,
+            symbol_edited_list: None,
+            gather_definitions_for_editing: false,
+            user_provided_context: None,
+            disable_followups_and_correctness: false,
+            diff_recent_changes: None,
+            previous_user_queries: vec![],
+            plan_step_id: None,
+            expected_content_hash: None,
+        }
+    }
+
+    /// Captures the content the symbol was read at when the edit was
+    /// planned, so `code_editing_with_search_and_replace` can reject the
+    /// edit if the symbol changed underneath it before the edit executes.
+    pub fn expected_content_hash(mut self, expected_content_hash: Option<u64>) -> Self {
+        self.expected_content_hash = expected_content_hash;
+        self
+    }
+
+    pub fn outline(mut self, outline: bool) -> Self {
+        self.outline = outline;
+        self
+    }
+
+    pub fn is_new(mut self, is_new: bool) -> Self {
+        self.is_new = is_new;
+        self
+    }
+
+    pub fn is_full_edit(mut self, is_full_edit: bool) -> Self {
+        self.is_full_edit = is_full_edit;
+        self
+    }
+
+    pub fn symbol_edited_list(mut self, symbol_edited_list: Option<Vec<SymbolEditedItem>>) -> Self {
+        self.symbol_edited_list = symbol_edited_list;
+        self
+    }
+
+    pub fn gather_definitions_for_editing(mut self, gather_definitions_for_editing: bool) -> Self {
+        self.gather_definitions_for_editing = gather_definitions_for_editing;
+        self
+    }
+
+    pub fn user_provided_context(mut self, user_provided_context: Option<String>) -> Self {
+        self.user_provided_context = user_provided_context;
+        self
+    }
+
+    pub fn disable_followups_and_correctness(mut self, disable_followups_and_correctness: bool) -> Self {
+        self.disable_followups_and_correctness = disable_followups_and_correctness;
+        self
+    }
+
+    pub fn diff_recent_changes(mut self, diff_recent_changes: Option<DiffRecentChanges>) -> Self {
+        self.diff_recent_changes = diff_recent_changes;
+        self
+    }
+
+    pub fn previous_user_queries(mut self, previous_user_queries: Vec<String>) -> Self {
+        self.previous_user_queries = previous_user_queries;
+        self
+    }
+
+    pub fn plan_step_id(mut self, plan_step_id: Option<String>) -> Self {
+        self.plan_step_id = plan_step_id;
+        self
+    }
+
+    pub fn build(self) -> SymbolToEdit {
+        // Note: an earlier draft of this builder asserted "is_new implies an
+        // empty range", mirroring the invariant suggested when this builder
+        // was proposed. That does not actually hold in this codebase - the
+        // new-symbol-insertion call site in `types.rs` passes the full
+        // existing file content range together with `is_new: true` so it
+        // knows where to splice the new symbol in. The invariants below
+        // reflect what is actually true across every call site today.
+        debug_assert!(
+            !self.symbol_name.is_empty(),
+            "SymbolToEdit requires a non-empty symbol_name"
+        );
+        debug_assert!(
+            !self.fs_file_path.is_empty(),
+            "SymbolToEdit requires a non-empty fs_file_path"
+        );
+        debug_assert!(
+            !self.instructions.is_empty(),
+            "SymbolToEdit requires at least one instruction"
+        );
+        SymbolToEdit {
+            symbol_name: self.symbol_name,
+            range: self.range,
+            outline: self.outline,
+            fs_file_path: self.fs_file_path,
+            instructions: self.instructions,
+            previous_messages: vec![],
+            is_new: self.is_new,
+            is_full_edit: self.is_full_edit,
+            original_user_query: self.original_user_query,
+            symbol_edited_list: self.symbol_edited_list,
+            gather_definitions_for_editing: self.gather_definitions_for_editing,
+            user_provided_context: self.user_provided_context,
+            disable_followups_and_correctness: self.disable_followups_and_correctness,
+            apply_edits_directly: false,
+            diff_recent_changes: self.diff_recent_changes,
+            previous_user_queries: self.previous_user_queries,
+            plan_step_id: self.plan_step_id,
+            expected_content_hash: self.expected_content_hash,
+        }
+    }
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -199,3 +378,101 @@ impl SymbolToEditRequest {
         self.history.as_slice()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunking::text_document::Position;
+
+    fn builder() -> SymbolToEditBuilder {
+        SymbolToEditBuilder::new(
+            "some_symbol".to_owned(),
+            Range::new(Position::new(0, 0, 0), Position::new(0, 0, 0)),
+            "src/lib.rs".to_owned(),
+            vec!["do the thing".to_owned()],
+            "please do the thing".to_owned(),
+        )
+    }
+
+    #[test]
+    fn builder_defaults_match_the_old_constructors_common_case() {
+        let symbol_to_edit = builder().build();
+        assert!(!symbol_to_edit.is_outline());
+        assert!(!symbol_to_edit.is_new());
+        assert!(!symbol_to_edit.is_full_edit());
+        assert!(!symbol_to_edit.should_apply_edits_directory());
+        assert!(!symbol_to_edit.should_disable_followups_and_correctness());
+        assert!(!symbol_to_edit.should_gather_definitions_for_editing());
+        assert!(symbol_to_edit.symbol_edited_list().is_none());
+        assert_eq!(symbol_to_edit.user_provided_context(), None);
+        assert_eq!(symbol_to_edit.previous_user_queries(), &[] as &[String]);
+        assert_eq!(symbol_to_edit.plan_step_id(), None);
+    }
+
+    #[test]
+    fn builder_setters_override_the_defaults() {
+        let symbol_to_edit = builder()
+            .outline(true)
+            .is_new(true)
+            .is_full_edit(true)
+            .disable_followups_and_correctness(true)
+            .gather_definitions_for_editing(true)
+            .user_provided_context(Some("extra context".to_owned()))
+            .previous_user_queries(vec!["earlier query".to_owned()])
+            .plan_step_id(Some("step-1".to_owned()))
+            .build();
+        assert!(symbol_to_edit.is_outline());
+        assert!(symbol_to_edit.is_new());
+        assert!(symbol_to_edit.is_full_edit());
+        assert!(symbol_to_edit.should_disable_followups_and_correctness());
+        assert!(symbol_to_edit.should_gather_definitions_for_editing());
+        assert_eq!(
+            symbol_to_edit.user_provided_context(),
+            Some("extra context".to_owned())
+        );
+        assert_eq!(
+            symbol_to_edit.previous_user_queries(),
+            &["earlier query".to_owned()]
+        );
+        assert_eq!(symbol_to_edit.plan_step_id(), Some("step-1".to_owned()));
+    }
+
+    #[test]
+    fn old_constructor_still_delegates_to_the_builder() {
+        let symbol_to_edit = SymbolToEdit::new(
+            "some_symbol".to_owned(),
+            Range::new(Position::new(0, 0, 0), Position::new(0, 0, 0)),
+            "src/lib.rs".to_owned(),
+            vec!["do the thing".to_owned()],
+            true,
+            false,
+            false,
+            "please do the thing".to_owned(),
+            None,
+            false,
+            None,
+            false,
+            None,
+            vec![],
+            None,
+        );
+        assert!(symbol_to_edit.is_outline());
+        assert!(!symbol_to_edit.is_new());
+    }
+
+    #[test]
+    fn no_expected_content_hash_matches_anything() {
+        let symbol_to_edit = builder().build();
+        assert!(symbol_to_edit.matches_content_hash("fn foo() {}"));
+        assert!(symbol_to_edit.matches_content_hash("fn bar() {}"));
+    }
+
+    #[test]
+    fn stale_expected_content_hash_does_not_match() {
+        let symbol_to_edit = builder()
+            .expected_content_hash(Some(content_hash("fn foo() {}")))
+            .build();
+        assert!(symbol_to_edit.matches_content_hash("fn foo() {}"));
+        assert!(!symbol_to_edit.matches_content_hash("fn foo() { changed_underneath_us(); }"));
+    }
+}
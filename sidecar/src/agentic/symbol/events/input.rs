@@ -19,7 +19,7 @@ use crate::{
             search::big_search::{BigSearchRequest, SearchType},
         },
     },
-    user_context::types::UserContext,
+    user_context::types::{UserContext, DEFAULT_MAX_CONTEXT_BYTES},
 };
 
 use super::message_event::SymbolEventMessageProperties;
@@ -196,11 +196,16 @@ impl SymbolInputEvent {
     // on some states this might be wrong, I find it a bit easier to reason
     // altho fuck complexity we ball
     pub async fn tool_use_on_initial_invocation(
-        self,
+        mut self,
         recent_edits: String,
         lsp_diagnostics: String,
         message_properties: SymbolEventMessageProperties,
     ) -> Option<ToolInput> {
+        // the editor can hand us an arbitrarily large context (whole files,
+        // many variables); trim it down before it goes anywhere near an LLM
+        // request so an oversized selection fails predictably instead of as
+        // an opaque provider-side error further down the line
+        self.context = self.context.truncate_to(DEFAULT_MAX_CONTEXT_BYTES);
         // if its anthropic we purposefully override the llm here to be a better
         // model (if they are using their own api-keys and even the codestory provider)
         let llm_properties_for_symbol_search =
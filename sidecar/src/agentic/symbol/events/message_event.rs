@@ -9,6 +9,12 @@ use crate::agentic::symbol::{
 
 use super::input::SymbolEventRequestId;
 
+/// Default priority handed to events which do not explicitly opt into a
+/// different one via [`SymbolEventMessage::with_priority`]. Sits in the
+/// middle of the 0 (low) - 255 (high) range so both background analysis and
+/// user-initiated edits have room to preempt it.
+pub const DEFAULT_SYMBOL_EVENT_PRIORITY: u8 = 128;
+
 /// The properties which get sent along with each symbol event
 #[derive(Clone, Debug)]
 pub struct SymbolEventMessageProperties {
@@ -68,6 +74,13 @@ impl SymbolEventMessageProperties {
         self
     }
 
+    /// Retargets this request at a different editor instance mid-session
+    /// (e.g. after the user's VS Code window restarted on a new port).
+    pub fn set_editor_url(mut self, editor_url: String) -> Self {
+        self.editor_url = editor_url;
+        self
+    }
+
     pub fn set_cancellation_token(
         mut self,
         cancellation_token: tokio_util::sync::CancellationToken,
@@ -89,6 +102,10 @@ pub struct SymbolEventMessage {
     symbol_event_request: SymbolEventRequest,
     response_sender: tokio::sync::oneshot::Sender<SymbolEventResponse>,
     properties: SymbolEventMessageProperties,
+    // how urgently the hub should process this event relative to others
+    // sitting in its queue: 0 is lowest, 255 is highest. Defaults to
+    // `DEFAULT_SYMBOL_EVENT_PRIORITY` unless overridden via `with_priority`.
+    priority: u8,
 }
 
 impl SymbolEventMessage {
@@ -111,9 +128,22 @@ impl SymbolEventMessage {
                 llm_properties,
             ),
             response_sender,
+            priority: DEFAULT_SYMBOL_EVENT_PRIORITY,
         }
     }
 
+    /// Overrides the priority the hub processes this event with (0 = low,
+    /// 255 = high). User-initiated edits should use a high priority so they
+    /// preempt queued background analysis.
+    pub fn with_priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn priority(&self) -> u8 {
+        self.priority
+    }
+
     pub fn llm_properties(&self) -> &LLMProperties {
         &self.properties.llm_properties
     }
@@ -131,6 +161,7 @@ impl SymbolEventMessage {
             symbol_event_request,
             properties,
             response_sender,
+            priority: DEFAULT_SYMBOL_EVENT_PRIORITY,
         }
     }
 
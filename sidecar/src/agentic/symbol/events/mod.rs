@@ -7,5 +7,6 @@ pub mod initial_request;
 pub mod input;
 pub mod lsp;
 pub mod message_event;
+pub mod priority_channel;
 pub mod probe;
 pub mod types;
@@ -0,0 +1,156 @@
+//! A priority-aware stand in for the hub's `UnboundedSender`/`UnboundedReceiver`
+//! pair. `SymbolManager` dispatches every `SymbolEventMessage` through a single
+//! channel into the symbol locker; this queue drains the highest-priority
+//! message first (ties broken by arrival order) so a user-initiated edit can
+//! preempt queued background analysis instead of waiting behind it.
+//!
+//! The public surface intentionally mirrors the handful of `mpsc` methods the
+//! rest of the codebase relies on (`send`, `clone`, `recv`) so it can be
+//! swapped in without touching call sites beyond their type annotations.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::mpsc::error::SendError;
+use tokio::sync::Notify;
+
+use super::message_event::SymbolEventMessage;
+
+struct PriorityEnvelope {
+    priority: u8,
+    sequence: u64,
+    message: SymbolEventMessage,
+}
+
+impl PartialEq for PriorityEnvelope {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority && self.sequence == other.sequence
+    }
+}
+
+impl Eq for PriorityEnvelope {}
+
+impl PartialOrd for PriorityEnvelope {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PriorityEnvelope {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // BinaryHeap is a max-heap: the highest priority should pop first,
+        // and among equal priorities the message enqueued earlier (the
+        // smaller sequence number) should pop first.
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+struct QueueState {
+    heap: BinaryHeap<PriorityEnvelope>,
+    next_sequence: u64,
+    sender_count: usize,
+    receiver_dropped: bool,
+}
+
+/// Sending half of the hub's priority queue.
+pub struct SymbolEventPrioritySender {
+    state: Arc<Mutex<QueueState>>,
+    notify: Arc<Notify>,
+}
+
+impl SymbolEventPrioritySender {
+    pub fn send(&self, message: SymbolEventMessage) -> Result<(), SendError<SymbolEventMessage>> {
+        let mut state = self.state.lock().expect("hub priority queue lock poisoned");
+        if state.receiver_dropped {
+            return Err(SendError(message));
+        }
+        let sequence = state.next_sequence;
+        state.next_sequence += 1;
+        state.heap.push(PriorityEnvelope {
+            priority: message.priority(),
+            sequence,
+            message,
+        });
+        drop(state);
+        self.notify.notify_one();
+        Ok(())
+    }
+}
+
+impl Clone for SymbolEventPrioritySender {
+    fn clone(&self) -> Self {
+        self.state
+            .lock()
+            .expect("hub priority queue lock poisoned")
+            .sender_count += 1;
+        Self {
+            state: self.state.clone(),
+            notify: self.notify.clone(),
+        }
+    }
+}
+
+impl Drop for SymbolEventPrioritySender {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().expect("hub priority queue lock poisoned");
+        state.sender_count -= 1;
+        let all_senders_dropped = state.sender_count == 0;
+        drop(state);
+        if all_senders_dropped {
+            self.notify.notify_waiters();
+        }
+    }
+}
+
+/// Receiving half of the hub's priority queue.
+pub struct SymbolEventPriorityReceiver {
+    state: Arc<Mutex<QueueState>>,
+    notify: Arc<Notify>,
+}
+
+impl SymbolEventPriorityReceiver {
+    pub async fn recv(&mut self) -> Option<SymbolEventMessage> {
+        loop {
+            {
+                let mut state = self.state.lock().expect("hub priority queue lock poisoned");
+                if let Some(envelope) = state.heap.pop() {
+                    return Some(envelope.message);
+                }
+                if state.sender_count == 0 {
+                    return None;
+                }
+            }
+            self.notify.notified().await;
+        }
+    }
+}
+
+impl Drop for SymbolEventPriorityReceiver {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().expect("hub priority queue lock poisoned");
+        state.receiver_dropped = true;
+        drop(state);
+        self.notify.notify_waiters();
+    }
+}
+
+/// Creates an unbounded, priority-ordered channel for `SymbolEventMessage`s.
+pub fn unbounded_priority_channel() -> (SymbolEventPrioritySender, SymbolEventPriorityReceiver) {
+    let state = Arc::new(Mutex::new(QueueState {
+        heap: BinaryHeap::new(),
+        next_sequence: 0,
+        sender_count: 1,
+        receiver_dropped: false,
+    }));
+    let notify = Arc::new(Notify::new());
+    (
+        SymbolEventPrioritySender {
+            state: state.clone(),
+            notify: notify.clone(),
+        },
+        SymbolEventPriorityReceiver { state, notify },
+    )
+}
@@ -13,11 +13,10 @@ use llm_client::{
     clients::types::LLMType,
     provider::{LLMProvider, LLMProviderAPIKeys},
 };
-use tokio::sync::mpsc::UnboundedSender;
-
 use crate::{
     agentic::{
         symbol::events::initial_request::SymbolRequestHistoryItem,
+        symbol::events::priority_channel::SymbolEventPrioritySender,
         tool::{
             code_symbol::{new_sub_symbol::NewSymbol, probe::ProbeEnoughOrDeeperResponse},
             lsp::open_file::OpenFileResponse,
@@ -236,11 +235,43 @@ impl SymbolIdentifier {
     pub fn with_file_path(symbol_name: &str, fs_file_path: &str) -> Self {
         Self {
             symbol_name: symbol_name.to_owned(),
-            fs_file_path: Some(fs_file_path.to_owned()),
+            fs_file_path: Some(normalize_fs_file_path(fs_file_path)),
         }
     }
 }
 
+/// Normalizes a file path so that different but equivalent spellings of it
+/// (a leading `./`, `..` segments, or a relative path vs its absolute form)
+/// compare and hash the same on `SymbolIdentifier`. We prefer
+/// `std::fs::canonicalize` since it resolves a relative path against the
+/// repo root the process is running from, but the path may not exist on
+/// disk yet (e.g. a symbol about to be created), so we fall back to a purely
+/// lexical normalization in that case.
+fn normalize_fs_file_path(fs_file_path: &str) -> String {
+    std::fs::canonicalize(fs_file_path)
+        .map(|canonical_path| canonical_path.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| lexically_normalize_fs_file_path(fs_file_path))
+}
+
+fn lexically_normalize_fs_file_path(fs_file_path: &str) -> String {
+    use std::path::Component;
+    let mut normalized_components: Vec<Component> = Vec::new();
+    for component in std::path::Path::new(fs_file_path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if matches!(normalized_components.last(), Some(Component::Normal(_))) => {
+                normalized_components.pop();
+            }
+            other => normalized_components.push(other),
+        }
+    }
+    normalized_components
+        .into_iter()
+        .collect::<std::path::PathBuf>()
+        .to_string_lossy()
+        .into_owned()
+}
+
 #[derive(Debug)]
 pub struct SnippetReRankInformation {
     idx: usize,
@@ -279,6 +310,26 @@ impl SnippetReRankInformation {
         self.is_outline = true;
         self
     }
+
+    /// Wraps already-formatted XML `content` in the `<rerank_entry>` envelope
+    /// using this entry's `idx`, matching the format the reranker LLM expects.
+    pub fn to_xml_prompt(&self, content: &str) -> String {
+        let idx = self.idx;
+        format!(
+            r#"<rerank_entry>
+<id>
+{idx}
+</id>
+{content}
+</rerank_entry>"#
+        )
+    }
+
+    /// Convenience wrapper over [`Self::to_xml_prompt`] for the common case
+    /// where the content is a whole [`Snippet`].
+    pub fn to_full_xml_prompt(&self, snippet: &Snippet) -> String {
+        self.to_xml_prompt(&snippet.to_xml())
+    }
 }
 
 #[derive(Derivative)]
@@ -377,6 +428,43 @@ impl MechaCodeSymbolThinking {
             .map(|snippet| snippet.clone())
     }
 
+    /// Checks if `self` and `other` are editing overlapping regions of code
+    /// by comparing the ranges of their implementations file by file.
+    ///
+    /// Returns 0.0 when none of the implementations overlap and 1.0 when the
+    /// smaller of two overlapping implementations is completely contained in
+    /// the other, so the symbol manager can decide whether concurrent symbol
+    /// agents need to be serialized instead of raced against each other.
+    pub async fn compute_edit_overlap_score(&self, other: &MechaCodeSymbolThinking) -> f32 {
+        let self_implementations = self.implementations.lock().await;
+        let other_implementations = other.implementations.lock().await;
+        let mut max_overlap_score: f32 = 0.0;
+        for self_implementation in self_implementations.iter() {
+            for other_implementation in other_implementations.iter() {
+                if self_implementation.file_path() != other_implementation.file_path() {
+                    continue;
+                }
+                let self_range = self_implementation.range();
+                let other_range = other_implementation.range();
+                if !self_range.intersects_with_another_range(other_range) {
+                    continue;
+                }
+                let overlap_start = self_range.start_line().max(other_range.start_line());
+                let overlap_end = self_range.end_line().min(other_range.end_line());
+                let overlap_lines = (overlap_end.saturating_sub(overlap_start) + 1) as f32;
+                let self_lines = (self_range.end_line() - self_range.start_line() + 1) as f32;
+                let other_lines = (other_range.end_line() - other_range.start_line() + 1) as f32;
+                let smaller_range_lines = self_lines.min(other_lines);
+                if smaller_range_lines <= 0.0 {
+                    continue;
+                }
+                let overlap_score = (overlap_lines / smaller_range_lines).min(1.0);
+                max_overlap_score = max_overlap_score.max(overlap_score);
+            }
+        }
+        max_overlap_score
+    }
+
     /// This finds the sub-symbol which we want to probe
     /// The sub-symbol can be a function inside the class or a identifier in
     /// the class if needs be or just the class/function itself
@@ -1183,7 +1271,7 @@ impl MechaCodeSymbolThinking {
         original_request: &InitialRequestData,
         llm_properties: LLMProperties,
         tool_properties: &ToolProperties,
-        hub_sender: UnboundedSender<SymbolEventMessage>,
+        hub_sender: SymbolEventPrioritySender,
         message_properties: SymbolEventMessageProperties,
     ) -> Result<SymbolEventRequest, SymbolError> {
         println!(
@@ -1372,8 +1460,13 @@ impl MechaCodeSymbolThinking {
                 // be editing and then send those are requests to the hub
                 // which will forward it to the right symbol
                 let original_request_ref = &original_request;
+                // resolved concurrently (bounded by `tool_properties`) since
+                // `find_sub_symbol_in_range` is a network round-trip to the
+                // editor per sub-symbol; `buffered` (rather than
+                // `buffer_unordered`) keeps the results in the same order as
+                // `reverse_lookup` even though they finish out of order
                 let sub_symbols_to_edit = stream::iter(reverse_lookup.into_iter().map(|data| (data, message_properties.clone())))
-                    .filter_map(|(reverse_lookup, message_properties)| async move {
+                    .map(|(reverse_lookup, message_properties)| async move {
                         let idx = reverse_lookup.idx();
                         let range = reverse_lookup.range();
                         let fs_file_path = reverse_lookup.fs_file_path();
@@ -1434,6 +1527,8 @@ Reason to edit:
                             None => None,
                         }
                     })
+                    .buffered(tool_properties.sub_symbol_resolution_concurrency())
+                    .filter_map(|found| async move { found })
                     .collect::<Vec<_>>()
                     .await;
 
@@ -1617,21 +1712,11 @@ Reason to edit:
                 .outline_node_type()
                 .is_definition_assignment();
             if is_function || is_definition_assignment {
-                let function_body = snippet.to_xml();
+                let rerank_information =
+                    SnippetReRankInformation::new(0, snippet.range.clone(), snippet.fs_file_path.to_owned());
                 Some((
-                    format!(
-                        r#"<rerank_entry>
-<id>
-0
-</id>
-{function_body}
-</rerank_entry>"#
-                    ),
-                    vec![SnippetReRankInformation::new(
-                        0,
-                        snippet.range.clone(),
-                        snippet.fs_file_path.to_owned(),
-                    )],
+                    rerank_information.to_full_xml_prompt(&snippet),
+                    vec![rerank_information],
                 ))
             } else {
                 let implementations = self.get_implementations().await;
@@ -1648,21 +1733,22 @@ Reason to edit:
                         let language = snippet.language();
                         let content = snippet.content();
                         // let content = self.tool_box.get_compressed_symbol_view(snippet.content(), snippet.file_path());
-                        format!(
-                            r#"<rerank_entry>
-<id>
-{idx}
-</id>
-<file_path>
+                        let entry_content = format!(
+                            r#"<file_path>
 {location}
 </file_path>
 <content>
 ```{language}
 {content}
 ```
-</content>
-</rerank_entry>"#
+</content>"#
+                        );
+                        SnippetReRankInformation::new(
+                            idx,
+                            snippet.range().clone(),
+                            snippet.file_path().to_owned(),
                         )
+                        .to_xml_prompt(&entry_content)
                     })
                     .collect::<Vec<_>>()
                     .join("\n");
@@ -1727,21 +1813,11 @@ Reason to edit:
                 .outline_node_type()
                 .is_definition_assignment();
             if is_function || is_definition_assignment {
-                let function_body = snippet.to_xml();
+                let rerank_information =
+                    SnippetReRankInformation::new(0, snippet.range.clone(), snippet.fs_file_path.to_owned());
                 Some((
-                    format!(
-                        r#"<rerank_entry>
-<id>
-0
-</id>
-{function_body}
-</rerank_entry>"#
-                    ),
-                    vec![SnippetReRankInformation::new(
-                        0,
-                        snippet.range.clone(),
-                        snippet.fs_file_path.to_owned(),
-                    )],
+                    rerank_information.to_full_xml_prompt(&snippet),
+                    vec![rerank_information],
                 ))
             } else {
                 // and now we have the other symbols which might be a mix of the following
@@ -1843,19 +1919,13 @@ Reason to edit:
                     .map(|(class_snippet, functions, non_overlap_prefix)| {
                         let formatted_snippet = class_snippet.to_xml();
                         if class_snippet.is_class_definition() {
-                            let definition = format!(
-                                r#"<rerank_entry>
-<id>
-{symbol_index}
-</id>
-{formatted_snippet}
-</rerank_entry>"#
-                            );
-                            symbol_rerank_information.push(SnippetReRankInformation::new(
+                            let rerank_information = SnippetReRankInformation::new(
                                 symbol_index,
                                 class_snippet.range().clone(),
                                 class_snippet.fs_file_path().to_owned(),
-                            ));
+                            );
+                            let definition = rerank_information.to_xml_prompt(&formatted_snippet);
+                            symbol_rerank_information.push(rerank_information);
                             symbol_index = symbol_index + 1;
                             definition
                         } else {
@@ -1866,22 +1936,16 @@ Reason to edit:
                                 let start_line = non_overlap_prefix_range.start_line();
                                 let end_line = non_overlap_prefix_range.end_line();
                                 let language = class_snippet.language();
-                                let overlapp_snippet = format!(
-                                    r#"<rerank_entry>
-<id>
-{symbol_index}
-</id>
-<file_path>
+                                let entry_content = format!(
+                                    r#"<file_path>
 {file_path}:{start_line}-{end_line}
 </file_path>
 <content>
 ```{language}
 {non_overlap_prefix_content}
 ```
-</content>
-</rerank_entry>"#
-                                )
-                                .to_owned();
+</content>"#
+                                );
                                 // guard against impl blocks in rust, since including
                                 // just the impl statement can confuse the LLM
                                 if !class_snippet.is_class_declaration()
@@ -1890,14 +1954,15 @@ Reason to edit:
                                 {
                                     None
                                 } else {
-                                    symbol_rerank_information.push(
-                                        SnippetReRankInformation::new(
-                                            symbol_index,
-                                            non_overlap_prefix_range,
-                                            class_snippet.fs_file_path().to_owned(),
-                                        )
-                                        .set_is_outline(),
-                                    );
+                                    let rerank_information = SnippetReRankInformation::new(
+                                        symbol_index,
+                                        non_overlap_prefix_range,
+                                        class_snippet.fs_file_path().to_owned(),
+                                    )
+                                    .set_is_outline();
+                                    let overlapp_snippet =
+                                        rerank_information.to_xml_prompt(&entry_content);
+                                    symbol_rerank_information.push(rerank_information);
                                     symbol_index = symbol_index + 1;
                                     Some(overlapp_snippet)
                                 }
@@ -1908,19 +1973,14 @@ Reason to edit:
                                 .into_iter()
                                 .map(|function| {
                                     let function_body = function.to_xml();
-                                    let function_code_snippet = format!(
-                                        r#"<rerank_entry>
-<id>
-{symbol_index}
-</id>
-{function_body}
-</rerank_entry>"#
-                                    );
-                                    symbol_rerank_information.push(SnippetReRankInformation::new(
+                                    let rerank_information = SnippetReRankInformation::new(
                                         symbol_index,
                                         function.range().clone(),
                                         function.fs_file_path().to_owned(),
-                                    ));
+                                    );
+                                    let function_code_snippet =
+                                        rerank_information.to_xml_prompt(&function_body);
+                                    symbol_rerank_information.push(rerank_information);
                                     symbol_index = symbol_index + 1;
                                     function_code_snippet
                                 })
@@ -1969,19 +2029,13 @@ Reason to edit:
                     .into_iter()
                     .map(|uncovered_function| {
                         let formatted_content = uncovered_function.to_xml();
-                        let llm_snippet = format!(
-                            "<rerank_entry>
-<id>
-{symbol_index}
-</id>
-{formatted_content}
-</rerank_entry>"
-                        );
-                        symbol_rerank_information.push(SnippetReRankInformation::new(
+                        let rerank_information = SnippetReRankInformation::new(
                             symbol_index,
                             uncovered_function.range().clone(),
                             uncovered_function.fs_file_path().to_owned(),
-                        ));
+                        );
+                        let llm_snippet = rerank_information.to_xml_prompt(&formatted_content);
+                        symbol_rerank_information.push(rerank_information);
                         symbol_index = symbol_index + 1;
                         llm_snippet
                     })
@@ -2002,3 +2056,115 @@ Reason to edit:
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::SymbolIdentifier;
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    fn hash_of(identifier: &SymbolIdentifier) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        identifier.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_equivalent_relative_path_spellings_are_equal() {
+        let plain = SymbolIdentifier::with_file_path("foo", "Cargo.toml");
+        let dotted = SymbolIdentifier::with_file_path("foo", "./Cargo.toml");
+        let with_parent_dir = SymbolIdentifier::with_file_path("foo", "src/../Cargo.toml");
+
+        assert_eq!(plain, dotted);
+        assert_eq!(plain, with_parent_dir);
+        assert_eq!(hash_of(&plain), hash_of(&dotted));
+        assert_eq!(hash_of(&plain), hash_of(&with_parent_dir));
+    }
+
+    #[test]
+    fn test_relative_and_absolute_spellings_of_an_existing_path_are_equal() {
+        let absolute = std::fs::canonicalize("Cargo.toml")
+            .expect("Cargo.toml to exist relative to the crate root")
+            .to_string_lossy()
+            .into_owned();
+
+        let relative_identifier = SymbolIdentifier::with_file_path("foo", "Cargo.toml");
+        let absolute_identifier = SymbolIdentifier::with_file_path("foo", &absolute);
+
+        assert_eq!(relative_identifier, absolute_identifier);
+        assert_eq!(hash_of(&relative_identifier), hash_of(&absolute_identifier));
+    }
+
+    #[test]
+    fn test_new_symbol_is_unaffected_by_path_normalization() {
+        let symbol = SymbolIdentifier::new_symbol("foo");
+        assert_eq!(symbol.fs_file_path(), None);
+    }
+
+    /// Exercises the same `stream::iter(..).map(..).buffered(n)` pattern
+    /// `initial_request` uses to resolve `sub_symbols_to_edit` - standing in
+    /// for `find_sub_symbol_in_range` (which needs a real `ToolBox` talking
+    /// to an editor, so it can't be driven directly from a unit test) with a
+    /// fake resolver that occasionally has nothing to report, just like the
+    /// real closure returns `None` when no sub-symbol is found in range.
+    /// Asserts overlap actually happened (a concurrency floor) rather than
+    /// comparing two wall-clock durations against each other, so the
+    /// assertion holds regardless of how loaded the machine running it is.
+    #[tokio::test]
+    async fn buffered_sub_symbol_resolution_preserves_order_and_filters_misses_concurrently() {
+        use futures::{stream, StreamExt};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::time::Duration;
+
+        const NUM_SNIPPETS: usize = 20;
+        const CONCURRENCY: usize = 8;
+        const LOOKUP_LATENCY_MS: u64 = 20;
+
+        let in_flight = std::sync::Arc::new(AtomicUsize::new(0));
+        let max_in_flight = std::sync::Arc::new(AtomicUsize::new(0));
+
+        let fake_find_sub_symbol_in_range = {
+            let in_flight = in_flight.clone();
+            let max_in_flight = max_in_flight.clone();
+            move |idx: usize| {
+                let in_flight = in_flight.clone();
+                let max_in_flight = max_in_flight.clone();
+                async move {
+                    let now_in_flight = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_in_flight.fetch_max(now_in_flight, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(LOOKUP_LATENCY_MS)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    // every third lookup finds no sub-symbol in range, same
+                    // as `find_sub_symbol_in_range` erroring and the real
+                    // closure mapping that to `None`
+                    if idx % 3 == 0 {
+                        None
+                    } else {
+                        Some(idx)
+                    }
+                }
+            }
+        };
+
+        let resolved: Vec<usize> = stream::iter(0..NUM_SNIPPETS)
+            .map(fake_find_sub_symbol_in_range)
+            .buffered(CONCURRENCY)
+            .filter_map(|found| async move { found })
+            .collect()
+            .await;
+
+        let expected: Vec<usize> = (0..NUM_SNIPPETS).filter(|idx| idx % 3 != 0).collect();
+        assert_eq!(
+            resolved, expected,
+            "buffered resolution must preserve input order and drop misses, same as initial_request's chain"
+        );
+        assert!(
+            max_in_flight.load(Ordering::SeqCst) > 1,
+            "lookups should genuinely overlap under buffered(n), not run one at a time"
+        );
+        assert!(
+            max_in_flight.load(Ordering::SeqCst) <= CONCURRENCY,
+            "buffered(n) must never run more than n lookups at once"
+        );
+    }
+}
@@ -15,6 +15,7 @@ use tokio::sync::mpsc::UnboundedSender;
 use super::{
     errors::SymbolError,
     events::message_event::{SymbolEventMessage, SymbolEventMessageProperties},
+    events::priority_channel::SymbolEventPrioritySender,
     identifier::{LLMProperties, MechaCodeSymbolThinking, SymbolIdentifier},
     tool_box::ToolBox,
     tool_properties::ToolProperties,
@@ -41,14 +42,14 @@ pub struct SymbolLocker {
     >,
     // this is the main communication channel which we can use to send requests
     // to the right symbol
-    pub hub_sender: UnboundedSender<SymbolEventMessage>,
+    pub hub_sender: SymbolEventPrioritySender,
     tools: Arc<ToolBox>,
     llm_properties: LLMProperties,
 }
 
 impl SymbolLocker {
     pub fn new(
-        hub_sender: UnboundedSender<SymbolEventMessage>,
+        hub_sender: SymbolEventPrioritySender,
         tools: Arc<ToolBox>,
         llm_properties: LLMProperties,
     ) -> Self {
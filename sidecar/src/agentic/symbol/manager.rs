@@ -7,7 +7,6 @@ use std::sync::Arc;
 use futures::{stream, StreamExt};
 use llm_client::clients::types::LLMType;
 use llm_client::provider::{GoogleAIStudioKey, LLMProvider};
-use tokio::sync::mpsc::UnboundedSender;
 
 use crate::agentic::swe_bench::search_cache::LongContextSearchCache;
 use crate::agentic::symbol::events::input::SymbolEventRequestId;
@@ -26,6 +25,7 @@ use crate::{
 };
 
 use super::events::message_event::{SymbolEventMessage, SymbolEventMessageProperties};
+use super::events::priority_channel::{unbounded_priority_channel, SymbolEventPrioritySender};
 use super::identifier::LLMProperties;
 use super::tool_box::ToolBox;
 use super::ui_event::UIEventWithID;
@@ -42,7 +42,7 @@ use super::{
 pub struct SymbolManager {
     /// Channel sender for communication between symbols and the manager.
     /// This allows for asynchronous message passing within the system.
-    sender: UnboundedSender<SymbolEventMessage>,
+    sender: SymbolEventPrioritySender,
 
     /// Manages locking and unlocking of symbols to prevent concurrent access.
     /// This ensures thread-safety when multiple operations are performed on symbols simultaneously.
@@ -76,7 +76,7 @@ impl SymbolManager {
         editor_parsing: Arc<EditorParsing>,
         llm_properties: LLMProperties,
     ) -> Self {
-        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel::<SymbolEventMessage>();
+        let (sender, mut receiver) = unbounded_priority_channel();
         let tool_box = Arc::new(ToolBox::new(
             tools.clone(),
             symbol_broker.clone(),
@@ -107,7 +107,7 @@ impl SymbolManager {
         }
     }
 
-    pub fn hub_sender(&self) -> UnboundedSender<SymbolEventMessage> {
+    pub fn hub_sender(&self) -> SymbolEventPrioritySender {
         self.sender.clone()
     }
 
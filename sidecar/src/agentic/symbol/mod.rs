@@ -15,3 +15,4 @@ pub mod tool_properties;
 pub mod toolbox;
 pub mod types;
 pub mod ui_event;
+pub mod ui_event_stream;
@@ -25,7 +25,8 @@ use crate::{
     agentic::{
         symbol::{
             events::{
-                edit::SymbolToEditRequest, initial_request::SymbolEditedItem, types::SymbolEvent,
+                edit::SymbolToEditRequest, initial_request::SymbolEditedItem,
+                priority_channel::SymbolEventPrioritySender, types::SymbolEvent,
             },
             identifier::LLMProperties,
             ui_event::{InitialSearchSymbolInformation, UIEventWithID},
@@ -45,7 +46,7 @@ use super::{
     errors::SymbolError,
     events::{
         agent::{AgentIntentMessage, AgentMessage},
-        edit::SymbolToEdit,
+        edit::SymbolToEditBuilder,
         environment_event::{EditorStateChangeRequest, EnvironmentEvent, EnvironmentEventType},
         human::{HumanAgenticRequest, HumanAnchorRequest, HumanMessage},
         input::SymbolInputEvent,
@@ -118,7 +119,7 @@ pub struct ScratchPadAgent {
     // we store the previous user queries as a vec<string> here so we can show that to
     // the llm when its running inference
     previous_user_queries: Arc<Mutex<Vec<String>>>,
-    symbol_event_sender: UnboundedSender<SymbolEventMessage>,
+    symbol_event_sender: SymbolEventPrioritySender,
     // This is the cache which we have to send with every request
     _files_context: Arc<Mutex<Vec<ScratchPadFilesActive>>>,
     // This is the extra context which we send everytime with each request
@@ -131,7 +132,7 @@ impl ScratchPadAgent {
     pub async fn new(
         scratch_pad_path: String,
         tool_box: Arc<ToolBox>,
-        symbol_event_sender: UnboundedSender<SymbolEventMessage>,
+        symbol_event_sender: SymbolEventPrioritySender,
         user_provided_context: Option<String>,
     ) -> Self {
         let (reaction_sender, receiver) = tokio::sync::mpsc::unbounded_channel();
@@ -169,7 +170,7 @@ impl ScratchPadAgent {
     pub async fn start_scratch_pad(
         scratch_pad_file_path: PathBuf,
         tool_box: Arc<ToolBox>,
-        symbol_event_sender: UnboundedSender<SymbolEventMessage>,
+        symbol_event_sender: SymbolEventPrioritySender,
         _message_properties: SymbolEventMessageProperties,
         user_provided_context: Option<String>,
     ) -> (Self, UnboundedSender<EnvironmentEvent>) {
@@ -738,52 +739,72 @@ impl ScratchPadAgent {
 
             println!("symbol_manager::symbols_len::({})", symbols.len());
 
+            let total_symbols = symbols.len();
             // This is where we are creating all the symbols
             let _ = stream::iter(
                 // we are loosing context about the changes which we want to make
                 // to the symbol over here
-                symbols.into_iter().map(|symbol| {
+                symbols.into_iter().enumerate().map(|(index, symbol)| {
                     (
+                        index,
                         symbol,
                         user_query.to_owned(),
                         symbols_edited_list.to_vec(),
                         cache.to_owned(),
                         previous_user_queries.to_vec(),
                         message_properties.clone(),
+                        ui_sender.clone(),
+                        request_id.to_owned(),
                     )
                 }),
             )
             .map(
                 |(
+                    index,
                     (symbol_request, steps),
                     user_query,
                     _symbols_edited_list,
                     cache,
                     previous_user_queries,
                     message_properties,
+                    ui_sender,
+                    request_id,
                 )| async move {
                     let symbol_identifier = symbol_request.to_symbol_identifier_with_file_path();
+                    // honor cancellation between symbols so a large multi-symbol
+                    // edit can be stopped cleanly at the next boundary instead
+                    // of running to completion regardless
+                    if message_properties.cancellation_token().is_cancelled() {
+                        println!(
+                            "symbol_manager::agentic_editing::cancelled_before_symbol({})",
+                            symbol_identifier.symbol_name()
+                        );
+                        return;
+                    }
+                    let _ = ui_sender.send(UIEventWithID::agentic_editing_symbol_progress(
+                        request_id.to_owned(),
+                        request_id.to_owned(),
+                        symbol_identifier.symbol_name().to_owned(),
+                        symbol_identifier.fs_file_path().unwrap_or_default(),
+                        index,
+                        total_symbols,
+                    ));
                     {
                         // TODO(codestory+caching): We should be sending the edit request directly
                         // we are not providing any data over here
                         let symbol_event = SymbolEvent::Edit(SymbolToEditRequest::new(
-                            vec![SymbolToEdit::new(
+                            vec![SymbolToEditBuilder::new(
                                 symbol_identifier.symbol_name().to_owned(),
                                 Range::new(Position::new(0, 0, 0), Position::new(100000, 0, 0)),
                                 symbol_identifier.fs_file_path().unwrap_or_default(),
                                 steps,
-                                false,
-                                false,
-                                true,
                                 user_query.to_owned(),
-                                None,
-                                false,
-                                Some(cache),
-                                true, // we want to have code correctness
-                                None,
-                                previous_user_queries,
-                                None,
-                            )],
+                            )
+                            .is_full_edit(true)
+                            .user_provided_context(Some(cache))
+                            .disable_followups_and_correctness(true) // skip the followups and correctness checks
+                            .previous_user_queries(previous_user_queries)
+                            .build()],
                             symbol_identifier.clone(),
                             vec![],
                         ));
@@ -843,23 +864,18 @@ impl ScratchPadAgent {
             .await?;
         println!("scratch_pad_agent::human_message_anchor::recent_edits::done");
         let symbol_to_edit_request = SymbolToEditRequest::new(
-            vec![SymbolToEdit::new(
+            vec![SymbolToEditBuilder::new(
                 fs_file_path.to_owned(),
                 range.clone(),
                 fs_file_path.to_owned(),
                 vec![query.to_owned()],
-                false,
-                false,
-                true,
                 query.to_owned(),
-                None,
-                false,
-                Some(user_context_str),
-                true,
-                Some(recent_edits.clone()),
-                vec![],
-                None,
             )
+            .is_full_edit(true)
+            .user_provided_context(Some(user_context_str))
+            .disable_followups_and_correctness(true)
+            .diff_recent_changes(Some(recent_edits.clone()))
+            .build()
             .set_previous_messages(converted_messages)],
             SymbolIdentifier::with_file_path(&fs_file_path, &fs_file_path),
             vec![],
@@ -1322,23 +1338,17 @@ Please help me out by making the necessary code edits"#
             );
             let symbol_event_request = SymbolEventRequest::simple_edit_request(
                 symbol_identifier,
-                SymbolToEdit::new(
+                SymbolToEditBuilder::new(
                     active_file.to_owned(),
                     Range::new(Position::new(0, 0, 0), Position::new(10000, 0, 0)),
                     active_file.to_owned(),
                     vec![user_instruction.to_owned()],
-                    false,
-                    false,
-                    true,
                     user_instruction,
-                    None,
-                    false,
-                    Some(files_context.to_vec().join("\n")),
-                    true,
-                    None,
-                    vec![],
-                    None,
-                ),
+                )
+                .is_full_edit(true)
+                .user_provided_context(Some(files_context.to_vec().join("\n")))
+                .disable_followups_and_correctness(true)
+                .build(),
                 ToolProperties::new(),
             );
             let (sender, receiver) = tokio::sync::oneshot::channel();
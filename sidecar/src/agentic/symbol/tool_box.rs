@@ -11,14 +11,20 @@ use llm_client::provider::{
 use tokio::sync::mpsc::UnboundedSender;
 
 use crate::agentic::symbol::events::context_event::SelectionContextEvent;
+use crate::agentic::symbol::events::priority_channel::SymbolEventPrioritySender;
 use crate::agentic::symbol::helpers::{apply_inlay_hints_to_code, split_file_content_into_parts};
 use crate::agentic::symbol::identifier::{Snippet, SymbolIdentifier};
 use crate::agentic::tool::code_edit::filter_edit::{
     FilterEditOperationRequest, FilterEditOperationResponse,
 };
-use crate::agentic::tool::code_edit::search_and_replace::SearchAndReplaceEditingRequest;
+use crate::agentic::tool::code_edit::search_and_replace::{
+    context_window_limits, SearchAndReplaceEditingRequest, DEFAULT_CONTEXT_WINDOW_BUDGET,
+};
 use crate::agentic::tool::code_edit::test_correction::TestOutputCorrectionRequest;
-use crate::agentic::tool::code_edit::types::CodeEdit;
+use crate::agentic::tool::code_edit::types::{
+    CodeEdit, DEFAULT_ABOVE_CONTEXT_LIMIT, DEFAULT_BELOW_CONTEXT_LIMIT, SMALL_CONTEXT_ABOVE_LIMIT,
+    SMALL_CONTEXT_BELOW_LIMIT,
+};
 use crate::agentic::tool::code_symbol::correctness::{
     CodeCorrectnessAction, CodeCorrectnessRequest,
 };
@@ -80,6 +86,7 @@ use crate::agentic::tool::lsp::file_diagnostics::{FileDiagnosticsInput, FileDiag
 use crate::agentic::tool::lsp::get_outline_nodes::{
     OutlineNodesUsingEditorRequest, OutlineNodesUsingEditorResponse,
 };
+use crate::agentic::tool::lsp::get_workspace_symbols::GetWorkspaceSymbolsInput;
 use crate::agentic::tool::lsp::go_to_previous_word::GoToPreviousWordRequest;
 use crate::agentic::tool::lsp::gotodefintion::{
     DefinitionPathAndRange, GoToDefinitionRequest, GoToDefinitionResponse,
@@ -94,6 +101,7 @@ use crate::agentic::tool::lsp::grep_symbol::{
     LSPGrepSymbolInCodebaseRequest, LSPGrepSymbolInCodebaseResponse,
 };
 use crate::agentic::tool::lsp::inlay_hints::InlayHintsRequest;
+use crate::agentic::tool::lsp::list_open_files::{ListOpenFilesInput, OpenFileEntry};
 use crate::agentic::tool::lsp::open_file::OpenFileResponse;
 use crate::agentic::tool::lsp::quick_fix::{
     GetQuickFixRequest, GetQuickFixResponse, LSPQuickFixInvocationRequest,
@@ -2200,7 +2208,7 @@ We also believe this symbol needs to be probed because of:
         outline_node: OutlineNodeContent,
         symbol_edited: &SymbolToEdit,
         symbol_followup_bfs: &SymbolFollowupBFS,
-        hub_sender: UnboundedSender<SymbolEventMessage>,
+        hub_sender: SymbolEventPrioritySender,
         message_properties: SymbolEventMessageProperties,
         tool_properties: ToolProperties,
     ) -> Result<Vec<SymbolFollowupBFS>, SymbolError> {
@@ -2349,7 +2357,7 @@ Please update this code to accommodate these changes. Consider:
         class_outline_node: OutlineNodeContent,
         symbol_edited: &SymbolToEdit,
         class_symbol_followup: &SymbolFollowupBFS,
-        hub_sender: UnboundedSender<SymbolEventMessage>,
+        hub_sender: SymbolEventPrioritySender,
         message_properties: SymbolEventMessageProperties,
         tool_properties: ToolProperties,
     ) -> Result<Vec<SymbolFollowupBFS>, SymbolError> {
@@ -2878,7 +2886,7 @@ Please update this code to accommodate these changes. Consider:
         symbol_followup: &SymbolFollowupBFS,
         original_code: &str,
         edited_code: &str,
-        hub_sender: UnboundedSender<SymbolEventMessage>,
+        hub_sender: SymbolEventPrioritySender,
         message_properties: SymbolEventMessageProperties,
         tool_properties: &ToolProperties,
     ) -> Result<Vec<SymbolFollowupBFS>, SymbolError> {
@@ -3567,7 +3575,7 @@ Please update this code to accommodate these changes. Consider:
     pub async fn check_for_followups_bfs(
         &self,
         mut symbol_followups: Vec<SymbolFollowupBFS>,
-        hub_sender: UnboundedSender<SymbolEventMessage>,
+        hub_sender: SymbolEventPrioritySender,
         message_properties: SymbolEventMessageProperties,
         tool_properties: &ToolProperties,
     ) -> Result<(), SymbolError> {
@@ -3702,7 +3710,7 @@ Please update this code to accommodate these changes. Consider:
         llm: LLMType,
         provider: LLMProvider,
         api_keys: LLMProviderAPIKeys,
-        hub_sender: UnboundedSender<SymbolEventMessage>,
+        hub_sender: SymbolEventPrioritySender,
         message_properties: SymbolEventMessageProperties,
         tool_properties: &ToolProperties,
     ) -> Result<(), SymbolError> {
@@ -3960,7 +3968,7 @@ Please update this code to accommodate these changes. Consider:
         llm: LLMType,
         provider: LLMProvider,
         api_key: LLMProviderAPIKeys,
-        hub_sender: UnboundedSender<SymbolEventMessage>,
+        hub_sender: SymbolEventPrioritySender,
         message_properties: SymbolEventMessageProperties,
         tool_properties: &ToolProperties,
     ) -> Result<(), SymbolError> {
@@ -4060,7 +4068,7 @@ Please update this code to accommodate these changes. Consider:
         original_code: &str,
         symbol_edited: &SymbolToEdit,
         edited_symbol: &OutlineNodeContent,
-        hub_sender: UnboundedSender<SymbolEventMessage>,
+        hub_sender: SymbolEventPrioritySender,
         message_properties: SymbolEventMessageProperties,
         tool_properties: &ToolProperties,
     ) -> Result<(), SymbolError> {
@@ -4188,7 +4196,7 @@ Please update this code to accommodate these changes. Consider:
         member: ClassSymbolMember,
         position_to_search: Position,
         outline_nodes: Vec<OutlineNode>,
-        hub_sender: UnboundedSender<SymbolEventMessage>,
+        hub_sender: SymbolEventPrioritySender,
         message_properties: SymbolEventMessageProperties,
         tool_properties: &ToolProperties,
     ) -> Result<(), SymbolError> {
@@ -4372,7 +4380,7 @@ Please update this code to accommodate these changes. Consider:
         // references here might be from everywhere: functions in the class, implementation block
         // or even the function
         reference_locations: Vec<ReferenceLocation>,
-        hub_sender: UnboundedSender<SymbolEventMessage>,
+        hub_sender: SymbolEventPrioritySender,
         message_properties: SymbolEventMessageProperties,
         tool_properties: &ToolProperties,
     ) -> Result<(), SymbolError> {
@@ -4605,7 +4613,7 @@ Make the necessary changes if required making sure that nothing breaks"#
         &self,
         outline_node: OutlineNode,
         instruction: String,
-        hub_sender: UnboundedSender<SymbolEventMessage>,
+        hub_sender: SymbolEventPrioritySender,
         message_properties: SymbolEventMessageProperties,
         tool_properties: ToolProperties,
     ) -> Result<(), SymbolError> {
@@ -4661,7 +4669,7 @@ Make the necessary changes if required making sure that nothing breaks"#
         outline_node: OutlineNode,
         // this is becoming annoying now cause we will need a drain for this while
         // writing a unit-test for this
-        hub_sender: UnboundedSender<SymbolEventMessage>,
+        hub_sender: SymbolEventPrioritySender,
         message_properties: SymbolEventMessageProperties,
         tool_properties: &ToolProperties,
     ) -> Result<(), SymbolError> {
@@ -4830,7 +4838,7 @@ Make the necessary changes if required making sure that nothing breaks"#
         tool_properties: &ToolProperties,
         llm_properties: LLMProperties,
         history: Vec<SymbolRequestHistoryItem>,
-        hub_sender: UnboundedSender<SymbolEventMessage>,
+        hub_sender: SymbolEventPrioritySender,
         message_properties: SymbolEventMessageProperties,
     ) -> Result<bool, SymbolError> {
         // over here we want to ping the other symbols and send them requests, there is a search
@@ -4982,7 +4990,7 @@ instruction:
         api_keys: LLMProviderAPIKeys,
         tool_properties: &ToolProperties,
         _history: Vec<SymbolRequestHistoryItem>,
-        hub_sender: UnboundedSender<SymbolEventMessage>,
+        hub_sender: SymbolEventPrioritySender,
         message_properties: SymbolEventMessageProperties,
     ) -> Result<(), SymbolError> {
         let instructions = symbol_edited.instructions().join("\n");
@@ -5244,7 +5252,7 @@ instruction:
         symbol_identifier: SymbolIdentifier,
         tool_properties: ToolProperties,
         message_properties: SymbolEventMessageProperties,
-        hub_sender: UnboundedSender<SymbolEventMessage>,
+        hub_sender: SymbolEventPrioritySender,
     ) -> Result<(), SymbolError> {
         println!("tool_box::check_code_correctness::code_correctness_with_edits (edit self)");
         let (sender, receiver) = tokio::sync::oneshot::channel();
@@ -5279,7 +5287,7 @@ instruction:
         message_properties: SymbolEventMessageProperties,
         _tool_properties: ToolProperties,
         _symbol_identifier: SymbolIdentifier,
-        _hub_sender: UnboundedSender<SymbolEventMessage>,
+        _hub_sender: SymbolEventPrioritySender,
         symbol_edited: SymbolToEdit,
     ) -> Result<(), SymbolError> {
         // TODO(skcd): This needs to change because we will now have 3 actions which can
@@ -5446,6 +5454,9 @@ instruction:
         user_provided_context: Option<String>,
         message_properties: SymbolEventMessageProperties,
     ) -> Result<String, SymbolError> {
+        if !sub_symbol.matches_content_hash(file_content) {
+            return Err(SymbolError::StaleSymbolContent(fs_file_path.to_owned()));
+        }
         println!("============tool_box::code_edit_search_and_replace============");
         println!(
             "tool_box::code_edit_search_and_replace::fs_file_path({})::symbol_name({})",
@@ -5457,8 +5468,40 @@ instruction:
             "tool_box::code_edit_search_and_replace::instructions({})",
             &instruction
         );
-        let (_, _, in_range_selection) =
+        let (above, below, in_range_selection) =
             split_file_content_into_parts(file_content, selection_range);
+        // scale the above/below context budget with how much room the model
+        // actually has rather than always handing it the same fixed default,
+        // while still leaving the vast majority of the context window free
+        // for the rest of the prompt (system message, conversation, tool
+        // descriptions)
+        let context_window_budget = (message_properties.llm_properties().llm().context_window()
+            / 20)
+            .max(DEFAULT_CONTEXT_WINDOW_BUDGET);
+        let average_line_chars = {
+            let total_chars = file_content.chars().count();
+            let total_lines = file_content.lines().count().max(1);
+            total_chars / total_lines
+        };
+        let (above_context_limit, below_context_limit) =
+            context_window_limits(context_window_budget, average_line_chars);
+        let trimmed_above = above
+            .unwrap_or_default()
+            .lines()
+            .rev()
+            .take(above_context_limit)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .collect::<Vec<_>>()
+            .join("\n");
+        let trimmed_below = below
+            .unwrap_or_default()
+            .lines()
+            .take(below_context_limit)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let in_range_selection = format!("{trimmed_above}\n{in_range_selection}\n{trimmed_below}");
         // TODO(skcd): This might not be the perfect place to get cache-hits we might
         // want to send over the static list of edits at the start of each iteration?
         let recent_edits = self
@@ -5523,6 +5566,8 @@ FILEPATH: {fs_file_path}
             sub_symbol.plan_step_id(),
             sub_symbol.previous_message(),
             message_properties.cancellation_token(),
+            false,
+            context_window_budget,
         ));
         println!(
             "tool_box::code_edit_outline::start::symbol_name({})",
@@ -5602,6 +5647,14 @@ FILEPATH: {fs_file_path}
         let session_id = message_properties.root_request_id().to_owned();
         let exchange_id = message_properties.request_id_str().to_owned();
         let llm_properties = message_properties.llm_properties().clone();
+        let total_lines_in_file = file_content.lines().count();
+        let (above_context_limit, below_context_limit) = if llm_properties.llm().is_anthropic() {
+            (DEFAULT_ABOVE_CONTEXT_LIMIT, DEFAULT_BELOW_CONTEXT_LIMIT)
+        } else {
+            (SMALL_CONTEXT_ABOVE_LIMIT, SMALL_CONTEXT_BELOW_LIMIT)
+        };
+        let above_context_limit = above_context_limit.min(total_lines_in_file);
+        let below_context_limit = below_context_limit.min(total_lines_in_file);
         let request = ToolInput::CodeEditing(CodeEdit::new(
             above,
             below,
@@ -5627,6 +5680,8 @@ FILEPATH: {fs_file_path}
             user_provided_context,
             session_id,
             exchange_id,
+            above_context_limit,
+            below_context_limit,
         ));
         self.tools
             .invoke(request)
@@ -5740,7 +5795,7 @@ FILEPATH: {fs_file_path}
         provider: LLMProvider,
         api_keys: LLMProviderAPIKeys,
         query: &str,
-        hub_sender: UnboundedSender<SymbolEventMessage>,
+        hub_sender: SymbolEventPrioritySender,
         message_properties: SymbolEventMessageProperties,
         tool_properties: &ToolProperties,
     ) -> Result<Vec<Option<(CodeSymbolWithThinking, String)>>, SymbolError> {
@@ -5750,6 +5805,11 @@ FILEPATH: {fs_file_path}
             .map(|language_config| language_config.get_language())
             .flatten()
             .unwrap_or("".to_owned());
+        // bigger context windows can afford to look at (and later process)
+        // more candidate symbols before the prompt or the editing loop
+        // downstream gets overwhelmed
+        let max_symbols = (llm.context_window() / 10_000)
+            .max(crate::agentic::tool::code_symbol::important::DEFAULT_MAX_IMPORTANT_SYMBOLS);
         let request = ToolInput::RequestImportantSymbols(CodeSymbolImportantRequest::new(
             None,
             vec![],
@@ -5762,6 +5822,7 @@ FILEPATH: {fs_file_path}
             language,
             query.to_owned(),
             message_properties.root_request_id().to_owned(),
+            max_symbols,
         ));
         let response = self
             .tools
@@ -6232,12 +6293,24 @@ FILEPATH: {fs_file_path}
                 api_keys,
                 message_properties.root_request_id().to_owned(),
             ));
-        self.tools
+        let response = self
+            .tools
             .invoke(request)
             .await
             .map_err(|e| SymbolError::ToolError(e))?
             .code_to_edit_in_symbol()
-            .ok_or(SymbolError::WrongToolOutput)
+            .ok_or(SymbolError::WrongToolOutput)?;
+
+        // Surface which snippets got selected as soon as we have the answer,
+        // instead of only the caller finding out once it has finished acting
+        // on `code_to_edit_in_symbol` - useful for large symbols where this
+        // call can take a while.
+        let root_request_id = message_properties.root_request_id().to_owned();
+        for event in response.snippet_selected_events(&root_request_id, &root_request_id) {
+            let _ = message_properties.ui_sender().send(event);
+        }
+
+        Ok(response)
     }
 
     /// Grabs the location where we should be adding the new symbol
@@ -6623,6 +6696,54 @@ FILEPATH: {fs_file_path}
             .ok_or(SymbolError::WrongToolOutput)
     }
 
+    /// Figures out the `use` statement to add when generated code references
+    /// `symbol_name` but it isn't defined in `current_file`. Locates the
+    /// symbol's first textual occurrence in `current_file`, resolves its
+    /// definition with `go_to_definition`, and turns the definition's file
+    /// path into a crate-relative module path. Returns `Ok(None)` (rather
+    /// than an error) when there's nothing useful to import: the symbol
+    /// isn't referenced in `current_file`, has no resolvable definition, or
+    /// is already defined in `current_file` itself.
+    pub async fn resolve_import_path(
+        &self,
+        symbol_name: &str,
+        current_file: &str,
+        message_properties: SymbolEventMessageProperties,
+    ) -> Result<Option<String>, SymbolError> {
+        let file_contents = self
+            .file_open(current_file.to_owned(), message_properties.clone())
+            .await?
+            .contents();
+
+        let symbol_position = match self
+            .find_in_file(file_contents, symbol_name.to_owned())
+            .await?
+            .get_position()
+        {
+            Some(position) => position,
+            None => return Ok(None),
+        };
+
+        let definition_file_path = match self
+            .go_to_definition(current_file, symbol_position, message_properties)
+            .await?
+            .definitions()
+            .into_iter()
+            .next()
+        {
+            Some(definition) => definition.file_path().to_owned(),
+            None => return Ok(None),
+        };
+
+        // already visible in `current_file` without an import
+        if definition_file_path == current_file {
+            return Ok(None);
+        }
+
+        Ok(module_path_from_file_path(&definition_file_path)
+            .map(|module_path| format!("use {module_path}::{symbol_name};")))
+    }
+
     pub async fn edits_required_full_symbol(
         &self,
         symbol_content: &str,
@@ -6684,6 +6805,27 @@ FILEPATH: {fs_file_path}
         Ok(output)
     }
 
+    /// Lightweight existence check for `symbol_name` in `fs_file_path`, meant
+    /// as a guard before acting on a symbol which might have been deleted by
+    /// a previous agent turn. Just asks the editor for the outline nodes and
+    /// reuses the same matching rules as `find_snippet_for_symbol`, but skips
+    /// all of its fallbacks (find-in-file, go-to-definition, content
+    /// extraction) so it stays cheap enough to call as a guard.
+    pub async fn check_symbol_exists(
+        &self,
+        symbol_name: &str,
+        fs_file_path: &str,
+        message_properties: SymbolEventMessageProperties,
+    ) -> Result<bool, SymbolError> {
+        let outline_nodes = self
+            .get_outline_nodes_from_editor(fs_file_path, message_properties)
+            .await
+            .unwrap_or_default();
+        Ok(!self
+            .grab_symbols_from_outline(outline_nodes, symbol_name)
+            .is_empty())
+    }
+
     // This helps us find the snippet for the symbol in the file, this is the
     // best way to do this as this is always exact and we never make mistakes
     // over here since we are using the LSP as well
@@ -6766,7 +6908,41 @@ FILEPATH: {fs_file_path}
                 ))
             }
         } else {
-            Err(SymbolError::OutlineNodeNotFound(symbol_name.to_owned()))
+            // the editor couldn't even give us outline nodes for the file
+            // (e.g. it hasn't indexed it yet); fall back to a workspace-wide
+            // symbol search by name instead of giving up outright
+            let workspace_symbols = self
+                .tools
+                .invoke(ToolInput::GetWorkspaceSymbols(GetWorkspaceSymbolsInput::new(
+                    symbol_name.to_owned(),
+                    10,
+                    message_properties.editor_url(),
+                )))
+                .await
+                .ok()
+                .and_then(|response| response.get_workspace_symbols_response());
+            if let Some(matching_symbol) = workspace_symbols.and_then(|response| {
+                response
+                    .symbols()
+                    .iter()
+                    .find(|symbol| symbol.name() == symbol_name)
+                    .cloned()
+            }) {
+                let synthetic_definition = GoToDefinitionResponse::new(vec![
+                    DefinitionPathAndRange::new(
+                        matching_symbol.fs_file_path().to_owned(),
+                        matching_symbol.range().clone(),
+                    ),
+                ]);
+                self.grab_symbol_content_from_definition(
+                    symbol_name,
+                    synthetic_definition,
+                    message_properties,
+                )
+                .await
+            } else {
+                Err(SymbolError::OutlineNodeNotFound(symbol_name.to_owned()))
+            }
         }
     }
 
@@ -8651,6 +8827,8 @@ FILEPATH: {fs_file_path}
             None,
             vec![],
             message_properties.cancellation_token(),
+            false,
+            DEFAULT_CONTEXT_WINDOW_BUDGET,
         );
         let search_and_replace = ToolInput::SearchAndReplaceEditing(search_and_replace_request);
         let cloned_tools = self.tools.clone();
@@ -10274,4 +10452,68 @@ FILEPATH: {fs_file_path}
         let response = self.tools.invoke(tool_input).await.map_err(|e| SymbolError::ToolError(e))?.get_pending_spawned_process_output().ok_or(SymbolError::WrongToolOutput)?;
         Ok(response.output())
     }
+
+    /// Asks the editor which files it currently has open, so the session's
+    /// view of open files reflects what the user has actually done since the
+    /// session started rather than just the snapshot taken at the first turn.
+    pub async fn grab_open_files_from_editor(&self, message_properties: SymbolEventMessageProperties) -> Result<Vec<OpenFileEntry>, SymbolError> {
+        let tool_input = ToolInput::ListOpenFiles(ListOpenFilesInput::new(message_properties.editor_url()));
+        let response = self.tools.invoke(tool_input).await.map_err(|e| SymbolError::ToolError(e))?.get_list_open_files().ok_or(SymbolError::WrongToolOutput)?;
+        Ok(response.open_files().to_vec())
+    }
+}
+
+/// Turns a Rust source file path into the crate-relative module path used in
+/// a `use` statement, e.g. `/repo/src/agentic/foo/bar.rs` ->
+/// `crate::agentic::foo::bar`. `mod.rs`/`lib.rs`/`main.rs` name the parent
+/// module rather than a module of their own, so they're dropped in favour of
+/// the directory they live in. Returns `None` for paths without a `src`
+/// component, since we have no reliable crate root to anchor the path to.
+fn module_path_from_file_path(fs_file_path: &str) -> Option<String> {
+    let normalized = fs_file_path.replace('\\', "/");
+    let mut segments = normalized.split('/').collect::<Vec<_>>();
+
+    let src_index = segments.iter().position(|segment| *segment == "src")?;
+    segments.drain(..=src_index);
+
+    if let Some(last) = segments.last_mut() {
+        *last = last.trim_end_matches(".rs");
+    }
+    segments.retain(|segment| !matches!(*segment, "lib" | "main" | "mod" | ""));
+
+    if segments.is_empty() {
+        return None;
+    }
+
+    Some(format!("crate::{}", segments.join("::")))
+}
+
+#[cfg(test)]
+mod module_path_tests {
+    use super::module_path_from_file_path;
+
+    #[test]
+    fn converts_nested_file_path_to_module_path() {
+        assert_eq!(
+            module_path_from_file_path("/repo/sidecar/src/agentic/foo/bar.rs"),
+            Some("crate::agentic::foo::bar".to_owned())
+        );
+    }
+
+    #[test]
+    fn drops_mod_lib_and_main_segments() {
+        assert_eq!(
+            module_path_from_file_path("/repo/sidecar/src/agentic/foo/mod.rs"),
+            Some("crate::agentic::foo".to_owned())
+        );
+        assert_eq!(
+            module_path_from_file_path("/repo/sidecar/src/lib.rs"),
+            None
+        );
+    }
+
+    #[test]
+    fn returns_none_without_a_src_component() {
+        assert_eq!(module_path_from_file_path("/repo/README.md"), None);
+    }
 }
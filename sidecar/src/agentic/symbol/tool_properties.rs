@@ -14,8 +14,20 @@ pub struct ToolProperties {
     // be following while making the edits
     plan_for_input: Option<String>,
     apply_edits_directly: bool,
+    // the llm to fall back to for tools which don't have a more specific
+    // override configured above, so callers don't have to thread explicit
+    // `LLMProperties` through every tool invocation
+    default_llm_properties: Option<LLMProperties>,
+    // how many `find_sub_symbol_in_range` lookups `MechaCodeSymbolThinking::initial_request`
+    // (and its probing counterpart) are allowed to have in flight at once
+    // while resolving `sub_symbols_to_edit`/`sub_symbols_to_probe`
+    sub_symbol_resolution_concurrency: usize,
 }
 
+/// [`ToolProperties::sub_symbol_resolution_concurrency`] when nothing else
+/// has been configured.
+pub const DEFAULT_SUB_SYMBOL_RESOLUTION_CONCURRENCY: usize = 8;
+
 impl ToolProperties {
     pub fn new() -> Self {
         Self {
@@ -27,9 +39,29 @@ impl ToolProperties {
             fast_code_symbol_search: None,
             plan_for_input: None,
             apply_edits_directly: false,
+            default_llm_properties: None,
+            sub_symbol_resolution_concurrency: DEFAULT_SUB_SYMBOL_RESOLUTION_CONCURRENCY,
         }
     }
 
+    pub fn set_sub_symbol_resolution_concurrency(mut self, concurrency: usize) -> Self {
+        self.sub_symbol_resolution_concurrency = concurrency;
+        self
+    }
+
+    pub fn sub_symbol_resolution_concurrency(&self) -> usize {
+        self.sub_symbol_resolution_concurrency
+    }
+
+    pub fn set_default_llm_properties(mut self, default_llm_properties: LLMProperties) -> Self {
+        self.default_llm_properties = Some(default_llm_properties);
+        self
+    }
+
+    pub fn get_default_llm_properties(&self) -> Option<LLMProperties> {
+        self.default_llm_properties.clone()
+    }
+
     pub fn should_apply_edits_directly(&self) -> bool {
         self.apply_edits_directly
     }
@@ -15,7 +15,7 @@ use llm_client::{
 };
 use logging::parea::{PareaClient, PareaLogEvent};
 use tokio::sync::{
-    mpsc::{UnboundedReceiver, UnboundedSender},
+    mpsc::UnboundedReceiver,
     Mutex,
 };
 use tokio_stream::wrappers::UnboundedReceiverStream;
@@ -25,6 +25,7 @@ use crate::{
     agentic::{
         symbol::{
             events::edit::SymbolToEditRequest,
+            events::priority_channel::SymbolEventPrioritySender,
             helpers::find_needle_position,
             identifier::Snippet,
             ui_event::{SymbolEventProbeRequest, SymbolEventSubStep, SymbolEventSubStepRequest},
@@ -43,7 +44,7 @@ use crate::{
 use super::{
     errors::SymbolError,
     events::{
-        edit::SymbolToEdit,
+        edit::{SymbolToEdit, SymbolToEditBuilder},
         initial_request::{InitialRequestData, SymbolEditedItem, SymbolRequestHistoryItem},
         message_event::{SymbolEventMessage, SymbolEventMessageProperties},
         probe::{SymbolToProbeHistory, SymbolToProbeRequest},
@@ -255,7 +256,7 @@ pub struct Symbol {
     #[derivative(PartialEq = "ignore")]
     #[derivative(Hash = "ignore")]
     #[derivative(Debug = "ignore")]
-    hub_sender: UnboundedSender<SymbolEventMessage>,
+    hub_sender: SymbolEventPrioritySender,
     #[derivative(PartialEq = "ignore")]
     #[derivative(Hash = "ignore")]
     #[derivative(Debug = "ignore")]
@@ -300,7 +301,7 @@ impl Symbol {
         mecha_code_symbol: MechaCodeSymbolThinking,
         // this can be used to talk to other symbols and get them
         // to act on certain things
-        hub_sender: UnboundedSender<SymbolEventMessage>,
+        hub_sender: SymbolEventPrioritySender,
         tools: Arc<ToolBox>,
         llm_properties: LLMProperties,
         tool_properties: ToolProperties,
@@ -371,7 +372,7 @@ impl Symbol {
     async fn probe_request_handler(
         &self,
         request: SymbolToProbeRequest,
-        hub_sender: UnboundedSender<SymbolEventMessage>,
+        hub_sender: SymbolEventPrioritySender,
         message_properties: SymbolEventMessageProperties,
     ) -> Result<String, SymbolError> {
         let original_request_id = request.original_request_id().to_owned();
@@ -472,7 +473,7 @@ impl Symbol {
     async fn probe_request(
         &self,
         request: SymbolToProbeRequest,
-        hub_sender: UnboundedSender<SymbolEventMessage>,
+        hub_sender: SymbolEventPrioritySender,
         message_properties: SymbolEventMessageProperties,
     ) -> Result<String, SymbolError> {
         let original_request_id = request.original_request_id().to_owned();
@@ -1390,7 +1391,23 @@ Satisfy the requirement either by making edits or gathering the required informa
             "symbol::generate_initial_request::symbol_name({})",
             self.symbol_name()
         );
-        if self.mecha_code_symbol.is_snippet_present().await {
+        // `is_snippet_present` only reflects whatever was cached when this
+        // symbol was constructed, so a previous agent turn could have deleted
+        // it since. Confirm it is still there before trusting the cache,
+        // falling back to "still present" if we can't reach the editor to
+        // check, since that failure mode is unrelated to the symbol's
+        // existence.
+        let snippet_still_present = self.mecha_code_symbol.is_snippet_present().await
+            && self
+                .tools
+                .check_symbol_exists(
+                    self.symbol_name(),
+                    self.fs_file_path(),
+                    message_properties.clone(),
+                )
+                .await
+                .unwrap_or(true);
+        if snippet_still_present {
             let request = if request_data.full_symbol_request() {
                 self.mecha_code_symbol
                     .full_symbol_initial_request(
@@ -1426,25 +1443,21 @@ Satisfy the requirement either by making edits or gathering the required informa
 
             // if the last line is not empty, then we want to create an empty line
             // and then start inserting the code over there
-            let sub_symbol_to_edit = SymbolToEdit::new(
+            let sub_symbol_to_edit = SymbolToEditBuilder::new(
                 self.symbol_name().to_owned(),
                 file_content_range,
                 self.fs_file_path().to_owned(),
                 vec![request_data.get_plan()],
-                false,
-                true,
-                false,
                 request_data.get_original_question().to_owned(),
+            )
+            .is_new(true)
+            .symbol_edited_list(
                 request_data
                     .symbols_edited_list()
                     .map(|symbol_edited_list| symbol_edited_list.to_vec()),
-                false,
-                None,
-                true, // should we disable followups and correctness check
-                None,
-                vec![],
-                None,
-            );
+            )
+            .disable_followups_and_correctness(true)
+            .build();
             let mut history = request_data.history().to_vec();
             history.push(SymbolRequestHistoryItem::new(
                 self.symbol_name().to_owned(),
@@ -1711,6 +1724,10 @@ Satisfy the requirement either by making edits or gathering the required informa
                 } else {
                     (llm_properties, true)
                 }
+            } else if let Some(default_llm_properties) =
+                self.tool_properties.get_default_llm_properties()
+            {
+                (default_llm_properties, false)
             } else {
                 (self.llm_properties.clone(), false)
             };
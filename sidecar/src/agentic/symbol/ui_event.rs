@@ -6,7 +6,8 @@ use std::collections::HashMap;
 
 use crate::{
     agentic::tool::{
-        code_symbol::models::anthropic::StepListItem, input::ToolInputPartial, r#type::ToolType,
+        code_symbol::models::anthropic::StepListItem, input::ToolInputPartial,
+        plan::plan_impact::PlanImpactSummary, r#type::ToolType,
         ref_filter::ref_filter::Location, search::iterative::IterativeSearchEvent,
         session::tool_use_agent::ToolParameters,
     },
@@ -499,6 +500,86 @@ impl UIEventWithID {
         }
     }
 
+    /// Sent once, right before plan generation starts streaming steps -
+    /// distinct from [`Self::start_plan_generation`], which flips the
+    /// exchange's loading state rather than telling the editor a plan is
+    /// being generated at all.
+    pub fn plan_generation_started(session_id: String, exchange_id: String) -> Self {
+        Self {
+            request_id: session_id.to_owned(),
+            exchange_id: exchange_id.to_owned(),
+            event: UIEvent::PlanEvent(PlanMessageEvent::PlanGenerationStarted(
+                PlanGenerationStartedEvent {
+                    session_id,
+                    exchange_id,
+                },
+            )),
+        }
+    }
+
+    /// Sent for each step as soon as it is fully parsed out of the streaming
+    /// plan-generation response, so the editor can show steps appearing one
+    /// at a time instead of waiting for the whole plan.
+    pub fn plan_step_generated(
+        session_id: String,
+        exchange_id: String,
+        step_index: usize,
+        step_description: String,
+        tool_type: ToolType,
+    ) -> Self {
+        Self {
+            request_id: session_id.to_owned(),
+            exchange_id: exchange_id.to_owned(),
+            event: UIEvent::PlanEvent(PlanMessageEvent::PlanStepGenerated(
+                PlanStepGeneratedEvent {
+                    session_id,
+                    exchange_id,
+                    step_index,
+                    step_description,
+                    tool_type,
+                },
+            )),
+        }
+    }
+
+    /// Sent once the plan-generation stream ends, so the editor knows no
+    /// more `plan_step_generated` events are coming.
+    pub fn plan_generation_completed(
+        session_id: String,
+        exchange_id: String,
+        total_steps: usize,
+    ) -> Self {
+        Self {
+            request_id: session_id.to_owned(),
+            exchange_id: exchange_id.to_owned(),
+            event: UIEvent::PlanEvent(PlanMessageEvent::PlanGenerationCompleted(
+                PlanGenerationCompletedEvent {
+                    session_id,
+                    exchange_id,
+                    total_steps,
+                },
+            )),
+        }
+    }
+
+    pub fn plan_impact_summary(
+        session_id: String,
+        exchange_id: String,
+        impact_summary: PlanImpactSummary,
+    ) -> Self {
+        Self {
+            request_id: session_id.to_owned(),
+            exchange_id: exchange_id.to_owned(),
+            event: UIEvent::PlanEvent(PlanMessageEvent::PlanImpactSummaryComputed(
+                PlanImpactSummaryEvent {
+                    session_id,
+                    exchange_id,
+                    impact_summary,
+                },
+            )),
+        }
+    }
+
     pub fn inference_started(session_id: String, exchange_id: String) -> Self {
         Self {
             request_id: session_id,
@@ -529,6 +610,26 @@ impl UIEventWithID {
         }
     }
 
+    pub fn request_soft_stopped(session_id: String, exchange_id: String) -> Self {
+        Self {
+            request_id: session_id,
+            exchange_id,
+            event: UIEvent::ExchangeEvent(ExchangeMessageEvent::ExecutionState(
+                ExecutionExchangeStateEvent::SoftStopped,
+            )),
+        }
+    }
+
+    pub fn tool_error(session_id: String, exchange_id: String, message: String) -> Self {
+        Self {
+            request_id: session_id,
+            exchange_id,
+            event: UIEvent::ExchangeEvent(ExchangeMessageEvent::ExecutionState(
+                ExecutionExchangeStateEvent::ToolError(message),
+            )),
+        }
+    }
+
     pub fn edits_started_in_exchange(
         session_id: String,
         exchange_id: String,
@@ -585,6 +686,19 @@ impl UIEventWithID {
         }
     }
 
+    pub fn edits_rejected(session_id: String, exchange_id: String) -> Self {
+        Self {
+            request_id: session_id,
+            exchange_id,
+            event: UIEvent::ExchangeEvent(ExchangeMessageEvent::EditsExchangeState(
+                EditsExchangeStateEvent {
+                    edits_state: EditsStateEvent::Rejected,
+                    files: vec![],
+                },
+            )),
+        }
+    }
+
     pub fn start_plan_generation(session_id: String, exchange_id: String) -> Self {
         Self {
             request_id: session_id,
@@ -657,6 +771,113 @@ impl UIEventWithID {
         }
     }
 
+    pub fn context_compacted(
+        session_id: String,
+        exchange_id: String,
+        summarized_exchanges: usize,
+        retained_exchanges: usize,
+    ) -> Self {
+        Self {
+            request_id: session_id,
+            exchange_id,
+            event: UIEvent::FrameworkEvent(FrameworkEvent::ContextCompacted(
+                ContextCompactedEvent::new(summarized_exchanges, retained_exchanges),
+            )),
+        }
+    }
+
+    /// A prompt was about to overflow `llm`'s context window and had to be
+    /// trimmed before it could be sent - lets the front-end explain a
+    /// smaller-than-expected context instead of the user just noticing the
+    /// agent "forgot" something.
+    pub fn context_window_remediated(
+        session_id: String,
+        exchange_id: String,
+        llm: String,
+        estimated_tokens_before: usize,
+        context_window: usize,
+        actions_taken: Vec<String>,
+    ) -> Self {
+        Self {
+            request_id: session_id,
+            exchange_id,
+            event: UIEvent::FrameworkEvent(FrameworkEvent::ContextWindowRemediated(
+                ContextWindowRemediatedEvent {
+                    llm,
+                    estimated_tokens_before,
+                    context_window,
+                    actions_taken,
+                },
+            )),
+        }
+    }
+
+    /// Acknowledges that a session's editor connection has moved to a new
+    /// URL (e.g. the user restarted or switched VS Code windows), so the
+    /// front-end can show it stayed in sync rather than silently retargeting.
+    pub fn editor_url_switched(
+        session_id: String,
+        exchange_id: String,
+        previous_editor_url: String,
+        new_editor_url: String,
+    ) -> Self {
+        Self {
+            request_id: session_id,
+            exchange_id,
+            event: UIEvent::FrameworkEvent(FrameworkEvent::EditorUrlSwitched(
+                EditorUrlSwitchedEvent::new(previous_editor_url, new_editor_url),
+            )),
+        }
+    }
+
+    /// A step-by-step review loop just finished a tool call and is blocked
+    /// waiting for the user to continue.
+    pub fn awaiting_user_continue(session_id: String, exchange_id: String, timeout_ms: u64) -> Self {
+        Self {
+            request_id: session_id,
+            exchange_id,
+            event: UIEvent::FrameworkEvent(FrameworkEvent::AwaitingUserContinue(
+                AwaitingUserContinueEvent::new(timeout_ms),
+            )),
+        }
+    }
+
+    /// A tool result we're about to hand the model looked like it was trying
+    /// to smuggle in instructions (e.g. "ignore previous instructions"), so
+    /// warn the front-end even though we still show the model the content,
+    /// wrapped and captioned as untrusted.
+    pub fn possible_prompt_injection_detected(
+        session_id: String,
+        exchange_id: String,
+        tool_type: ToolType,
+        matched_pattern: String,
+    ) -> Self {
+        Self {
+            request_id: session_id,
+            exchange_id,
+            event: UIEvent::FrameworkEvent(FrameworkEvent::PossiblePromptInjection(
+                PossiblePromptInjectionEvent::new(tool_type, matched_pattern),
+            )),
+        }
+    }
+
+    /// One or more exchanges the user never explicitly reviewed were
+    /// resolved automatically (accepted or rejected) because a new request
+    /// arrived while they were still open.
+    pub fn exchanges_auto_resolved(
+        session_id: String,
+        exchange_id: String,
+        exchanges: Vec<AutoResolvedExchange>,
+    ) -> Self {
+        Self {
+            request_id: session_id,
+            exchange_id,
+            event: UIEvent::FrameworkEvent(FrameworkEvent::ExchangesAutoResolved(
+                ExchangesAutoResolvedEvent::new(exchanges),
+            )),
+        }
+    }
+
     pub fn tool_use_detected(
         session_id: String,
         exchange_id: String,
@@ -673,6 +894,64 @@ impl UIEventWithID {
         }
     }
 
+    /// Relays the `<thinking>` block an editing model produced before its
+    /// SEARCH/REPLACE blocks, so the editor can show the reasoning behind an
+    /// edit as it happens instead of discarding it.
+    pub fn agent_thinking(session_id: String, exchange_id: String, thinking: String) -> Self {
+        Self {
+            request_id: session_id.to_owned(),
+            exchange_id,
+            event: UIEvent::FrameworkEvent(FrameworkEvent::AgentThinking(AgentThinkingEvent {
+                thinking,
+            })),
+        }
+    }
+
+    /// One of these is sent right before we start dispatching the edit for
+    /// each symbol in `perform_agentic_editing`.
+    pub fn agentic_editing_symbol_progress(
+        session_id: String,
+        exchange_id: String,
+        symbol_name: String,
+        fs_file_path: String,
+        index: usize,
+        total_symbols: usize,
+    ) -> Self {
+        Self {
+            request_id: session_id,
+            exchange_id,
+            event: UIEvent::FrameworkEvent(FrameworkEvent::AgenticEditingSymbolProgress(
+                AgenticEditingSymbolProgressEvent {
+                    symbol_name,
+                    fs_file_path,
+                    index,
+                    total_symbols,
+                },
+            )),
+        }
+    }
+
+    /// One decision out of `filter_code_snippets_in_symbol_for_editing`'s
+    /// filtered list, streamed as soon as the filtering call finishes rather
+    /// than only surfacing once the whole `filtered_list` is available.
+    pub fn code_to_edit_snippet_selected(
+        session_id: String,
+        exchange_id: String,
+        snippet_id: usize,
+        reason_to_edit: String,
+    ) -> Self {
+        Self {
+            request_id: session_id,
+            exchange_id,
+            event: UIEvent::FrameworkEvent(FrameworkEvent::CodeToEditSnippetSelected(
+                CodeToEditSnippetSelectedEvent {
+                    snippet_id,
+                    reason_to_edit,
+                },
+            )),
+        }
+    }
+
     /// Sends over the tool thinking to the external world
     pub fn tool_thinking(session_id: String, exchange_id: String, tool_thinking: String) -> Self {
         Self {
@@ -841,6 +1120,12 @@ pub struct EditedCodeStreamingRequest {
     // The exchange id this edit is part of
     exchange_id: String,
     plan_step_id: Option<String>,
+    // The text `range` matched before the edit started, so the client can
+    // show a strikethrough of what's being removed while the replacement
+    // streams in. Only known for edits which resolved a range by matching
+    // against existing content (eg a SEARCH/REPLACE block); other edit flows
+    // leave this `None`.
+    matched_original_text: Option<String>,
 }
 
 impl EditedCodeStreamingRequest {
@@ -851,6 +1136,7 @@ impl EditedCodeStreamingRequest {
         fs_file_path: String,
         exchange_id: String,
         plan_step_id: Option<String>,
+        matched_original_text: Option<String>,
     ) -> Self {
         Self {
             edit_request_id,
@@ -862,6 +1148,7 @@ impl EditedCodeStreamingRequest {
             apply_directly: false,
             exchange_id,
             plan_step_id,
+            matched_original_text,
         }
     }
 
@@ -884,6 +1171,7 @@ impl EditedCodeStreamingRequest {
             apply_directly: false,
             exchange_id,
             plan_step_id,
+            matched_original_text: None,
         }
     }
 
@@ -905,6 +1193,7 @@ impl EditedCodeStreamingRequest {
             apply_directly: false,
             exchange_id,
             plan_step_id,
+            matched_original_text: None,
         }
     }
 
@@ -1028,6 +1317,7 @@ impl SymbolEventSubStepRequest {
                     apply_directly: false,
                     exchange_id,
                     plan_step_id,
+                    matched_original_text: None,
                 },
             )),
         }
@@ -1055,6 +1345,7 @@ impl SymbolEventSubStepRequest {
                     apply_directly: false,
                     exchange_id,
                     plan_step_id,
+                    matched_original_text: None,
                 },
             )),
         }
@@ -1101,6 +1392,7 @@ impl SymbolEventSubStepRequest {
                     apply_directly: false,
                     exchange_id,
                     plan_step_id,
+                    matched_original_text: None,
                 },
             )),
         }
@@ -1260,10 +1552,114 @@ pub enum FrameworkEvent {
     ReferencesUsed(FrameworkReferencesUsed),
     TerminalCommand(TerminalCommandEvent),
     ToolUseDetected(ToolUseDetectedEvent),
+    AgentThinking(AgentThinkingEvent),
+    AgenticEditingSymbolProgress(AgenticEditingSymbolProgressEvent),
     ToolThinking(ToolThinkingEvent),
     ToolNotFound(ToolNotFoundEvent),
     ToolTypeFound(ToolTypeFoundEvent),
     ToolParameterFound(ToolParameterFoundEvent),
+    ContextCompacted(ContextCompactedEvent),
+    EditorUrlSwitched(EditorUrlSwitchedEvent),
+    PossiblePromptInjection(PossiblePromptInjectionEvent),
+    AwaitingUserContinue(AwaitingUserContinueEvent),
+    ExchangesAutoResolved(ExchangesAutoResolvedEvent),
+    CodeToEditSnippetSelected(CodeToEditSnippetSelectedEvent),
+    ContextWindowRemediated(ContextWindowRemediatedEvent),
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct EditorUrlSwitchedEvent {
+    previous_editor_url: String,
+    new_editor_url: String,
+}
+
+impl EditorUrlSwitchedEvent {
+    pub fn new(previous_editor_url: String, new_editor_url: String) -> Self {
+        Self {
+            previous_editor_url,
+            new_editor_url,
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct ContextCompactedEvent {
+    summarized_exchanges: usize,
+    retained_exchanges: usize,
+}
+
+impl ContextCompactedEvent {
+    pub fn new(summarized_exchanges: usize, retained_exchanges: usize) -> Self {
+        Self {
+            summarized_exchanges,
+            retained_exchanges,
+        }
+    }
+}
+
+/// A tool's output (file content, terminal output, ...) contained text which
+/// reads like an instruction aimed at the agent rather than data, so the
+/// front-end can flag it to the user instead of silently trusting it.
+#[derive(Debug, serde::Serialize)]
+pub struct PossiblePromptInjectionEvent {
+    tool_type: ToolType,
+    matched_pattern: String,
+}
+
+impl PossiblePromptInjectionEvent {
+    pub fn new(tool_type: ToolType, matched_pattern: String) -> Self {
+        Self {
+            tool_type,
+            matched_pattern,
+        }
+    }
+}
+
+/// Sent when a step-by-step review session has just finished a tool call and
+/// is now blocked waiting for the user to call `continue_agentic` (or for
+/// `timeout_ms` to elapse), so the editor can show a "waiting for you"
+/// affordance instead of a spinner.
+#[derive(Debug, serde::Serialize)]
+pub struct AwaitingUserContinueEvent {
+    timeout_ms: u64,
+}
+
+impl AwaitingUserContinueEvent {
+    pub fn new(timeout_ms: u64) -> Self {
+        Self { timeout_ms }
+    }
+}
+
+/// One exchange which was still open (never explicitly accepted or rejected
+/// by the user) when a new request arrived and forced a resolution.
+#[derive(Debug, serde::Serialize)]
+pub struct AutoResolvedExchange {
+    exchange_id: String,
+    reason: String,
+}
+
+impl AutoResolvedExchange {
+    pub fn new(exchange_id: String, reason: String) -> Self {
+        Self {
+            exchange_id,
+            reason,
+        }
+    }
+}
+
+/// Sent whenever `accept_open_exchanges_if_any` had to resolve one or more
+/// exchanges the user never reviewed, so the front-end can surface exactly
+/// which ones were auto-accepted or auto-rejected instead of the review pane
+/// silently emptying out from under the user.
+#[derive(Debug, serde::Serialize)]
+pub struct ExchangesAutoResolvedEvent {
+    exchanges: Vec<AutoResolvedExchange>,
+}
+
+impl ExchangesAutoResolvedEvent {
+    pub fn new(exchanges: Vec<AutoResolvedExchange>) -> Self {
+        Self { exchanges }
+    }
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -1286,6 +1682,44 @@ pub struct ToolThinkingEvent {
     thinking: String,
 }
 
+#[derive(Debug, serde::Serialize)]
+pub struct AgentThinkingEvent {
+    thinking: String,
+}
+
+/// Progress marker for `perform_agentic_editing`, one per symbol as we get to
+/// it, so the editor can show something more useful than a spinner for
+/// edits which touch many symbols.
+#[derive(Debug, serde::Serialize)]
+pub struct AgenticEditingSymbolProgressEvent {
+    symbol_name: String,
+    fs_file_path: String,
+    index: usize,
+    total_symbols: usize,
+}
+
+/// One snippet's inclusion decision from `filter_code_snippets_in_symbol_for_editing`,
+/// sent as soon as the filtering LLM call finishes so the editor can show
+/// progress on large symbols instead of a long silent pause followed by the
+/// whole filtered list at once.
+#[derive(Debug, serde::Serialize)]
+pub struct CodeToEditSnippetSelectedEvent {
+    snippet_id: usize,
+    reason_to_edit: String,
+}
+
+/// A prompt was predicted to overflow its model's context window before it
+/// was sent, and one or more remediations (dropping the oldest compactable
+/// messages, switching to a larger-context model from the failover chain,
+/// ...) were applied to bring it back under the limit.
+#[derive(Debug, serde::Serialize)]
+pub struct ContextWindowRemediatedEvent {
+    llm: String,
+    estimated_tokens_before: usize,
+    context_window: usize,
+    actions_taken: Vec<String>,
+}
+
 #[derive(Debug, serde::Serialize)]
 pub struct ToolUseDetectedEvent {
     tool_use_partial_input: ToolInputPartial,
@@ -1341,6 +1775,14 @@ pub enum ExecutionExchangeStateEvent {
     Inference,
     InReview,
     Cancelled,
+    // the agent wrapped up after finishing its current tool call in response
+    // to a soft-stop request, rather than being aborted mid-tool like
+    // `Cancelled` or reaching `attempt_completion` on its own
+    SoftStopped,
+    // a tool call failed with an error the user can act on (e.g. the editor
+    // extension is unreachable), rather than the loop silently retrying or
+    // panicking
+    ToolError(String),
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -1349,6 +1791,7 @@ pub enum EditsStateEvent {
     MarkedComplete,
     Cancelled,
     Accepted,
+    Rejected,
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -1392,6 +1835,32 @@ pub enum PlanMessageEvent {
     PlanStepCompleteAdded(PlanStepAddEvent),
     PlanStepTitleAdded(PlanStepTitleEvent),
     PlanStepDescriptionUpdate(PlanStepDescriptionUpdateEvent),
+    PlanImpactSummaryComputed(PlanImpactSummaryEvent),
+    PlanGenerationStarted(PlanGenerationStartedEvent),
+    PlanStepGenerated(PlanStepGeneratedEvent),
+    PlanGenerationCompleted(PlanGenerationCompletedEvent),
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct PlanGenerationStartedEvent {
+    session_id: String,
+    exchange_id: String,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct PlanStepGeneratedEvent {
+    session_id: String,
+    exchange_id: String,
+    step_index: usize,
+    step_description: String,
+    tool_type: ToolType,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct PlanGenerationCompletedEvent {
+    session_id: String,
+    exchange_id: String,
+    total_steps: usize,
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -1422,3 +1891,10 @@ pub struct PlanStepTitleEvent {
     title: String,
     index: usize,
 }
+
+#[derive(Debug, serde::Serialize)]
+pub struct PlanImpactSummaryEvent {
+    session_id: String,
+    exchange_id: String,
+    impact_summary: PlanImpactSummary,
+}
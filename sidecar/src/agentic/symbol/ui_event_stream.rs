@@ -0,0 +1,230 @@
+//! `UIEventWithID`s normally flow over an in-process `UnboundedSender`,
+//! which the bundled editor consumes directly. An external process (a
+//! standalone monitoring UI, say) can't reach into that channel, so this
+//! adapter lets events cross a process boundary instead: each event is
+//! serialized as one line of JSON and written to any byte stream (a file, a
+//! TCP socket, ...), and a consumer on the other end reads it back line by
+//! line.
+//!
+//! Most of the types reachable from `UIEvent` (tool input partials, symbol
+//! identifiers, ...) only ever need to be produced in-process and don't
+//! implement `Deserialize` - adding it across all of them would be a large,
+//! unrelated change. Consumers of this stream get the envelope
+//! (`request_id`, `exchange_id`) fully typed and the event body as a raw
+//! `serde_json::Value`, which is enough to route events and pull out
+//! whatever fields the consumer actually cares about.
+
+use serde::Deserialize;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+
+use super::ui_event::UIEventWithID;
+
+/// Governs what happens when the writer can't keep up with the rate events
+/// are produced at.
+#[derive(Debug, Clone, Copy)]
+pub enum BackpressurePolicy {
+    /// Queue up to `capacity` events, blocking the producer once full so no
+    /// event is ever lost.
+    Buffer { capacity: usize },
+    /// Queue up to `capacity` events, silently dropping the newest event
+    /// once full so the producer is never blocked.
+    Drop { capacity: usize },
+}
+
+impl BackpressurePolicy {
+    fn capacity(&self) -> usize {
+        match self {
+            BackpressurePolicy::Buffer { capacity } => *capacity,
+            BackpressurePolicy::Drop { capacity } => *capacity,
+        }
+    }
+}
+
+/// A handle producers use to push events into the sink; cheap to clone so
+/// every place which currently holds a `UnboundedSender<UIEventWithID>` can
+/// hold one of these instead.
+#[derive(Clone)]
+pub struct JsonLinesEventSink {
+    sender: mpsc::Sender<UIEventWithID>,
+    policy: BackpressurePolicy,
+}
+
+impl JsonLinesEventSink {
+    /// Spawns a background task which serializes every event received on
+    /// the returned sink as a line of JSON and writes it to `writer`,
+    /// applying `policy` when the writer can't keep up. The task finishes
+    /// once every clone of the returned sink has been dropped.
+    pub fn spawn<W>(writer: W, policy: BackpressurePolicy) -> (Self, tokio::task::JoinHandle<()>)
+    where
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
+        let (sender, mut receiver) = mpsc::channel(policy.capacity());
+        let handle = tokio::spawn(async move {
+            let mut writer = writer;
+            while let Some(event) = receiver.recv().await {
+                let line = match serde_json::to_string(&event) {
+                    Ok(line) => line,
+                    // an event which can't be serialized isn't actionable by
+                    // the consumer either way, so drop it and keep the sink
+                    // alive for the rest of the stream
+                    Err(_) => continue,
+                };
+                if writer.write_all(line.as_bytes()).await.is_err() {
+                    break;
+                }
+                if writer.write_all(b"\n").await.is_err() {
+                    break;
+                }
+            }
+            let _ = writer.flush().await;
+        });
+        (Self { sender, policy }, handle)
+    }
+
+    /// Pushes an event into the sink, honouring the configured backpressure
+    /// policy. Returns whether the event was actually queued: for `Buffer` it
+    /// awaits until there's room and is `true` unless the writer task has
+    /// stopped; for `Drop` it is `false` when the queue was full.
+    pub async fn send(&self, event: UIEventWithID) -> bool {
+        match self.policy {
+            BackpressurePolicy::Buffer { .. } => self.sender.send(event).await.is_ok(),
+            BackpressurePolicy::Drop { .. } => self.sender.try_send(event).is_ok(),
+        }
+    }
+}
+
+/// The wire shape a consumer needs to route events, without depending on
+/// every type transitively reachable from `UIEvent` implementing
+/// `Deserialize`. `event` can be parsed further into whatever shape the
+/// consumer expects for the event kind it cares about.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ParsedUIEvent {
+    request_id: String,
+    exchange_id: String,
+    event: serde_json::Value,
+}
+
+impl ParsedUIEvent {
+    pub fn request_id(&self) -> &str {
+        &self.request_id
+    }
+
+    pub fn exchange_id(&self) -> &str {
+        &self.exchange_id
+    }
+
+    pub fn event(&self) -> &serde_json::Value {
+        &self.event
+    }
+}
+
+/// Parses newline-delimited JSON `UIEventWithID`s, as produced by
+/// [`JsonLinesEventSink`], skipping blank lines.
+pub fn parse_json_lines(content: &str) -> Result<Vec<ParsedUIEvent>, serde_json::Error> {
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(serde_json::from_str)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agentic::symbol::ui_event::UIEventWithID;
+
+    #[tokio::test]
+    async fn round_trips_a_sequence_of_event_variants_through_json_lines() {
+        let mut buffer = Vec::new();
+        let events = vec![
+            UIEventWithID::code_iteration_finished("request-1".to_owned()),
+            UIEventWithID::start_long_context_search("request-1".to_owned()),
+            UIEventWithID::finish_long_context_search("request-1".to_owned()),
+            UIEventWithID::finish_edit_request("request-1".to_owned()),
+        ];
+
+        // write each event's line directly, the same shape `JsonLinesEventSink`
+        // produces, without needing a real socket/file for the test
+        for event in &events {
+            let line = serde_json::to_string(event).expect("event to serialize");
+            buffer.extend_from_slice(line.as_bytes());
+            buffer.push(b'\n');
+        }
+
+        let content = String::from_utf8(buffer).expect("valid utf8");
+        let parsed = parse_json_lines(&content).expect("lines to parse");
+
+        assert_eq!(parsed.len(), events.len());
+        assert!(parsed.iter().all(|event| event.request_id() == "request-1"));
+        assert!(parsed[0].event().get("FrameworkEvent").is_some());
+    }
+
+    #[tokio::test]
+    async fn drop_policy_never_blocks_and_reports_when_full() {
+        // give the background task a writer whose buffer is far smaller than
+        // one serialized event and never drain the other end, so its first
+        // `write_all` stalls forever and the queue actually fills up instead
+        // of draining as fast as events arrive
+        let (write_half, _read_half) = tokio::io::duplex(1);
+        let (sink, handle) =
+            JsonLinesEventSink::spawn(write_half, BackpressurePolicy::Drop { capacity: 1 });
+
+        // dequeued by the background task, which then blocks on its first
+        // write, so this always has room
+        assert!(
+            sink.send(UIEventWithID::code_iteration_finished(
+                "request-2".to_owned(),
+            ))
+            .await
+        );
+        // give the background task a chance to actually pull the event off
+        // the queue and start (and stall on) its write
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        // the queue's single slot is now empty again, so this fills it
+        assert!(
+            sink.send(UIEventWithID::code_iteration_finished(
+                "request-2".to_owned(),
+            ))
+            .await
+        );
+        // the queue is now full and the writer is permanently stalled, so
+        // this one is deterministically dropped rather than queued
+        let dropped = sink
+            .send(UIEventWithID::code_iteration_finished(
+                "request-2".to_owned(),
+            ))
+            .await;
+        assert!(!dropped, "send should report the event as dropped once the queue is full");
+
+        drop(sink);
+        drop(handle);
+    }
+
+    #[tokio::test]
+    async fn buffer_policy_delivers_every_event_to_the_writer() {
+        let (mut read_half, write_half) = tokio::io::duplex(4096);
+        let (sink, handle) =
+            JsonLinesEventSink::spawn(write_half, BackpressurePolicy::Buffer { capacity: 8 });
+
+        for _ in 0..5 {
+            assert!(
+                sink.send(UIEventWithID::code_iteration_finished(
+                    "request-3".to_owned()
+                ))
+                .await
+            );
+        }
+        drop(sink);
+        let _ = handle.await;
+
+        use tokio::io::AsyncReadExt;
+        let mut output = String::new();
+        read_half
+            .read_to_string(&mut output)
+            .await
+            .expect("read all buffered events");
+        let parsed = parse_json_lines(&output).expect("lines to parse");
+        assert_eq!(parsed.len(), 5);
+    }
+}
@@ -1,5 +1,11 @@
 use async_trait::async_trait;
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::HashMap,
+    hash::{Hash, Hasher},
+    sync::Arc,
+    time::Instant,
+};
+use tokio::sync::broadcast;
 
 use llm_client::broker::LLMBroker;
 
@@ -36,9 +42,13 @@ use super::{
     input::ToolInput,
     lsp::{
         create_file::LSPCreateFile,
+        delete_file::LSPDeleteFile,
         diagnostics::LSPDiagnostics,
+        duplicate_symbol::LSPDuplicateSymbol,
+        extract_function::LSPExtractFunction,
         file_diagnostics::FileDiagnostics,
-        get_outline_nodes::OutlineNodesUsingEditorClient,
+        get_outline_nodes::{LSPGetOutlineNodes, OutlineNodesUsingEditorClient},
+        get_workspace_symbols::LSPGetWorkspaceSymbols,
         go_to_previous_word::GoToPreviousWordClient,
         gotodefintion::LSPGoToDefinition,
         gotoimplementations::LSPGoToImplementation,
@@ -47,26 +57,33 @@ use super::{
         grep_symbol::GrepSymbolInCodebase,
         inlay_hints::InlayHints,
         list_files::ListFilesClient,
-        open_file::LSPOpenFile,
+        list_open_files::LSPListOpenFiles,
+        move_file::LSPMoveFile,
+        open_file::{LSPOpenFile, OpenFileRequest, OpenFileResponse},
         quick_fix::{LSPQuickFixClient, LSPQuickFixInvocationClient},
         search_file::SearchFileContentClient,
         subprocess_spawned_output::SubProcessSpawnedPendingOutputClient,
         undo_changes::UndoChangesMadeDuringExchange,
     },
+    metrics::{NoOpToolMetrics, ToolMetrics},
     output::ToolOutput,
     plan::{
         add_steps::PlanAddStepClient, generator::StepGeneratorClient, reasoning::ReasoningClient,
         updater::PlanUpdaterClient,
     },
     r#type::{Tool, ToolType},
+    rate_limiter::ToolRateLimiter,
     ref_filter::ref_filter::ReferenceFilterBroker,
     repo_map::generator::RepoMapGeneratorClient,
     rerank::base::ReRankBroker,
+    rust::inline_value::InlineValueClient,
     search::big_search::BigSearchBroker,
     session::{
         ask_followup_question::AskFollowupQuestions, attempt_completion::AttemptCompletionClient,
-        chat::SessionChatClient, exchange::SessionExchangeClient,
-        hot_streak::SessionHotStreakClient,
+        chat::SessionChatClient, code_review::CodeReviewTool, exchange::SessionExchangeClient,
+        find_symbol_definition::FindSymbolDefinitionTool, hot_streak::SessionHotStreakClient,
+        run_tests::RunTestsTool,
+        show_diff::ShowDiffTool, summarize_context::SummarizeContextClient,
     },
     swe_bench::test_tool::SWEBenchTestTool,
     terminal::terminal::TerminalTool,
@@ -75,6 +92,14 @@ use super::{
 pub struct ToolBrokerConfiguration {
     editor_agent: Option<LLMProperties>,
     apply_edits_directly: bool,
+    // how long a completed response stays shareable with callers who ask for
+    // the exact same tool input right after it finished, on top of coalescing
+    // callers who ask for it while it's still in-flight
+    deduplication_window_ms: u64,
+    // requests-per-second cap per LLM provider name (see `LLMProvider`'s
+    // `Display` impl), enforced across every tool invocation regardless of
+    // which specific tool is making the call
+    llm_rate_limits: HashMap<String, f64>,
 }
 
 impl ToolBrokerConfiguration {
@@ -82,8 +107,116 @@ impl ToolBrokerConfiguration {
         Self {
             editor_agent,
             apply_edits_directly,
+            deduplication_window_ms: 0,
+            llm_rate_limits: HashMap::new(),
         }
     }
+
+    pub fn with_deduplication_window_ms(mut self, deduplication_window_ms: u64) -> Self {
+        self.deduplication_window_ms = deduplication_window_ms;
+        self
+    }
+
+    /// Caps requests to `provider` (e.g. `LLMProvider::OpenAI.to_string()`)
+    /// at `requests_per_second`, shared across every tool invocation the
+    /// broker makes on that provider's behalf.
+    pub fn with_llm_rate_limit(mut self, provider: String, requests_per_second: f64) -> Self {
+        self.llm_rate_limits.insert(provider, requests_per_second);
+        self
+    }
+}
+
+/// A conservative default cap for the `default_llm_provider` bucket, applied
+/// at every real `ToolBroker` construction site so a fan-out of LLM-issuing
+/// tool calls can't collectively blow through the provider's own
+/// requests-per-minute limit. Individual deployments can override it by
+/// calling `with_llm_rate_limit` again with a different value.
+pub const DEFAULT_LLM_REQUESTS_PER_SECOND: f64 = 5.0;
+
+/// Whether `tool_type` itself makes an LLM call (directly, or by delegating
+/// to a broker/tool that does) as opposed to doing local/editor work (file
+/// I/O, LSP queries, running a terminal command, ...). Only these need to go
+/// through `ToolRateLimiter::acquire` - throttling the rest against the same
+/// bucket would slow down unrelated tool calls for no reason.
+fn is_llm_issuing_tool(tool_type: &ToolType) -> bool {
+    matches!(
+        tool_type,
+        ToolType::CodeEditing
+            | ToolType::FindCodeSnippets
+            | ToolType::ReRank
+            | ToolType::RequestImportantSymbols
+            | ToolType::FindCodeSymbolsCodeBaseWide
+            | ToolType::UtilityCodeSymbolSearch
+            | ToolType::FilterCodeSnippetsForEditing
+            | ToolType::CodeCorrectnessActionSelection
+            | ToolType::CodeEditingForError
+            | ToolType::FilterCodeSnippetsSingleSymbolForEditing
+            | ToolType::ClassSymbolFollowup
+            | ToolType::ProbePossible
+            | ToolType::ProbeQuestion
+            | ToolType::ProbeSubSymbol
+            | ToolType::ProbeFollowAlongSymbol
+            | ToolType::ProbeSummarizeAnswer
+            | ToolType::RepoMapSearch
+            | ToolType::ImportantFilesFinder
+            | ToolType::BigSearch
+            | ToolType::TestCorrection
+            | ToolType::CodeSymbolsToFollowInitialRequest
+            | ToolType::ProbeSubSymbolFiltering
+            | ToolType::ProbeEnoughOrDeeper
+            | ToolType::ProbeCreateQuestionForSymbol
+            | ToolType::PlanningBeforeCodeEdit
+            | ToolType::NewSubSymbolRequired
+            | ToolType::ProbeTryHardAnswer
+            | ToolType::FindFileForNewSymbol
+            | ToolType::FindSymbolsToEditInContext
+            | ToolType::ReRankingCodeSnippetsForCodeEditingContext
+            | ToolType::ApplyOutlineEditToRange
+            | ToolType::FilterEditOperation
+            | ToolType::CodeSymbolNewLocation
+            | ToolType::ShouldEditCode
+            | ToolType::SearchAndReplaceEditing
+            | ToolType::ReferencesFilter
+            | ToolType::ScratchPadAgent
+            | ToolType::Reasoning
+            | ToolType::PlanUpdater
+            | ToolType::StepGenerator
+            | ToolType::PlanStepAdd
+            | ToolType::ContextDrivenChatReply
+            | ToolType::ContextDriveHotStreakReply
+            | ToolType::SummarizeContext
+    )
+}
+
+/// Hashes the `Debug` representation of a `ToolInput`, since most of the
+/// individual request types across the tool broker don't implement
+/// `serde::Serialize`/`Hash` and adding that everywhere just to key a dedup
+/// cache would be a huge, unrelated change. `Debug` output is stable enough
+/// for two structurally identical requests to hash the same.
+fn hash_tool_input(input: &ToolInput) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    format!("{:?}", input).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A response to an in-flight or recently-completed `OpenFile` request,
+/// shared between whoever is waiting on it.
+#[derive(Clone)]
+enum OpenFileDedupEntry {
+    InFlight(broadcast::Sender<Arc<Result<OpenFileResponse, String>>>),
+    Completed(Arc<Result<OpenFileResponse, String>>, Instant),
+}
+
+/// Turns a shared dedup result back into what a normal (non-deduplicated)
+/// `OpenFile` invocation would have returned, since the errors that flow
+/// through the dedup cache are stringified (`ToolError` isn't `Clone`).
+fn convert_open_file_dedup_result(
+    response: Arc<Result<OpenFileResponse, String>>,
+) -> Result<ToolOutput, ToolError> {
+    match &*response {
+        Ok(response) => Ok(ToolOutput::FileOpen(response.clone())),
+        Err(message) => Err(ToolError::DeduplicatedRequestFailed(message.to_owned())),
+    }
 }
 
 // TODO(skcd): We want to use a different serializer and deserializer for this
@@ -91,6 +224,24 @@ impl ToolBrokerConfiguration {
 // sure that we do not store everything about the tool but a representation of it
 pub struct ToolBroker {
     tools: HashMap<ToolType, Box<dyn Tool + Send + Sync>>,
+    metrics: Arc<dyn ToolMetrics + Send + Sync>,
+    // Coalesces identical concurrent (and recently-completed) `OpenFile`
+    // calls so that multiple symbol agents opening the same file at once
+    // only hit the editor once. Scoped to `OpenFile` for now since it's the
+    // hot path multiple agents actually collide on; other tool inputs would
+    // need their response types to be `Clone` before they could share a
+    // cached response the same way.
+    open_file_dedup: tokio::sync::Mutex<HashMap<u64, OpenFileDedupEntry>>,
+    deduplication_window: std::time::Duration,
+    // shared across every invocation so a fan-out of tool calls can't
+    // collectively exceed the provider's requests-per-minute limit even
+    // though each tool individually respects it
+    rate_limiter: Arc<ToolRateLimiter>,
+    // the provider most of this broker's LLM-backed tools were constructed
+    // with (see `fail_over_llm` in `ToolBroker::new`); `ToolInput` doesn't
+    // tag which provider a given call is bound for, so we rate limit every
+    // invocation against this one shared provider rather than per-tool
+    default_llm_provider: String,
 }
 
 impl ToolBroker {
@@ -391,6 +542,10 @@ impl ToolBroker {
             ToolType::OutlineNodesUsingEditor,
             Box::new(OutlineNodesUsingEditorClient::new()),
         );
+        tools.insert(
+            ToolType::GetOutlineNodes,
+            Box::new(LSPGetOutlineNodes::new()),
+        );
         tools.insert(
             ToolType::ReferencesFilter,
             Box::new(ReferenceFilterBroker::new(
@@ -416,6 +571,17 @@ impl ToolBroker {
             Box::new(StepGeneratorClient::new(llm_client.clone())),
         );
         tools.insert(ToolType::CreateFile, Box::new(LSPCreateFile::new()));
+        tools.insert(ToolType::DeleteFile, Box::new(LSPDeleteFile::new()));
+        tools.insert(ToolType::MoveFile, Box::new(LSPMoveFile::new()));
+        tools.insert(
+            ToolType::ExtractFunction,
+            Box::new(LSPExtractFunction::new()),
+        );
+        tools.insert(
+            ToolType::DuplicateSymbol,
+            Box::new(LSPDuplicateSymbol::new()),
+        );
+        tools.insert(ToolType::InlineValue, Box::new(InlineValueClient::new()));
         tools.insert(
             ToolType::PlanStepAdd,
             Box::new(PlanAddStepClient::new(llm_client.clone())),
@@ -443,9 +609,24 @@ impl ToolBroker {
         );
         tools.insert(
             ToolType::ContextDriveHotStreakReply,
-            Box::new(SessionHotStreakClient::new(llm_client)),
+            Box::new(SessionHotStreakClient::new(llm_client.clone())),
+        );
+        tools.insert(
+            ToolType::SummarizeContext,
+            Box::new(SummarizeContextClient::new(llm_client)),
+        );
+        tools.insert(ToolType::ShowDiff, Box::new(ShowDiffTool::new()));
+        tools.insert(ToolType::CodeReview, Box::new(CodeReviewTool::new()));
+        tools.insert(
+            ToolType::FindSymbolDefinition,
+            Box::new(FindSymbolDefinitionTool::new()),
+        );
+        tools.insert(
+            ToolType::GetWorkspaceSymbols,
+            Box::new(LSPGetWorkspaceSymbols::new()),
         );
         tools.insert(ToolType::TerminalCommand, Box::new(TerminalTool::new()));
+        tools.insert(ToolType::RunTests, Box::new(RunTestsTool::new()));
         tools.insert(
             ToolType::SearchFileContentWithRegex,
             Box::new(SearchFileContentClient::new()),
@@ -467,8 +648,99 @@ impl ToolBroker {
             ToolType::SubProcessSpawnedPendingOutput,
             Box::new(SubProcessSpawnedPendingOutputClient::new()),
         );
+        tools.insert(ToolType::ListOpenFiles, Box::new(LSPListOpenFiles::new()));
         // we also want to add the re-ranking tool here, so we invoke it freely
-        Self { tools }
+        Self {
+            tools,
+            metrics: Arc::new(NoOpToolMetrics),
+            open_file_dedup: tokio::sync::Mutex::new(HashMap::new()),
+            deduplication_window: std::time::Duration::from_millis(
+                tool_broker_config.deduplication_window_ms,
+            ),
+            rate_limiter: Arc::new(ToolRateLimiter::new(tool_broker_config.llm_rate_limits)),
+            default_llm_provider: fail_over_llm.provider().to_string(),
+        }
+    }
+
+    /// Swaps in a different metrics implementation, e.g. `LoggingToolMetrics`
+    /// or a backend-specific one, in place of the `NoOpToolMetrics` default.
+    pub fn with_metrics(mut self, metrics: Arc<dyn ToolMetrics + Send + Sync>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Coalesces identical concurrent `OpenFile` calls (and, within
+    /// `deduplication_window`, calls for the same file made right after one
+    /// just finished) into a single request to the editor.
+    async fn invoke_open_file_deduplicated(
+        &self,
+        request: OpenFileRequest,
+    ) -> Result<ToolOutput, ToolError> {
+        let key = hash_tool_input(&ToolInput::OpenFile(request.clone()));
+        let mut receiver = {
+            let mut in_flight = self.open_file_dedup.lock().await;
+            match in_flight.get(&key) {
+                Some(OpenFileDedupEntry::InFlight(sender)) => Some(sender.subscribe()),
+                Some(OpenFileDedupEntry::Completed(response, completed_at))
+                    if completed_at.elapsed() < self.deduplication_window =>
+                {
+                    return convert_open_file_dedup_result(response.clone());
+                }
+                _ => {
+                    let (sender, _) = broadcast::channel(1);
+                    in_flight.insert(key, OpenFileDedupEntry::InFlight(sender));
+                    None
+                }
+            }
+        };
+
+        if let Some(receiver) = receiver.as_mut() {
+            return match receiver.recv().await {
+                Ok(response) => convert_open_file_dedup_result(response),
+                // the in-flight request's sender was dropped without sending
+                // (e.g. it panicked), fall through and issue our own request
+                Err(_) => self.invoke_open_file_deduplicated_uncached(request, key).await,
+            };
+        }
+
+        self.invoke_open_file_deduplicated_uncached(request, key)
+            .await
+    }
+
+    async fn invoke_open_file_deduplicated_uncached(
+        &self,
+        request: OpenFileRequest,
+        key: u64,
+    ) -> Result<ToolOutput, ToolError> {
+        let tool_type = ToolType::OpenFile;
+        let start = Instant::now();
+        let result = match self.tools.get(&tool_type) {
+            Some(tool) => tool.invoke(ToolInput::OpenFile(request)).await,
+            None => Err(ToolError::MissingTool),
+        };
+        self.metrics
+            .record(tool_type, start.elapsed(), result.is_ok());
+
+        let shareable_result = Arc::new(match &result {
+            Ok(ToolOutput::FileOpen(response)) => Ok(response.clone()),
+            Ok(_other) => Err("open file tool returned an unexpected output type".to_owned()),
+            Err(err) => Err(err.to_string()),
+        });
+
+        let mut in_flight = self.open_file_dedup.lock().await;
+        if let Some(OpenFileDedupEntry::InFlight(sender)) = in_flight.get(&key) {
+            let _ = sender.send(shareable_result.clone());
+        }
+        if self.deduplication_window.is_zero() {
+            in_flight.remove(&key);
+        } else {
+            in_flight.insert(
+                key,
+                OpenFileDedupEntry::Completed(shareable_result, Instant::now()),
+            );
+        }
+
+        result
     }
 
     pub fn get_tool_description(&self, tool_type: &ToolType) -> Option<String> {
@@ -488,12 +760,22 @@ impl ToolBroker {
 #[async_trait]
 impl Tool for ToolBroker {
     async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
+        if let ToolInput::OpenFile(request) = &input {
+            return self.invoke_open_file_deduplicated(request.clone()).await;
+        }
         let tool_type = input.tool_type();
+        if is_llm_issuing_tool(&tool_type) {
+            self.rate_limiter.acquire(&self.default_llm_provider).await;
+        }
+        let start = Instant::now();
         if let Some(tool) = self.tools.get(&tool_type) {
             let result = tool.invoke(input).await;
+            self.metrics
+                .record(tool_type.clone(), start.elapsed(), result.is_ok());
             result
         } else {
             let result = Err(ToolError::MissingTool);
+            self.metrics.record(tool_type, start.elapsed(), false);
             result
         }
     }
@@ -506,3 +788,36 @@ impl Tool for ToolBroker {
         r#"Notice that you could technically give a tool input over here, but we recommend NOT to do that and instead use individual tools if you are working with that"#.to_owned()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::hash_tool_input;
+    use crate::agentic::tool::input::ToolInput;
+    use crate::agentic::tool::lsp::open_file::OpenFileRequest;
+
+    #[test]
+    fn identical_open_file_requests_hash_the_same() {
+        let first = ToolInput::OpenFile(OpenFileRequest::new(
+            "/tmp/foo.rs".to_owned(),
+            "http://localhost:42424".to_owned(),
+        ));
+        let second = ToolInput::OpenFile(OpenFileRequest::new(
+            "/tmp/foo.rs".to_owned(),
+            "http://localhost:42424".to_owned(),
+        ));
+        assert_eq!(hash_tool_input(&first), hash_tool_input(&second));
+    }
+
+    #[test]
+    fn different_open_file_requests_hash_differently() {
+        let first = ToolInput::OpenFile(OpenFileRequest::new(
+            "/tmp/foo.rs".to_owned(),
+            "http://localhost:42424".to_owned(),
+        ));
+        let second = ToolInput::OpenFile(OpenFileRequest::new(
+            "/tmp/bar.rs".to_owned(),
+            "http://localhost:42424".to_owned(),
+        ));
+        assert_ne!(hash_tool_input(&first), hash_tool_input(&second));
+    }
+}
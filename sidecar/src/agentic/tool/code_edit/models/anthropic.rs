@@ -4,12 +4,6 @@ use crate::agentic::tool::code_edit::types::CodeEdit;
 
 use super::broker::{CodeEditPromptFormatters, CodeSnippetForEditing};
 
-/// How many lines above the selection can we show to the llm
-/// this is used to truncate the size of the context we show the llm when its
-/// making edits, since we will grab the correct context required almost always
-/// with our definitions
-const SURROUNDING_CONTEXT_LIMIT: usize = 200;
-
 pub struct AnthropicCodeEditFromatter {}
 
 impl AnthropicCodeEditFromatter {
@@ -460,7 +454,7 @@ Follow the user's requirements carefully and to the letter.
                     // limit it to 100 lines from the start
                     let mut lines = code_above.lines().collect::<Vec<_>>();
                     lines.reverse();
-                    lines.truncate(SURROUNDING_CONTEXT_LIMIT);
+                    lines.truncate(context.above_context_limit());
                     lines.reverse();
                     lines.join("\n")
                 })
@@ -471,7 +465,7 @@ Follow the user's requirements carefully and to the letter.
                 .below_context()
                 .map(|code_below| {
                     let mut lines = code_below.lines().collect::<Vec<_>>();
-                    lines.truncate(SURROUNDING_CONTEXT_LIMIT / 3);
+                    lines.truncate(context.below_context_limit());
                     lines.join("\n")
                 })
                 .as_deref(),
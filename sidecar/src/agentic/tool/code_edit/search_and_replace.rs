@@ -2,27 +2,39 @@
 
 use async_trait::async_trait;
 use futures::{lock::Mutex, StreamExt};
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
 use tokio::sync::{mpsc::UnboundedSender, Semaphore};
 
 use llm_client::{
     broker::LLMBroker,
-    clients::types::{LLMClientCompletionRequest, LLMClientMessage},
+    clients::types::{
+        estimate_tokens_for_messages, LLMClientCompletionRequest, LLMClientMessage,
+        SamplingParams,
+    },
 };
 
 use crate::{
     agentic::{
         symbol::{
             identifier::{LLMProperties, SymbolIdentifier},
+            tool_box::ToolBox,
             ui_event::{EditedCodeStreamingRequest, UIEventWithID},
         },
         tool::{
+            context_guard::ensure_within_context_window,
             errors::ToolError,
             helpers::{
                 cancellation_future::run_with_cancellation, diff_recent_changes::DiffRecentChanges,
             },
             input::ToolInput,
-            lsp::{diagnostics::DiagnosticWithSnippet, open_file::OpenFileRequest},
+            lsp::{
+                diagnostics::DiagnosticWithSnippet,
+                open_file::{looks_like_binary, OpenFileRequest},
+            },
             output::ToolOutput,
             r#type::Tool,
             session::chat::{SessionChatMessage, SessionChatRole},
@@ -31,7 +43,46 @@ use crate::{
     chunking::text_document::{Position, Range},
 };
 
-const _SURROUNDING_CONTEXT_LIMIT: usize = 200;
+/// Pulls the content out of a `<thinking>...</thinking>` block once the
+/// stream has produced a complete one, so we can relay it to the editor as
+/// soon as it shows up instead of waiting for the whole response.
+fn extract_thinking_block(stream_answer: &str) -> Option<String> {
+    let start = stream_answer.find("<thinking>")? + "<thinking>".len();
+    let end = stream_answer[start..].find("</thinking>")? + start;
+    Some(stream_answer[start..end].trim().to_owned())
+}
+
+/// The token budget `SearchAndReplaceEditingRequest` falls back to when the
+/// caller doesn't have a more specific budget of its own to hand it, mirrors
+/// the fallback used for repo-map generation.
+pub const DEFAULT_CONTEXT_WINDOW_BUDGET: usize = 3000;
+
+/// Assumed average characters per line when the caller doesn't have the
+/// real file's average line length handy, just enough to turn a token
+/// budget into a sensible line count.
+const AVERAGE_LINE_CHARS_FALLBACK: usize = 40;
+
+/// Splits a token budget into how many lines of surrounding context above
+/// and below the edit selection we can afford to show the model, weighting
+/// leading context more heavily than trailing context the same way the
+/// plain code-edit path does (see `DEFAULT_ABOVE_CONTEXT_LIMIT` and
+/// `DEFAULT_BELOW_CONTEXT_LIMIT` in `code_edit::types`).
+pub fn context_window_limits(
+    context_window_budget: usize,
+    average_line_chars: usize,
+) -> (usize, usize) {
+    let average_line_chars = if average_line_chars == 0 {
+        AVERAGE_LINE_CHARS_FALLBACK
+    } else {
+        average_line_chars
+    };
+    // ~4 characters per token, so the budget converted to characters is what
+    // we actually have to split between the above and below context.
+    let char_budget = (context_window_budget.saturating_mul(4)) as f64;
+    let above_limit = (char_budget * 0.3 / average_line_chars as f64) as usize;
+    let below_limit = (char_budget * 0.1 / average_line_chars as f64) as usize;
+    (above_limit, below_limit)
+}
 
 struct DropDetector<T>(T);
 
@@ -45,13 +96,22 @@ impl<T> Drop for DropDetector<T> {
 pub struct SearchAndReplaceEditingResponse {
     updated_code: String,
     response: String,
+    ambiguity_resolutions: Vec<AmbiguityResolution>,
+    thinking: String,
 }
 
 impl SearchAndReplaceEditingResponse {
-    pub fn new(updated_code: String, response: String) -> Self {
+    pub fn new(
+        updated_code: String,
+        response: String,
+        ambiguity_resolutions: Vec<AmbiguityResolution>,
+        thinking: String,
+    ) -> Self {
         Self {
             updated_code,
             response,
+            ambiguity_resolutions,
+            thinking,
         }
     }
 
@@ -62,13 +122,27 @@ impl SearchAndReplaceEditingResponse {
     pub fn response(&self) -> &str {
         &self.response
     }
+
+    /// Every ambiguous SEARCH block match we hit while applying this edit,
+    /// along with how (or whether) it got resolved, so callers can audit the
+    /// decision instead of trusting it blindly.
+    pub fn ambiguity_resolutions(&self) -> &[AmbiguityResolution] {
+        &self.ambiguity_resolutions
+    }
+
+    /// The model's reasoning pulled out of the `<thinking>...</thinking>`
+    /// block, kept separate from the SEARCH/REPLACE edits themselves.
+    pub fn thinking(&self) -> &str {
+        &self.thinking
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct SearchAndReplaceEditingRequest {
     fs_file_path: String,
-    // TODO(skcd): we use this to detect the range where we want to perform the edits
-    _edit_range: Range,
+    // the range of the symbol we are editing, used to disambiguate SEARCH
+    // blocks which match more than one location in the file
+    edit_range: Range,
     context_in_edit_selection: String,
     complete_file: String,
     extra_data: String,
@@ -99,6 +173,16 @@ pub struct SearchAndReplaceEditingRequest {
     previous_messages: Vec<SessionChatMessage>,
     // cancellation token
     cancellation_token: tokio_util::sync::CancellationToken,
+    // skip the few-shot example messages entirely, for models where they
+    // just waste context without improving the SEARCH/REPLACE output
+    disable_few_shot_examples: bool,
+    // the token budget the caller trimmed the surrounding above/below
+    // context down to before folding it into `context_in_edit_selection`,
+    // kept around so it can be logged/inspected alongside the request
+    context_window_budget: usize,
+    // sampling overrides for this edit; `None` falls back to this tool's
+    // existing hardcoded defaults so current callers see no change
+    sampling_params: Option<SamplingParams>,
 }
 
 impl SearchAndReplaceEditingRequest {
@@ -128,10 +212,12 @@ impl SearchAndReplaceEditingRequest {
         plan_step_id: Option<String>,
         previous_messages: Vec<SessionChatMessage>,
         cancellation_token: tokio_util::sync::CancellationToken,
+        disable_few_shot_examples: bool,
+        context_window_budget: usize,
     ) -> Self {
         Self {
             fs_file_path,
-            _edit_range: edit_range,
+            edit_range,
             context_in_edit_selection,
             complete_file,
             extra_data,
@@ -153,8 +239,49 @@ impl SearchAndReplaceEditingRequest {
             plan_step_id,
             previous_messages,
             cancellation_token,
+            disable_few_shot_examples,
+            context_window_budget,
+            sampling_params: None,
         }
     }
+
+    /// Overrides the sampling parameters (temperature, max tokens, stop
+    /// sequences) used for this edit's LLM call; `top_p` is carried but not
+    /// applied to the request. Without this, the tool's existing hardcoded
+    /// defaults apply.
+    pub fn with_sampling_params(mut self, sampling_params: SamplingParams) -> Self {
+        self.sampling_params = Some(sampling_params);
+        self
+    }
+
+    pub fn sampling_params(&self) -> Option<&SamplingParams> {
+        self.sampling_params.as_ref()
+    }
+
+    /// The token budget used to size the above/below context which was
+    /// folded into [`Self::context_in_edit_selection`] before this request
+    /// was built.
+    pub fn context_window_budget(&self) -> usize {
+        self.context_window_budget
+    }
+
+    pub fn edit_range(&self) -> &Range {
+        &self.edit_range
+    }
+
+    /// The language few-shot examples should be picked for, guessed from the
+    /// file's extension the same way the SEARCH/REPLACE format's own fence
+    /// hint (```rust, ```python, ...) would be.
+    pub fn language(&self) -> &str {
+        language_for_fs_file_path(&self.fs_file_path)
+    }
+
+    /// Whether the few-shot SEARCH/REPLACE examples should be skipped
+    /// entirely, for models where they just burn context without improving
+    /// the output.
+    pub fn disable_few_shot_examples(&self) -> bool {
+        self.disable_few_shot_examples
+    }
 }
 
 pub struct StreamedEditingForEditor {
@@ -500,11 +627,27 @@ Think carefully since this is a long file where you have to make the changes"#
         messages
     }
 
-    fn example_messages(&self) -> Vec<LLMClientMessage> {
-        vec![
-            LLMClientMessage::user(r#"Change get_factorial() to use math.factorial"#.to_owned()),
-            LLMClientMessage::assistant(
-                r#"To make this change we need to modify `mathweb/flask/app.py` to:
+    /// Picks the few-shot SEARCH/REPLACE examples to prime the model with,
+    /// keyed off `language` (as returned by `language_for_fs_file_path`).
+    /// Falls back to the generic python example for languages we don't have
+    /// a dedicated pair for, since the format itself is language-agnostic
+    /// and the python example demonstrates every block shape (edit, delete,
+    /// insert, new file) a model needs to see.
+    fn example_messages(&self, language: &str) -> Vec<LLMClientMessage> {
+        match language {
+            "rust" => rust_example_messages(),
+            "typescript" | "javascript" => typescript_example_messages(),
+            "go" => go_example_messages(),
+            _ => python_example_messages(),
+        }
+    }
+}
+
+fn python_example_messages() -> Vec<LLMClientMessage> {
+    vec![
+        LLMClientMessage::user(r#"Change get_factorial() to use math.factorial"#.to_owned()),
+        LLMClientMessage::assistant(
+            r#"To make this change we need to modify `mathweb/flask/app.py` to:
 
 1. Import the math package.
 2. Remove the existing factorial() function.
@@ -545,11 +688,11 @@ mathweb/flask/app.py
     return str(math.factorial(n))
 >>>>>>> REPLACE
 ```"#
-                    .to_owned(),
-            ),
-            LLMClientMessage::user(r#"Refactor hello() into its own file."#.to_owned()),
-            LLMClientMessage::assistant(
-                r#"To make this change we need to modify `main.py` and make a new file `hello.py`:
+                .to_owned(),
+        ),
+        LLMClientMessage::user(r#"Refactor hello() into its own file."#.to_owned()),
+        LLMClientMessage::assistant(
+            r#"To make this change we need to modify `main.py` and make a new file `hello.py`:
 
 1. Make a new hello.py file with hello() in it.
 2. Remove hello() from main.py and replace it with an import.
@@ -578,26 +721,230 @@ def hello():
 from hello import hello
 >>>>>>> REPLACE
 ```"#
-                    .to_owned(),
-            )
-            .cache_point(),
-        ]
+                .to_owned(),
+        )
+        .cache_point(),
+    ]
+}
+
+fn rust_example_messages() -> Vec<LLMClientMessage> {
+    vec![
+        LLMClientMessage::user(r#"Change factorial() to use a fold instead of recursion"#.to_owned()),
+        LLMClientMessage::assistant(
+            r#"To make this change we need to modify `src/math.rs` to:
+
+1. Replace the recursive factorial() with an iterator based implementation.
+
+Here are the *SEARCH/REPLACE* blocks:
+
+src/math.rs
+```rust
+<<<<<<< SEARCH
+fn factorial(n: u64) -> u64 {
+    if n == 0 {
+        1
+    } else {
+        n * factorial(n - 1)
     }
 }
+=======
+fn factorial(n: u64) -> u64 {
+    (1..=n).fold(1, |acc, x| acc * x)
+}
+>>>>>>> REPLACE
+```"#
+                .to_owned(),
+        ),
+        LLMClientMessage::user(r#"Move greet() into its own module."#.to_owned()),
+        LLMClientMessage::assistant(
+            r#"To make this change we need to modify `src/main.rs` and make a new file `src/greet.rs`:
+
+1. Make a new src/greet.rs file with greet() in it.
+2. Remove greet() from src/main.rs and replace it with a module declaration and import.
+
+Here are the *SEARCH/REPLACE* blocks:
+
+src/greet.rs
+```rust
+<<<<<<< SEARCH
+=======
+pub fn greet() {
+    println!("hello");
+}
+>>>>>>> REPLACE
+```
+
+src/main.rs
+```rust
+<<<<<<< SEARCH
+fn greet() {
+    println!("hello");
+}
+=======
+mod greet;
+use greet::greet;
+>>>>>>> REPLACE
+```"#
+                .to_owned(),
+        )
+        .cache_point(),
+    ]
+}
+
+fn typescript_example_messages() -> Vec<LLMClientMessage> {
+    vec![
+        LLMClientMessage::user(r#"Change getFactorial() to use a reduce instead of recursion"#.to_owned()),
+        LLMClientMessage::assistant(
+            r#"To make this change we need to modify `src/math.ts` to:
+
+1. Replace the recursive getFactorial() with a reduce based implementation.
+
+Here are the *SEARCH/REPLACE* blocks:
+
+src/math.ts
+```typescript
+<<<<<<< SEARCH
+function getFactorial(n: number): number {
+  if (n === 0) {
+    return 1;
+  }
+  return n * getFactorial(n - 1);
+}
+=======
+function getFactorial(n: number): number {
+  return Array.from({ length: n }, (_, i) => i + 1).reduce((acc, x) => acc * x, 1);
+}
+>>>>>>> REPLACE
+```"#
+                .to_owned(),
+        ),
+        LLMClientMessage::user(r#"Move greet() into its own file."#.to_owned()),
+        LLMClientMessage::assistant(
+            r#"To make this change we need to modify `src/main.ts` and make a new file `src/greet.ts`:
+
+1. Make a new src/greet.ts file with greet() in it.
+2. Remove greet() from src/main.ts and replace it with an import.
+
+Here are the *SEARCH/REPLACE* blocks:
+
+src/greet.ts
+```typescript
+<<<<<<< SEARCH
+=======
+export function greet(): void {
+  console.log("hello");
+}
+>>>>>>> REPLACE
+```
+
+src/main.ts
+```typescript
+<<<<<<< SEARCH
+function greet(): void {
+  console.log("hello");
+}
+=======
+import { greet } from "./greet";
+>>>>>>> REPLACE
+```"#
+                .to_owned(),
+        )
+        .cache_point(),
+    ]
+}
+
+fn go_example_messages() -> Vec<LLMClientMessage> {
+    vec![
+        LLMClientMessage::user(r#"Change Factorial() to use a loop instead of recursion"#.to_owned()),
+        LLMClientMessage::assistant(
+            r#"To make this change we need to modify `mathutil/factorial.go` to:
+
+1. Replace the recursive Factorial() with a loop based implementation.
+
+Here are the *SEARCH/REPLACE* blocks:
+
+mathutil/factorial.go
+```go
+<<<<<<< SEARCH
+func Factorial(n int) int {
+	if n == 0 {
+		return 1
+	}
+	return n * Factorial(n-1)
+}
+=======
+func Factorial(n int) int {
+	result := 1
+	for i := 2; i <= n; i++ {
+		result *= i
+	}
+	return result
+}
+>>>>>>> REPLACE
+```"#
+                .to_owned(),
+        ),
+        LLMClientMessage::user(r#"Move Greet() into its own file."#.to_owned()),
+        LLMClientMessage::assistant(
+            r#"To make this change we need to modify `main.go` and make a new file `greet.go`:
+
+1. Make a new greet.go file with Greet() in it.
+2. Remove Greet() from main.go.
+
+Here are the *SEARCH/REPLACE* blocks:
+
+greet.go
+```go
+<<<<<<< SEARCH
+=======
+package main
+
+import "fmt"
+
+func Greet() {
+	fmt.Println("hello")
+}
+>>>>>>> REPLACE
+```
+
+main.go
+```go
+<<<<<<< SEARCH
+func Greet() {
+	fmt.Println("hello")
+}
+=======
+>>>>>>> REPLACE
+```"#
+                .to_owned(),
+        )
+        .cache_point(),
+    ]
+}
 
 #[async_trait]
 impl Tool for SearchAndReplaceEditing {
     async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
         let context = input.should_search_and_replace_editing()?;
+        // never let the agent try to search/replace its way through a
+        // binary blob, the SEARCH/REPLACE format only makes sense for text
+        if looks_like_binary(&context.complete_file) {
+            return Err(ToolError::BinaryFileNotSupported(
+                context.fs_file_path.to_owned(),
+            ));
+        }
         let is_warmup = context.is_warmup;
         let previous_messages = context.previous_messages.to_vec();
         let cancellation_token = context.cancellation_token.clone();
         let whole_file_context = context.complete_file.to_owned();
         let start_line = 0;
+        let symbol_range = context.edit_range().clone();
         let symbol_identifier = context.symbol_identifier.clone();
         let ui_sender = context.ui_sender.clone();
         let fs_file_path = context.fs_file_path.to_owned();
         let editor_url = context.editor_url.to_owned();
+        let disable_few_shot_examples = context.disable_few_shot_examples;
+        let sampling_params = context.sampling_params.clone();
         let file_lock;
         {
             let cloned_file_locker = self.file_locker.clone();
@@ -640,10 +987,20 @@ impl Tool for SearchAndReplaceEditing {
                 SessionChatRole::Assistant => {
                     LLMClientMessage::assistant(previous_message.message().to_owned())
                 }
+                SessionChatRole::ToolOutput => LLMClientMessage::user(
+                    crate::agentic::tool::helpers::prompt_injection::wrap_untrusted_tool_output(
+                        previous_message.message(),
+                    ),
+                ),
             })
             .collect::<Vec<_>>();
+        let language = language_for_fs_file_path(&fs_file_path);
         let user_messages = self.user_messages(context);
-        let example_messages = self.example_messages();
+        let example_messages = if disable_few_shot_examples {
+            vec![]
+        } else {
+            self.example_messages(language)
+        };
         let mut request = LLMClientCompletionRequest::new(
             llm_properties.llm().to_owned(),
             vec![system_message]
@@ -655,9 +1012,35 @@ impl Tool for SearchAndReplaceEditing {
             0.2,
             None,
         );
+        if let Some(sampling_params) = sampling_params.as_ref() {
+            request = request.with_sampling_params(sampling_params);
+        }
         if is_warmup {
             request = request.set_max_tokens(1);
         }
+        // No failover chain exists for search/replace editing today, so the
+        // guard here can only drop the oldest compactable messages - it
+        // still turns a would-be opaque provider error into a typed one
+        // with the sizes involved if that's not enough.
+        let estimated_tokens_before =
+            estimate_tokens_for_messages(request.messages()) + llm_properties.llm().max_output_tokens();
+        let guarded = ensure_within_context_window(
+            request.messages().to_vec(),
+            llm_properties.llm().to_owned(),
+            llm_properties.llm().max_output_tokens(),
+            &[],
+        )?;
+        if !guarded.actions_taken.is_empty() {
+            let _ = ui_sender.send(UIEventWithID::context_window_remediated(
+                session_id.to_owned(),
+                exchange_id.to_owned(),
+                llm_properties.llm().to_string(),
+                estimated_tokens_before,
+                llm_properties.llm().context_window(),
+                guarded.actions_taken,
+            ));
+        }
+        request = request.set_llm(guarded.llm).set_messages(guarded.messages);
         let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
         let cloned_llm_client = self.llm_client.clone();
         let cloned_root_request_id = root_request_id.to_owned();
@@ -683,8 +1066,12 @@ impl Tool for SearchAndReplaceEditing {
 
         let (edits_sender, mut edits_receiver) = tokio::sync::mpsc::unbounded_channel();
         // let (locks_sender, mut locks_receiver) = tokio::sync::mpsc::unbounded_channel();
-        let mut search_and_replace_accumulator =
-            SearchAndReplaceAccumulator::new(whole_file_context, start_line, edits_sender);
+        let mut search_and_replace_accumulator = SearchAndReplaceAccumulator::new(
+            whole_file_context,
+            start_line,
+            symbol_range,
+            edits_sender,
+        );
 
         // we want to figure out how poll the llm stream while locking up until the file is free
         // from the lock over here for the file path we are interested in
@@ -756,7 +1143,7 @@ impl Tool for SearchAndReplaceEditing {
                             drop(DropDetector(edit_lock));
                         }
                     }
-                    Some(EditDelta::EditStarted(range)) => {
+                    Some(EditDelta::EditStarted(range, matched_original_text)) => {
                         streamed_edit_client
                             .send_edit_event(
                                 editor_url.to_owned(),
@@ -767,6 +1154,7 @@ impl Tool for SearchAndReplaceEditing {
                                     fs_file_path.to_owned(),
                                     cloned_exchange_id.to_owned(),
                                     cloned_plan_step_id.clone(),
+                                    matched_original_text,
                                 ),
                             )
                             .await;
@@ -849,12 +1237,27 @@ impl Tool for SearchAndReplaceEditing {
         // Note: The cancellation token here is so polluted, we could do this way better
         // instead of making sure that each future is run with cancellation
         let mut delta_stream = tokio_stream::wrappers::UnboundedReceiverStream::new(receiver);
+        let mut agent_thinking_sent = false;
         while let Some(Some(stream_msg)) =
             run_with_cancellation(cancellation_token.clone(), delta_stream.next()).await
         {
             let delta = stream_msg.delta();
             if let Some(delta) = delta {
                 stream_answer.push_str(&delta);
+                // the model's <thinking> block always precedes the first
+                // SEARCH/REPLACE block, so relay it to the editor before we
+                // hand this delta to the accumulator (which is what can end
+                // up emitting `EditStarted`)
+                if !agent_thinking_sent {
+                    if let Some(thinking) = extract_thinking_block(&stream_answer) {
+                        agent_thinking_sent = true;
+                        let _ = ui_sender.send(UIEventWithID::agent_thinking(
+                            root_request_id.to_owned(),
+                            exchange_id.to_owned(),
+                            thinking,
+                        ));
+                    }
+                }
                 // we have some delta over here which we can process
                 search_and_replace_accumulator
                     .add_delta(delta.to_owned())
@@ -892,6 +1295,8 @@ impl Tool for SearchAndReplaceEditing {
                 SearchAndReplaceEditingResponse::new(
                     search_and_replace_accumulator.code_lines.join("\n"),
                     response,
+                    search_and_replace_accumulator.ambiguity_resolutions,
+                    search_and_replace_accumulator.thinking,
                 ),
             )),
             // wrong error over here but its fine for now
@@ -909,7 +1314,9 @@ impl Tool for SearchAndReplaceEditing {
 }
 
 pub enum EditDelta {
-    EditStarted(Range),
+    /// The range being edited, plus the original text it matched (`None` for
+    /// an empty SEARCH block, since there's nothing to strike through).
+    EditStarted(Range, Option<String>),
     EditDelta((Range, String)),
     EditEnd(Range),
     EditLockAcquire(tokio::sync::oneshot::Sender<Option<String>>),
@@ -934,24 +1341,51 @@ pub struct SearchAndReplaceAccumulator {
     search_block_status: SearchBlockStatus,
     updated_block: Option<String>,
     sender: UnboundedSender<EditDelta>,
+    /// Maps a line's content hash to every line index in `code_lines` carrying
+    /// that content, so a SEARCH block's first line can be located in O(1)
+    /// instead of rescanning the whole buffer. Rebuilt whenever `code_lines`
+    /// is replaced wholesale (construction, file reload, post-edit update).
+    line_hash_index: HashMap<u64, Vec<usize>>,
+    /// End line (relative to `code_lines`) of the most recently matched
+    /// SEARCH block, used to prefer later occurrences when the same snippet
+    /// appears more than once in the file.
+    last_match_end_line: Option<usize>,
+    /// The range of the symbol this edit is scoped to, used to disambiguate
+    /// a SEARCH block which matches more than one location in the file.
+    symbol_range: Range,
+    /// Audit trail of every ambiguous SEARCH block match we hit and how (or
+    /// whether) it got resolved.
+    pub ambiguity_resolutions: Vec<AmbiguityResolution>,
+    /// Whether we are currently between a `<thinking>` and `</thinking>` line,
+    /// so lines in between are collected into `thinking` instead of being
+    /// checked for SEARCH/REPLACE markers (the model's reasoning can quote
+    /// the format while explaining it, which would otherwise be misread as a
+    /// real edit marker).
+    in_thinking_block: bool,
+    /// Every line seen inside a `<thinking>...</thinking>` block, exposed
+    /// separately from the edit response instead of being applied as code.
+    thinking: String,
 }
 
 impl SearchAndReplaceAccumulator {
     pub fn new(
         code_to_edit: String,
         start_line: usize,
+        symbol_range: Range,
         sender: UnboundedSender<EditDelta>,
     ) -> Self {
         println!(
             "search_and_replace_accumulator::code_to_edit_lines::{}",
             code_to_edit.lines().into_iter().collect::<Vec<_>>().len()
         );
+        let code_lines = code_to_edit
+            .lines()
+            .into_iter()
+            .map(|line| line.to_owned())
+            .collect::<Vec<_>>();
+        let line_hash_index = build_line_hash_index(&code_lines);
         Self {
-            code_lines: code_to_edit
-                .lines()
-                .into_iter()
-                .map(|line| line.to_owned())
-                .collect::<Vec<_>>(),
+            code_lines,
             start_line,
             answer_up_until_now: "".to_owned(),
             answer_to_show: "".to_owned(),
@@ -959,9 +1393,21 @@ impl SearchAndReplaceAccumulator {
             search_block_status: SearchBlockStatus::NoBlock,
             updated_block: None,
             sender,
+            line_hash_index,
+            last_match_end_line: None,
+            symbol_range,
+            ambiguity_resolutions: Vec::new(),
+            in_thinking_block: false,
+            thinking: "".to_owned(),
         }
     }
 
+    /// Rebuilds the line-hash index after `code_lines` has been replaced
+    /// wholesale (as opposed to mutated in place).
+    fn reindex_code_lines(&mut self) {
+        self.line_hash_index = build_line_hash_index(&self.code_lines);
+    }
+
     pub async fn end_streaming(&mut self) {
         let _ = self.sender.send(EditDelta::EndPollingStream);
     }
@@ -993,6 +1439,24 @@ impl SearchAndReplaceAccumulator {
 
             match self.search_block_status.clone() {
                 SearchBlockStatus::NoBlock => {
+                    if answer_line_at_index.trim() == "<thinking>" {
+                        self.in_thinking_block = true;
+                        continue;
+                    }
+                    if answer_line_at_index.trim() == "</thinking>" {
+                        self.in_thinking_block = false;
+                        continue;
+                    }
+                    if self.in_thinking_block {
+                        // lines inside a thinking block are never checked
+                        // against the SEARCH/REPLACE markers, even if the
+                        // model quotes them while explaining the format
+                        if !self.thinking.is_empty() {
+                            self.thinking.push('\n');
+                        }
+                        self.thinking.push_str(answer_line_at_index);
+                        continue;
+                    }
                     if answer_line_at_index == head {
                         self.search_block_status = SearchBlockStatus::BlockStart;
                         let mut answer_lines = self
@@ -1042,18 +1506,15 @@ impl SearchAndReplaceAccumulator {
                                 .into_iter()
                                 .map(|line| line.to_owned())
                                 .collect::<Vec<_>>();
+                            self.reindex_code_lines();
                         }
                         // and hold the lock for a while until we have the replace block
-                        let range = get_range_for_search_block(
-                            &self.code_lines.join("\n"),
-                            self.start_line,
-                            "",
-                        );
+                        let range = self.get_range_for_search_block("");
                         match range {
                             Some(range) => {
                                 self.search_block_status =
                                     SearchBlockStatus::BlockFound(("".to_owned(), range.clone()));
-                                let _ = self.sender.send(EditDelta::EditStarted(range));
+                                let _ = self.sender.send(EditDelta::EditStarted(range, None));
                                 // If we have a range over here, we probably want to show it on the answer lines
                                 // to do this: we need to do the following:
                                 // - go back couple of steps here (or the line length of the accumulated block + 2 (for ```language and Locating relevant snippet...))
@@ -1127,20 +1588,19 @@ impl SearchAndReplaceAccumulator {
                                 .into_iter()
                                 .map(|line| line.to_owned())
                                 .collect::<Vec<_>>();
+                            self.reindex_code_lines();
                         }
                         // and hold the lock for a while until we have the replace block
-                        let range = get_range_for_search_block(
-                            &self.code_lines.join("\n"),
-                            self.start_line,
-                            &accumulated,
-                        );
+                        let range = self.get_range_for_search_block(&accumulated);
                         match range {
                             Some(range) => {
                                 self.search_block_status = SearchBlockStatus::BlockFound((
                                     accumulated.to_owned(),
                                     range.clone(),
                                 ));
-                                let _ = self.sender.send(EditDelta::EditStarted(range));
+                                let _ = self
+                                    .sender
+                                    .send(EditDelta::EditStarted(range, Some(accumulated.clone())));
                                 // If we have a range over here, we probably want to show it on the answer lines
                                 // to do this: we need to do the following:
                                 // - go back couple of steps here (or the line length of the accumulated block + 2 (for ```language and Locating relevant snippet...))
@@ -1249,6 +1709,7 @@ impl SearchAndReplaceAccumulator {
             if let Some(updated_answer) = self.updated_block.clone() {
                 self.code_lines = updated_answer.lines().map(|line| line.to_owned()).collect();
             }
+            self.reindex_code_lines();
             return;
         }
         if let Some(updated_answer) = self.updated_block.clone() {
@@ -1277,6 +1738,7 @@ impl SearchAndReplaceAccumulator {
                 .map(|line| line.to_owned())
                 .collect();
         }
+        self.reindex_code_lines();
         self.updated_block = None;
     }
 
@@ -1297,6 +1759,357 @@ impl SearchAndReplaceAccumulator {
             )));
         }
     }
+
+    /// Finds the range `search_block` occupies in `self.code_lines`.
+    ///
+    /// Instead of rescanning every line of the buffer for every block (which
+    /// is O(file_lines x block_lines) and visibly lags on large files when
+    /// the model emits many small blocks), we look up candidate start
+    /// positions for the block's first line in `line_hash_index` in O(1) and
+    /// only verify the full block line-by-line for those candidates.
+    ///
+    /// When more than one candidate verifies (common with short getter
+    /// bodies which repeat verbatim across a file), we no longer silently
+    /// take the first one. We resolve it, in order:
+    /// 1. Proximity to the previously applied block - prefer the closest
+    ///    candidate at or after the end of the last edit, so we keep
+    ///    matching forward through the file.
+    /// 2. Containment within the symbol range this edit is scoped to - only
+    ///    used when exactly one candidate falls inside it.
+    /// 3. Otherwise the match is genuinely ambiguous: we record it and
+    ///    report a failed block (`None`) instead of guessing.
+    fn get_range_for_search_block(&mut self, search_block: &str) -> Option<Range> {
+        if self.code_lines.is_empty() {
+            return Some(Range::new(Position::new(0, 0, 0), Position::new(0, 0, 0)));
+        }
+
+        let search_block_lines = search_block.lines().into_iter().collect::<Vec<_>>();
+        let search_block_len = search_block_lines.len();
+        if search_block_len == 0 || self.code_lines.len() < search_block_len {
+            // return early over here if we do not want to edit this
+            return None;
+        }
+
+        let candidates = self
+            .line_hash_index
+            .get(&hash_line(search_block_lines[0]))
+            .cloned()
+            .unwrap_or_default();
+
+        let mut verified_matches = candidates
+            .into_iter()
+            .filter(|&start_idx| {
+                start_idx + search_block_len <= self.code_lines.len()
+                    && self.code_lines[start_idx..start_idx + search_block_len]
+                        .iter()
+                        .map(|line| line.as_str())
+                        .eq(search_block_lines.iter().copied())
+            })
+            .collect::<Vec<_>>();
+        verified_matches.sort();
+
+        let chosen_start_idx = match verified_matches.as_slice() {
+            [] => None,
+            [only_match] => Some(*only_match),
+            multiple_matches => self.resolve_ambiguous_match(search_block, multiple_matches),
+        };
+
+        chosen_start_idx.map(|start_idx| {
+            self.last_match_end_line = Some(start_idx + search_block_len);
+            Range::new(
+                Position::new(start_idx + self.start_line, 0, 0),
+                Position::new(
+                    start_idx + search_block_len - 1 + self.start_line,
+                    0,
+                    0,
+                ),
+            )
+        })
+    }
+
+    /// Picks a single occurrence out of `candidates` (all of which verified
+    /// against the SEARCH block) using the proximity-then-containment
+    /// heuristics, recording the decision on `ambiguity_resolutions` either
+    /// way so it can be audited later.
+    fn resolve_ambiguous_match(
+        &mut self,
+        search_block: &str,
+        candidates: &[usize],
+    ) -> Option<usize> {
+        let candidate_start_lines = candidates
+            .iter()
+            .map(|&start_idx| start_idx + self.start_line)
+            .collect::<Vec<_>>();
+
+        if let Some(last_match_end_line) = self.last_match_end_line {
+            if let Some(&closest) = candidates
+                .iter()
+                .filter(|&&start_idx| start_idx >= last_match_end_line)
+                .min_by_key(|&&start_idx| start_idx - last_match_end_line)
+            {
+                self.ambiguity_resolutions.push(AmbiguityResolution::new(
+                    search_block.to_owned(),
+                    candidate_start_lines,
+                    AmbiguityResolutionKind::ProximityToPreviousEdit(closest + self.start_line),
+                ));
+                return Some(closest);
+            }
+        }
+
+        let contained_in_symbol_range = candidates
+            .iter()
+            .filter(|&&start_idx| {
+                self.symbol_range
+                    .contains_check_line(&Range::new(
+                        Position::new(start_idx + self.start_line, 0, 0),
+                        Position::new(start_idx + self.start_line, 0, 0),
+                    ))
+            })
+            .collect::<Vec<_>>();
+        if let [&only_contained] = contained_in_symbol_range.as_slice() {
+            self.ambiguity_resolutions.push(AmbiguityResolution::new(
+                search_block.to_owned(),
+                candidate_start_lines,
+                AmbiguityResolutionKind::SymbolRangeContainment(only_contained + self.start_line),
+            ));
+            return Some(only_contained);
+        }
+
+        self.ambiguity_resolutions.push(AmbiguityResolution::new(
+            search_block.to_owned(),
+            candidate_start_lines,
+            AmbiguityResolutionKind::Unresolved,
+        ));
+        None
+    }
+}
+
+/// An [`EditDelta`] plus the file it belongs to, emitted by
+/// [`MultiFileSearchAndReplaceAccumulator`] so a caller watching a single
+/// stream can tell which file's edit progressed when the LLM's response
+/// covers more than one file.
+#[allow(dead_code)]
+pub struct TaggedEditDelta {
+    pub fs_file_path: String,
+    pub delta: EditDelta,
+}
+
+/// Multi-file variant of [`SearchAndReplaceAccumulator`]. The LLM sometimes
+/// legitimately produces SEARCH/REPLACE blocks for more than one file in a
+/// single response (eg a rename which also needs its call sites updated
+/// elsewhere), so this accumulator watches for the file path line the format
+/// requires ahead of every SEARCH/REPLACE block and hands deltas off to a
+/// per-file [`SearchAndReplaceAccumulator`], loading that file's content on
+/// demand via the `ToolBox` the first time we see a block for it.
+///
+/// Not yet wired into `SearchAndReplaceEditing::invoke`, which still drives a
+/// single file end-to-end (lock acquisition, editor apply, all of it); that
+/// plumbing needs to grow a lock/apply step per file before this can replace
+/// it there. Landing the accumulator on its own first so it can be exercised
+/// and reviewed independently of that larger change.
+#[allow(dead_code)]
+pub struct MultiFileSearchAndReplaceAccumulator {
+    /// One accumulator per file we have seen a SEARCH/REPLACE block for so
+    /// far, created the first time we switch to that file.
+    accumulators: HashMap<String, SearchAndReplaceAccumulator>,
+    /// The receiving end of each per-file accumulator's own `EditDelta`
+    /// channel, drained after every `add_delta` call so we can re-emit its
+    /// events tagged with the file path on our own channel.
+    receivers: HashMap<String, tokio::sync::mpsc::UnboundedReceiver<EditDelta>>,
+    /// Content for every file we have loaded so far, keyed by path.
+    files: HashMap<String, String>,
+    current_file_path: String,
+    /// Whether we are currently inside a ```-fenced code block; a bare,
+    /// non-empty line is only ever the file path the format requires when we
+    /// are *not* inside one.
+    in_fence: bool,
+    /// The most recent bare, non-fenced line we saw, our best guess at the
+    /// file path for whichever fence opens next.
+    pending_path_candidate: Option<String>,
+    start_line: usize,
+    symbol_range: Range,
+    tool_box: Arc<ToolBox>,
+    answer_up_until_now: String,
+    previous_answer_line_number: Option<usize>,
+    sender: UnboundedSender<TaggedEditDelta>,
+}
+
+#[allow(dead_code)]
+impl MultiFileSearchAndReplaceAccumulator {
+    pub fn new(
+        initial_file_path: String,
+        initial_file_content: String,
+        start_line: usize,
+        symbol_range: Range,
+        tool_box: Arc<ToolBox>,
+        sender: UnboundedSender<TaggedEditDelta>,
+    ) -> Self {
+        let mut files = HashMap::new();
+        files.insert(initial_file_path.clone(), initial_file_content);
+        Self {
+            accumulators: HashMap::new(),
+            receivers: HashMap::new(),
+            files,
+            current_file_path: initial_file_path,
+            in_fence: false,
+            pending_path_candidate: None,
+            start_line,
+            symbol_range,
+            tool_box,
+            answer_up_until_now: "".to_owned(),
+            previous_answer_line_number: None,
+            sender,
+        }
+    }
+
+    /// Switches the file subsequent SEARCH/REPLACE blocks are attributed to,
+    /// loading its content via the `ToolBox` the first time we see it.
+    async fn switch_to_file(&mut self, fs_file_path: String) {
+        if self.current_file_path == fs_file_path {
+            return;
+        }
+        if !self.files.contains_key(&fs_file_path) {
+            let content = self
+                .tool_box
+                .get_file_content(&fs_file_path)
+                .await
+                .unwrap_or_default();
+            self.files.insert(fs_file_path.clone(), content);
+        }
+        self.current_file_path = fs_file_path;
+    }
+
+    fn accumulator_for_current_file(&mut self) -> &mut SearchAndReplaceAccumulator {
+        let current_file_path = self.current_file_path.clone();
+        if !self.accumulators.contains_key(&current_file_path) {
+            let content = self
+                .files
+                .get(&current_file_path)
+                .cloned()
+                .unwrap_or_default();
+            let (edits_sender, edits_receiver) = tokio::sync::mpsc::unbounded_channel();
+            let accumulator = SearchAndReplaceAccumulator::new(
+                content,
+                self.start_line,
+                self.symbol_range.clone(),
+                edits_sender,
+            );
+            self.accumulators
+                .insert(current_file_path.clone(), accumulator);
+            self.receivers.insert(current_file_path.clone(), edits_receiver);
+        }
+        self.accumulators
+            .get_mut(&current_file_path)
+            .expect("just inserted above")
+    }
+
+    fn drain_deltas_for(&mut self, fs_file_path: &str) {
+        if let Some(receiver) = self.receivers.get_mut(fs_file_path) {
+            while let Ok(delta) = receiver.try_recv() {
+                let _ = self.sender.send(TaggedEditDelta {
+                    fs_file_path: fs_file_path.to_owned(),
+                    delta,
+                });
+            }
+        }
+    }
+
+    pub async fn add_delta(&mut self, delta: String) {
+        self.answer_up_until_now.push_str(&delta);
+        let Some(line_number_to_process) = get_last_newline_line_number(&self.answer_up_until_now)
+        else {
+            return;
+        };
+        let line_number_to_process_until = line_number_to_process - 1;
+        let answer_up_until_now = self.answer_up_until_now.to_owned();
+        let answer_lines = answer_up_until_now.lines().into_iter().collect::<Vec<_>>();
+        let start_index = self.previous_answer_line_number.map_or(0, |n| n + 1);
+
+        for line_number in start_index..=line_number_to_process_until {
+            self.previous_answer_line_number = Some(line_number);
+            let line = answer_lines[line_number];
+
+            if !self.in_fence {
+                if line.starts_with("```") {
+                    self.in_fence = true;
+                    if let Some(candidate) = self.pending_path_candidate.take() {
+                        self.switch_to_file(candidate).await;
+                    }
+                } else if !line.trim().is_empty() {
+                    self.pending_path_candidate = Some(line.to_owned());
+                }
+            } else if line == "```" {
+                self.in_fence = false;
+            }
+
+            let current_file_path = self.current_file_path.clone();
+            self.accumulator_for_current_file()
+                .add_delta(format!("{}\n", line))
+                .await;
+            self.drain_deltas_for(&current_file_path);
+        }
+    }
+
+    pub async fn end_streaming(&mut self) {
+        let file_paths = self.accumulators.keys().cloned().collect::<Vec<_>>();
+        for fs_file_path in file_paths {
+            if let Some(accumulator) = self.accumulators.get_mut(&fs_file_path) {
+                accumulator.end_streaming().await;
+            }
+            self.drain_deltas_for(&fs_file_path);
+        }
+    }
+}
+
+/// How an ambiguous SEARCH block match (the same snippet verified at more
+/// than one location in the file) was resolved, kept around so a reviewer
+/// can audit whether we picked the right occurrence.
+#[derive(Debug, Clone)]
+pub struct AmbiguityResolution {
+    search_block_preview: String,
+    candidate_start_lines: Vec<usize>,
+    resolution: AmbiguityResolutionKind,
+}
+
+impl AmbiguityResolution {
+    fn new(
+        search_block_preview: String,
+        candidate_start_lines: Vec<usize>,
+        resolution: AmbiguityResolutionKind,
+    ) -> Self {
+        Self {
+            search_block_preview,
+            candidate_start_lines,
+            resolution,
+        }
+    }
+
+    pub fn search_block_preview(&self) -> &str {
+        &self.search_block_preview
+    }
+
+    pub fn candidate_start_lines(&self) -> &[usize] {
+        &self.candidate_start_lines
+    }
+
+    pub fn resolution(&self) -> &AmbiguityResolutionKind {
+        &self.resolution
+    }
+}
+
+/// The heuristic (if any) which picked a single occurrence out of the
+/// candidates, tried in this order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AmbiguityResolutionKind {
+    /// Picked the candidate closest to (and at or after) the end of the
+    /// previously applied edit. Carries the chosen start line.
+    ProximityToPreviousEdit(usize),
+    /// Picked the only candidate whose start line falls within the symbol
+    /// range the edit request was scoped to. Carries the chosen start line.
+    SymbolRangeContainment(usize),
+    /// None of the heuristics could single out one candidate; the block was
+    /// reported as failed instead of guessing.
+    Unresolved,
 }
 
 /// Helps to get the last line number which has a \n
@@ -1305,54 +2118,68 @@ fn get_last_newline_line_number(s: &str) -> Option<usize> {
         .map(|last_index| s[..=last_index].chars().filter(|&c| c == '\n').count())
 }
 
-fn get_range_for_search_block(
-    code_to_look_at: &str,
-    start_line: usize,
-    search_block: &str,
-) -> Option<Range> {
-    let code_to_look_at_lines = code_to_look_at
-        .lines()
-        .into_iter()
-        .enumerate()
-        .map(|(idx, line)| (idx + start_line, line.to_owned()))
-        .collect::<Vec<_>>();
-
-    if code_to_look_at == "" {
-        return Some(Range::new(Position::new(0, 0, 0), Position::new(0, 0, 0)));
-    }
-
-    let search_block_lines = search_block.lines().into_iter().collect::<Vec<_>>();
-    let search_block_len = search_block_lines.len();
-    if code_to_look_at_lines.len() < search_block_len {
-        // return early over here if we do not want to edit this
-        return None;
-    }
-    for i in 0..=code_to_look_at_lines.len() - search_block_len {
-        if code_to_look_at_lines[i..i + search_block_len]
-            .iter()
-            .map(|(_, content)| content)
-            .collect::<Vec<_>>()
-            == search_block_lines
-        {
-            // we have our answer over here, now return the range
-            return Some(Range::new(
-                Position::new(code_to_look_at_lines[i].0, 0, 0),
-                Position::new(code_to_look_at_lines[i + search_block_len - 1].0, 0, 0),
-            ));
-        }
+fn hash_line(line: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    line.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn build_line_hash_index(code_lines: &[String]) -> HashMap<u64, Vec<usize>> {
+    let mut index: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (idx, line) in code_lines.iter().enumerate() {
+        index.entry(hash_line(line)).or_insert_with(Vec::new).push(idx);
+    }
+    index
+}
+
+/// Guesses the language of `fs_file_path` from its extension, purely so we
+/// can pick language-appropriate few-shot examples for the SEARCH/REPLACE
+/// prompt. This is deliberately a cheap extension lookup rather than
+/// `TSLanguageConfig::get_language`, which needs a fully constructed
+/// language config/parser for a job this simple.
+fn language_for_fs_file_path(fs_file_path: &str) -> &'static str {
+    let extension = fs_file_path.rsplit('.').next().unwrap_or("");
+    match extension {
+        "rs" => "rust",
+        "ts" | "tsx" => "typescript",
+        "js" | "jsx" => "javascript",
+        "go" => "go",
+        "py" => "python",
+        _ => "python",
     }
-    None
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::agentic::tool::{
-        errors::ToolError, input::ToolInput, lsp::open_file::OpenFileResponse, output::ToolOutput,
-        r#type::Tool,
+    use crate::{
+        agentic::{
+            symbol::identifier::{LLMProperties, SymbolIdentifier},
+            tool::{
+                errors::ToolError, input::ToolInput, lsp::open_file::OpenFileResponse,
+                output::ToolOutput, r#type::Tool,
+            },
+        },
+        chunking::text_document::{Position, Range},
     };
 
-    use super::SearchAndReplaceAccumulator;
+    use super::{
+        context_window_limits, AmbiguityResolutionKind, SearchAndReplaceAccumulator,
+        SearchAndReplaceEditing, SearchAndReplaceEditingRequest, DEFAULT_CONTEXT_WINDOW_BUDGET,
+    };
     use async_trait::async_trait;
+    use llm_client::{
+        broker::LLMBroker,
+        clients::types::LLMType,
+        config::LLMBrokerConfiguration,
+        provider::{LLMProvider, LLMProviderAPIKeys, OpenAIProvider},
+    };
+    use std::sync::Arc;
+
+    /// A symbol range wide enough to never influence containment-based
+    /// disambiguation in tests which are not exercising that heuristic.
+    fn whole_file_range() -> Range {
+        Range::new(Position::new(0, 0, 0), Position::new(usize::MAX, 0, 0))
+    }
 
     struct CacheFileOutput {
         content: String,
@@ -1378,6 +2205,60 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_search_and_replace_refuses_binary_file() {
+        let llm_broker = LLMBroker::new(LLMBrokerConfiguration::new(std::env::temp_dir()))
+            .await
+            .expect("llm broker should initialise against a scratch data dir");
+        let llm_properties = LLMProperties::new(
+            LLMType::Gpt4O,
+            LLMProvider::OpenAI,
+            LLMProviderAPIKeys::OpenAI(OpenAIProvider::new("".to_owned())),
+        );
+        let lsp_open_file: Arc<Box<dyn Tool + Send + Sync>> = Arc::new(Box::new(CacheFileOutput {
+            content: "".to_owned(),
+        }));
+        let editing_tool =
+            SearchAndReplaceEditing::new(Arc::new(llm_broker), llm_properties.clone(), lsp_open_file);
+
+        let (ui_sender, _ui_receiver) = tokio::sync::mpsc::unbounded_channel();
+        let request = SearchAndReplaceEditingRequest::new(
+            "image.png".to_owned(),
+            whole_file_range(),
+            "".to_owned(),
+            "\u{0}PNG\r\n".to_owned(),
+            "".to_owned(),
+            llm_properties,
+            None,
+            "resize the image".to_owned(),
+            "root_request_id".to_owned(),
+            SymbolIdentifier::new_symbol("image.png"),
+            "edit_request_id".to_owned(),
+            ui_sender,
+            None,
+            "".to_owned(),
+            None,
+            vec![],
+            vec![],
+            false,
+            "session_id".to_owned(),
+            "exchange_id".to_owned(),
+            None,
+            vec![],
+            tokio_util::sync::CancellationToken::new(),
+            false,
+            DEFAULT_CONTEXT_WINDOW_BUDGET,
+        );
+
+        let output = editing_tool
+            .invoke(ToolInput::SearchAndReplaceEditing(request))
+            .await;
+        assert!(matches!(
+            output,
+            Err(ToolError::BinaryFileNotSupported(fs_file_path)) if fs_file_path == "image.png"
+        ));
+    }
+
     /// TODO(skcd): Broken test here to debug multiple search and replace blocks being
     /// part of the same edit
     #[tokio::test]
@@ -1589,7 +2470,7 @@ mod tests {
 
         let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
         let mut search_and_replace_accumulator =
-            SearchAndReplaceAccumulator::new(input_data.to_owned(), 0, sender);
+            SearchAndReplaceAccumulator::new(input_data.to_owned(), 0, whole_file_range(), sender);
         search_and_replace_accumulator
             .add_delta(edits.to_owned())
             .await;
@@ -1828,7 +2709,7 @@ impl SymbolToEdit {
 ```"#;
         let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
         let mut search_and_replace_accumulator =
-            SearchAndReplaceAccumulator::new(original_code.to_owned(), 0, sender);
+            SearchAndReplaceAccumulator::new(original_code.to_owned(), 0, whole_file_range(), sender);
         search_and_replace_accumulator
             .add_delta(edits.to_owned())
             .await;
@@ -1917,7 +2798,7 @@ blahblah2
 ```"#;
         let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
         let mut search_and_replace_accumulator =
-            SearchAndReplaceAccumulator::new(code.to_owned(), 0, sender);
+            SearchAndReplaceAccumulator::new(code.to_owned(), 0, whole_file_range(), sender);
         search_and_replace_accumulator
             .add_delta(edits.to_owned())
             .await;
@@ -1951,7 +2832,7 @@ fn add_numbers(a: i32, b: i32) -> i32 {
 ```"#;
         let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
         let mut search_and_replace_accumulator =
-            SearchAndReplaceAccumulator::new(code.to_owned(), 0, sender);
+            SearchAndReplaceAccumulator::new(code.to_owned(), 0, whole_file_range(), sender);
         search_and_replace_accumulator
             .add_delta(edits.to_owned())
             .await;
@@ -1968,4 +2849,204 @@ fn add_numbers(a: i32, b: i32) -> i32 {
 }"#
         );
     }
+
+    #[tokio::test]
+    async fn test_incremental_search_handles_large_buffer_quickly() {
+        // 20k lines, each unique, so every SEARCH block match is a distinct
+        // lookup into the line-hash index rather than a linear rescan.
+        let code = (0..20_000)
+            .map(|line_number| format!("line_{}", line_number))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let mut search_and_replace_accumulator =
+            SearchAndReplaceAccumulator::new(code, 0, whole_file_range(), sender);
+
+        let started = std::time::Instant::now();
+        for block_start in (0..20_000).step_by(200).take(100) {
+            let search_block = format!("line_{}", block_start);
+            let range = search_and_replace_accumulator.get_range_for_search_block(&search_block);
+            assert!(range.is_some());
+        }
+        assert!(
+            started.elapsed() < std::time::Duration::from_secs(1),
+            "matching 100 blocks against a 20k-line buffer should stay well under a second"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_repeated_search_block_prefers_match_after_previous() {
+        let code = "duplicate\nmiddle\nduplicate\nend".to_owned();
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        // Scope the symbol range to just the first occurrence, so the very
+        // first (no prior edit to be proximate to) ambiguous match resolves
+        // via containment instead of being reported as ambiguous.
+        let symbol_range = Range::new(Position::new(0, 0, 0), Position::new(0, 0, 0));
+        let mut search_and_replace_accumulator =
+            SearchAndReplaceAccumulator::new(code, 0, symbol_range, sender);
+
+        let first_match = search_and_replace_accumulator
+            .get_range_for_search_block("duplicate")
+            .expect("first duplicate to match via symbol range containment");
+        assert_eq!(first_match.start_line(), 0);
+        assert_eq!(
+            search_and_replace_accumulator.ambiguity_resolutions[0].resolution(),
+            &AmbiguityResolutionKind::SymbolRangeContainment(0)
+        );
+
+        // Once we've applied an edit, the next ambiguous match prefers the
+        // occurrence after it instead of re-consulting the symbol range.
+        let second_match = search_and_replace_accumulator
+            .get_range_for_search_block("duplicate")
+            .expect("second duplicate to match after the first via proximity");
+        assert_eq!(second_match.start_line(), 2);
+        assert_eq!(
+            search_and_replace_accumulator.ambiguity_resolutions[1].resolution(),
+            &AmbiguityResolutionKind::ProximityToPreviousEdit(2)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ambiguous_search_block_without_heuristic_match_is_reported_not_guessed() {
+        let code = "duplicate\nmiddle\nduplicate\nend".to_owned();
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        // A symbol range which contains neither occurrence, and no previous
+        // edit to be proximate to: neither heuristic can single out one
+        // candidate, so we must report the ambiguity rather than guess.
+        let symbol_range = Range::new(Position::new(10, 0, 0), Position::new(10, 0, 0));
+        let mut search_and_replace_accumulator =
+            SearchAndReplaceAccumulator::new(code, 0, symbol_range, sender);
+
+        let range = search_and_replace_accumulator.get_range_for_search_block("duplicate");
+        assert!(range.is_none());
+
+        let resolution = &search_and_replace_accumulator.ambiguity_resolutions[0];
+        assert_eq!(resolution.resolution(), &AmbiguityResolutionKind::Unresolved);
+        assert_eq!(resolution.candidate_start_lines(), &[0, 2]);
+    }
+
+    #[test]
+    fn test_language_for_fs_file_path() {
+        assert_eq!(super::language_for_fs_file_path("src/main.rs"), "rust");
+        assert_eq!(super::language_for_fs_file_path("src/index.ts"), "typescript");
+        assert_eq!(super::language_for_fs_file_path("src/App.tsx"), "typescript");
+        assert_eq!(super::language_for_fs_file_path("src/index.js"), "javascript");
+        assert_eq!(super::language_for_fs_file_path("mathutil/factorial.go"), "go");
+        assert_eq!(super::language_for_fs_file_path("app.py"), "python");
+        // an unknown extension falls back to the generic python example
+        assert_eq!(super::language_for_fs_file_path("README.md"), "python");
+    }
+
+    async fn editing_tool_for_example_messages() -> SearchAndReplaceEditing {
+        let llm_broker = LLMBroker::new(LLMBrokerConfiguration::new(std::env::temp_dir()))
+            .await
+            .expect("llm broker should initialise against a scratch data dir");
+        let llm_properties = LLMProperties::new(
+            LLMType::Gpt4O,
+            LLMProvider::OpenAI,
+            LLMProviderAPIKeys::OpenAI(OpenAIProvider::new("".to_owned())),
+        );
+        let lsp_open_file: Arc<Box<dyn Tool + Send + Sync>> = Arc::new(Box::new(CacheFileOutput {
+            content: "".to_owned(),
+        }));
+        SearchAndReplaceEditing::new(Arc::new(llm_broker), llm_properties, lsp_open_file)
+    }
+
+    #[tokio::test]
+    async fn test_example_messages_are_language_specific() {
+        let editing_tool = editing_tool_for_example_messages().await;
+
+        let rust_examples = editing_tool.example_messages("rust");
+        let rust_text = rust_examples
+            .iter()
+            .map(|message| message.content().to_owned())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(rust_text.contains("```rust"));
+        assert!(!rust_text.contains("```python"));
+
+        let typescript_examples = editing_tool.example_messages("typescript");
+        let typescript_text = typescript_examples
+            .iter()
+            .map(|message| message.content().to_owned())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(typescript_text.contains("```typescript"));
+        assert!(!typescript_text.contains("```python"));
+
+        // an unrecognised language falls back to the generic python example
+        let fallback_examples = editing_tool.example_messages("cobol");
+        let fallback_text = fallback_examples
+            .iter()
+            .map(|message| message.content().to_owned())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(fallback_text.contains("```python"));
+    }
+
+    #[test]
+    fn test_extract_thinking_block_pulls_out_the_content() {
+        let stream_answer = "<thinking>\nI should edit the function\n</thinking>\n<<<<<<< SEARCH";
+        assert_eq!(
+            super::extract_thinking_block(stream_answer),
+            Some("I should edit the function".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_extract_thinking_block_is_none_until_the_closing_tag_arrives() {
+        let stream_answer = "<thinking>\nI should edit the function";
+        assert_eq!(super::extract_thinking_block(stream_answer), None);
+    }
+
+    #[test]
+    fn test_extract_thinking_block_is_none_without_a_thinking_tag() {
+        let stream_answer = "<<<<<<< SEARCH\nfoo\n=======\nbar\n>>>>>>> REPLACE";
+        assert_eq!(super::extract_thinking_block(stream_answer), None);
+    }
+
+    #[tokio::test]
+    async fn test_thinking_block_quoting_search_marker_does_not_trigger_a_spurious_edit() {
+        let input_data = r#"fn greet() {
+    println!("hello");
+}"#;
+        let edits = r#"<thinking>
+The format uses a marker like this to start a block:
+<<<<<<< SEARCH
+but I should not act on this quoted example.
+</thinking>
+"#;
+
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let mut search_and_replace_accumulator =
+            SearchAndReplaceAccumulator::new(input_data.to_owned(), 0, whole_file_range(), sender);
+        search_and_replace_accumulator
+            .add_delta(edits.to_owned())
+            .await;
+
+        // the quoted marker inside the thinking block must not be mistaken
+        // for a real SEARCH block, so the file contents are untouched
+        assert_eq!(search_and_replace_accumulator.code_lines.join("\n"), input_data);
+        assert_eq!(
+            search_and_replace_accumulator.thinking,
+            "The format uses a marker like this to start a block:\n<<<<<<< SEARCH\nbut I should not act on this quoted example."
+        );
+    }
+
+    #[test]
+    fn context_window_limits_weights_above_context_more_than_below() {
+        let (above_limit, below_limit) = context_window_limits(DEFAULT_CONTEXT_WINDOW_BUDGET, 40);
+        assert!(above_limit > below_limit);
+        assert!(above_limit > 0);
+        assert!(below_limit > 0);
+    }
+
+    #[test]
+    fn context_window_limits_falls_back_when_average_line_chars_is_zero() {
+        let (above_limit, below_limit) = context_window_limits(DEFAULT_CONTEXT_WINDOW_BUDGET, 0);
+        let (above_limit_with_fallback, below_limit_with_fallback) =
+            context_window_limits(DEFAULT_CONTEXT_WINDOW_BUDGET, 40);
+        assert_eq!(above_limit, above_limit_with_fallback);
+        assert_eq!(below_limit, below_limit_with_fallback);
+    }
 }
@@ -22,6 +22,22 @@ use crate::{
 
 use super::models::broker::CodeEditBroker;
 
+/// How many lines above the selection we show the llm by default, this is
+/// used to truncate the context we show when making edits since we grab the
+/// correct context required almost always via our definitions.
+pub const DEFAULT_ABOVE_CONTEXT_LIMIT: usize = 200;
+
+/// How many lines below the selection we show the llm by default, kept
+/// smaller than the above limit since trailing context is usually less
+/// relevant than the code leading up to the edit.
+pub const DEFAULT_BELOW_CONTEXT_LIMIT: usize = 66;
+
+/// Above/below context limits for models with a small context window, where
+/// we need to be a lot more conservative about how much surrounding code we
+/// show alongside the edit itself.
+pub const SMALL_CONTEXT_ABOVE_LIMIT: usize = 100;
+pub const SMALL_CONTEXT_BELOW_LIMIT: usize = 33;
+
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct CodeEditingPartialRequest {
     fs_file_path: String,
@@ -91,6 +107,12 @@ pub struct CodeEdit {
     session_id: String,
     // The exchange id to which the edit belongs
     exchange_id: String,
+    // How many lines of code above the selection we are allowed to show the
+    // llm, chosen by the caller based on the model's context window
+    above_context_limit: usize,
+    // How many lines of code below the selection we are allowed to show the
+    // llm, chosen by the caller based on the model's context window
+    below_context_limit: usize,
 }
 
 impl CodeEdit {
@@ -117,6 +139,8 @@ impl CodeEdit {
         user_provided_context: Option<String>,
         session_id: String,
         exchange_id: String,
+        above_context_limit: usize,
+        below_context_limit: usize,
     ) -> Self {
         Self {
             code_above,
@@ -141,6 +165,8 @@ impl CodeEdit {
             user_provided_context,
             session_id,
             exchange_id,
+            above_context_limit,
+            below_context_limit,
         }
     }
 }
@@ -237,6 +263,14 @@ impl CodeEdit {
             .map(|above_context| above_context.as_str())
     }
 
+    pub fn above_context_limit(&self) -> usize {
+        self.above_context_limit
+    }
+
+    pub fn below_context_limit(&self) -> usize {
+        self.below_context_limit
+    }
+
     pub fn below_context(&self) -> Option<&str> {
         self.code_below
             .as_ref()
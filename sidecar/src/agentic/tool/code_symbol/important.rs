@@ -5,8 +5,10 @@
 //! These are like state-machines which are holding memory and moving forward and collaborating.
 
 use async_trait::async_trait;
+use futures::{stream, Stream, StreamExt};
 use std::{
     collections::{HashMap, HashSet},
+    pin::Pin,
     sync::Arc,
 };
 
@@ -165,11 +167,19 @@ impl Tool for CodeSymbolImportantBroker {
                     }
                     either::Right(context) => {
                         if let Some(implementation) = self.llms.get(context.model()) {
-                            return implementation
-                                .context_wide_search(context) // this needs message properties
+                            let concurrency_limit = context.concurrency_limit();
+                            let chunks = context.chunked_by_user_context();
+                            let responses = stream::iter(chunks)
+                                .map(|chunk| implementation.context_wide_search(chunk))
+                                .buffer_unordered(concurrency_limit.max(1))
+                                .collect::<Vec<_>>()
                                 .await
-                                .map(|response| ToolOutput::important_symbols(response))
-                                .map_err(|e| ToolError::CodeSymbolError(e));
+                                .into_iter()
+                                .collect::<Result<Vec<_>, _>>()
+                                .map_err(|e| ToolError::CodeSymbolError(e))?;
+                            return Ok(ToolOutput::important_symbols(
+                                CodeSymbolImportantResponse::merge_and_dedupe(responses),
+                            ));
                         }
                     }
                 };
@@ -200,8 +210,25 @@ pub struct CodeSymbolImportantWideSearch {
     recent_edits: String,
     lsp_diagnostics: String,
     message_properties: SymbolEventMessageProperties,
+    // How many files worth of user context we pack into a single wide-search
+    // prompt before splitting off another chunk
+    chunk_size: usize,
+    // How many chunked wide-search prompts we are willing to have in flight
+    // against the llm at the same time
+    concurrency_limit: usize,
 }
 
+/// Chunking a wide search into this many files per prompt by default keeps
+/// individual prompts well within context limits for large user contexts
+/// while still being a no-op (a single chunk) for the common small-context case.
+const DEFAULT_WIDE_SEARCH_CHUNK_SIZE: usize = 10;
+/// Default number of chunked wide-search prompts we run concurrently.
+const DEFAULT_WIDE_SEARCH_CONCURRENCY_LIMIT: usize = 3;
+/// Default cap on how many symbols `get_important_symbols` is allowed to
+/// select when the caller has no better estimate from the model's context
+/// window.
+pub const DEFAULT_MAX_IMPORTANT_SYMBOLS: usize = 10;
+
 impl CodeSymbolImportantWideSearch {
     pub fn new(
         user_context: UserContext,
@@ -227,9 +254,54 @@ impl CodeSymbolImportantWideSearch {
             message_properties,
             lsp_diagnostics,
             recent_edits,
+            chunk_size: DEFAULT_WIDE_SEARCH_CHUNK_SIZE,
+            concurrency_limit: DEFAULT_WIDE_SEARCH_CONCURRENCY_LIMIT,
         }
     }
 
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    pub fn with_concurrency_limit(mut self, concurrency_limit: usize) -> Self {
+        self.concurrency_limit = concurrency_limit;
+        self
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    pub fn concurrency_limit(&self) -> usize {
+        self.concurrency_limit
+    }
+
+    /// Splits this request into one request per chunk of the user context's
+    /// files, so a caller can run them concurrently instead of packing the
+    /// whole context into a single prompt.
+    pub fn chunked_by_user_context(&self) -> Vec<CodeSymbolImportantWideSearch> {
+        self.user_context
+            .chunk_by_files(self.chunk_size)
+            .into_iter()
+            .map(|user_context_chunk| CodeSymbolImportantWideSearch {
+                user_context: user_context_chunk,
+                user_query: self.user_query.clone(),
+                llm_type: self.llm_type.clone(),
+                llm_provider: self.llm_provider.clone(),
+                api_key: self.api_key.clone(),
+                file_extension_filters: self.file_extension_filters.clone(),
+                root_request_id: self.root_request_id.clone(),
+                symbol_outline: self.symbol_outline.clone(),
+                recent_edits: self.recent_edits.clone(),
+                lsp_diagnostics: self.lsp_diagnostics.clone(),
+                message_properties: self.message_properties.clone(),
+                chunk_size: self.chunk_size,
+                concurrency_limit: self.concurrency_limit,
+            })
+            .collect()
+    }
+
     pub fn lsp_diagnostics(&self) -> &str {
         &self.lsp_diagnostics
     }
@@ -756,6 +828,10 @@ pub struct CodeSymbolImportantRequest {
     // this at the start will be the user query
     query: String,
     root_request_id: String,
+    // caps how many symbols the model is allowed to select, so a file with
+    // hundreds of functions can't flood the downstream editing loop with
+    // dozens of "important" symbols
+    max_symbols: usize,
 }
 
 impl CodeSymbolImportantRequest {
@@ -771,6 +847,7 @@ impl CodeSymbolImportantRequest {
         language: String,
         query: String,
         root_request_id: String,
+        max_symbols: usize,
     ) -> Self {
         Self {
             symbol_identifier,
@@ -784,6 +861,7 @@ impl CodeSymbolImportantRequest {
             query,
             language,
             root_request_id,
+            max_symbols,
         }
     }
 
@@ -826,6 +904,10 @@ impl CodeSymbolImportantRequest {
     pub fn provider(&self) -> &LLMProvider {
         &self.llm_provider
     }
+
+    pub fn max_symbols(&self) -> usize {
+        self.max_symbols
+    }
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -833,6 +915,11 @@ pub struct CodeSymbolWithThinking {
     code_symbol: String,
     thinking: String,
     file_path: String,
+    // Relevance score parsed from the model output, when the model emits
+    // one. `None` when the model didn't score this symbol, in which case
+    // callers should fall back to its position in `ordered_symbols`.
+    #[serde(default)]
+    score: Option<f32>,
 }
 
 impl CodeSymbolWithThinking {
@@ -841,6 +928,21 @@ impl CodeSymbolWithThinking {
             code_symbol,
             thinking,
             file_path,
+            score: None,
+        }
+    }
+
+    pub fn new_with_score(
+        code_symbol: String,
+        thinking: String,
+        file_path: String,
+        score: Option<f32>,
+    ) -> Self {
+        Self {
+            code_symbol,
+            thinking,
+            file_path,
+            score,
         }
     }
 
@@ -849,6 +951,7 @@ impl CodeSymbolWithThinking {
             code_symbol: "".to_owned(),
             thinking: "".to_owned(),
             file_path: path.to_owned(),
+            score: None,
         }
     }
 
@@ -869,6 +972,10 @@ impl CodeSymbolWithThinking {
         &self.file_path
     }
 
+    pub fn score(&self) -> Option<f32> {
+        self.score
+    }
+
     /// If the symbol name consists of a.b.c kind of format we want to grab
     /// just the a instead of the whole string since we always work on the
     /// top level symbol
@@ -890,6 +997,7 @@ impl CodeSymbolWithThinking {
                         code_symbol: object_qualifier.to_string(),
                         thinking: self.thinking,
                         file_path: self.file_path,
+                        score: self.score,
                     }
                 } else {
                     let mut code_symbol_parts = self.code_symbol.split(".").collect::<Vec<_>>();
@@ -900,6 +1008,7 @@ impl CodeSymbolWithThinking {
                             code_symbol: code_symbol_parts.remove(0).to_owned(),
                             thinking: self.thinking,
                             file_path: self.file_path,
+                            score: self.score,
                         }
                     }
                 }
@@ -924,6 +1033,7 @@ impl CodeSymbolWithThinking {
                         code_symbol: object_qualifier.to_string(),
                         thinking: self.thinking,
                         file_path: self.file_path,
+                        score: self.score,
                     }
                 } else {
                     let mut code_symbol_parts = self.code_symbol.split("::").collect::<Vec<_>>();
@@ -934,6 +1044,7 @@ impl CodeSymbolWithThinking {
                             code_symbol: code_symbol_parts.remove(0).to_owned(),
                             thinking: self.thinking,
                             file_path: self.file_path,
+                            score: self.score,
                         }
                     }
                 }
@@ -1090,6 +1201,7 @@ impl From<FileImportantResponse> for CodeSymbolImportantResponse {
                 code_symbol: String::from(""),
                 thinking: String::from(""),
                 file_path: file_path.clone(),
+                score: None,
             })
             .collect();
 
@@ -1170,6 +1282,55 @@ impl CodeSymbolImportantResponse {
         self.ordered_symbols.as_slice()
     }
 
+    /// Enforces `max_symbols` even when the model ignores the limit we put
+    /// in the prompt, keeping the earliest (most important) entries of each
+    /// list.
+    pub fn truncate_to(mut self, max_symbols: usize) -> Self {
+        self.symbols.truncate(max_symbols);
+        self.ordered_symbols.truncate(max_symbols);
+        self
+    }
+
+    /// A symbol's own `score` when the model emitted one, otherwise a
+    /// positional score derived from where it sits in `ordered_symbols`
+    /// (earlier entries rank higher), so `top_k` still has something to sort
+    /// by for models that never emit explicit scores.
+    fn effective_score(&self, symbol: &CodeSymbolWithThinking) -> f32 {
+        if let Some(score) = symbol.score() {
+            return score;
+        }
+        let position = self
+            .ordered_symbols
+            .iter()
+            .position(|ordered_symbol| {
+                ordered_symbol.file_path() == symbol.file_path()
+                    && ordered_symbol.code_symbol() == symbol.code_symbol()
+            })
+            .unwrap_or(self.ordered_symbols.len());
+        1.0 / (position as f32 + 1.0)
+    }
+
+    /// The `k` most relevant symbols, ranked by `score` (falling back to
+    /// position in `ordered_symbols` for symbols the model didn't score), so
+    /// callers can take just the most relevant symbols when context is tight.
+    pub fn top_k(&self, k: usize) -> Vec<&CodeSymbolWithThinking> {
+        let mut scored = self
+            .symbols
+            .iter()
+            .map(|symbol| (self.effective_score(symbol), symbol))
+            .collect::<Vec<_>>();
+        scored.sort_by(|(score_a, _), (score_b, _)| {
+            score_b
+                .partial_cmp(score_a)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        scored
+            .into_iter()
+            .take(k)
+            .map(|(_, symbol)| symbol)
+            .collect()
+    }
+
     pub fn ordered_symbols_to_plan(&self) -> String {
         // We try to create a shallow plan here for our agents using the initial
         // plan, this will help them stay in place and follow the initial logic
@@ -1231,6 +1392,26 @@ impl CodeSymbolImportantResponse {
         }
     }
 
+    /// Merges responses from several chunked wide-search prompts over the
+    /// same user context, deduping `symbols` by `(code_symbol, file_path)`
+    /// so files which show up in more than one chunk (or get suggested by
+    /// more than one prompt) are only reported once.
+    pub fn merge_and_dedupe(responses: Vec<CodeSymbolImportantResponse>) -> Self {
+        let merged = Self::merge_functional(responses);
+        let mut seen = HashSet::new();
+        let symbols = merged
+            .symbols
+            .into_iter()
+            .filter(|symbol| {
+                seen.insert((symbol.code_symbol.clone(), symbol.file_path.clone()))
+            })
+            .collect();
+        Self {
+            symbols,
+            ordered_symbols: merged.ordered_symbols,
+        }
+    }
+
     pub fn merge_functional(response: Vec<CodeSymbolImportantResponse>) -> Self {
         let symbols = response
             .iter()
@@ -1260,6 +1441,24 @@ pub trait CodeSymbolImportant {
         context_wide_search: CodeSymbolImportantWideSearch,
     ) -> Result<CodeSymbolImportantResponse, CodeSymbolError>;
 
+    /// Same LLM call as `context_wide_search`, except each `<step_list>`
+    /// entry is handed to the caller as soon as it is parsed off the
+    /// streaming response instead of only once the full response has
+    /// buffered. Useful when the agent wants to start looking at the first
+    /// symbols while the LLM is still generating the rest of the list.
+    ///
+    /// Note there is no failover/retry here (unlike `context_wide_search`):
+    /// once we have started yielding symbols to the caller a retry would
+    /// mean re-emitting symbols it has already seen, so a stream failure is
+    /// simply surfaced as the terminal `Err` item on the stream.
+    async fn context_wide_search_stream(
+        &self,
+        context_wide_search: CodeSymbolImportantWideSearch,
+    ) -> Result<
+        Pin<Box<dyn Stream<Item = Result<CodeSymbolWithSteps, CodeSymbolError>> + Send>>,
+        CodeSymbolError,
+    >;
+
     async fn gather_utility_symbols(
         &self,
         utility_symbol_request: CodeSymbolUtilityRequest,
@@ -1304,6 +1503,7 @@ mod tests {
     use crate::{
         agentic::tool::code_symbol::important::CodeSymbolWithSteps,
         chunking::languages::TSLanguageParsing,
+        user_context::types::UserContext,
     };
 
     use super::{CodeSymbolImportantResponse, CodeSymbolWithThinking};
@@ -1347,4 +1547,158 @@ mod tests {
             "CSAuthenticationService"
         );
     }
+
+    #[test]
+    fn user_context_chunks_by_files() {
+        let mut user_context = UserContext::default();
+        for idx in 0..5 {
+            user_context = user_context.update_file_content_map(
+                format!("/tmp/file_{idx}.rs"),
+                "// content".to_owned(),
+                "rust".to_owned(),
+            );
+        }
+        let chunks = user_context.chunk_by_files(2);
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].file_content_map.len(), 2);
+        assert_eq!(chunks[1].file_content_map.len(), 2);
+        assert_eq!(chunks[2].file_content_map.len(), 1);
+    }
+
+    #[test]
+    fn merge_and_dedupe_drops_duplicate_symbols_across_chunks() {
+        let first_chunk_response = CodeSymbolImportantResponse::new(
+            vec![
+                CodeSymbolWithThinking::new(
+                    "Foo::bar".to_owned(),
+                    "found in first chunk".to_owned(),
+                    "/tmp/foo.rs".to_owned(),
+                ),
+                CodeSymbolWithThinking::new(
+                    "Foo::baz".to_owned(),
+                    "found in first chunk".to_owned(),
+                    "/tmp/foo.rs".to_owned(),
+                ),
+            ],
+            vec![],
+        );
+        let second_chunk_response = CodeSymbolImportantResponse::new(
+            vec![
+                // duplicate of the one already surfaced by the first chunk
+                CodeSymbolWithThinking::new(
+                    "Foo::bar".to_owned(),
+                    "found again in second chunk".to_owned(),
+                    "/tmp/foo.rs".to_owned(),
+                ),
+                CodeSymbolWithThinking::new(
+                    "Quux::new".to_owned(),
+                    "found in second chunk".to_owned(),
+                    "/tmp/quux.rs".to_owned(),
+                ),
+            ],
+            vec![],
+        );
+        let merged = CodeSymbolImportantResponse::merge_and_dedupe(vec![
+            first_chunk_response,
+            second_chunk_response,
+        ]);
+        assert_eq!(merged.symbols().len(), 3);
+        assert!(merged
+            .symbols()
+            .iter()
+            .any(|symbol| symbol.code_symbol() == "Foo::bar"
+                && symbol.thinking() == "found in first chunk"));
+        assert!(merged
+            .symbols()
+            .iter()
+            .any(|symbol| symbol.code_symbol() == "Quux::new"));
+    }
+
+    #[test]
+    fn top_k_returns_the_highest_scored_symbols() {
+        let response = CodeSymbolImportantResponse::new(
+            vec![
+                CodeSymbolWithThinking::new_with_score(
+                    "Foo::low".to_owned(),
+                    "".to_owned(),
+                    "/tmp/foo.rs".to_owned(),
+                    Some(0.1),
+                ),
+                CodeSymbolWithThinking::new_with_score(
+                    "Foo::high".to_owned(),
+                    "".to_owned(),
+                    "/tmp/foo.rs".to_owned(),
+                    Some(0.9),
+                ),
+                CodeSymbolWithThinking::new_with_score(
+                    "Foo::mid".to_owned(),
+                    "".to_owned(),
+                    "/tmp/foo.rs".to_owned(),
+                    Some(0.5),
+                ),
+                CodeSymbolWithThinking::new_with_score(
+                    "Foo::lowest".to_owned(),
+                    "".to_owned(),
+                    "/tmp/foo.rs".to_owned(),
+                    Some(0.0),
+                ),
+            ],
+            vec![],
+        );
+        let top_symbols = response
+            .top_k(3)
+            .into_iter()
+            .map(|symbol| symbol.code_symbol())
+            .collect::<Vec<_>>();
+        assert_eq!(top_symbols, vec!["Foo::high", "Foo::mid", "Foo::low"]);
+    }
+
+    #[test]
+    fn top_k_falls_back_to_ordered_symbols_position_when_unscored() {
+        let response = CodeSymbolImportantResponse::new(
+            vec![
+                CodeSymbolWithThinking::new(
+                    "Foo::third".to_owned(),
+                    "".to_owned(),
+                    "/tmp/foo.rs".to_owned(),
+                ),
+                CodeSymbolWithThinking::new(
+                    "Foo::first".to_owned(),
+                    "".to_owned(),
+                    "/tmp/foo.rs".to_owned(),
+                ),
+                CodeSymbolWithThinking::new(
+                    "Foo::second".to_owned(),
+                    "".to_owned(),
+                    "/tmp/foo.rs".to_owned(),
+                ),
+            ],
+            vec![
+                CodeSymbolWithSteps::new(
+                    "Foo::first".to_owned(),
+                    vec![],
+                    false,
+                    "/tmp/foo.rs".to_owned(),
+                ),
+                CodeSymbolWithSteps::new(
+                    "Foo::second".to_owned(),
+                    vec![],
+                    false,
+                    "/tmp/foo.rs".to_owned(),
+                ),
+                CodeSymbolWithSteps::new(
+                    "Foo::third".to_owned(),
+                    vec![],
+                    false,
+                    "/tmp/foo.rs".to_owned(),
+                ),
+            ],
+        );
+        let top_symbols = response
+            .top_k(2)
+            .into_iter()
+            .map(|symbol| symbol.code_symbol())
+            .collect::<Vec<_>>();
+        assert_eq!(top_symbols, vec!["Foo::first", "Foo::second"]);
+    }
 }
@@ -5,6 +5,7 @@ use llm_client::{
     clients::types::{LLMClientCompletionRequest, LLMClientMessage},
 };
 use serde_xml_rs::from_str;
+use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Instant;
 use tracing::info;
@@ -36,6 +37,10 @@ use crate::agentic::{
 pub struct AnthropicCodeSymbolImportant {
     llm_client: Arc<LLMBroker>,
     fail_over_llm: LLMProperties,
+    // prepended to the symbol-importance system prompt so callers can inject
+    // project-specific conventions (error handling style, preferred crates,
+    // and so on) which the ranking should respect
+    system_prompt_prefix: Option<String>,
 }
 
 impl AnthropicCodeSymbolImportant {
@@ -43,9 +48,15 @@ impl AnthropicCodeSymbolImportant {
         Self {
             llm_client,
             fail_over_llm,
+            system_prompt_prefix: None,
         }
     }
 
+    pub fn with_system_prompt_prefix(mut self, prefix: String) -> Self {
+        self.system_prompt_prefix = Some(prefix);
+        self
+    }
+
     fn parse_code_edit_reply(response: &str) -> Result<String, CodeSymbolError> {
         let lines = response
             .lines()
@@ -4721,7 +4732,8 @@ Implement the GrokFillInMiddleFormatter following the similar pattern in `CodeLl
         if code_symbol_important_request.symbol_identifier().is_some() {
             todo!("we need to figure it out")
         } else {
-            format!(
+            let max_symbols = code_symbol_important_request.max_symbols();
+            let system_message = format!(
                 r#"You are responsible context to plan for a change requested in <user_query>. Your job is to select the most important symbols that you must explore in order to gather necessary context to execute the change. Do not suggest the change itself.
 
 - You are working in an editor so you can go-to-definition on certain symbols, but you can only do that for code which is present in <code_selection> section.
@@ -4730,6 +4742,7 @@ Implement the GrokFillInMiddleFormatter following the similar pattern in `CodeLl
 - The code which is already present on the file will be also visible to you when making changes, so do not worry about the symbols which you can already see.
 - Make sure to select code symbols for which you will need to look deeper since you might end up using a function on some attribute from that symbol.
 - Strictly follow the reply format which is mentioned to you below, your reply should always start with <reply> tag and end with </reply> tag
+- Select at most {max_symbols} symbols, focus on the ones you are most confident you will need.
 
 Let's focus on the step which is, gathering all the required symbol definitions and types.
 
@@ -4821,7 +4834,11 @@ Other LLM's are implementing FillInMiddleFormatter trait, grok will also require
 </symbol>
 </symbol_list>
 </reply>"#
-            )
+            );
+            match &self.system_prompt_prefix {
+                Some(prefix) => format!("{prefix}\n\n{system_message}"),
+                None => system_message,
+            }
         }
     }
 
@@ -5334,7 +5351,7 @@ impl CodeSymbolImportant for AnthropicCodeSymbolImportant {
                     if let Ok(parsed_response) = Reply::parse_response(&response)
                         .map(|reply| reply.to_code_symbol_important_response())
                     {
-                        return Ok(parsed_response);
+                        return Ok(parsed_response.truncate_to(code_symbols.max_symbols()));
                     } else {
                         retries = retries + 1;
                     }
@@ -5492,6 +5509,90 @@ impl CodeSymbolImportant for AnthropicCodeSymbolImportant {
         }
     }
 
+    async fn context_wide_search_stream(
+        &self,
+        code_symbols: CodeSymbolImportantWideSearch,
+    ) -> Result<
+        Pin<Box<dyn futures::Stream<Item = Result<CodeSymbolWithSteps, CodeSymbolError>> + Send>>,
+        CodeSymbolError,
+    > {
+        let api_key = code_symbols.api_key();
+        let provider = code_symbols.llm_provider();
+        let model = code_symbols.model().clone();
+        let root_request_id = code_symbols.root_request_id().to_owned();
+        let system_message = LLMClientMessage::system(self.system_message_context_wide());
+        let user_message = LLMClientMessage::user(
+            self.user_message_for_codebase_wide_search(code_symbols)
+                .await?,
+        );
+        let messages =
+            LLMClientCompletionRequest::new(model, vec![system_message, user_message], 0.0, None);
+
+        let (llm_delta_sender, llm_delta_receiver) = tokio::sync::mpsc::unbounded_channel();
+        let (symbol_sender, symbol_receiver) = tokio::sync::mpsc::unbounded_channel();
+
+        let cloned_llm_client = self.llm_client.clone();
+        let cloned_symbol_sender = symbol_sender.clone();
+        tokio::spawn(async move {
+            let response = cloned_llm_client
+                .stream_completion(
+                    api_key,
+                    messages,
+                    provider,
+                    vec![
+                        ("event_type".to_owned(), "context_wide_search".to_owned()),
+                        ("root_id".to_owned(), root_request_id.clone()),
+                    ]
+                    .into_iter()
+                    .collect(),
+                    llm_delta_sender,
+                )
+                .await
+                .map_err(|e| CodeSymbolError::LLMClientError(e));
+
+            // We only surface an error here: a fully successful response has
+            // already had every symbol it contains streamed out below as
+            // soon as each `<step_list>` finished parsing, so there is
+            // nothing left to send on the happy path.
+            if let Err(e) = response {
+                let _ = cloned_symbol_sender.send(Err(e));
+            }
+        });
+
+        // Consume the raw delta stream and hand the caller each symbol the
+        // moment its `<step_list>` entry is fully parsed, the same way
+        // `context_wide_search` streams `step_list` items for the UI thinking
+        // events, except here the parsed symbol itself is the payload.
+        tokio::spawn(async move {
+            let mut delta_stream =
+                tokio_stream::wrappers::UnboundedReceiverStream::new(llm_delta_receiver);
+            let mut xml_processor = XmlProcessor::new();
+            while let Some(stream_msg) = delta_stream.next().await {
+                if let Some(delta) = stream_msg.delta() {
+                    xml_processor.append(&delta);
+
+                    let step_lists = xml_processor.extract_all_tag_contents("step_list");
+                    for step_list in step_lists {
+                        let wrapped_step = XmlProcessor::wrap_xml("step_list", &step_list);
+                        if let Some(step_list_item) = StepListItem::parse_from_str(&wrapped_step) {
+                            let symbol = CodeSymbolWithSteps::new(
+                                step_list_item.name,
+                                step_list_item.step,
+                                step_list_item.new,
+                                step_list_item.file_path,
+                            );
+                            let _ = symbol_sender.send(Ok(symbol));
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(Box::pin(
+            tokio_stream::wrappers::UnboundedReceiverStream::new(symbol_receiver),
+        ))
+    }
+
     async fn gather_utility_symbols(
         &self,
         utility_symbol_request: CodeSymbolUtilityRequest,
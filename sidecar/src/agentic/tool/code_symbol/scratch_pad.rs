@@ -598,7 +598,7 @@ impl Tool for ScratchPadAgentBroker {
         if is_cache_warmup {
             println!("scratch_pad_agent::cache_warmup::skipping_early");
             return Ok(ToolOutput::SearchAndReplaceEditing(
-                SearchAndReplaceEditingResponse::new("".to_owned(), "".to_owned()),
+                SearchAndReplaceEditingResponse::new("".to_owned(), "".to_owned(), vec![], "".to_owned()),
             ));
         }
 
@@ -623,6 +623,7 @@ impl Tool for ScratchPadAgentBroker {
                     fs_file_path.to_owned(),
                     exchange_id.to_owned(),
                     None,
+                    None,
                 )
                 .set_apply_directly(),
             )
@@ -730,7 +731,7 @@ impl Tool for ScratchPadAgentBroker {
 
         match stream_result {
             Some(Ok(response)) => Ok(ToolOutput::SearchAndReplaceEditing(
-                SearchAndReplaceEditingResponse::new(response.to_owned(), response.to_owned()),
+                SearchAndReplaceEditingResponse::new(response.to_owned(), response.to_owned(), vec![], "".to_owned()),
             )),
             _ => Err(ToolError::MissingTool),
         }
@@ -0,0 +1,163 @@
+//! Checks an assembled prompt against its model's context window before it
+//! goes out over the wire, so callers find out about an overflow from a
+//! typed error with the sizes involved instead of the provider's opaque
+//! "prompt too long" response.
+//!
+//! Remediation is deliberately limited to what can be done generically over
+//! a `Vec<LLMClientMessage>` without knowing what a particular call site's
+//! messages mean: drop the oldest compactable (non-system) messages first,
+//! then fall back to a larger-context model from the caller's failover
+//! chain. Call sites which can do something smarter with the space they
+//! have (e.g. shrinking `code_above`/`code_below` in search-and-replace
+//! editing) should do that themselves before falling through to this guard.
+
+use llm_client::clients::types::{estimate_tokens_for_messages, LLMClientMessage, LLMType};
+
+use super::errors::ToolError;
+
+/// Result of successfully bringing a prompt back under budget: the
+/// (possibly trimmed) messages, the (possibly switched) model, and a
+/// human-readable log of what was done, in order, so a caller can surface
+/// it as a UI event.
+pub struct ContextGuardOutcome {
+    pub messages: Vec<LLMClientMessage>,
+    pub llm: LLMType,
+    pub actions_taken: Vec<String>,
+}
+
+/// Checks `messages` against `llm`'s context window, reserving
+/// `reserved_output_tokens` for the response. If the estimate already fits,
+/// returns immediately with no actions taken. Otherwise applies remediations
+/// in order until it fits or they're exhausted:
+///
+/// 1. Drop the oldest compactable message (any non-system message, oldest
+///    first) one at a time.
+/// 2. Switch to the model in `failover_llms` with the largest context
+///    window, if that is strictly larger than `llm`'s.
+///
+/// If the prompt still doesn't fit after both are exhausted, returns
+/// [`ToolError::ContextWindowExceeded`] with the sizes involved.
+pub fn ensure_within_context_window(
+    mut messages: Vec<LLMClientMessage>,
+    mut llm: LLMType,
+    reserved_output_tokens: usize,
+    failover_llms: &[LLMType],
+) -> Result<ContextGuardOutcome, ToolError> {
+    let mut actions_taken = vec![];
+
+    loop {
+        let estimated_tokens = estimate_tokens_for_messages(&messages) + reserved_output_tokens;
+        if estimated_tokens <= llm.context_window() {
+            return Ok(ContextGuardOutcome {
+                messages,
+                llm,
+                actions_taken,
+            });
+        }
+
+        if let Some(oldest_compactable_index) =
+            messages.iter().position(|message| !message.role().is_system())
+        {
+            messages.remove(oldest_compactable_index);
+            actions_taken.push(format!(
+                "dropped oldest compactable message (index {})",
+                oldest_compactable_index
+            ));
+            continue;
+        }
+
+        if let Some(bigger_llm) = failover_llms
+            .iter()
+            .filter(|candidate| candidate.context_window() > llm.context_window())
+            .max_by_key(|candidate| candidate.context_window())
+        {
+            actions_taken.push(format!(
+                "switched from {} ({} tokens) to {} ({} tokens)",
+                llm,
+                llm.context_window(),
+                bigger_llm,
+                bigger_llm.context_window()
+            ));
+            llm = bigger_llm.clone();
+            continue;
+        }
+
+        return Err(ToolError::ContextWindowExceeded {
+            estimated_tokens,
+            context_window: llm.context_window(),
+            llm,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message_of_len(role_user: bool, chars: usize) -> LLMClientMessage {
+        let content = "a".repeat(chars);
+        if role_user {
+            LLMClientMessage::user(content)
+        } else {
+            LLMClientMessage::system(content)
+        }
+    }
+
+    #[test]
+    fn fits_within_budget_takes_no_action() {
+        let messages = vec![message_of_len(false, 10), message_of_len(true, 10)];
+        let outcome =
+            ensure_within_context_window(messages, LLMType::ClaudeSonnet, 100, &[]).unwrap();
+        assert!(outcome.actions_taken.is_empty());
+        assert_eq!(outcome.messages.len(), 2);
+    }
+
+    #[test]
+    fn drops_oldest_compactable_messages_before_touching_system_message() {
+        // Gpt4 has an 8192 token context window; make the user messages
+        // alone blow way past it so the guard has to drop some.
+        let messages = vec![
+            message_of_len(false, 40),
+            message_of_len(true, 40_000),
+            message_of_len(true, 40),
+        ];
+        let outcome = ensure_within_context_window(messages, LLMType::Gpt4, 0, &[]).unwrap();
+        assert!(!outcome.actions_taken.is_empty());
+        // the system message must survive every remediation pass
+        assert!(outcome.messages.iter().any(|message| message.role().is_system()));
+    }
+
+    #[test]
+    fn switches_to_a_larger_context_model_when_dropping_messages_is_not_enough() {
+        let messages = vec![message_of_len(false, 40)];
+        let outcome = ensure_within_context_window(
+            messages,
+            LLMType::Gpt4,
+            9_000,
+            &[LLMType::ClaudeSonnet],
+        )
+        .unwrap();
+        assert_eq!(outcome.llm, LLMType::ClaudeSonnet);
+        assert!(outcome
+            .actions_taken
+            .iter()
+            .any(|action| action.contains("switched from")));
+    }
+
+    #[test]
+    fn returns_a_typed_error_with_sizes_when_no_remediation_is_enough() {
+        let messages = vec![message_of_len(false, 40)];
+        let result = ensure_within_context_window(messages, LLMType::Gpt4, 1_000_000, &[]);
+        match result {
+            Err(ToolError::ContextWindowExceeded {
+                context_window,
+                llm,
+                ..
+            }) => {
+                assert_eq!(context_window, LLMType::Gpt4.context_window());
+                assert_eq!(llm, LLMType::Gpt4);
+            }
+            other => panic!("expected ContextWindowExceeded, got {:?}", other.map(|_| ())),
+        }
+    }
+}
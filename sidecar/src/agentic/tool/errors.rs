@@ -85,4 +85,25 @@ pub enum ToolError {
 
     #[error("Readline error")]
     ReadLineError,
+
+    #[error("Cannot edit binary file: {0}")]
+    BinaryFileNotSupported(String),
+
+    #[error("Deduplicated request failed: {0}")]
+    DeduplicatedRequestFailed(String),
+
+    #[error("Inline value compilation failed: {0}")]
+    InlineValueCompilationFailed(String),
+
+    #[error("Expected tool output for {expected} but got {got}")]
+    UnexpectedOutput { expected: ToolType, got: String },
+
+    #[error(
+        "Prompt too large even after remediation: estimated {estimated_tokens} tokens against a {context_window} token context window for {llm}"
+    )]
+    ContextWindowExceeded {
+        estimated_tokens: usize,
+        context_window: usize,
+        llm: LLMType,
+    },
 }
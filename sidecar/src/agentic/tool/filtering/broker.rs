@@ -9,7 +9,10 @@ use llm_client::{
 };
 
 use crate::agentic::{
-    symbol::identifier::{LLMProperties, Snippet},
+    symbol::{
+        identifier::{LLMProperties, Snippet},
+        ui_event::UIEventWithID,
+    },
     tool::{
         errors::ToolError,
         filtering::errors::CodeToEditFilteringError,
@@ -177,6 +180,26 @@ impl CodeToEditSymbolResponse {
         &self.code_to_not_edit_list
     }
 
+    /// One `UIEventWithID` per snippet this response decided to edit, so a
+    /// caller can stream partial progress out to the editor instead of only
+    /// surfacing the filtered list once the whole response is in. Doesn't
+    /// change `code_to_edit_list`/`code_to_not_edit_list` themselves - this
+    /// is purely a side channel for progress reporting.
+    pub fn snippet_selected_events(&self, session_id: &str, exchange_id: &str) -> Vec<UIEventWithID> {
+        self.code_to_edit_list
+            .snippets()
+            .iter()
+            .map(|snippet| {
+                UIEventWithID::code_to_edit_snippet_selected(
+                    session_id.to_owned(),
+                    exchange_id.to_owned(),
+                    snippet.id(),
+                    snippet.reason_to_edit().to_owned(),
+                )
+            })
+            .collect()
+    }
+
     fn unescape_xml(s: String) -> String {
         s.replace("\"", "&quot;")
             .replace("'", "&apos;")
@@ -719,6 +742,41 @@ This is the same code as the previous entry, so there's no need to edit it again
         assert!(code_to_edit_formatter.is_ok());
     }
 
+    #[test]
+    fn test_snippet_selected_events_emits_one_event_per_edited_snippet() {
+        let response = format!(
+            r#"
+<code_to_edit_list>
+<code_to_edit>
+<id>0</id>
+<reason_to_edit>
+first snippet reason
+</reason_to_edit>
+</code_to_edit>
+<code_to_edit>
+<id>2</id>
+<reason_to_edit>
+second snippet reason
+</reason_to_edit>
+</code_to_edit>
+</code_to_edit_list>
+
+<code_to_not_edit_list>
+<code_to_not_edit>
+<id>1</id>
+<reason_to_not_edit>
+not relevant to this change
+</reason_to_not_edit>
+</code_to_not_edit>
+</code_to_not_edit_list>"#
+        )
+        .to_owned();
+        let code_to_edit_formatter = CodeToEditSymbolResponse::parse_response(&response)
+            .expect("response should parse");
+        let events = code_to_edit_formatter.snippet_selected_events("session-1", "exchange-1");
+        assert_eq!(events.len(), 2);
+    }
+
     #[test]
     fn test_code_to_probe_sub_symbol() {
         let response = r#"<code_to_probe_list>
@@ -5,6 +5,8 @@
 
 use llm_client::clients::types::LLMClientMessage;
 
+use crate::agentic::symbol::errors::SymbolError;
+
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct DiffFileContent {
     fs_file_path: String,
@@ -19,6 +21,42 @@ impl DiffFileContent {
         }
     }
 
+    /// Reads `fs_file_path`'s content as it was last committed (`git show
+    /// HEAD:path`), so diffs built from the result are always relative to
+    /// the committed baseline rather than whatever the file happened to
+    /// contain when some earlier step read it. Falls back to the file's
+    /// current on-disk content when `fs_file_path` isn't inside a git
+    /// repository (or has no committed version yet, e.g. a new file).
+    pub fn from_git_index(fs_file_path: &str) -> Result<Self, SymbolError> {
+        let parent_directory = std::path::Path::new(fs_file_path)
+            .parent()
+            .map(|parent| parent.to_owned())
+            .unwrap_or_else(|| std::path::PathBuf::from("."));
+        let file_name = std::path::Path::new(fs_file_path)
+            .file_name()
+            .and_then(|file_name| file_name.to_str())
+            .unwrap_or(fs_file_path);
+
+        let output = std::process::Command::new("git")
+            .current_dir(&parent_directory)
+            .arg("show")
+            .arg(format!("HEAD:./{}", file_name))
+            .output();
+
+        match output {
+            Ok(output) if output.status.success() => Ok(Self::new(
+                fs_file_path.to_owned(),
+                String::from_utf8_lossy(&output.stdout).into_owned(),
+            )),
+            // not a git repository, or the file has no committed version
+            // (e.g. it was just created) - fall back to whatever is on disk
+            _ => {
+                let file_content = std::fs::read_to_string(fs_file_path).map_err(SymbolError::IOError)?;
+                Ok(Self::new(fs_file_path.to_owned(), file_content))
+            }
+        }
+    }
+
     pub fn fs_file_path(&self) -> &str {
         &self.fs_file_path
     }
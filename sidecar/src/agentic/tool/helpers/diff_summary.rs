@@ -0,0 +1,133 @@
+//! Condenses large diffs down to per-file hunk headers, +/- counts and a
+//! little context from each hunk, so a big refactor's diff doesn't balloon
+//! every subsequent prompt. The full diff is never thrown away by this
+//! module, callers are expected to hold onto it separately (eg on the
+//! exchange which produced it) for the `ShowDiff` tool to fetch back later.
+
+/// Diffs at or under this many lines are shown to the agent in full, exactly
+/// as they are today.
+pub const DEFAULT_DIFF_SUMMARY_THRESHOLD_LINES: usize = 200;
+
+/// How many lines of context to keep from the start and end of each hunk
+/// when we condense it.
+const HUNK_CONTEXT_LINES: usize = 2;
+
+struct DiffHunk {
+    header: String,
+    body: Vec<String>,
+}
+
+/// One file's worth of diff, everything from a `diff --git` line up to the
+/// next one (or the end of the diff).
+struct FileDiff {
+    header: String,
+    hunks: Vec<DiffHunk>,
+}
+
+fn parse_file_diffs(diff: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = vec![];
+    for line in diff.lines() {
+        if line.starts_with("diff --git ") {
+            files.push(FileDiff {
+                header: line.to_owned(),
+                hunks: vec![],
+            });
+            continue;
+        }
+        let Some(current_file) = files.last_mut() else {
+            // diff content before we have seen a `diff --git` header, we
+            // have no file to attribute it to so it's dropped from the
+            // summary (the full diff passed in is preserved by the caller
+            // regardless)
+            continue;
+        };
+        if line.starts_with("@@ ") {
+            current_file.hunks.push(DiffHunk {
+                header: line.to_owned(),
+                body: vec![],
+            });
+            continue;
+        }
+        if let Some(current_hunk) = current_file.hunks.last_mut() {
+            current_hunk.body.push(line.to_owned());
+        }
+    }
+    files
+}
+
+fn summarize_hunk(hunk: &DiffHunk) -> String {
+    let added = hunk.body.iter().filter(|line| line.starts_with('+')).count();
+    let removed = hunk.body.iter().filter(|line| line.starts_with('-')).count();
+    let mut summary = format!("{} (+{} -{} lines)", hunk.header, added, removed);
+    if hunk.body.len() <= HUNK_CONTEXT_LINES * 2 {
+        summary.push('\n');
+        summary.push_str(&hunk.body.join("\n"));
+    } else {
+        summary.push('\n');
+        summary.push_str(&hunk.body[..HUNK_CONTEXT_LINES].join("\n"));
+        summary.push_str(&format!(
+            "\n... {} lines omitted ...\n",
+            hunk.body.len() - HUNK_CONTEXT_LINES * 2
+        ));
+        summary.push_str(&hunk.body[hunk.body.len() - HUNK_CONTEXT_LINES..].join("\n"));
+    }
+    summary
+}
+
+/// Condenses `diff` down to per-file hunk headers, +/- counts and the first
+/// and last couple of lines of each hunk, when it's longer than
+/// `threshold_lines`. Returns `None` when the diff is already short enough
+/// that condensing it wouldn't help (or we couldn't find any `diff --git`
+/// headers to key the summary off of), meaning the caller should just use
+/// the diff as-is.
+pub fn summarize_diff_if_too_large(diff: &str, threshold_lines: usize) -> Option<String> {
+    if diff.lines().count() <= threshold_lines {
+        return None;
+    }
+    let files = parse_file_diffs(diff);
+    if files.is_empty() {
+        return None;
+    }
+    Some(
+        files
+            .iter()
+            .map(|file| {
+                let hunks = file
+                    .hunks
+                    .iter()
+                    .map(summarize_hunk)
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                format!("{}\n{}", file.header, hunks)
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::summarize_diff_if_too_large;
+
+    #[test]
+    fn test_short_diff_is_not_summarized() {
+        let diff = "diff --git a/foo.rs b/foo.rs\n@@ -1,1 +1,1 @@\n-old\n+new";
+        assert_eq!(summarize_diff_if_too_large(diff, 200), None);
+    }
+
+    #[test]
+    fn test_long_diff_is_condensed_to_hunk_headers_and_counts() {
+        let mut body = vec!["diff --git a/foo.rs b/foo.rs".to_owned(), "@@ -1,50 +1,50 @@".to_owned()];
+        for i in 0..50 {
+            body.push(format!("-old line {}", i));
+            body.push(format!("+new line {}", i));
+        }
+        let diff = body.join("\n");
+
+        let summary = summarize_diff_if_too_large(&diff, 10).expect("diff should be condensed");
+        assert!(summary.contains("diff --git a/foo.rs b/foo.rs"));
+        assert!(summary.contains("@@ -1,50 +1,50 @@ (+50 -50 lines)"));
+        assert!(summary.contains("lines omitted"));
+        assert!(summary.lines().count() < diff.lines().count());
+    }
+}
@@ -1,2 +1,4 @@
 pub(crate) mod cancellation_future;
 pub(crate) mod diff_recent_changes;
+pub(crate) mod diff_summary;
+pub(crate) mod prompt_injection;
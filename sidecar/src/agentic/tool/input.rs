@@ -41,9 +41,15 @@ use super::{
     kw_search::tool::KeywordSearchQuery,
     lsp::{
         create_file::CreateFileRequest,
+        delete_file::{DeleteFileInput, DeleteFileInputPartial},
         diagnostics::LSPDiagnosticsInput,
+        duplicate_symbol::{DuplicateSymbolInput, DuplicateSymbolInputPartial},
+        extract_function::ExtractFunctionInput,
         file_diagnostics::{FileDiagnosticsInput, WorkspaceDiagnosticsPartial},
-        get_outline_nodes::OutlineNodesUsingEditorRequest,
+        get_outline_nodes::{
+            GetOutlineNodesInput, GetOutlineNodesInputPartial, OutlineNodesUsingEditorRequest,
+        },
+        get_workspace_symbols::{GetWorkspaceSymbolsInput, GetWorkspaceSymbolsInputPartial},
         go_to_previous_word::GoToPreviousWordRequest,
         gotodefintion::GoToDefinitionRequest,
         gotoimplementations::GoToImplementationRequest,
@@ -51,6 +57,8 @@ use super::{
         grep_symbol::LSPGrepSymbolInCodebaseRequest,
         inlay_hints::InlayHintsRequest,
         list_files::ListFilesInput,
+        list_open_files::ListOpenFilesInput,
+        move_file::{MoveFileInput, MoveFileInputPartial},
         open_file::{OpenFileRequest, OpenFileRequestPartial},
         quick_fix::{GetQuickFixRequest, LSPQuickFixInvocationRequest},
         search_file::{SearchFileContentInput, SearchFileContentInputPartial},
@@ -65,11 +73,19 @@ use super::{
     ref_filter::ref_filter::ReferenceFilterRequest,
     repo_map::generator::{RepoMapGeneratorRequest, RepoMapGeneratorRequestPartial},
     rerank::base::ReRankEntriesForBroker,
+    rust::inline_value::InlineValueInput,
     search::big_search::BigSearchRequest,
     session::{
         ask_followup_question::AskFollowupQuestionsRequest,
         attempt_completion::AttemptCompletionClientRequest, chat::SessionChatClientRequest,
-        exchange::SessionExchangeNewRequest, hot_streak::SessionHotStreakRequest,
+        code_review::{CodeReviewInput, CodeReviewRequestPartial},
+        exchange::SessionExchangeNewRequest,
+        find_symbol_definition::{FindSymbolDefinitionInput, FindSymbolDefinitionRequestPartial},
+        hot_streak::SessionHotStreakRequest,
+        run_tests::{RunTestsInput, RunTestsRequestPartial},
+        session::AideAgentMode,
+        show_diff::{ShowDiffInput, ShowDiffRequestPartial},
+        summarize_context::{SummarizeContextInputPartial, SummarizeContextRequest},
     },
     swe_bench::test_tool::SWEBenchTestRequest,
     terminal::terminal::{TerminalInput, TerminalInputPartial},
@@ -86,6 +102,16 @@ pub enum ToolInputPartial {
     AskFollowupQuestions(AskFollowupQuestionsRequest),
     AttemptCompletion(AttemptCompletionClientRequest),
     RepoMapGeneration(RepoMapGeneratorRequestPartial),
+    DeleteFile(DeleteFileInputPartial),
+    MoveFile(MoveFileInputPartial),
+    SummarizeContext(SummarizeContextInputPartial),
+    ShowDiff(ShowDiffRequestPartial),
+    CodeReview(CodeReviewRequestPartial),
+    GetOutlineNodes(GetOutlineNodesInputPartial),
+    DuplicateSymbol(DuplicateSymbolInputPartial),
+    FindSymbolDefinition(FindSymbolDefinitionRequestPartial),
+    GetWorkspaceSymbols(GetWorkspaceSymbolsInputPartial),
+    RunTests(RunTestsRequestPartial),
 }
 
 impl ToolInputPartial {
@@ -100,6 +126,16 @@ impl ToolInputPartial {
             Self::AskFollowupQuestions(_) => ToolType::AskFollowupQuestions,
             Self::AttemptCompletion(_) => ToolType::AttemptCompletion,
             Self::RepoMapGeneration(_) => ToolType::RepoMapGeneration,
+            Self::DeleteFile(_) => ToolType::DeleteFile,
+            Self::MoveFile(_) => ToolType::MoveFile,
+            Self::SummarizeContext(_) => ToolType::SummarizeContext,
+            Self::ShowDiff(_) => ToolType::ShowDiff,
+            Self::CodeReview(_) => ToolType::CodeReview,
+            Self::GetOutlineNodes(_) => ToolType::GetOutlineNodes,
+            Self::DuplicateSymbol(_) => ToolType::DuplicateSymbol,
+            Self::FindSymbolDefinition(_) => ToolType::FindSymbolDefinition,
+            Self::GetWorkspaceSymbols(_) => ToolType::GetWorkspaceSymbols,
+            Self::RunTests(_) => ToolType::RunTests,
         }
     }
 
@@ -116,6 +152,147 @@ impl ToolInputPartial {
             Self::AskFollowupQuestions(ask_followup_question) => ask_followup_question.to_string(),
             Self::AttemptCompletion(attempt_completion) => attempt_completion.to_string(),
             Self::RepoMapGeneration(repo_map_generator) => repo_map_generator.to_string(),
+            Self::DeleteFile(delete_file) => delete_file.to_string(),
+            Self::MoveFile(move_file) => move_file.to_string(),
+            Self::SummarizeContext(summarize_context) => summarize_context.to_string(),
+            Self::ShowDiff(show_diff) => show_diff.to_string(),
+            Self::CodeReview(code_review) => code_review.to_string(),
+            Self::GetOutlineNodes(get_outline_nodes) => get_outline_nodes.to_string(),
+            Self::DuplicateSymbol(duplicate_symbol) => duplicate_symbol.to_string(),
+            Self::FindSymbolDefinition(find_symbol_definition) => {
+                find_symbol_definition.to_string()
+            }
+            Self::GetWorkspaceSymbols(get_workspace_symbols) => get_workspace_symbols.to_string(),
+            Self::RunTests(run_tests) => run_tests.to_string(),
+        }
+    }
+
+    /// Sanity-checks a tool call the model just emitted before we dispatch
+    /// it, so obviously malformed input (an empty file path, a regex which
+    /// doesn't compile) gets fed back to the model as a validation error
+    /// instead of failing deep inside the tool itself.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::CodeEditing(code_editing) => {
+                if code_editing.fs_file_path().trim().is_empty() {
+                    return Err("fs_file_path can not be empty for code_edit_input".to_owned());
+                }
+                Ok(())
+            }
+            Self::ListFiles(list_files) => {
+                if list_files.directory_path().trim().is_empty() {
+                    return Err("directory_path can not be empty for list_files".to_owned());
+                }
+                Ok(())
+            }
+            Self::SearchFileContentWithRegex(search_file_content_with_regex) => {
+                if search_file_content_with_regex
+                    .directory_path()
+                    .trim()
+                    .is_empty()
+                {
+                    return Err("directory_path can not be empty for search_files".to_owned());
+                }
+                if let Err(e) =
+                    regex::Regex::new(search_file_content_with_regex.regex_pattern())
+                {
+                    return Err(format!(
+                        "regex_pattern `{}` is not a valid regex: {}",
+                        search_file_content_with_regex.regex_pattern(),
+                        e
+                    ));
+                }
+                Ok(())
+            }
+            Self::OpenFile(open_file) => {
+                if open_file.fs_file_path().trim().is_empty() {
+                    return Err("fs_file_path can not be empty for open_file".to_owned());
+                }
+                Ok(())
+            }
+            Self::TerminalCommand(terminal_command) => {
+                if terminal_command.command().trim().is_empty() {
+                    return Err("command can not be empty for execute_command".to_owned());
+                }
+                Ok(())
+            }
+            Self::DeleteFile(delete_file) => {
+                if delete_file.fs_file_path().trim().is_empty() {
+                    return Err("fs_file_path can not be empty for delete_file".to_owned());
+                }
+                Ok(())
+            }
+            Self::MoveFile(move_file) => {
+                if move_file.fs_file_path().trim().is_empty()
+                    || move_file.new_fs_file_path().trim().is_empty()
+                {
+                    return Err(
+                        "fs_file_path and new_fs_file_path can not be empty for move_file"
+                            .to_owned(),
+                    );
+                }
+                Ok(())
+            }
+            Self::DuplicateSymbol(duplicate_symbol) => {
+                if duplicate_symbol.fs_file_path().trim().is_empty()
+                    || duplicate_symbol.symbol_name().trim().is_empty()
+                    || duplicate_symbol.new_name().trim().is_empty()
+                {
+                    return Err(
+                        "fs_file_path, symbol_name and new_name can not be empty for duplicate_symbol"
+                            .to_owned(),
+                    );
+                }
+                Ok(())
+            }
+            Self::FindSymbolDefinition(find_symbol_definition) => {
+                if find_symbol_definition.symbol_name().trim().is_empty() {
+                    return Err(
+                        "symbol_name can not be empty for find_symbol_definition".to_owned(),
+                    );
+                }
+                Ok(())
+            }
+            Self::GetWorkspaceSymbols(get_workspace_symbols) => {
+                if get_workspace_symbols.query().trim().is_empty() {
+                    return Err("query can not be empty for get_workspace_symbols".to_owned());
+                }
+                Ok(())
+            }
+            Self::LSPDiagnostics(_)
+            | Self::AskFollowupQuestions(_)
+            | Self::AttemptCompletion(_)
+            | Self::RepoMapGeneration(_)
+            | Self::SummarizeContext(_)
+            | Self::ShowDiff(_)
+            | Self::CodeReview(_)
+            | Self::GetOutlineNodes(_)
+            | Self::RunTests(_) => Ok(()),
+        }
+    }
+
+    /// Chat mode is a read-only capability: the agent can look around the
+    /// codebase to ground its answer, but it should never be able to mutate
+    /// files or run commands just because a chat reply happened to invoke
+    /// the same tool-use loop as an edit. Reject write-capable tool calls
+    /// here, at construction time, before they ever reach a tool
+    /// invocation; read-only tools (list files, open file, search, outline,
+    /// diagnostics, etc.) stay allowed so the reply can still be grounded.
+    pub fn validate_for_mode(&self, agent_mode: &AideAgentMode) -> Result<(), String> {
+        if !matches!(agent_mode, AideAgentMode::Chat) {
+            return Ok(());
+        }
+        match self {
+            Self::CodeEditing(_)
+            | Self::TerminalCommand(_)
+            | Self::DeleteFile(_)
+            | Self::MoveFile(_)
+            | Self::DuplicateSymbol(_)
+            | Self::RunTests(_) => Err(format!(
+                "{} can not be used in chat mode, which is read-only; switch to edit mode if a change is needed",
+                self.to_tool_type()
+            )),
+            _ => Ok(()),
         }
     }
 }
@@ -207,6 +384,12 @@ pub enum ToolInput {
     GenerateStep(StepGeneratorRequest),
     // Create file
     CreateFile(CreateFileRequest),
+    // Delete file
+    DeleteFile(DeleteFileInput),
+    // Move or rename file
+    MoveFile(MoveFileInput),
+    // Duplicate a symbol under a new name
+    DuplicateSymbol(DuplicateSymbolInput),
     FileDiagnostics(FileDiagnosticsInput),
     // Plan step add
     PlanStepAdd(PlanAddRequest),
@@ -236,6 +419,29 @@ pub enum ToolInput {
     RepoMapGeneration(RepoMapGeneratorRequest),
     // Sub process generation input
     SubProcessSpawnedPendingOutput(SubProcessSpawnedPendingOutputRequest),
+    // Summarize the session so far to free up context
+    SummarizeContext(SummarizeContextRequest),
+    // Fetch back the full diff for a file whose edit summary was condensed
+    ShowDiff(ShowDiffInput),
+    // Diagnostics, an optional test run and a diff already gathered by the
+    // session, to be boiled down into a clean/needs-work verdict
+    CodeReview(CodeReviewInput),
+    // Get the symbol map (outline nodes) for a file
+    GetOutlineNodes(GetOutlineNodesInput),
+    // Ask the editor which files are currently open in buffers
+    ListOpenFiles(ListOpenFilesInput),
+    // Extract a selection into its own function using the editor's refactoring
+    ExtractFunction(ExtractFunctionInput),
+    // Evaluate a constant expression without running the full test suite
+    InlineValue(InlineValueInput),
+    // Candidate resolution for a symbol name, already gathered by the
+    // session, to be boiled down into a definition report
+    FindSymbolDefinition(FindSymbolDefinitionInput),
+    // Search for symbols across the whole workspace by name prefix
+    GetWorkspaceSymbols(GetWorkspaceSymbolsInput),
+    // Run the project's test suite and parse the output into a structured
+    // pass/fail result
+    RunTests(RunTestsInput),
 }
 
 impl ToolInput {
@@ -304,6 +510,9 @@ impl ToolInput {
             ToolInput::UpdatePlan(_) => ToolType::PlanUpdater,
             ToolInput::GenerateStep(_) => ToolType::StepGenerator,
             ToolInput::CreateFile(_) => ToolType::CreateFile,
+            ToolInput::DeleteFile(_) => ToolType::DeleteFile,
+            ToolInput::MoveFile(_) => ToolType::MoveFile,
+            ToolInput::DuplicateSymbol(_) => ToolType::DuplicateSymbol,
             ToolInput::FileDiagnostics(_) => ToolType::FileDiagnostics,
             ToolInput::PlanStepAdd(_) => ToolType::PlanStepAdd,
             ToolInput::GoToPreviousWord(_) => ToolType::GoToPreviousWordRange,
@@ -321,6 +530,72 @@ impl ToolInput {
             ToolInput::SubProcessSpawnedPendingOutput(_) => {
                 ToolType::SubProcessSpawnedPendingOutput
             }
+            ToolInput::SummarizeContext(_) => ToolType::SummarizeContext,
+            ToolInput::ShowDiff(_) => ToolType::ShowDiff,
+            ToolInput::CodeReview(_) => ToolType::CodeReview,
+            ToolInput::GetOutlineNodes(_) => ToolType::GetOutlineNodes,
+            ToolInput::ListOpenFiles(_) => ToolType::ListOpenFiles,
+            ToolInput::ExtractFunction(_) => ToolType::ExtractFunction,
+            ToolInput::InlineValue(_) => ToolType::InlineValue,
+            ToolInput::FindSymbolDefinition(_) => ToolType::FindSymbolDefinition,
+            ToolInput::GetWorkspaceSymbols(_) => ToolType::GetWorkspaceSymbols,
+            ToolInput::RunTests(_) => ToolType::RunTests,
+        }
+    }
+
+    pub fn is_extract_function(self) -> Result<ExtractFunctionInput, ToolError> {
+        if let ToolInput::ExtractFunction(request) = self {
+            Ok(request)
+        } else {
+            Err(ToolError::WrongToolInput(ToolType::ExtractFunction))
+        }
+    }
+
+    pub fn is_show_diff(self) -> Result<ShowDiffInput, ToolError> {
+        if let ToolInput::ShowDiff(request) = self {
+            Ok(request)
+        } else {
+            Err(ToolError::WrongToolInput(ToolType::ShowDiff))
+        }
+    }
+
+    pub fn is_code_review(self) -> Result<CodeReviewInput, ToolError> {
+        if let ToolInput::CodeReview(request) = self {
+            Ok(request)
+        } else {
+            Err(ToolError::WrongToolInput(ToolType::CodeReview))
+        }
+    }
+
+    pub fn is_find_symbol_definition(self) -> Result<FindSymbolDefinitionInput, ToolError> {
+        if let ToolInput::FindSymbolDefinition(request) = self {
+            Ok(request)
+        } else {
+            Err(ToolError::WrongToolInput(ToolType::FindSymbolDefinition))
+        }
+    }
+
+    pub fn get_workspace_symbols(self) -> Result<GetWorkspaceSymbolsInput, ToolError> {
+        if let ToolInput::GetWorkspaceSymbols(request) = self {
+            Ok(request)
+        } else {
+            Err(ToolError::WrongToolInput(ToolType::GetWorkspaceSymbols))
+        }
+    }
+
+    pub fn get_outline_nodes(self) -> Result<GetOutlineNodesInput, ToolError> {
+        if let ToolInput::GetOutlineNodes(request) = self {
+            Ok(request)
+        } else {
+            Err(ToolError::WrongToolInput(ToolType::GetOutlineNodes))
+        }
+    }
+
+    pub fn is_list_open_files(self) -> Result<ListOpenFilesInput, ToolError> {
+        if let ToolInput::ListOpenFiles(request) = self {
+            Ok(request)
+        } else {
+            Err(ToolError::WrongToolInput(ToolType::ListOpenFiles))
         }
     }
 
@@ -388,6 +663,14 @@ impl ToolInput {
         }
     }
 
+    pub fn is_summarize_context(self) -> Result<SummarizeContextRequest, ToolError> {
+        if let ToolInput::SummarizeContext(request) = self {
+            Ok(request)
+        } else {
+            Err(ToolError::WrongToolInput(ToolType::SummarizeContext))
+        }
+    }
+
     pub fn is_undo_request_during_session(
         self,
     ) -> Result<UndoChangesMadeDuringExchangeRequest, ToolError> {
@@ -1026,6 +1309,38 @@ impl ToolInput {
         }
     }
 
+    pub fn is_file_delete(self) -> Result<DeleteFileInput, ToolError> {
+        if let ToolInput::DeleteFile(delete_file) = self {
+            Ok(delete_file)
+        } else {
+            Err(ToolError::WrongToolInput(ToolType::DeleteFile))
+        }
+    }
+
+    pub fn is_file_move(self) -> Result<MoveFileInput, ToolError> {
+        if let ToolInput::MoveFile(move_file) = self {
+            Ok(move_file)
+        } else {
+            Err(ToolError::WrongToolInput(ToolType::MoveFile))
+        }
+    }
+
+    pub fn is_duplicate_symbol(self) -> Result<DuplicateSymbolInput, ToolError> {
+        if let ToolInput::DuplicateSymbol(duplicate_symbol) = self {
+            Ok(duplicate_symbol)
+        } else {
+            Err(ToolError::WrongToolInput(ToolType::DuplicateSymbol))
+        }
+    }
+
+    pub fn is_inline_value(self) -> Result<InlineValueInput, ToolError> {
+        if let ToolInput::InlineValue(inline_value) = self {
+            Ok(inline_value)
+        } else {
+            Err(ToolError::WrongToolInput(ToolType::InlineValue))
+        }
+    }
+
     pub fn is_terminal_command(self) -> Result<TerminalInput, ToolError> {
         if let ToolInput::TerminalCommand(terminal_command) = self {
             Ok(terminal_command)
@@ -1033,4 +1348,99 @@ impl ToolInput {
             Err(ToolError::WrongToolInput(ToolType::TerminalCommand))
         }
     }
+
+    pub fn is_run_tests(self) -> Result<RunTestsInput, ToolError> {
+        if let ToolInput::RunTests(run_tests) = self {
+            Ok(run_tests)
+        } else {
+            Err(ToolError::WrongToolInput(ToolType::RunTests))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_rejects_an_empty_fs_file_path_on_code_editing() {
+        let partial = ToolInputPartial::CodeEditing(CodeEditingPartialRequest::new(
+            "".to_owned(),
+            "add a doc comment".to_owned(),
+        ));
+        let error = partial.validate().expect_err("empty fs_file_path");
+        assert!(error.contains("fs_file_path"));
+    }
+
+    #[test]
+    fn validate_rejects_an_invalid_regex_on_search_file_content() {
+        let partial = ToolInputPartial::SearchFileContentWithRegex(
+            SearchFileContentInputPartial::new(
+                "src".to_owned(),
+                "fn(".to_owned(),
+                None,
+            ),
+        );
+        let error = partial.validate().expect_err("unbalanced parenthesis");
+        assert!(error.contains("regex_pattern"));
+    }
+
+    #[test]
+    fn validate_accepts_well_formed_input() {
+        let partial = ToolInputPartial::SearchFileContentWithRegex(
+            SearchFileContentInputPartial::new(
+                "src".to_owned(),
+                "fn main".to_owned(),
+                None,
+            ),
+        );
+        assert!(partial.validate().is_ok());
+    }
+
+    fn write_capable_partials() -> Vec<ToolInputPartial> {
+        vec![
+            ToolInputPartial::CodeEditing(CodeEditingPartialRequest::new(
+                "src/main.rs".to_owned(),
+                "add a doc comment".to_owned(),
+            )),
+            ToolInputPartial::TerminalCommand(TerminalInputPartial::new("rm -rf .".to_owned())),
+            ToolInputPartial::DeleteFile(DeleteFileInputPartial::new("src/main.rs".to_owned())),
+            ToolInputPartial::MoveFile(MoveFileInputPartial::new(
+                "src/main.rs".to_owned(),
+                "src/lib.rs".to_owned(),
+            )),
+            ToolInputPartial::DuplicateSymbol(DuplicateSymbolInputPartial::new(
+                "src/main.rs".to_owned(),
+                "Foo".to_owned(),
+                "Bar".to_owned(),
+            )),
+            ToolInputPartial::RunTests(RunTestsRequestPartial::new(None, None)),
+        ]
+    }
+
+    #[test]
+    fn validate_for_mode_rejects_every_write_capable_partial_in_chat_mode() {
+        for partial in write_capable_partials() {
+            let error = partial
+                .validate_for_mode(&AideAgentMode::Chat)
+                .expect_err(&format!("{:?} should be rejected in chat mode", partial));
+            assert!(error.contains("chat mode"));
+        }
+    }
+
+    #[test]
+    fn validate_for_mode_allows_read_only_partials_in_chat_mode() {
+        let partial = ToolInputPartial::OpenFile(OpenFileRequestPartial::new(
+            "src/main.rs".to_owned(),
+        ));
+        assert!(partial.validate_for_mode(&AideAgentMode::Chat).is_ok());
+    }
+
+    #[test]
+    fn validate_for_mode_allows_write_capable_partials_outside_chat_mode() {
+        for partial in write_capable_partials() {
+            assert!(partial.validate_for_mode(&AideAgentMode::Edit).is_ok());
+            assert!(partial.validate_for_mode(&AideAgentMode::Plan).is_ok());
+        }
+    }
 }
@@ -0,0 +1,154 @@
+//! Deletes the file using the editor endpoint
+
+use crate::agentic::tool::{errors::ToolError, input::ToolInput, output::ToolOutput, r#type::Tool};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteFileInput {
+    fs_file_path: String,
+    editor_url: String,
+}
+
+impl DeleteFileInput {
+    pub fn new(fs_file_path: String, editor_url: String) -> Self {
+        Self {
+            fs_file_path,
+            editor_url,
+        }
+    }
+}
+
+/// The partial version of `DeleteFileInput` which the agent can invoke
+/// directly, before the `editor_url` is filled in by the session layer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteFileInputPartial {
+    fs_file_path: String,
+}
+
+impl DeleteFileInputPartial {
+    pub fn new(fs_file_path: String) -> Self {
+        Self { fs_file_path }
+    }
+
+    pub fn fs_file_path(&self) -> &str {
+        &self.fs_file_path
+    }
+
+    pub fn to_string(&self) -> String {
+        format!(
+            r#"<delete_file>
+<fs_file_path>
+{}
+</fs_file_path>
+</delete_file>"#,
+            &self.fs_file_path
+        )
+    }
+}
+
+/// Shape of the response the editor sends back over http, kept separate from
+/// `DeleteFileOutput` since the editor has no notion of `previous_content`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct EditorDeleteFileResponse {
+    deleted: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeleteFileOutput {
+    deleted: bool,
+    // The content of the file before it was deleted, so the caller can offer
+    // to restore it if the deletion turns out to be unwanted. `None` when we
+    // could not read the file before deleting it (e.g. it did not exist).
+    previous_content: Option<String>,
+}
+
+impl DeleteFileOutput {
+    pub fn new(deleted: bool, previous_content: Option<String>) -> Self {
+        Self {
+            deleted,
+            previous_content,
+        }
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.deleted
+    }
+
+    pub fn previous_content(&self) -> Option<&str> {
+        self.previous_content.as_deref()
+    }
+}
+
+pub struct LSPDeleteFile {
+    client: reqwest::Client,
+}
+
+impl LSPDeleteFile {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for LSPDeleteFile {
+    async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
+        let context = input.is_file_delete()?;
+        // grab the content before we delete it so a rejected delete can still
+        // be recovered from, this has to happen regardless of whether we are
+        // going through the editor or deleting the file ourselves since the
+        // editor does not hand the previous content back to us
+        let previous_content = tokio::fs::read_to_string(&context.fs_file_path).await.ok();
+        // when there is no editor to delegate to (e.g. running headless) we
+        // fall back to deleting the file ourselves instead of failing outright
+        if context.editor_url.is_empty() {
+            let deleted = tokio::fs::remove_file(&context.fs_file_path).await.is_ok();
+            return Ok(ToolOutput::FileDelete(DeleteFileOutput::new(
+                deleted,
+                previous_content,
+            )));
+        }
+        let editor_endpoint = context.editor_url.to_owned() + "/delete_file";
+        let response = self
+            .client
+            .post(editor_endpoint)
+            .body(serde_json::to_string(&context).map_err(|_e| ToolError::SerdeConversionFailed)?)
+            .send()
+            .await
+            .map_err(|_e| ToolError::ErrorCommunicatingWithEditor)?;
+        let deleted: bool = response
+            .json()
+            .await
+            .map(|response: EditorDeleteFileResponse| response.deleted)
+            .map_err(|_e| ToolError::ErrorCommunicatingWithEditor)?;
+        Ok(ToolOutput::FileDelete(DeleteFileOutput::new(
+            deleted,
+            previous_content,
+        )))
+    }
+
+    fn tool_description(&self) -> String {
+        format!(
+            r#"### delete_file
+Request to delete the file at the specified path through the editor, instead of running a shell command like \`rm\`.
+Use this when a file is no longer needed and should be removed as part of the task. This is destructive and cannot be undone by the editor's own undo, so only delete files you are confident should go."#
+        )
+    }
+
+    fn tool_input_format(&self) -> String {
+        format!(
+            r#"Parameters:
+- fs_file_path: (required) The absolute path of the file to delete.
+
+Usage:
+<delete_file>
+<fs_file_path>
+File path here
+</fs_file_path>
+</delete_file>
+"#
+        )
+    }
+}
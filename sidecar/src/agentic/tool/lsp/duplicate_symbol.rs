@@ -0,0 +1,163 @@
+//! Duplicates a symbol using the editor endpoint, so the agent can keep the
+//! original around as a backup before modifying it. The editor is
+//! responsible for reading the symbol, rewriting any references to itself
+//! inside its own body to point at the new name, and inserting the copy
+//! adjacent to the original.
+
+use async_trait::async_trait;
+
+use crate::{
+    agentic::tool::{errors::ToolError, input::ToolInput, output::ToolOutput, r#type::Tool},
+    chunking::text_document::Range,
+};
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DuplicateSymbolInput {
+    fs_file_path: String,
+    symbol_name: String,
+    new_name: String,
+    editor_url: String,
+}
+
+impl DuplicateSymbolInput {
+    pub fn new(fs_file_path: String, symbol_name: String, new_name: String, editor_url: String) -> Self {
+        Self {
+            fs_file_path,
+            symbol_name,
+            new_name,
+            editor_url,
+        }
+    }
+}
+
+/// The partial version of `DuplicateSymbolInput` which the agent can invoke
+/// directly, before the `editor_url` is filled in by the session layer.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DuplicateSymbolInputPartial {
+    fs_file_path: String,
+    symbol_name: String,
+    new_name: String,
+}
+
+impl DuplicateSymbolInputPartial {
+    pub fn new(fs_file_path: String, symbol_name: String, new_name: String) -> Self {
+        Self {
+            fs_file_path,
+            symbol_name,
+            new_name,
+        }
+    }
+
+    pub fn fs_file_path(&self) -> &str {
+        &self.fs_file_path
+    }
+
+    pub fn symbol_name(&self) -> &str {
+        &self.symbol_name
+    }
+
+    pub fn new_name(&self) -> &str {
+        &self.new_name
+    }
+
+    pub fn to_string(&self) -> String {
+        format!(
+            r#"<duplicate_symbol>
+<fs_file_path>
+{}
+</fs_file_path>
+<symbol_name>
+{}
+</symbol_name>
+<new_name>
+{}
+</new_name>
+</duplicate_symbol>"#,
+            &self.fs_file_path, &self.symbol_name, &self.new_name
+        )
+    }
+}
+
+/// Shape of the response the editor sends back over http.
+#[derive(Debug, Clone, serde::Deserialize)]
+struct EditorDuplicateSymbolResponse {
+    new_symbol_range: Range,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct DuplicateSymbolOutput {
+    new_symbol_range: Range,
+}
+
+impl DuplicateSymbolOutput {
+    pub fn new_symbol_range(&self) -> &Range {
+        &self.new_symbol_range
+    }
+}
+
+pub struct LSPDuplicateSymbol {
+    client: reqwest::Client,
+}
+
+impl LSPDuplicateSymbol {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for LSPDuplicateSymbol {
+    async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
+        let context = input.is_duplicate_symbol()?;
+        let editor_endpoint = context.editor_url.to_owned() + "/duplicate_symbol";
+        let response = self
+            .client
+            .post(editor_endpoint)
+            .body(serde_json::to_string(&context).map_err(|_e| ToolError::SerdeConversionFailed)?)
+            .send()
+            .await
+            .map_err(|_e| ToolError::ErrorCommunicatingWithEditor)?;
+        let response: EditorDuplicateSymbolResponse = response
+            .json()
+            .await
+            .map_err(|_e| ToolError::SerdeConversionFailed)?;
+        Ok(ToolOutput::duplicate_symbol(DuplicateSymbolOutput {
+            new_symbol_range: response.new_symbol_range,
+        }))
+    }
+
+    fn tool_description(&self) -> String {
+        format!(
+            r#"### duplicate_symbol
+Request to duplicate a symbol (function, class, etc) in the given file through the editor.
+The editor reads the symbol, rewrites any references to itself inside its own body to point
+at the new name, and inserts the copy right next to the original. Use this when you want to
+keep the original around as a backup before modifying it, instead of editing it in place."#
+        )
+    }
+
+    fn tool_input_format(&self) -> String {
+        format!(
+            r#"Parameters:
+- fs_file_path: (required) The absolute path of the file containing the symbol.
+- symbol_name: (required) The name of the symbol to duplicate.
+- new_name: (required) The name to give the duplicated symbol.
+
+Usage:
+<duplicate_symbol>
+<fs_file_path>
+File path here
+</fs_file_path>
+<symbol_name>
+Symbol name here
+</symbol_name>
+<new_name>
+New symbol name here
+</new_name>
+</duplicate_symbol>
+"#
+        )
+    }
+}
@@ -0,0 +1,100 @@
+//! Extracts a selection of code into its own function using the editor's
+//! built-in refactoring, so the result is guaranteed to compile and the
+//! call site is guaranteed to be updated correctly, which is not something
+//! an LLM-written `CodeEditing` instruction can promise.
+
+use async_trait::async_trait;
+
+use crate::{
+    agentic::tool::{errors::ToolError, input::ToolInput, output::ToolOutput, r#type::Tool},
+    chunking::text_document::Range,
+};
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ExtractFunctionInput {
+    fs_file_path: String,
+    selection_range: Range,
+    new_function_name: String,
+    editor_url: String,
+}
+
+impl ExtractFunctionInput {
+    pub fn new(
+        fs_file_path: String,
+        selection_range: Range,
+        new_function_name: String,
+        editor_url: String,
+    ) -> Self {
+        Self {
+            fs_file_path,
+            selection_range,
+            new_function_name,
+            editor_url,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+struct EditorExtractFunctionResponse {
+    extracted_function_range: Range,
+    call_site_updated: bool,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ExtractFunctionOutput {
+    extracted_function_range: Range,
+    call_site_updated: bool,
+}
+
+impl ExtractFunctionOutput {
+    pub fn extracted_function_range(&self) -> &Range {
+        &self.extracted_function_range
+    }
+
+    pub fn call_site_updated(&self) -> bool {
+        self.call_site_updated
+    }
+}
+
+pub struct LSPExtractFunction {
+    client: reqwest::Client,
+}
+
+impl LSPExtractFunction {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for LSPExtractFunction {
+    async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
+        let context = input.is_extract_function()?;
+        let editor_endpoint = context.editor_url.to_owned() + "/extract_function";
+        let response = self
+            .client
+            .post(editor_endpoint)
+            .body(serde_json::to_string(&context).map_err(|_e| ToolError::SerdeConversionFailed)?)
+            .send()
+            .await
+            .map_err(|_e| ToolError::ErrorCommunicatingWithEditor)?;
+        let response: EditorExtractFunctionResponse = response
+            .json()
+            .await
+            .map_err(|_e| ToolError::SerdeConversionFailed)?;
+        Ok(ToolOutput::extract_function(ExtractFunctionOutput {
+            extracted_function_range: response.extracted_function_range,
+            call_site_updated: response.call_site_updated,
+        }))
+    }
+
+    fn tool_description(&self) -> String {
+        "".to_owned()
+    }
+
+    fn tool_input_format(&self) -> String {
+        "".to_owned()
+    }
+}
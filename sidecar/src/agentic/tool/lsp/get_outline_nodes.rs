@@ -444,6 +444,178 @@ impl Tool for OutlineNodesUsingEditorClient {
     }
 }
 
+/// A lightweight summary of an [`OutlineNode`], carrying just enough for the
+/// LLM to decide which symbols in a file are worth reading or editing
+/// without pulling in the symbol's full body (which `OutlineNodesUsingEditor`
+/// carries internally, but which is more than the LLM needs for this).
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct OutlineNodeSummary {
+    name: String,
+    kind: String,
+    start_line: usize,
+    end_line: usize,
+}
+
+impl OutlineNodeSummary {
+    fn from_outline_node_content(content: &OutlineNodeContent) -> Self {
+        Self {
+            name: content.name().to_owned(),
+            kind: format!("{:?}", content.outline_node_type()),
+            start_line: content.range().start_line(),
+            end_line: content.range().end_line(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    pub fn start_line(&self) -> usize {
+        self.start_line
+    }
+
+    pub fn end_line(&self) -> usize {
+        self.end_line
+    }
+}
+
+/// The subset of `GetOutlineNodesInput` the LLM actually provides; the
+/// `editor_url` is filled in by the session before the tool is invoked, the
+/// same way `OpenFileRequestPartial` doesn't carry it either.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct GetOutlineNodesInputPartial {
+    fs_file_path: String,
+}
+
+impl GetOutlineNodesInputPartial {
+    pub fn new(fs_file_path: String) -> Self {
+        Self { fs_file_path }
+    }
+
+    pub fn fs_file_path(&self) -> &str {
+        &self.fs_file_path
+    }
+
+    pub fn to_string(&self) -> String {
+        format!(
+            r#"<get_outline_nodes>
+<fs_file_path>
+{}
+</fs_file_path>
+</get_outline_nodes>"#,
+            &self.fs_file_path
+        )
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct GetOutlineNodesInput {
+    fs_file_path: String,
+    editor_url: String,
+}
+
+impl GetOutlineNodesInput {
+    pub fn new(fs_file_path: String, editor_url: String) -> Self {
+        Self {
+            fs_file_path,
+            editor_url,
+        }
+    }
+
+    pub fn fs_file_path(&self) -> &str {
+        &self.fs_file_path
+    }
+
+    pub fn editor_url(&self) -> &str {
+        &self.editor_url
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct GetOutlineNodesOutput {
+    nodes: Vec<OutlineNodeSummary>,
+}
+
+impl GetOutlineNodesOutput {
+    pub fn nodes(&self) -> &[OutlineNodeSummary] {
+        self.nodes.as_slice()
+    }
+}
+
+/// Lets the agent ask for a file's symbol map (top-level and nested
+/// class/function symbols with their line ranges) up front, so it can decide
+/// which symbols are worth reading in full instead of opening the whole
+/// file. Talks to the same editor endpoint as `OutlineNodesUsingEditor`
+/// (which is only used internally for context-gathering), but returns the
+/// condensed `OutlineNodeSummary` shape instead of the full outline nodes.
+pub struct LSPGetOutlineNodes {
+    client: reqwest::Client,
+}
+
+impl LSPGetOutlineNodes {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for LSPGetOutlineNodes {
+    async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
+        let context = input.get_outline_nodes()?;
+        let fs_file_path = context.fs_file_path().to_owned();
+        let editor_request =
+            OutlineNodesUsingEditorRequest::new(fs_file_path.to_owned(), context.editor_url().to_owned());
+        let editor_endpoint = context.editor_url().to_owned() + "/get_outline_nodes";
+        let response = self
+            .client
+            .post(editor_endpoint)
+            .body(
+                serde_json::to_string(&editor_request)
+                    .map_err(|_e| ToolError::SerdeConversionFailed)?,
+            )
+            .send()
+            .await
+            .map_err(|_e| ToolError::ErrorCommunicatingWithEditor)?;
+        let response: OutlineNodesUsingEditorResponse = response.json().await.map_err(|e| {
+            eprintln!("{:?}", e);
+            ToolError::SerdeConversionFailed
+        })?;
+        let outline_nodes = response.to_outline_nodes(fs_file_path);
+        let nodes = outline_nodes
+            .iter()
+            .flat_map(|outline_node| {
+                std::iter::once(outline_node.content()).chain(outline_node.children().iter())
+            })
+            .map(OutlineNodeSummary::from_outline_node_content)
+            .collect::<Vec<_>>();
+        Ok(ToolOutput::get_outline_nodes(GetOutlineNodesOutput { nodes }))
+    }
+
+    fn tool_description(&self) -> String {
+        r#"### get_outline_nodes
+Request to get the symbol map (classes, functions and their line ranges) for a file without reading its full content. Use this to understand a file's structure before deciding which symbols are worth reading or editing."#.to_owned()
+    }
+
+    fn tool_input_format(&self) -> String {
+        r#"Parameters:
+- fs_file_path: (required) The absolute path of the file to get the outline for.
+
+Usage:
+<get_outline_nodes>
+<fs_file_path>
+File path here
+</fs_file_path>
+</get_outline_nodes>"#
+            .to_owned()
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
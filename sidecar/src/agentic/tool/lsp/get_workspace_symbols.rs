@@ -0,0 +1,158 @@
+use crate::{
+    agentic::tool::{errors::ToolError, input::ToolInput, output::ToolOutput, r#type::Tool},
+    chunking::text_document::Range,
+};
+use async_trait::async_trait;
+
+/// The subset of `GetWorkspaceSymbolsInput` the LLM actually provides; the
+/// `editor_url` is filled in by the session before the tool is invoked, the
+/// same way `GetOutlineNodesInputPartial` doesn't carry it either.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct GetWorkspaceSymbolsInputPartial {
+    query: String,
+    max_results: usize,
+}
+
+impl GetWorkspaceSymbolsInputPartial {
+    pub fn new(query: String, max_results: usize) -> Self {
+        Self { query, max_results }
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn max_results(&self) -> usize {
+        self.max_results
+    }
+
+    pub fn to_string(&self) -> String {
+        format!(
+            r#"<get_workspace_symbols>
+<query>
+{}
+</query>
+<max_results>
+{}
+</max_results>
+</get_workspace_symbols>"#,
+            &self.query, self.max_results
+        )
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct GetWorkspaceSymbolsInput {
+    query: String,
+    max_results: usize,
+    editor_url: String,
+}
+
+impl GetWorkspaceSymbolsInput {
+    pub fn new(query: String, max_results: usize, editor_url: String) -> Self {
+        Self {
+            query,
+            max_results,
+            editor_url,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WorkspaceSymbol {
+    name: String,
+    kind: String,
+    fs_file_path: String,
+    range: Range,
+}
+
+impl WorkspaceSymbol {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    pub fn fs_file_path(&self) -> &str {
+        &self.fs_file_path
+    }
+
+    pub fn range(&self) -> &Range {
+        &self.range
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct GetWorkspaceSymbolsOutput {
+    symbols: Vec<WorkspaceSymbol>,
+}
+
+impl GetWorkspaceSymbolsOutput {
+    pub fn symbols(&self) -> &[WorkspaceSymbol] {
+        self.symbols.as_slice()
+    }
+}
+
+pub struct LSPGetWorkspaceSymbols {
+    client: reqwest::Client,
+}
+
+impl LSPGetWorkspaceSymbols {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for LSPGetWorkspaceSymbols {
+    async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
+        let context = input.get_workspace_symbols()?;
+        let editor_endpoint = context.editor_url.to_owned() + "/workspace_symbols";
+        let response = self
+            .client
+            .post(editor_endpoint)
+            .body(serde_json::to_string(&context).map_err(|_e| ToolError::SerdeConversionFailed)?)
+            .send()
+            .await
+            .map_err(|_e| ToolError::ErrorCommunicatingWithEditor)?;
+        let response: GetWorkspaceSymbolsOutput = response
+            .json()
+            .await
+            .map_err(|_e| ToolError::SerdeConversionFailed)?;
+
+        Ok(ToolOutput::GetWorkspaceSymbols(response))
+    }
+
+    fn tool_description(&self) -> String {
+        "".to_owned()
+    }
+
+    fn tool_input_format(&self) -> String {
+        "".to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LSPGetWorkspaceSymbols;
+    use crate::agentic::tool::{input::ToolInput, r#type::Tool};
+
+    /// This test runs with a live editor, sometime later we can abstract this
+    /// part out
+    #[tokio::test]
+    async fn test_lsp_invocation() {
+        let input = ToolInput::GetWorkspaceSymbols(super::GetWorkspaceSymbolsInput::new(
+            "SymbolManager".to_owned(),
+            20,
+            "http://localhost:42423".to_owned(),
+        ));
+        let lsp_get_workspace_symbols = LSPGetWorkspaceSymbols::new();
+        let result = lsp_get_workspace_symbols.invoke(input).await;
+        println!("{:?}", result);
+        assert!(false);
+    }
+}
@@ -31,6 +31,10 @@ pub struct GoToDefinitionResponse {
 }
 
 impl GoToDefinitionResponse {
+    pub fn new(definitions: Vec<DefinitionPathAndRange>) -> Self {
+        Self { definitions }
+    }
+
     pub fn definitions(self) -> Vec<DefinitionPathAndRange> {
         self.definitions
     }
@@ -47,6 +51,13 @@ pub struct DefinitionPathAndRange {
 }
 
 impl DefinitionPathAndRange {
+    pub fn new(fs_file_path: String, range: Range) -> Self {
+        Self {
+            fs_file_path,
+            range,
+        }
+    }
+
     pub fn file_path(&self) -> &str {
         &self.fs_file_path
     }
@@ -25,6 +25,10 @@ impl LocationInformation {
     pub fn fs_file_path(&self) -> &str {
         &self.fs_file_path
     }
+
+    pub fn range(&self) -> &Range {
+        &self.range
+    }
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
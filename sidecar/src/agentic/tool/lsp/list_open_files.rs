@@ -0,0 +1,93 @@
+//! Asks the editor which files it currently has open in buffers, so the
+//! session's view of open files doesn't go stale between the point the user
+//! started the session and whenever the agent actually looks at it.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::agentic::tool::{errors::ToolError, input::ToolInput, output::ToolOutput, r#type::Tool};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListOpenFilesInput {
+    editor_url: String,
+}
+
+impl ListOpenFilesInput {
+    pub fn new(editor_url: String) -> Self {
+        Self { editor_url }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenFileEntry {
+    fs_file_path: String,
+    is_active: bool,
+    is_dirty: bool,
+}
+
+impl OpenFileEntry {
+    pub fn fs_file_path(&self) -> &str {
+        &self.fs_file_path
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.is_active
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.is_dirty
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListOpenFilesOutput {
+    open_files: Vec<OpenFileEntry>,
+}
+
+impl ListOpenFilesOutput {
+    pub fn open_files(&self) -> &[OpenFileEntry] {
+        self.open_files.as_slice()
+    }
+}
+
+pub struct LSPListOpenFiles {
+    client: reqwest::Client,
+}
+
+impl LSPListOpenFiles {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for LSPListOpenFiles {
+    async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
+        let context = input.is_list_open_files()?;
+        let editor_endpoint = context.editor_url.to_owned() + "/list_open_files";
+        let response = self
+            .client
+            .post(editor_endpoint)
+            .body(serde_json::to_string(&context).map_err(|_e| ToolError::SerdeConversionFailed)?)
+            .send()
+            .await
+            .map_err(|_e| ToolError::ErrorCommunicatingWithEditor)?;
+
+        let response: ListOpenFilesOutput = response
+            .json()
+            .await
+            .map_err(|_e| ToolError::SerdeConversionFailed)?;
+
+        Ok(ToolOutput::list_open_files(response))
+    }
+
+    fn tool_description(&self) -> String {
+        "".to_owned()
+    }
+
+    fn tool_input_format(&self) -> String {
+        "".to_owned()
+    }
+}
@@ -1,9 +1,13 @@
 //! We want to talk to the LSP and get useful information out of this
 //! This way we can talk to the LSP running in the editor from the sidecar
 pub mod create_file;
+pub mod delete_file;
 pub mod diagnostics;
+pub mod duplicate_symbol;
+pub mod extract_function;
 pub mod file_diagnostics;
 pub mod get_outline_nodes;
+pub mod get_workspace_symbols;
 pub(crate) mod go_to_previous_word;
 pub mod gotodefintion;
 pub mod gotoimplementations;
@@ -12,6 +16,8 @@ pub(crate) mod gototypedefinition;
 pub mod grep_symbol;
 pub mod inlay_hints;
 pub mod list_files;
+pub(crate) mod list_open_files;
+pub mod move_file;
 pub mod open_file;
 pub mod quick_fix;
 pub mod search_file;
@@ -0,0 +1,236 @@
+//! Moves (renames) a file using the editor endpoint
+
+use crate::agentic::tool::{errors::ToolError, input::ToolInput, output::ToolOutput, r#type::Tool};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+/// Directories we never want to walk into while rewriting import paths, since
+/// they're either version control internals or dependency/build output which
+/// should not be edited by hand.
+const SKIPPED_DIRECTORIES: &[&str] = &["target", "node_modules", ".git"];
+
+/// Turns an absolute file path into the extension-less, forward-slash form
+/// most import statements use (e.g. `src/foo/bar.rs` -> `foo/bar` relative to
+/// `root_directory`). Returns `None` when `fs_file_path` doesn't live under
+/// `root_directory`.
+fn import_style_path(root_directory: &str, fs_file_path: &str) -> Option<String> {
+    let relative_path = std::path::Path::new(fs_file_path)
+        .strip_prefix(root_directory)
+        .ok()?;
+    let without_extension = relative_path.with_extension("");
+    let as_forward_slash = without_extension
+        .components()
+        .filter_map(|component| component.as_os_str().to_str())
+        .collect::<Vec<_>>()
+        .join("/");
+    if as_forward_slash.is_empty() {
+        None
+    } else {
+        Some(as_forward_slash)
+    }
+}
+
+/// Walks `root_directory` rewriting any occurrence of the moved file's
+/// import-style path with its new one, so other files which imported it by
+/// path keep pointing at valid code. Best-effort: this is a plain text
+/// substitution rather than a per-language aware rewrite, and only touches
+/// files which decode as UTF-8. Returns the paths of the files it changed.
+pub async fn rewrite_import_references(
+    root_directory: &str,
+    old_fs_file_path: &str,
+    new_fs_file_path: &str,
+) -> std::io::Result<Vec<String>> {
+    let (Some(old_import_path), Some(new_import_path)) = (
+        import_style_path(root_directory, old_fs_file_path),
+        import_style_path(root_directory, new_fs_file_path),
+    ) else {
+        return Ok(vec![]);
+    };
+    if old_import_path == new_import_path {
+        return Ok(vec![]);
+    }
+
+    let mut updated_files = vec![];
+    let mut directories_to_visit = vec![std::path::PathBuf::from(root_directory)];
+    while let Some(directory) = directories_to_visit.pop() {
+        let mut entries = tokio::fs::read_dir(&directory).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.is_dir() {
+                let is_skipped = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .map(|name| SKIPPED_DIRECTORIES.contains(&name))
+                    .unwrap_or_default();
+                if !is_skipped {
+                    directories_to_visit.push(path);
+                }
+                continue;
+            }
+
+            // the moved file itself already has its final content, no need
+            // to rewrite anything inside it
+            if path == std::path::Path::new(new_fs_file_path) {
+                continue;
+            }
+
+            let Ok(content) = tokio::fs::read_to_string(&path).await else {
+                continue;
+            };
+            if !content.contains(&old_import_path) {
+                continue;
+            }
+
+            let updated_content = content.replace(&old_import_path, &new_import_path);
+            tokio::fs::write(&path, updated_content).await?;
+            updated_files.push(path.to_string_lossy().into_owned());
+        }
+    }
+
+    Ok(updated_files)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MoveFileInput {
+    fs_file_path: String,
+    new_fs_file_path: String,
+    editor_url: String,
+}
+
+impl MoveFileInput {
+    pub fn new(fs_file_path: String, new_fs_file_path: String, editor_url: String) -> Self {
+        Self {
+            fs_file_path,
+            new_fs_file_path,
+            editor_url,
+        }
+    }
+}
+
+/// The partial version of `MoveFileInput` which the agent can invoke
+/// directly, before the `editor_url` is filled in by the session layer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MoveFileInputPartial {
+    fs_file_path: String,
+    new_fs_file_path: String,
+}
+
+impl MoveFileInputPartial {
+    pub fn new(fs_file_path: String, new_fs_file_path: String) -> Self {
+        Self {
+            fs_file_path,
+            new_fs_file_path,
+        }
+    }
+
+    pub fn fs_file_path(&self) -> &str {
+        &self.fs_file_path
+    }
+
+    pub fn new_fs_file_path(&self) -> &str {
+        &self.new_fs_file_path
+    }
+
+    pub fn to_string(&self) -> String {
+        format!(
+            r#"<move_file>
+<fs_file_path>
+{}
+</fs_file_path>
+<new_fs_file_path>
+{}
+</new_fs_file_path>
+</move_file>"#,
+            &self.fs_file_path, &self.new_fs_file_path
+        )
+    }
+}
+
+/// Shape of the response the editor sends back over http.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct EditorMoveFileResponse {
+    moved: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MoveFileOutput {
+    moved: bool,
+}
+
+impl MoveFileOutput {
+    pub fn new(moved: bool) -> Self {
+        Self { moved }
+    }
+
+    pub fn is_moved(&self) -> bool {
+        self.moved
+    }
+}
+
+pub struct LSPMoveFile {
+    client: reqwest::Client,
+}
+
+impl LSPMoveFile {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for LSPMoveFile {
+    async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
+        let context = input.is_file_move()?;
+        // when there is no editor to delegate to (e.g. running headless) we
+        // fall back to renaming the file ourselves instead of failing outright
+        if context.editor_url.is_empty() {
+            let moved = tokio::fs::rename(&context.fs_file_path, &context.new_fs_file_path)
+                .await
+                .is_ok();
+            return Ok(ToolOutput::FileMove(MoveFileOutput::new(moved)));
+        }
+        let editor_endpoint = context.editor_url.to_owned() + "/move_file";
+        let response = self
+            .client
+            .post(editor_endpoint)
+            .body(serde_json::to_string(&context).map_err(|_e| ToolError::SerdeConversionFailed)?)
+            .send()
+            .await
+            .map_err(|_e| ToolError::ErrorCommunicatingWithEditor)?;
+        let moved: bool = response
+            .json()
+            .await
+            .map(|response: EditorMoveFileResponse| response.moved)
+            .map_err(|_e| ToolError::ErrorCommunicatingWithEditor)?;
+        Ok(ToolOutput::FileMove(MoveFileOutput::new(moved)))
+    }
+
+    fn tool_description(&self) -> String {
+        format!(
+            r#"### move_file
+Request to move or rename the file at the specified path through the editor, instead of running a shell command like \`mv\`.
+Use this when a file needs to be relocated or renamed as part of the task. Other files that still import the old path may need to be updated afterwards."#
+        )
+    }
+
+    fn tool_input_format(&self) -> String {
+        format!(
+            r#"Parameters:
+- fs_file_path: (required) The absolute path of the file to move.
+- new_fs_file_path: (required) The absolute path to move the file to.
+
+Usage:
+<move_file>
+<fs_file_path>
+File path here
+</fs_file_path>
+<new_fs_file_path>
+New file path here
+</new_fs_file_path>
+</move_file>
+"#
+        )
+    }
+}
@@ -35,6 +35,11 @@ impl OpenFileRequestPartial {
 pub struct OpenFileRequest {
     fs_file_path: String,
     editor_url: String,
+    /// When set, only the (start_line, end_line) slice (0-indexed, inclusive)
+    /// of the file is returned instead of the whole file, saving context
+    /// tokens on large files.
+    #[serde(default)]
+    range: Option<(usize, usize)>,
 }
 
 impl OpenFileRequest {
@@ -42,8 +47,30 @@ impl OpenFileRequest {
         Self {
             fs_file_path,
             editor_url,
+            range: None,
         }
     }
+
+    pub fn with_range(fs_file_path: String, editor_url: String, range: (usize, usize)) -> Self {
+        Self {
+            fs_file_path,
+            editor_url,
+            range: Some(range),
+        }
+    }
+
+    pub fn range(&self) -> Option<(usize, usize)> {
+        self.range
+    }
+}
+
+/// Files never legitimately contain a NUL byte in the way source, config or
+/// other text the agent cares about would; a JSON-decodable string
+/// containing one is our proxy for "this is actually binary content"
+/// (images, compiled artifacts, etc.) which just happens to have survived
+/// the editor's UTF-8 round trip.
+pub fn looks_like_binary(content: &str) -> bool {
+    content.contains('\0')
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -55,6 +82,24 @@ pub struct OpenFileResponse {
     language: String,
 }
 
+impl OpenFileResponse {
+    /// Whether the contents we read back look like a binary blob rather
+    /// than text.
+    pub fn is_binary(&self) -> bool {
+        self.exists && looks_like_binary(&self.file_contents)
+    }
+
+    /// Swaps the file contents for a short "binary file" marker, so the
+    /// agent doesn't try to read or search/replace raw binary data.
+    pub fn redacted_for_binary(self) -> Self {
+        let byte_count = self.file_contents.len();
+        Self {
+            file_contents: format!("<binary file, {} bytes, contents not shown>", byte_count),
+            ..self
+        }
+    }
+}
+
 impl OpenFileResponse {
     pub fn to_string(&self) -> String {
         let fs_file_path = &self.fs_file_path;
@@ -154,6 +199,76 @@ impl OpenFileResponse {
             .len()
     }
 
+    /// Best-effort orientation aid for a ranged read: walks backwards from
+    /// `start_line` for the nearest line that looks like the start of a
+    /// symbol definition at or above the same indentation. This is a
+    /// heuristic over the raw text rather than a real outline lookup, but is
+    /// enough to tell the agent what symbol a narrow slice sits inside of.
+    pub fn nearest_enclosing_symbol_name(&self, start_line: usize) -> Option<String> {
+        const DEFINITION_KEYWORDS: &[&str] = &[
+            "pub fn ", "fn ", "pub struct ", "struct ", "pub enum ", "enum ", "pub trait ",
+            "trait ", "impl ", "class ", "def ", "function ", "interface ",
+        ];
+        let lines = self.file_contents.lines().collect::<Vec<_>>();
+        if lines.is_empty() || start_line >= lines.len() {
+            return None;
+        }
+        let target_indent = lines[start_line].len() - lines[start_line].trim_start().len();
+        for line in lines[..=start_line].iter().rev() {
+            let trimmed = line.trim_start();
+            let indent = line.len() - trimmed.len();
+            if indent > target_indent {
+                continue;
+            }
+            if let Some(keyword) = DEFINITION_KEYWORDS
+                .iter()
+                .find(|keyword| trimmed.starts_with(**keyword))
+            {
+                let symbol_name = trimmed[keyword.len()..]
+                    .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .next()
+                    .unwrap_or("");
+                if !symbol_name.is_empty() {
+                    return Some(symbol_name.to_owned());
+                }
+            }
+        }
+        None
+    }
+
+    /// Restricts this response to `start_line..=end_line` (0-indexed,
+    /// inclusive, clamped to the file's bounds), noting the enclosing symbol
+    /// so the slice still has some orientation for the reader.
+    pub fn sliced_to_range(self, start_line: usize, end_line: usize) -> Self {
+        if !self.exists {
+            return self;
+        }
+        let symbol_name = self.nearest_enclosing_symbol_name(start_line);
+        let lines = self.file_contents.lines().collect::<Vec<_>>();
+        let end_line = end_line.min(lines.len().saturating_sub(1));
+        let sliced_contents = if start_line > end_line {
+            "".to_owned()
+        } else {
+            lines[start_line..=end_line].join("\n")
+        };
+        let annotated_contents = match symbol_name {
+            Some(symbol_name) => format!(
+                "// showing lines {}-{} (inside {})\n{}",
+                start_line, end_line, symbol_name, sliced_contents
+            ),
+            None => format!(
+                "// showing lines {}-{}\n{}",
+                start_line, end_line, sliced_contents
+            ),
+        };
+        Self {
+            fs_file_path: self.fs_file_path,
+            file_contents: annotated_contents,
+            exists: self.exists,
+            language: self.language,
+        }
+    }
+
     pub fn full_range(&self) -> Range {
         let mut file_content_len = self.file_content_len();
         if file_content_len != 0 {
@@ -183,6 +298,7 @@ impl Tool for LSPOpenFile {
     async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
         // we want to create a new file open request over here
         let context = input.is_file_open()?;
+        let range = context.range();
         // now we send it over to the editor
         let editor_endpoint = context.editor_url.to_owned() + "/file_open";
         let response = self
@@ -196,6 +312,17 @@ impl Tool for LSPOpenFile {
             .json()
             .await
             .map_err(|_e| ToolError::ErrorCommunicatingWithEditor)?;
+        // do not try to slice or hand back raw binary content, the agent
+        // only gets to know the file is binary and how big it is
+        if response.is_binary() {
+            return Ok(ToolOutput::FileOpen(response.redacted_for_binary()));
+        }
+        // the editor always hands back the whole file, so a range read is
+        // just a client-side slice of that response
+        let response = match range {
+            Some((start_line, end_line)) => response.sliced_to_range(start_line, end_line),
+            None => response,
+        };
         Ok(ToolOutput::FileOpen(response))
     }
 
@@ -223,3 +350,70 @@ File path here
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{looks_like_binary, OpenFileResponse};
+
+    #[test]
+    fn test_sliced_to_range_returns_only_requested_lines() {
+        let file_contents = r#"fn outer() {
+    let a = 1;
+    let b = 2;
+    let c = 3;
+}
+
+fn other() {
+    let d = 4;
+}"#
+        .to_owned();
+        let response = OpenFileResponse::new(
+            "test.rs".to_owned(),
+            file_contents,
+            true,
+            "rust".to_owned(),
+        );
+
+        let sliced = response.sliced_to_range(1, 2);
+        assert_eq!(
+            sliced.contents_ref(),
+            "// showing lines 1-2 (inside outer)\n    let a = 1;\n    let b = 2;"
+        );
+    }
+
+    #[test]
+    fn test_sliced_to_range_clamps_to_file_bounds() {
+        let response = OpenFileResponse::new(
+            "test.rs".to_owned(),
+            "line_0\nline_1\nline_2".to_owned(),
+            true,
+            "rust".to_owned(),
+        );
+
+        let sliced = response.sliced_to_range(1, 100);
+        assert_eq!(sliced.contents_ref(), "// showing lines 1-2\nline_1\nline_2");
+    }
+
+    #[test]
+    fn test_looks_like_binary_detects_null_bytes() {
+        assert!(looks_like_binary("\u{0}PNG\r\n"));
+        assert!(!looks_like_binary("fn main() {}"));
+    }
+
+    #[test]
+    fn test_redacted_for_binary_hides_contents() {
+        let response = OpenFileResponse::new(
+            "image.png".to_owned(),
+            "\u{0}PNG\r\n".to_owned(),
+            true,
+            "".to_owned(),
+        );
+
+        assert!(response.is_binary());
+        let redacted = response.redacted_for_binary();
+        assert_eq!(
+            redacted.contents_ref(),
+            "<binary file, 6 bytes, contents not shown>"
+        );
+    }
+}
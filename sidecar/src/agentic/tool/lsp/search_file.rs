@@ -130,6 +130,8 @@ pub struct SearchFileContentInputPartial {
     directory_path: String,
     regex_pattern: String,
     file_pattern: Option<String>,
+    #[serde(default)]
+    exclude_pattern: Option<String>,
 }
 
 impl SearchFileContentInputPartial {
@@ -142,9 +144,15 @@ impl SearchFileContentInputPartial {
             directory_path,
             regex_pattern,
             file_pattern,
+            exclude_pattern: None,
         }
     }
 
+    pub fn with_exclude_pattern(mut self, exclude_pattern: Option<String>) -> Self {
+        self.exclude_pattern = exclude_pattern;
+        self
+    }
+
     pub fn directory_path(&self) -> &str {
         &self.directory_path
     }
@@ -157,6 +165,10 @@ impl SearchFileContentInputPartial {
         self.file_pattern.as_deref()
     }
 
+    pub fn exclude_pattern(&self) -> Option<&str> {
+        self.exclude_pattern.as_deref()
+    }
+
     pub fn to_string(&self) -> String {
         format!(
             r#"<search_files>
@@ -169,10 +181,16 @@ impl SearchFileContentInputPartial {
 <file_pattern>
 {}
 </file_pattern>
+<exclude_pattern>
+{}
+</exclude_pattern>
 </search_files>"#,
             self.directory_path,
             self.regex_pattern,
             self.file_pattern
+                .clone()
+                .unwrap_or("not provided".to_owned()),
+            self.exclude_pattern
                 .clone()
                 .unwrap_or("not provided".to_owned())
         )
@@ -184,6 +202,7 @@ pub struct SearchFileContentInput {
     directory_path: String,
     regex_pattern: String,
     file_pattern: Option<String>,
+    exclude_pattern: Option<String>,
     editor_url: String,
 }
 
@@ -192,12 +211,14 @@ impl SearchFileContentInput {
         directory_path: String,
         regex_pattern: String,
         file_pattern: Option<String>,
+        exclude_pattern: Option<String>,
         editor_url: String,
     ) -> Self {
         Self {
             directory_path,
             regex_pattern,
             file_pattern,
+            exclude_pattern,
             editor_url,
         }
     }
@@ -240,16 +261,26 @@ impl Tool for SearchFileContentClient {
         let binary_path = response.rip_grep_path;
         let regex_pattern = &context.regex_pattern;
         let file_pattern = &context.file_pattern.unwrap_or("*".to_owned());
-        let args = vec![
-            "--json",
-            "-e",
-            regex_pattern,
-            "--glob",
-            file_pattern,
-            "--context",
-            "1",
-            &context.directory_path,
+        // ripgrep applies `--glob` args in order, and a `!`-prefixed glob
+        // excludes rather than includes, so the exclude pattern (when given)
+        // is just another `--glob` flag alongside the include one
+        let exclude_glob = context
+            .exclude_pattern
+            .map(|exclude_pattern| format!("!{exclude_pattern}"));
+        let mut args = vec![
+            "--json".to_owned(),
+            "-e".to_owned(),
+            regex_pattern.to_owned(),
+            "--glob".to_owned(),
+            file_pattern.to_owned(),
         ];
+        if let Some(exclude_glob) = &exclude_glob {
+            args.push("--glob".to_owned());
+            args.push(exclude_glob.to_owned());
+        }
+        args.push("--context".to_owned());
+        args.push("1".to_owned());
+        args.push(context.directory_path.to_owned());
 
         println!("search_files::args::({:?})", args);
 
@@ -361,6 +392,7 @@ This tool searches for patterns or specific content across multiple files, displ
 - directory_path: (required) The absolute path of the directory to search in. This directory will be recursively searched.
 - regex_pattern: (required) The regular expression pattern to search for. Uses Rust regex syntax.
 - file_pattern: (optional) Glob pattern to filter files (e.g., '*.ts' for TypeScript files). If not provided, it will search all files (*).
+- exclude_pattern: (optional) Glob pattern for files to exclude from the search (e.g., '*.test.ts' to skip test files).
 
 Usage:
 <search_files>
@@ -373,6 +405,9 @@ Your regex pattern here
 <file_pattern>
 file pattern here (optional)
 </file_pattern>
+<exclude_pattern>
+exclude pattern here (optional)
+</exclude_pattern>
 </search_files>
 "#
         )
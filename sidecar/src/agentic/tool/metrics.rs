@@ -0,0 +1,92 @@
+//! A lightweight hook for observing tool invocations: how often each tool
+//! runs, how long it takes, and whether it succeeded. `ToolBroker` calls this
+//! around every `invoke`, so operators can wire it up to whatever telemetry
+//! backend (Prometheus, statsd, ...) they care about without the broker
+//! needing to know about any of them.
+
+use std::time::Duration;
+
+use super::r#type::ToolType;
+
+/// Implemented by anything which wants to observe tool invocations.
+pub trait ToolMetrics {
+    fn record(&self, tool: ToolType, duration: Duration, success: bool);
+}
+
+/// Default implementation which does nothing; used when no metrics backend
+/// has been wired up.
+pub struct NoOpToolMetrics;
+
+impl ToolMetrics for NoOpToolMetrics {
+    fn record(&self, _tool: ToolType, _duration: Duration, _success: bool) {}
+}
+
+/// Emits one log line per invocation. Good enough to eyeball tool behaviour
+/// locally; operators wanting Prometheus/statsd should implement `ToolMetrics`
+/// themselves and forward into their backend of choice.
+pub struct LoggingToolMetrics;
+
+impl ToolMetrics for LoggingToolMetrics {
+    fn record(&self, tool: ToolType, duration: Duration, success: bool) {
+        tracing::info!(
+            event_name = "tool_invocation",
+            tool = ?tool,
+            duration_ms = duration.as_millis() as u64,
+            success,
+            "tool invocation recorded",
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    pub(crate) struct RecordingToolMetrics {
+        recorded: Mutex<Vec<(ToolType, Duration, bool)>>,
+    }
+
+    impl RecordingToolMetrics {
+        pub(crate) fn recorded(&self) -> Vec<(ToolType, Duration, bool)> {
+            self.recorded.lock().expect("lock poisoned").clone()
+        }
+    }
+
+    impl ToolMetrics for RecordingToolMetrics {
+        fn record(&self, tool: ToolType, duration: Duration, success: bool) {
+            self.recorded
+                .lock()
+                .expect("lock poisoned")
+                .push((tool, duration, success));
+        }
+    }
+
+    #[test]
+    fn test_recording_metrics_tracks_counts_and_outcomes() {
+        let metrics = RecordingToolMetrics::default();
+
+        metrics.record(ToolType::ListFiles, Duration::from_millis(10), true);
+        metrics.record(ToolType::ListFiles, Duration::from_millis(20), false);
+        metrics.record(ToolType::OpenFile, Duration::from_millis(5), true);
+
+        let recorded = metrics.recorded();
+        assert_eq!(recorded.len(), 3);
+
+        let list_files_calls: Vec<_> = recorded
+            .iter()
+            .filter(|(tool, _, _)| *tool == ToolType::ListFiles)
+            .collect();
+        assert_eq!(list_files_calls.len(), 2);
+        assert!(list_files_calls.iter().any(|(_, _, success)| *success));
+        assert!(list_files_calls.iter().any(|(_, _, success)| !*success));
+
+        let open_file_calls: Vec<_> = recorded
+            .iter()
+            .filter(|(tool, _, _)| *tool == ToolType::OpenFile)
+            .collect();
+        assert_eq!(open_file_calls.len(), 1);
+        assert!(open_file_calls[0].2);
+    }
+}
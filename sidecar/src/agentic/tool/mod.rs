@@ -17,6 +17,7 @@
 pub mod broker;
 pub mod code_edit;
 pub mod code_symbol;
+pub mod context_guard;
 pub mod editor;
 pub mod errors;
 pub mod file;
@@ -29,11 +30,14 @@ pub mod input;
 pub mod jitter;
 pub mod kw_search;
 pub mod lsp;
+pub mod metrics;
 pub mod output;
 pub mod plan;
+pub mod rate_limiter;
 pub mod ref_filter;
 pub mod repo_map;
 pub mod rerank;
+pub mod rust;
 pub mod search;
 pub mod session;
 pub mod swe_bench;
@@ -3,6 +3,8 @@
 use crate::agentic::symbol::ui_event::RelevantReference;
 
 use super::{
+    errors::ToolError,
+    r#type::ToolType,
     code_edit::{
         filter_edit::FilterEditOperationResponse,
         search_and_replace::SearchAndReplaceEditingResponse,
@@ -35,9 +37,13 @@ use super::{
     grep::file::FindInFileResponse,
     lsp::{
         create_file::CreateFileResponse,
+        delete_file::DeleteFileOutput,
         diagnostics::LSPDiagnosticsOutput,
+        duplicate_symbol::DuplicateSymbolOutput,
+        extract_function::ExtractFunctionOutput,
         file_diagnostics::FileDiagnosticsOutput,
-        get_outline_nodes::OutlineNodesUsingEditorResponse,
+        get_outline_nodes::{GetOutlineNodesOutput, OutlineNodesUsingEditorResponse},
+        get_workspace_symbols::GetWorkspaceSymbolsOutput,
         go_to_previous_word::GoToPreviousWordResponse,
         gotodefintion::GoToDefinitionResponse,
         gotoimplementations::GoToImplementationResponse,
@@ -45,6 +51,8 @@ use super::{
         grep_symbol::LSPGrepSymbolInCodebaseResponse,
         inlay_hints::InlayHintsResponse,
         list_files::ListFilesOutput,
+        list_open_files::ListOpenFilesOutput,
+        move_file::MoveFileOutput,
         open_file::OpenFileResponse,
         quick_fix::{GetQuickFixResponse, LSPQuickFixInvocationResponse},
         search_file::SearchFileContentWithRegexOutput,
@@ -54,10 +62,16 @@ use super::{
     plan::{generator::StepGeneratorResponse, reasoning::ReasoningResponse},
     repo_map::generator::RepoMapGeneratorResponse,
     rerank::base::ReRankEntriesForBroker,
+    rust::inline_value::InlineValueOutput,
     session::{
         ask_followup_question::AskFollowupQuestionsResponse,
         attempt_completion::AttemptCompletionClientResponse, chat::SessionChatClientResponse,
         exchange::SessionExchangeNewResponse, hot_streak::SessionHotStreakResponse,
+        code_review::CodeReviewOutput,
+        find_symbol_definition::FindSymbolDefinitionOutput,
+        run_tests::RunTestsOutput,
+        show_diff::ShowDiffOutput,
+        summarize_context::SummarizeContextResponse,
     },
     swe_bench::test_tool::SWEBenchTestRepsonse,
     terminal::terminal::TerminalOutput,
@@ -187,6 +201,12 @@ pub enum ToolOutput {
     StepGenerator(StepGeneratorResponse),
     // File create
     FileCreate(CreateFileResponse),
+    // File delete
+    FileDelete(DeleteFileOutput),
+    // File move/rename
+    FileMove(MoveFileOutput),
+    // Duplicated symbol
+    DuplicateSymbol(DuplicateSymbolOutput),
     // File diagnostics
     FileDiagnostics(FileDiagnosticsOutput),
     // Plan add step
@@ -217,6 +237,26 @@ pub enum ToolOutput {
     RepoMapGeneration(RepoMapGeneratorResponse),
     // spawned subprocess and their output which is pending
     SubProcessSpawnedPendingOutput(SubProcessSpanwedPendingOutputResponse),
+    // summary produced for compacting the session's context
+    SummarizeContext(SummarizeContextResponse),
+    // full diff fetched back for a file whose edit summary was condensed
+    ShowDiff(ShowDiffOutput),
+    // clean/needs-work verdict from a code review pass
+    CodeReview(CodeReviewOutput),
+    // condensed symbol map (outline nodes) for a file
+    GetOutlineNodes(GetOutlineNodesOutput),
+    // files the editor currently has open in buffers
+    ListOpenFiles(ListOpenFilesOutput),
+    // result of extracting a selection into its own function
+    ExtractFunction(ExtractFunctionOutput),
+    // result of evaluating a constant expression inline
+    InlineValue(InlineValueOutput),
+    // definition location and snippet resolved for a symbol name
+    FindSymbolDefinition(FindSymbolDefinitionOutput),
+    // symbols matching a name prefix across the whole workspace
+    GetWorkspaceSymbols(GetWorkspaceSymbolsOutput),
+    // structured pass/fail result of running the project's test suite
+    RunTests(RunTestsOutput),
 }
 
 impl ToolOutput {
@@ -238,6 +278,61 @@ impl ToolOutput {
         ToolOutput::ContextDriveHotStreakReply(response)
     }
 
+    pub fn summarize_context(response: SummarizeContextResponse) -> Self {
+        ToolOutput::SummarizeContext(response)
+    }
+
+    pub fn show_diff(response: ShowDiffOutput) -> Self {
+        ToolOutput::ShowDiff(response)
+    }
+
+    pub fn get_show_diff_response(self) -> Option<ShowDiffOutput> {
+        match self {
+            ToolOutput::ShowDiff(response) => Some(response),
+            _ => None,
+        }
+    }
+
+    pub fn code_review(response: CodeReviewOutput) -> Self {
+        ToolOutput::CodeReview(response)
+    }
+
+    pub fn get_code_review_response(self) -> Option<CodeReviewOutput> {
+        match self {
+            ToolOutput::CodeReview(response) => Some(response),
+            _ => None,
+        }
+    }
+
+    pub fn find_symbol_definition(response: FindSymbolDefinitionOutput) -> Self {
+        ToolOutput::FindSymbolDefinition(response)
+    }
+
+    pub fn get_find_symbol_definition_response(self) -> Option<FindSymbolDefinitionOutput> {
+        match self {
+            ToolOutput::FindSymbolDefinition(response) => Some(response),
+            _ => None,
+        }
+    }
+
+    pub fn get_workspace_symbols(response: GetWorkspaceSymbolsOutput) -> Self {
+        ToolOutput::GetWorkspaceSymbols(response)
+    }
+
+    pub fn get_workspace_symbols_response(self) -> Option<GetWorkspaceSymbolsOutput> {
+        match self {
+            ToolOutput::GetWorkspaceSymbols(response) => Some(response),
+            _ => None,
+        }
+    }
+
+    pub fn get_run_tests_response(self) -> Option<RunTestsOutput> {
+        match self {
+            ToolOutput::RunTests(response) => Some(response),
+            _ => None,
+        }
+    }
+
     pub fn undo_changes_made_during_session(
         response: UndoChangesMadeDuringExchangeRespnose,
     ) -> Self {
@@ -272,6 +367,18 @@ impl ToolOutput {
         ToolOutput::FileCreate(response)
     }
 
+    pub fn file_delete(response: DeleteFileOutput) -> Self {
+        ToolOutput::FileDelete(response)
+    }
+
+    pub fn file_move(response: MoveFileOutput) -> Self {
+        ToolOutput::FileMove(response)
+    }
+
+    pub fn duplicate_symbol(response: DuplicateSymbolOutput) -> Self {
+        ToolOutput::DuplicateSymbol(response)
+    }
+
     pub fn edited_files(response: EditedFilesResponse) -> Self {
         ToolOutput::EditedFiles(response)
     }
@@ -279,6 +386,18 @@ impl ToolOutput {
         ToolOutput::OutlineNodesUsingEditor(response)
     }
 
+    pub fn get_outline_nodes(response: GetOutlineNodesOutput) -> Self {
+        ToolOutput::GetOutlineNodes(response)
+    }
+
+    pub fn list_open_files(response: ListOpenFilesOutput) -> Self {
+        ToolOutput::ListOpenFiles(response)
+    }
+
+    pub fn extract_function(response: ExtractFunctionOutput) -> Self {
+        ToolOutput::ExtractFunction(response)
+    }
+
     pub fn git_diff_response(response: GitDiffClientResponse) -> Self {
         ToolOutput::GitDiff(response)
     }
@@ -459,6 +578,21 @@ impl ToolOutput {
         }
     }
 
+    /// Like [`Self::get_file_open_response`] but returns a [`ToolError`] naming
+    /// the unexpected variant instead of `None`.
+    pub fn checked_file_open_response(self) -> Result<OpenFileResponse, ToolError> {
+        match self {
+            ToolOutput::FileOpen(file_open) => Ok(file_open),
+            other => {
+                let got = other.variant_name();
+                Err(ToolError::UnexpectedOutput {
+                    expected: ToolType::OpenFile,
+                    got,
+                })
+            }
+        }
+    }
+
     pub fn grep_single_file(self) -> Option<FindInFileResponse> {
         match self {
             ToolOutput::GrepSingleFile(grep_single_file) => Some(grep_single_file),
@@ -724,6 +858,13 @@ impl ToolOutput {
         }
     }
 
+    pub fn get_outline_nodes_output(self) -> Option<GetOutlineNodesOutput> {
+        match self {
+            ToolOutput::GetOutlineNodes(response) => Some(response),
+            _ => None,
+        }
+    }
+
     pub fn get_relevant_references(self) -> Option<Vec<RelevantReference>> {
         match self {
             ToolOutput::ReferencesFilter(response) => Some(response),
@@ -759,6 +900,34 @@ impl ToolOutput {
         }
     }
 
+    pub fn get_file_delete_response(self) -> Option<DeleteFileOutput> {
+        match self {
+            ToolOutput::FileDelete(response) => Some(response),
+            _ => None,
+        }
+    }
+
+    pub fn get_file_move_response(self) -> Option<MoveFileOutput> {
+        match self {
+            ToolOutput::FileMove(response) => Some(response),
+            _ => None,
+        }
+    }
+
+    pub fn get_duplicate_symbol_response(self) -> Option<DuplicateSymbolOutput> {
+        match self {
+            ToolOutput::DuplicateSymbol(response) => Some(response),
+            _ => None,
+        }
+    }
+
+    pub fn get_inline_value_output(self) -> Option<InlineValueOutput> {
+        match self {
+            ToolOutput::InlineValue(response) => Some(response),
+            _ => None,
+        }
+    }
+
     pub fn file_diagnostics(output: FileDiagnosticsOutput) -> Self {
         ToolOutput::FileDiagnostics(output)
     }
@@ -821,6 +990,13 @@ impl ToolOutput {
         }
     }
 
+    pub fn get_summarize_context_response(self) -> Option<SummarizeContextResponse> {
+        match self {
+            ToolOutput::SummarizeContext(response) => Some(response),
+            _ => None,
+        }
+    }
+
     pub fn terminal_command(self) -> Option<TerminalOutput> {
         match self {
             ToolOutput::TerminalCommand(response) => Some(response),
@@ -849,6 +1025,82 @@ impl ToolOutput {
         }
     }
 
+    /// Name of the variant we are holding, used only to name the mismatch when a
+    /// `checked_*` accessor below is called against the wrong `ToolOutput` variant.
+    fn variant_name(&self) -> String {
+        let debug = format!("{:?}", self);
+        debug
+            .split(['(', ' '])
+            .next()
+            .unwrap_or(&debug)
+            .to_owned()
+    }
+
+    /// Like [`Self::terminal_command`] but surfaces a mismatch as a [`ToolError`]
+    /// instead of silently discarding which variant was actually returned, so
+    /// callers can turn it into a corrective message for the agent.
+    pub fn checked_terminal_command(self) -> Result<TerminalOutput, ToolError> {
+        match self {
+            ToolOutput::TerminalCommand(response) => Ok(response),
+            other => {
+                let got = other.variant_name();
+                Err(ToolError::UnexpectedOutput {
+                    expected: ToolType::TerminalCommand,
+                    got,
+                })
+            }
+        }
+    }
+
+    /// Like [`Self::get_search_file_content_with_regex`] but returns a [`ToolError`]
+    /// naming the unexpected variant instead of `None`.
+    pub fn checked_search_file_content_with_regex(
+        self,
+    ) -> Result<SearchFileContentWithRegexOutput, ToolError> {
+        match self {
+            ToolOutput::SearchFileContentWithRegex(response) => Ok(response),
+            other => {
+                let got = other.variant_name();
+                Err(ToolError::UnexpectedOutput {
+                    expected: ToolType::SearchFileContentWithRegex,
+                    got,
+                })
+            }
+        }
+    }
+
+    /// Like [`Self::get_list_files_directory`] but returns a [`ToolError`] naming
+    /// the unexpected variant instead of `None`.
+    pub fn checked_list_files_directory(self) -> Result<ListFilesOutput, ToolError> {
+        match self {
+            ToolOutput::ListFiles(response) => Ok(response),
+            other => {
+                let got = other.variant_name();
+                Err(ToolError::UnexpectedOutput {
+                    expected: ToolType::ListFiles,
+                    got,
+                })
+            }
+        }
+    }
+
+    /// Like [`Self::repo_map_generator_response`] but returns a [`ToolError`]
+    /// naming the unexpected variant instead of `None`.
+    pub fn checked_repo_map_generator_response(
+        self,
+    ) -> Result<RepoMapGeneratorResponse, ToolError> {
+        match self {
+            ToolOutput::RepoMapGeneration(response) => Ok(response),
+            other => {
+                let got = other.variant_name();
+                Err(ToolError::UnexpectedOutput {
+                    expected: ToolType::RepoMapGeneration,
+                    got,
+                })
+            }
+        }
+    }
+
     pub fn get_pending_spawned_process_output(
         self,
     ) -> Option<SubProcessSpanwedPendingOutputResponse> {
@@ -857,4 +1109,47 @@ impl ToolOutput {
             _ => None,
         }
     }
+
+    pub fn get_list_open_files(self) -> Option<ListOpenFilesOutput> {
+        match self {
+            ToolOutput::ListOpenFiles(response) => Some(response),
+            _ => None,
+        }
+    }
+
+    pub fn get_extract_function_response(self) -> Option<ExtractFunctionOutput> {
+        match self {
+            ToolOutput::ExtractFunction(response) => Some(response),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agentic::tool::terminal::terminal::TerminalOutput;
+
+    #[test]
+    fn checked_terminal_command_returns_the_output_on_a_matching_variant() {
+        let terminal_output: TerminalOutput =
+            serde_json::from_str(r#"{"output": "ok"}"#).expect("valid terminal output fixture");
+        let output = ToolOutput::TerminalCommand(terminal_output);
+        assert!(output.checked_terminal_command().is_ok());
+    }
+
+    #[test]
+    fn checked_terminal_command_names_the_mismatch_on_the_wrong_variant() {
+        let output = ToolOutput::CodeEditTool("diff".to_owned());
+        let error = output
+            .checked_terminal_command()
+            .expect_err("a code-edit output should not satisfy a terminal-command extraction");
+        match error {
+            ToolError::UnexpectedOutput { expected, got } => {
+                assert_eq!(expected, ToolType::TerminalCommand);
+                assert_eq!(got, "CodeEditTool");
+            }
+            other => panic!("expected UnexpectedOutput, got {other:?}"),
+        }
+    }
 }
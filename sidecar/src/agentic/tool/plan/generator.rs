@@ -502,7 +502,9 @@ impl Tool for StepGeneratorClient {
         let is_deep_reasoning = context.is_deep_reasoning;
         let stream_steps = context.stream_steps.clone();
 
-        let mut messages = vec![LLMClientMessage::system(Self::system_message())];
+        // the system prompt never changes between plan generation calls, so
+        // it's always worth marking as a cache point
+        let mut messages = vec![LLMClientMessage::system(Self::system_message()).cache_point()];
         // Add the previous running messages over here
         messages.extend(previous_messages.into_iter().map(|previous_message| {
             match previous_message.role() {
@@ -512,8 +514,19 @@ impl Tool for StepGeneratorClient {
                 SessionChatRole::Assistant => {
                     LLMClientMessage::assistant(previous_message.message().to_owned())
                 }
+                SessionChatRole::ToolOutput => LLMClientMessage::user(
+                    crate::agentic::tool::helpers::prompt_injection::wrap_untrusted_tool_output(
+                        previous_message.message(),
+                    ),
+                ),
             }
         }));
+        // everything up to this point is a repeat of the last iteration's
+        // request, so mark it as cacheable before appending the freshly
+        // varying tail
+        if let Some(last_message) = messages.last_mut() {
+            last_message.set_cache_point();
+        }
         messages.push(LLMClientMessage::user(
             Self::user_message(
                 context.user_query(),
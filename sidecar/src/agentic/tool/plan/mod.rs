@@ -1,6 +1,7 @@
 pub(crate) mod add_steps;
 pub mod generator;
 pub mod plan;
+pub mod plan_impact;
 pub mod plan_step;
 pub(crate) mod reasoning;
 pub mod service;
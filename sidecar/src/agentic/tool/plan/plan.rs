@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::{agentic::tool::lsp::open_file::OpenFileResponse, user_context::types::UserContext};
 
-use super::plan_step::PlanStep;
+use super::{plan_impact::PlanImpactSummary, plan_step::PlanStep};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Plan {
@@ -16,6 +16,10 @@ pub struct Plan {
     checkpoint: Option<usize>,
     storage_path: String,
     original_file_content: HashMap<String, OpenFileResponse>,
+    // populated once we have a "these files will likely change" pass over
+    // the generated steps; absent on plans persisted before this existed
+    #[serde(default)]
+    impact_summary: Option<PlanImpactSummary>,
 }
 
 impl Plan {
@@ -36,9 +40,22 @@ impl Plan {
             checkpoint: None,
             storage_path,
             original_file_content: Default::default(),
+            impact_summary: None,
         }
     }
 
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn impact_summary(&self) -> Option<&PlanImpactSummary> {
+        self.impact_summary.as_ref()
+    }
+
+    pub fn set_impact_summary(&mut self, impact_summary: PlanImpactSummary) {
+        self.impact_summary = Some(impact_summary);
+    }
+
     /// Drops the steps which are present in the plan until a point
     pub fn drop_plan_steps(mut self, drop_from: usize) -> Self {
         if drop_from < self.steps.len() {
@@ -54,6 +71,14 @@ impl Plan {
         &self.storage_path
     }
 
+    /// Re-homes this plan under a new id and storage path, used when a session
+    /// carrying this plan is forked so the copy is not mistaken for the original.
+    pub fn with_id_and_storage_path(mut self, id: String, storage_path: String) -> Self {
+        self.id = id;
+        self.storage_path = storage_path;
+        self
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -0,0 +1,68 @@
+//! A consolidated "these files will likely change" view over a plan, computed
+//! once step generation has finished so the editor can show it to the user
+//! before they approve execution.
+
+use serde::{Deserialize, Serialize};
+
+use crate::chunking::text_document::Range;
+
+/// A file already on disk which the plan's steps touch, along with the
+/// symbols (as reported by the outline) whose ranges overlap the change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanImpactedFile {
+    fs_file_path: String,
+    symbol_ranges: Vec<(String, Range)>,
+}
+
+impl PlanImpactedFile {
+    pub fn new(fs_file_path: String, symbol_ranges: Vec<(String, Range)>) -> Self {
+        Self {
+            fs_file_path,
+            symbol_ranges,
+        }
+    }
+
+    pub fn fs_file_path(&self) -> &str {
+        &self.fs_file_path
+    }
+
+    pub fn symbol_ranges(&self) -> &[(String, Range)] {
+        &self.symbol_ranges
+    }
+}
+
+/// Consolidated preview of what a plan is expected to touch, persisted
+/// alongside the plan so the editor can re-fetch it after the fact instead of
+/// having to recompute it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanImpactSummary {
+    plan_id: String,
+    impacted_files: Vec<PlanImpactedFile>,
+    files_to_be_created: Vec<String>,
+}
+
+impl PlanImpactSummary {
+    pub fn new(
+        plan_id: String,
+        impacted_files: Vec<PlanImpactedFile>,
+        files_to_be_created: Vec<String>,
+    ) -> Self {
+        Self {
+            plan_id,
+            impacted_files,
+            files_to_be_created,
+        }
+    }
+
+    pub fn plan_id(&self) -> &str {
+        &self.plan_id
+    }
+
+    pub fn impacted_files(&self) -> &[PlanImpactedFile] {
+        &self.impacted_files
+    }
+
+    pub fn files_to_be_created(&self) -> &[String] {
+        &self.files_to_be_created
+    }
+}
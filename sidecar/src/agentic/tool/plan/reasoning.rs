@@ -203,6 +203,7 @@ impl Tool for ReasoningClient {
                     fs_file_path.to_owned(),
                     exchange_id.to_owned(),
                     None,
+                    None,
                 )
                 .set_apply_directly(),
             )
@@ -59,6 +59,10 @@ impl PlanService {
         self.tool_box.clone()
     }
 
+    pub fn plan_storage_directory(&self) -> &std::path::Path {
+        &self.plan_storage_directory
+    }
+
     pub async fn save_plan(&self, plan: &Plan, path: &str) -> std::io::Result<()> {
         let serialized = serde_json::to_string(plan).unwrap();
         let mut file = tokio::fs::File::create(path).await?;
@@ -0,0 +1,132 @@
+//! A shared rate limiter sitting in front of every `ToolBroker` invocation,
+//! keyed per LLM provider. When many tools hit the same provider
+//! concurrently (edit fan-out, wide search, tool selection), they can
+//! collectively exceed the provider's requests-per-minute limit even though
+//! each tool individually respects it; acquiring a permit here before
+//! dispatch smooths those bursts out instead of tripping a 429 that none of
+//! the individual tools could have predicted on their own.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+use tokio::sync::Mutex;
+
+/// A single provider's bucket. Deliberately capped at one token rather than
+/// `requests_per_second` worth of burst capacity: this is meant to smooth a
+/// concurrent fan-out down to a steady rate, not to let a fan-out spend a
+/// saved-up burst all at once.
+struct TokenBucket {
+    requests_per_second: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(requests_per_second: f64) -> Self {
+        Self {
+            requests_per_second,
+            tokens: 0.0,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.requests_per_second).min(1.0);
+        self.last_refill = now;
+    }
+
+    /// Returns how long the caller should sleep before it can take a token,
+    /// or `None` if a token was available right now (and has been consumed).
+    fn try_acquire(&mut self) -> Option<Duration> {
+        self.refill();
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            None
+        } else {
+            let tokens_needed = 1.0 - self.tokens;
+            Some(Duration::from_secs_f64(
+                tokens_needed / self.requests_per_second,
+            ))
+        }
+    }
+}
+
+/// Rate limiter shared across every `ToolBroker` invocation, keyed per LLM
+/// provider name. Providers with no configured limit are left unthrottled.
+pub struct ToolRateLimiter {
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+    limits: HashMap<String, f64>,
+}
+
+impl ToolRateLimiter {
+    pub fn new(limits: HashMap<String, f64>) -> Self {
+        Self {
+            buckets: Mutex::new(HashMap::new()),
+            limits,
+        }
+    }
+
+    /// Blocks until a permit for `provider` is available. A no-op for
+    /// providers without a configured rate limit.
+    pub async fn acquire(&self, provider: &str) {
+        let Some(&requests_per_second) = self.limits.get(provider) else {
+            return;
+        };
+        loop {
+            let wait = {
+                let mut buckets = self.buckets.lock().await;
+                let bucket = buckets
+                    .entry(provider.to_owned())
+                    .or_insert_with(|| TokenBucket::new(requests_per_second));
+                bucket.try_acquire()
+            };
+            match wait {
+                None => return,
+                Some(duration) => tokio::time::sleep(duration).await,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn ten_concurrent_calls_at_2_per_second_take_at_least_five_seconds() {
+        let mut limits = HashMap::new();
+        limits.insert("test-provider".to_owned(), 2.0);
+        let limiter = Arc::new(ToolRateLimiter::new(limits));
+
+        let start = Instant::now();
+        let handles = (0..10)
+            .map(|_| {
+                let limiter = limiter.clone();
+                tokio::spawn(async move { limiter.acquire("test-provider").await })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        assert!(
+            start.elapsed() >= Duration::from_secs_f64(4.5),
+            "expected 10 permits at 2 req/s to take close to 5 seconds, took {:?}",
+            start.elapsed()
+        );
+    }
+
+    #[tokio::test]
+    async fn unconfigured_provider_is_not_throttled() {
+        let limiter = ToolRateLimiter::new(HashMap::new());
+        let start = Instant::now();
+        for _ in 0..10 {
+            limiter.acquire("unconfigured-provider").await;
+        }
+        assert!(start.elapsed() < Duration::from_millis(100));
+    }
+}
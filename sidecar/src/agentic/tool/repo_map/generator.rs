@@ -39,6 +39,11 @@ impl RepoMapGeneratorRequestPartial {
     }
 }
 
+/// The token budget `RepoMapGeneratorRequest` falls back to when the caller
+/// doesn't have a more specific budget of its own (e.g. derived from the
+/// target model's context window) to hand it.
+pub const DEFAULT_REPO_MAP_TOKEN_LIMIT: usize = 3000;
+
 #[derive(Debug, Clone)]
 pub struct RepoMapGeneratorRequest {
     directory_path: String,
@@ -57,16 +62,27 @@ impl RepoMapGeneratorRequest {
 #[derive(Debug, Clone)]
 pub struct RepoMapGeneratorResponse {
     repo_map: String,
+    // the token limit the map was generated against, so callers which use a
+    // caller-supplied budget can tell whether the map used their limit or
+    // fell back to `DEFAULT_REPO_MAP_TOKEN_LIMIT`
+    token_limit: usize,
 }
 
 impl RepoMapGeneratorResponse {
-    pub fn new(repo_map: String) -> Self {
-        Self { repo_map }
+    pub fn new(repo_map: String, token_limit: usize) -> Self {
+        Self {
+            repo_map,
+            token_limit,
+        }
     }
 
     pub fn repo_map(&self) -> &str {
         &self.repo_map
     }
+
+    pub fn token_limit(&self) -> usize {
+        self.token_limit
+    }
 }
 
 pub struct RepoMapGeneratorClient {}
@@ -106,7 +122,10 @@ impl Tool for RepoMapGeneratorClient {
         repo_map_string
             .map_err(|e| ToolError::RepoMapError(e))
             .map(|output| {
-                ToolOutput::repo_map_generation_reponse(RepoMapGeneratorResponse::new(output))
+                ToolOutput::repo_map_generation_reponse(RepoMapGeneratorResponse::new(
+                    output,
+                    token_count,
+                ))
             })
     }
 
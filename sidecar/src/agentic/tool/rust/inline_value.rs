@@ -0,0 +1,147 @@
+//! Evaluates a constant or compile-time expression without running the full
+//! test suite, so the agent can sanity-check a calculation (e.g. a bit-mask
+//! or a `const` computation) in a second or two instead of waiting on a full
+//! `cargo test` cycle.
+//!
+//! Only Rust is implemented today: we generate a minimal `fn main` which
+//! evaluates `expression` and prints its value and type, compile it with
+//! `rustc --edition 2021`, and run the resulting binary. `editor_url` and
+//! `language` are carried on the request so this can grow editor-proxied
+//! backends for other languages later, following the same shape as
+//! [`crate::agentic::tool::lsp::duplicate_symbol`]; for now any language
+//! other than `"rust"` is rejected with [`ToolError::NotSupportedLanguage`].
+
+use async_trait::async_trait;
+use std::process::Stdio;
+use tempfile::TempDir;
+use tokio::process::Command;
+
+use crate::agentic::tool::{errors::ToolError, input::ToolInput, output::ToolOutput, r#type::Tool};
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct InlineValueInput {
+    expression: String,
+    language: String,
+    context_imports: Vec<String>,
+    #[allow(dead_code)]
+    editor_url: String,
+}
+
+impl InlineValueInput {
+    pub fn new(
+        expression: String,
+        language: String,
+        context_imports: Vec<String>,
+        editor_url: String,
+    ) -> Self {
+        Self {
+            expression,
+            language,
+            context_imports,
+            editor_url,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct InlineValueOutput {
+    result: String,
+    type_name: String,
+}
+
+impl InlineValueOutput {
+    pub fn result(&self) -> &str {
+        &self.result
+    }
+
+    pub fn type_name(&self) -> &str {
+        &self.type_name
+    }
+}
+
+pub struct InlineValueClient {}
+
+impl InlineValueClient {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+async fn evaluate_rust_expression(
+    expression: &str,
+    context_imports: &[String],
+) -> Result<InlineValueOutput, ToolError> {
+    let imports = context_imports.join("\n");
+    let source = format!(
+        r#"{imports}
+fn main() {{
+    let __inline_value = {{ {expression} }};
+    println!("{{}}", std::any::type_name_of_val(&__inline_value));
+    println!("{{:?}}", __inline_value);
+}}
+"#,
+    );
+
+    let workdir = TempDir::new().map_err(|e| ToolError::IOError(e))?;
+    let source_path = workdir.path().join("main.rs");
+    let binary_path = workdir.path().join("main");
+    tokio::fs::write(&source_path, source)
+        .await
+        .map_err(|e| ToolError::IOError(e))?;
+
+    let compile_output = Command::new("rustc")
+        .arg("--edition")
+        .arg("2021")
+        .arg("-o")
+        .arg(&binary_path)
+        .arg(&source_path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .map_err(|e| ToolError::IOError(e))?;
+
+    if !compile_output.status.success() {
+        return Err(ToolError::InlineValueCompilationFailed(
+            String::from_utf8_lossy(&compile_output.stderr).into_owned(),
+        ));
+    }
+
+    let run_output = Command::new(&binary_path)
+        .output()
+        .await
+        .map_err(|e| ToolError::IOError(e))?;
+
+    if !run_output.status.success() {
+        return Err(ToolError::InlineValueCompilationFailed(
+            String::from_utf8_lossy(&run_output.stderr).into_owned(),
+        ));
+    }
+
+    let stdout = String::from_utf8_lossy(&run_output.stdout);
+    let mut lines = stdout.lines();
+    let type_name = lines.next().unwrap_or_default().to_owned();
+    let result = lines.next().unwrap_or_default().to_owned();
+
+    Ok(InlineValueOutput { result, type_name })
+}
+
+#[async_trait]
+impl Tool for InlineValueClient {
+    async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
+        let context = input.is_inline_value()?;
+        if context.language.to_lowercase() != "rust" {
+            return Err(ToolError::NotSupportedLanguage);
+        }
+        let output = evaluate_rust_expression(&context.expression, &context.context_imports).await?;
+        Ok(ToolOutput::InlineValue(output))
+    }
+
+    fn tool_description(&self) -> String {
+        "".to_owned()
+    }
+
+    fn tool_input_format(&self) -> String {
+        "".to_owned()
+    }
+}
@@ -0,0 +1,171 @@
+//! Sessions written out by `SessionService` accumulate indefinitely on disk.
+//! `SessionArchiver` sweeps a session directory for files which have not
+//! been touched in a while, optionally gzip-compresses them, and moves them
+//! out to a separate archive directory so the active session directory stays
+//! small.
+
+use std::path::{Path, PathBuf};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+
+use crate::agentic::symbol::errors::SymbolError;
+
+/// A single session which was moved into the archive directory.
+#[derive(Debug, Clone)]
+pub struct ArchivedSession {
+    original_path: PathBuf,
+    archive_path: PathBuf,
+}
+
+impl ArchivedSession {
+    fn new(original_path: PathBuf, archive_path: PathBuf) -> Self {
+        Self {
+            original_path,
+            archive_path,
+        }
+    }
+
+    pub fn original_path(&self) -> &Path {
+        &self.original_path
+    }
+
+    pub fn archive_path(&self) -> &Path {
+        &self.archive_path
+    }
+}
+
+/// Sweeps a session directory and moves old sessions out to `archive_dir`.
+pub struct SessionArchiver {
+    archive_dir: PathBuf,
+    max_age_days: u64,
+    compress: bool,
+}
+
+impl SessionArchiver {
+    pub fn new(archive_dir: PathBuf, max_age_days: u64, compress: bool) -> Self {
+        Self {
+            archive_dir,
+            max_age_days,
+            compress,
+        }
+    }
+
+    /// Finds sessions under `active_session_dir` which were last modified
+    /// more than `max_age_days` ago and moves them (optionally gzip
+    /// compressed) into `archive_dir`.
+    pub async fn archive_old_sessions(
+        &self,
+        active_session_dir: &Path,
+    ) -> Result<Vec<ArchivedSession>, SymbolError> {
+        tokio::fs::create_dir_all(&self.archive_dir)
+            .await
+            .map_err(SymbolError::IOError)?;
+
+        let max_age = std::time::Duration::from_secs(self.max_age_days * 24 * 60 * 60);
+        let now = std::time::SystemTime::now();
+
+        let mut archived_sessions = vec![];
+        let mut entries = tokio::fs::read_dir(active_session_dir)
+            .await
+            .map_err(SymbolError::IOError)?;
+        while let Some(entry) = entries.next_entry().await.map_err(SymbolError::IOError)? {
+            let path = entry.path();
+            if !path.is_file() {
+                continue;
+            }
+
+            let metadata = entry.metadata().await.map_err(SymbolError::IOError)?;
+            let modified = metadata.modified().map_err(SymbolError::IOError)?;
+            let age = now
+                .duration_since(modified)
+                .unwrap_or(std::time::Duration::ZERO);
+            if age < max_age {
+                continue;
+            }
+
+            let archived_session = self.archive_session(&path).await?;
+            archived_sessions.push(archived_session);
+        }
+
+        Ok(archived_sessions)
+    }
+
+    async fn archive_session(&self, session_path: &Path) -> Result<ArchivedSession, SymbolError> {
+        let file_name = session_path
+            .file_name()
+            .ok_or(SymbolError::ExpectedFileToExist)?
+            .to_owned();
+
+        if self.compress {
+            let mut archive_file_name = file_name.clone();
+            archive_file_name.push(".gz");
+            let archive_path = self.archive_dir.join(archive_file_name);
+
+            let content = tokio::fs::read(session_path)
+                .await
+                .map_err(SymbolError::IOError)?;
+            let compressed = tokio::task::spawn_blocking(move || {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                std::io::Write::write_all(&mut encoder, &content)?;
+                encoder.finish()
+            })
+            .await
+            .map_err(|_| SymbolError::CancelledResponseStream)?
+            .map_err(SymbolError::IOError)?;
+
+            tokio::fs::write(&archive_path, compressed)
+                .await
+                .map_err(SymbolError::IOError)?;
+            tokio::fs::remove_file(session_path)
+                .await
+                .map_err(SymbolError::IOError)?;
+
+            Ok(ArchivedSession::new(
+                session_path.to_path_buf(),
+                archive_path,
+            ))
+        } else {
+            let archive_path = self.archive_dir.join(file_name);
+            tokio::fs::rename(session_path, &archive_path)
+                .await
+                .map_err(SymbolError::IOError)?;
+
+            Ok(ArchivedSession::new(
+                session_path.to_path_buf(),
+                archive_path,
+            ))
+        }
+    }
+
+    /// Restores a previously archived session (gzip compressed or not) back
+    /// to `output_path`.
+    pub async fn restore(archive_path: &Path, output_path: &Path) -> Result<(), SymbolError> {
+        let content = tokio::fs::read(archive_path)
+            .await
+            .map_err(SymbolError::IOError)?;
+
+        let is_gzipped = archive_path
+            .extension()
+            .map(|extension| extension == "gz")
+            .unwrap_or_default();
+
+        let restored_content = if is_gzipped {
+            tokio::task::spawn_blocking(move || {
+                let mut decoder = GzDecoder::new(&content[..]);
+                let mut decompressed = Vec::new();
+                std::io::Read::read_to_end(&mut decoder, &mut decompressed)?;
+                Ok::<_, std::io::Error>(decompressed)
+            })
+            .await
+            .map_err(|_| SymbolError::CancelledResponseStream)?
+            .map_err(SymbolError::IOError)?
+        } else {
+            content
+        };
+
+        tokio::fs::write(output_path, restored_content)
+            .await
+            .map_err(SymbolError::IOError)?;
+        Ok(())
+    }
+}
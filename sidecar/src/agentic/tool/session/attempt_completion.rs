@@ -23,6 +23,10 @@ impl AttemptCompletionClientRequest {
         Self { result, command }
     }
 
+    pub fn command(&self) -> Option<String> {
+        self.command.clone()
+    }
+
     pub fn to_string(&self) -> String {
         format!(
             r#"<attempt_completion>
@@ -23,7 +23,7 @@ use async_trait::async_trait;
 use futures::StreamExt;
 use llm_client::{
     broker::LLMBroker,
-    clients::types::{LLMClientCompletionRequest, LLMClientMessage},
+    clients::types::{LLMClientCompletionRequest, LLMClientMessage, LLMClientMessageImage},
 };
 use tokio::sync::mpsc::UnboundedSender;
 
@@ -31,6 +31,10 @@ use tokio::sync::mpsc::UnboundedSender;
 pub enum SessionChatRole {
     User,
     Assistant,
+    // a tool's output (file content, terminal output, ...) rather than
+    // something the user typed, kept distinct from `User` so callers can
+    // wrap it as untrusted data instead of a genuine instruction
+    ToolOutput,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -58,6 +62,13 @@ impl SessionChatMessage {
         }
     }
 
+    pub fn tool_output(message: String) -> Self {
+        Self {
+            message,
+            role: SessionChatRole::ToolOutput,
+        }
+    }
+
     pub fn role(&self) -> &SessionChatRole {
         &self.role
     }
@@ -199,6 +210,18 @@ Respect these rules at all times:
     /// <messages>
     /// </messages>
     async fn user_message(&self, context: SessionChatClientRequest) -> Vec<LLMClientMessage> {
+        let supports_vision = context.llm_properties.llm().supports_vision();
+        let image_attachments = context
+            .user_context
+            .image_attachments()
+            .into_iter()
+            .map(|attachment| {
+                (
+                    attachment.image_base64().map(|data| data.to_owned()),
+                    attachment.image_media_type().to_owned(),
+                )
+            })
+            .collect::<Vec<_>>();
         let user_context = context
             .user_context
             .to_xml(Default::default())
@@ -208,7 +231,23 @@ Respect these rules at all times:
         // we want to add the user context at the very start of the message
         let mut messages = vec![];
         // add the user context
-        messages.push(LLMClientMessage::user(user_context).cache_point());
+        let mut user_context_message = LLMClientMessage::user(user_context);
+        if !image_attachments.is_empty() {
+            if supports_vision {
+                let images = image_attachments
+                    .into_iter()
+                    .filter_map(|(base64_data, media_type)| {
+                        base64_data.map(|data| LLMClientMessageImage::new(media_type, data))
+                    })
+                    .collect::<Vec<_>>();
+                user_context_message = user_context_message.attach_images(images);
+            } else {
+                user_context_message.concat_message(
+                    "Note: an image was attached but cannot be viewed by this model.",
+                );
+            }
+        }
+        messages.push(user_context_message.cache_point());
         messages.extend(diff_recent_changes);
         messages.extend(
             context
@@ -219,6 +258,15 @@ Respect these rules at all times:
                     SessionChatRole::Assistant => {
                         LLMClientMessage::assistant(previous_message.message)
                     }
+                    // LLM providers we talk to don't expose a distinct
+                    // "tool" role on the wire, so this still goes out as a
+                    // user message, just wrapped as untrusted tool output
+                    // rather than a genuine instruction
+                    SessionChatRole::ToolOutput => LLMClientMessage::user(
+                        crate::agentic::tool::helpers::prompt_injection::wrap_untrusted_tool_output(
+                            &previous_message.message,
+                        ),
+                    ),
                 }),
         );
         messages
@@ -0,0 +1,241 @@
+//! Composite verification step for the agent to run after it is done
+//! editing: workspace diagnostics, an optional test command, and the git
+//! diff are gathered in one shot and boiled down into a single clean /
+//! needs-work verdict, instead of the agent having to loop over
+//! `get_diagnostics`, `execute_command` and a diff tool separately and work
+//! out for itself whether the result looks okay.
+//!
+//! The composition itself happens in `SessionService`'s dispatch loop (it
+//! already has `ToolBox`/`ToolBroker` handles for the diagnostics, terminal
+//! and git-diff steps); this module only owns the request shape and the
+//! pure verdict/report logic so it can be unit tested without any of that
+//! plumbing.
+
+use std::fmt;
+
+use async_trait::async_trait;
+
+use crate::agentic::tool::{errors::ToolError, input::ToolInput, output::ToolOutput, r#type::Tool};
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CodeReviewRequestPartial {
+    // reuses the same optional shell command the agent already knows how to
+    // provide for `attempt_completion`, run here instead as the test suite
+    test_command: Option<String>,
+}
+
+impl CodeReviewRequestPartial {
+    pub fn new(test_command: Option<String>) -> Self {
+        Self { test_command }
+    }
+
+    pub fn test_command(&self) -> Option<&str> {
+        self.test_command.as_deref()
+    }
+
+    pub fn to_string(&self) -> String {
+        match &self.test_command {
+            Some(test_command) => format!(
+                r#"<code_review>
+<command>
+{}
+</command>
+</code_review>"#,
+                test_command
+            ),
+            None => "<code_review>\n</code_review>".to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeReviewVerdict {
+    Clean,
+    NeedsWork,
+}
+
+impl fmt::Display for CodeReviewVerdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodeReviewVerdict::Clean => write!(f, "clean"),
+            CodeReviewVerdict::NeedsWork => write!(f, "needs-work"),
+        }
+    }
+}
+
+/// Heuristic for whether a test command's output represents a failing run.
+/// We have no structured exit code to go on here (the terminal tool only
+/// gives us the combined stdout/stderr text), so we look for the markers
+/// the common test runners (cargo, pytest, jest, go test, ...) all agree on.
+/// Deliberately does not key off the bare word "fail" since a passing
+/// `cargo test` summary line ("4 passed; 0 failed") contains it too.
+pub fn test_run_failed(test_output: &str) -> bool {
+    let lowercased = test_output.to_lowercase();
+    if lowercased.contains("panicked") || lowercased.contains("error:") {
+        return true;
+    }
+    if regex::Regex::new(r"failed:? ?\d*[1-9]\d*")
+        .expect("hardcoded pattern to compile")
+        .is_match(&lowercased)
+        || regex::Regex::new(r"[1-9]\d* failed")
+            .expect("hardcoded pattern to compile")
+            .is_match(&lowercased)
+    {
+        return true;
+    }
+    // a single "FAILED" test-case line (pytest, jest) or TAP's "not ok"
+    test_output.contains("FAILED") || lowercased.contains("not ok")
+}
+
+/// A review is clean only when there are no diagnostics on the edited code
+/// and the tests (if any were run) passed.
+pub fn compute_verdict(diagnostics_count: usize, test_failed: Option<bool>) -> CodeReviewVerdict {
+    if diagnostics_count > 0 || test_failed == Some(true) {
+        CodeReviewVerdict::NeedsWork
+    } else {
+        CodeReviewVerdict::Clean
+    }
+}
+
+/// Renders the report we hand back to the model as a human message.
+pub fn format_report(
+    verdict: CodeReviewVerdict,
+    diagnostics_count: usize,
+    test_output: Option<&str>,
+    test_failed: Option<bool>,
+    diff: &str,
+) -> String {
+    let diagnostics_line = if diagnostics_count == 0 {
+        "No workspace diagnostics.".to_owned()
+    } else {
+        format!("{} workspace diagnostic(s) remain.", diagnostics_count)
+    };
+    let test_line = match (test_output, test_failed) {
+        (Some(output), Some(true)) => format!("Tests FAILED:\n{}", output),
+        (Some(output), Some(false)) => format!("Tests passed:\n{}", output),
+        (Some(output), None) => format!("Ran the test command:\n{}", output),
+        (None, _) => "No test command was run.".to_owned(),
+    };
+    format!(
+        r#"<code_review_verdict>
+{verdict}
+</code_review_verdict>
+{diagnostics_line}
+
+{test_line}
+
+<diff>
+{diff}
+</diff>"#
+    )
+}
+
+/// Already-gathered inputs for the review: `SessionService` fills these in
+/// from `ToolBox`/`ToolBroker` (workspace diagnostics, an optional test run,
+/// the git diff) before invoking this tool, the same way `ShowDiffInput`
+/// arrives with the diff it should render already resolved.
+#[derive(Debug, Clone)]
+pub struct CodeReviewInput {
+    diagnostics_count: usize,
+    test_output: Option<String>,
+    diff: String,
+}
+
+impl CodeReviewInput {
+    pub fn new(diagnostics_count: usize, test_output: Option<String>, diff: String) -> Self {
+        Self {
+            diagnostics_count,
+            test_output,
+            diff,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CodeReviewOutput {
+    formatted_report: String,
+}
+
+impl CodeReviewOutput {
+    pub fn formatted_report(&self) -> &str {
+        &self.formatted_report
+    }
+}
+
+pub struct CodeReviewTool {}
+
+impl CodeReviewTool {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Tool for CodeReviewTool {
+    async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
+        let context = input.is_code_review()?;
+        let test_failed = context.test_output.as_deref().map(test_run_failed);
+        let verdict = compute_verdict(context.diagnostics_count, test_failed);
+        let formatted_report = format_report(
+            verdict,
+            context.diagnostics_count,
+            context.test_output.as_deref(),
+            test_failed,
+            &context.diff,
+        );
+        Ok(ToolOutput::CodeReview(CodeReviewOutput { formatted_report }))
+    }
+
+    fn tool_description(&self) -> String {
+        r#"### code_review
+Runs a self-review pass: gathers the workspace's LSP diagnostics, optionally runs a test command you provide, and pulls the git diff of what you have changed so far, then boils all three down into a single clean/needs-work verdict. Use this once you believe you are done editing instead of checking diagnostics, running tests and eyeballing the diff as three separate steps."#.to_owned()
+    }
+
+    fn tool_input_format(&self) -> String {
+        r#"Parameters:
+- command: (optional) A shell command to run as the test suite for this review, e.g. `cargo test`. Omit the tag entirely if there is no test command to run.
+
+Usage:
+<code_review>
+<command>
+Test command here
+</command>
+</code_review>
+"#
+        .to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verdict_is_clean_when_nothing_is_wrong() {
+        assert_eq!(compute_verdict(0, None), CodeReviewVerdict::Clean);
+        assert_eq!(compute_verdict(0, Some(false)), CodeReviewVerdict::Clean);
+    }
+
+    #[test]
+    fn test_verdict_needs_work_when_diagnostics_remain() {
+        assert_eq!(compute_verdict(1, None), CodeReviewVerdict::NeedsWork);
+    }
+
+    #[test]
+    fn test_verdict_needs_work_when_test_command_fails() {
+        assert_eq!(compute_verdict(0, Some(true)), CodeReviewVerdict::NeedsWork);
+    }
+
+    #[test]
+    fn test_run_failed_recognises_common_failure_markers() {
+        assert!(test_run_failed("2 passed; 1 failed"));
+        assert!(test_run_failed("thread 'main' panicked at src/main.rs"));
+        assert!(test_run_failed("error: could not compile `sidecar`"));
+        assert!(test_run_failed("not ok 1 - should add numbers"));
+    }
+
+    #[test]
+    fn test_run_failed_is_false_for_a_clean_run() {
+        assert!(!test_run_failed("test result: ok. 4 passed; 0 failed"));
+    }
+}
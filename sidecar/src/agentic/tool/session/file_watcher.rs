@@ -0,0 +1,130 @@
+//! Watches `root_directory` for changes made outside the current session
+//! (e.g. the user editing in another window) so the tool-use loop can warn
+//! the agent that a file it opened earlier may now be stale.
+//!
+//! Watching is entirely optional: [`WorkspaceFileWatcher::start`] returns
+//! `None` if the watcher can't be set up, and nothing else in the session
+//! depends on one existing, so headless/eval runs which never call `start`
+//! are unaffected.
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use notify_debouncer_mini::{new_debouncer, notify::RecommendedWatcher, notify::RecursiveMode, DebounceEventResult, Debouncer};
+
+/// Directory names never worth watching: large, generated, and not
+/// something the agent would have shown the user content from anyway.
+const IGNORED_DIR_NAMES: &[&str] = &["target", "node_modules", ".git"];
+
+pub struct WorkspaceFileWatcher {
+    changed_since_shown: Arc<Mutex<HashSet<PathBuf>>>,
+    // kept alive for as long as the watcher should keep running; dropping it
+    // stops watching
+    _debouncer: Debouncer<RecommendedWatcher>,
+}
+
+impl WorkspaceFileWatcher {
+    /// Starts watching `root_directory`. Returns `None` if the watcher could
+    /// not be started (e.g. the path doesn't exist) - callers should just
+    /// carry on without one rather than fail the session over it.
+    pub fn start(root_directory: &Path) -> Option<Self> {
+        let changed_since_shown = Arc::new(Mutex::new(HashSet::new()));
+        let changed_since_shown_writer = changed_since_shown.clone();
+        let mut debouncer = new_debouncer(
+            Duration::from_secs(2),
+            move |result: DebounceEventResult| {
+                let events = match result {
+                    Ok(events) => events,
+                    Err(_) => return,
+                };
+                let mut changed = changed_since_shown_writer
+                    .lock()
+                    .expect("file watcher lock not poisoned");
+                for event in events {
+                    if is_ignored(&event.path) {
+                        continue;
+                    }
+                    changed.insert(event.path);
+                }
+            },
+        )
+        .ok()?;
+        debouncer
+            .watcher()
+            .watch(root_directory, RecursiveMode::Recursive)
+            .ok()?;
+        Some(Self {
+            changed_since_shown,
+            _debouncer: debouncer,
+        })
+    }
+
+    /// Drains the set of watched files which changed since this was last
+    /// called, restricted to `relevant_files` (the files the agent has
+    /// actually seen this session) - files the agent never opened don't need
+    /// calling out, and files outside `relevant_files` stay queued in case
+    /// they become relevant later.
+    pub fn take_changed_relevant_files(&self, relevant_files: &[String]) -> Vec<String> {
+        let relevant: HashSet<&str> = relevant_files.iter().map(|s| s.as_str()).collect();
+        let mut changed = self
+            .changed_since_shown
+            .lock()
+            .expect("file watcher lock not poisoned");
+        let still_irrelevant: HashSet<PathBuf> = changed
+            .iter()
+            .filter(|path| !relevant.contains(path.to_string_lossy().as_ref()))
+            .cloned()
+            .collect();
+        let mut matching: Vec<String> = changed
+            .iter()
+            .filter(|path| relevant.contains(path.to_string_lossy().as_ref()))
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect();
+        matching.sort();
+        *changed = still_irrelevant;
+        matching
+    }
+}
+
+fn is_ignored(path: &Path) -> bool {
+    path.components().any(|component| {
+        component
+            .as_os_str()
+            .to_str()
+            .map(|name| IGNORED_DIR_NAMES.contains(&name))
+            .unwrap_or(false)
+    })
+}
+
+/// Renders the note the tool-use loop injects before the next inference when
+/// `changed_files` is non-empty; `None` when there is nothing to report.
+pub fn stale_files_note(changed_files: &[String]) -> Option<String> {
+    if changed_files.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "these files changed outside this session, re-read them before relying on their previously shown content:\n{}",
+        changed_files.join("\n")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::stale_files_note;
+
+    #[test]
+    fn stale_files_note_is_none_when_nothing_changed() {
+        assert!(stale_files_note(&[]).is_none());
+    }
+
+    #[test]
+    fn stale_files_note_lists_every_changed_file() {
+        let note = stale_files_note(&["src/lib.rs".to_owned(), "src/main.rs".to_owned()]).unwrap();
+        assert!(note.contains("src/lib.rs"));
+        assert!(note.contains("src/main.rs"));
+    }
+}
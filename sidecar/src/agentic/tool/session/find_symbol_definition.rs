@@ -0,0 +1,319 @@
+//! Collapses "grep for a symbol, pick the right hit, go to its definition"
+//! into one tool call. The agent gives a symbol name (and, when it knows
+//! one, the file it saw the symbol used from, to disambiguate overloaded or
+//! reused names) and gets back the definition's file, range and a code
+//! snippet in one shot instead of looping over `grep_symbol_in_codebase` and
+//! `go_to_definition` itself.
+//!
+//! The composition (grep-symbol-in-codebase, go-to-definition, then reading
+//! the definition's snippet) happens in `SessionService`'s dispatch loop,
+//! which already owns the `ToolBox`/`ToolBroker` handles for those steps;
+//! this module only owns the request shape and the pure candidate-selection
+//! logic so it can be unit tested without any of that plumbing.
+
+use async_trait::async_trait;
+
+use crate::{
+    agentic::tool::{errors::ToolError, input::ToolInput, output::ToolOutput, r#type::Tool},
+    chunking::text_document::Range,
+};
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct FindSymbolDefinitionRequestPartial {
+    symbol_name: String,
+    from_file: Option<String>,
+}
+
+impl FindSymbolDefinitionRequestPartial {
+    pub fn new(symbol_name: String, from_file: Option<String>) -> Self {
+        Self {
+            symbol_name,
+            from_file,
+        }
+    }
+
+    pub fn symbol_name(&self) -> &str {
+        &self.symbol_name
+    }
+
+    pub fn from_file(&self) -> Option<&str> {
+        self.from_file.as_deref()
+    }
+
+    pub fn to_string(&self) -> String {
+        match &self.from_file {
+            Some(from_file) => format!(
+                r#"<find_symbol_definition>
+<symbol_name>
+{}
+</symbol_name>
+<from_file>
+{}
+</from_file>
+</find_symbol_definition>"#,
+                self.symbol_name, from_file
+            ),
+            None => format!(
+                r#"<find_symbol_definition>
+<symbol_name>
+{}
+</symbol_name>
+</find_symbol_definition>"#,
+                self.symbol_name
+            ),
+        }
+    }
+}
+
+/// A single grep hit for a symbol name, cheap to clone around while we pick
+/// the best one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolCandidate {
+    fs_file_path: String,
+    range: Range,
+}
+
+impl SymbolCandidate {
+    pub fn new(fs_file_path: String, range: Range) -> Self {
+        Self { fs_file_path, range }
+    }
+
+    pub fn fs_file_path(&self) -> &str {
+        &self.fs_file_path
+    }
+
+    pub fn range(&self) -> &Range {
+        &self.range
+    }
+}
+
+/// Result of picking a single grep hit to resolve the definition for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CandidateResolution {
+    Unique(SymbolCandidate),
+    Ambiguous(Vec<SymbolCandidate>),
+    NotFound,
+}
+
+/// Picks the single best candidate to resolve, preferring an exact hit in
+/// `from_file` when the caller told us which file it saw the symbol used
+/// from. Otherwise we only resolve automatically when there is exactly one
+/// candidate; two or more with no way to tell them apart is reported back as
+/// ambiguous rather than silently guessing the first one.
+pub fn resolve_candidate(
+    from_file: Option<&str>,
+    candidates: &[SymbolCandidate],
+) -> CandidateResolution {
+    if candidates.is_empty() {
+        return CandidateResolution::NotFound;
+    }
+    if let Some(from_file) = from_file {
+        if let Some(candidate) = candidates
+            .iter()
+            .find(|candidate| candidate.fs_file_path() == from_file)
+        {
+            return CandidateResolution::Unique(candidate.clone());
+        }
+    }
+    if candidates.len() == 1 {
+        CandidateResolution::Unique(candidates[0].clone())
+    } else {
+        CandidateResolution::Ambiguous(candidates.to_vec())
+    }
+}
+
+/// Already-resolved outcome `SessionService` fills in from `ToolBox`/
+/// `ToolBroker` (the grep search, the go-to-definition call and the snippet
+/// read) before invoking this tool, the same way `CodeReviewInput` arrives
+/// with diagnostics, a test run and a diff already gathered.
+#[derive(Debug, Clone)]
+pub enum FindSymbolDefinitionResolution {
+    Resolved {
+        fs_file_path: String,
+        range: Range,
+        snippet: String,
+    },
+    Ambiguous(Vec<SymbolCandidate>),
+    NotFound,
+}
+
+#[derive(Debug, Clone)]
+pub struct FindSymbolDefinitionInput {
+    symbol_name: String,
+    resolution: FindSymbolDefinitionResolution,
+}
+
+impl FindSymbolDefinitionInput {
+    pub fn new(symbol_name: String, resolution: FindSymbolDefinitionResolution) -> Self {
+        Self {
+            symbol_name,
+            resolution,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FindSymbolDefinitionOutput {
+    formatted_report: String,
+}
+
+impl FindSymbolDefinitionOutput {
+    pub fn formatted_report(&self) -> &str {
+        &self.formatted_report
+    }
+}
+
+/// Renders the report we hand back to the model as a human message.
+pub fn format_report(symbol_name: &str, resolution: &FindSymbolDefinitionResolution) -> String {
+    match resolution {
+        FindSymbolDefinitionResolution::Resolved {
+            fs_file_path,
+            range,
+            snippet,
+        } => format!(
+            r#"<definition>
+<fs_file_path>
+{fs_file_path}:{}-{}
+</fs_file_path>
+<content>
+{snippet}
+</content>
+</definition>"#,
+            range.start_line(),
+            range.end_line()
+        ),
+        FindSymbolDefinitionResolution::Ambiguous(candidates) => {
+            let candidate_list = candidates
+                .iter()
+                .map(|candidate| {
+                    format!(
+                        "- {}:{}-{}",
+                        candidate.fs_file_path(),
+                        candidate.range().start_line(),
+                        candidate.range().end_line()
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            format!(
+                "`{symbol_name}` is ambiguous, found it in more than one place. Re-invoke with `from_file` set to the one you meant:\n{candidate_list}"
+            )
+        }
+        FindSymbolDefinitionResolution::NotFound => {
+            format!("No definition could be found for symbol `{symbol_name}`.")
+        }
+    }
+}
+
+pub struct FindSymbolDefinitionTool {}
+
+impl FindSymbolDefinitionTool {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Tool for FindSymbolDefinitionTool {
+    async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
+        let context = input.is_find_symbol_definition()?;
+        let formatted_report = format_report(&context.symbol_name, &context.resolution);
+        Ok(ToolOutput::FindSymbolDefinition(FindSymbolDefinitionOutput {
+            formatted_report,
+        }))
+    }
+
+    fn tool_description(&self) -> String {
+        r#"### find_symbol_definition
+Looks up where a symbol is defined: searches the codebase for it, resolves the definition through the language server, and returns the definition's file, range and a code snippet in one step, instead of grepping for it, opening the file and calling go-to-definition yourself across three separate tool calls. If the symbol name is ambiguous (found in more than one place), the top candidates are returned instead of guessing which one you meant."#.to_owned()
+    }
+
+    fn tool_input_format(&self) -> String {
+        r#"Parameters:
+- symbol_name: (required) The name of the symbol to find the definition of.
+- from_file: (optional) The file you saw the symbol used from, to disambiguate when the name is not unique.
+
+Usage:
+<find_symbol_definition>
+<symbol_name>
+Symbol name here
+</symbol_name>
+<from_file>
+Optional file path here
+</from_file>
+</find_symbol_definition>
+"#
+        .to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunking::text_document::{Position, Range};
+
+    fn candidate(fs_file_path: &str, start_line: usize, end_line: usize) -> SymbolCandidate {
+        SymbolCandidate::new(
+            fs_file_path.to_owned(),
+            Range::new(
+                Position::new(start_line, 0, 0),
+                Position::new(end_line, 0, 0),
+            ),
+        )
+    }
+
+    #[test]
+    fn test_resolve_candidate_is_not_found_when_there_are_no_hits() {
+        assert_eq!(resolve_candidate(None, &[]), CandidateResolution::NotFound);
+    }
+
+    #[test]
+    fn test_resolve_candidate_resolves_a_single_hit_without_from_file() {
+        let only_hit = candidate("src/lib.rs", 10, 20);
+        let resolution = resolve_candidate(None, std::slice::from_ref(&only_hit));
+        assert_eq!(resolution, CandidateResolution::Unique(only_hit));
+    }
+
+    #[test]
+    fn test_resolve_candidate_is_ambiguous_without_from_file_to_disambiguate() {
+        let candidates = vec![candidate("src/a.rs", 1, 5), candidate("src/b.rs", 8, 12)];
+        assert_eq!(
+            resolve_candidate(None, &candidates),
+            CandidateResolution::Ambiguous(candidates)
+        );
+    }
+
+    #[test]
+    fn test_resolve_candidate_uses_from_file_to_disambiguate() {
+        let wanted = candidate("src/b.rs", 8, 12);
+        let candidates = vec![candidate("src/a.rs", 1, 5), wanted.clone()];
+        assert_eq!(
+            resolve_candidate(Some("src/b.rs"), &candidates),
+            CandidateResolution::Unique(wanted)
+        );
+    }
+
+    #[test]
+    fn test_format_report_resolves_a_known_symbol_to_its_definition_snippet() {
+        let resolution = FindSymbolDefinitionResolution::Resolved {
+            fs_file_path: "src/lib.rs".to_owned(),
+            range: Range::new(Position::new(10, 0, 0), Position::new(12, 0, 0)),
+            snippet: "pub fn hello() {}".to_owned(),
+        };
+        let report = format_report("hello", &resolution);
+        assert!(report.contains("src/lib.rs:10-12"));
+        assert!(report.contains("pub fn hello() {}"));
+    }
+
+    #[test]
+    fn test_format_report_lists_top_candidates_when_ambiguous() {
+        let resolution = FindSymbolDefinitionResolution::Ambiguous(vec![
+            candidate("src/a.rs", 1, 5),
+            candidate("src/b.rs", 8, 12),
+        ]);
+        let report = format_report("hello", &resolution);
+        assert!(report.contains("ambiguous"));
+        assert!(report.contains("src/a.rs:1-5"));
+        assert!(report.contains("src/b.rs:8-12"));
+    }
+}
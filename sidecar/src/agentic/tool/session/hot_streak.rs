@@ -208,6 +208,11 @@ Respect these rules at all times:
                     SessionChatRole::Assistant => {
                         LLMClientMessage::assistant(previous_message.message().to_owned())
                     }
+                    SessionChatRole::ToolOutput => LLMClientMessage::user(
+                        crate::agentic::tool::helpers::prompt_injection::wrap_untrusted_tool_output(
+                            previous_message.message(),
+                        ),
+                    ),
                 }),
         );
         let query = context.query.to_owned();
@@ -4,11 +4,20 @@
 //! This keeps track of all the different type of edits which we are going to be
 //! working on top of
 
+pub mod archiver;
 pub mod ask_followup_question;
 pub mod attempt_completion;
 pub(crate) mod chat;
+pub mod code_review;
 pub(crate) mod exchange;
+pub mod file_watcher;
+pub mod find_symbol_definition;
 pub(crate) mod hot_streak;
+pub mod run_tests;
 pub mod service;
 pub mod session;
+pub mod show_diff;
+pub(crate) mod structured_tool_use;
+pub(crate) mod summarize_context;
 pub mod tool_use_agent;
+pub mod workspace_roots;
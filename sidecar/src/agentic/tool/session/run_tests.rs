@@ -0,0 +1,508 @@
+//! Composite verification step: builds and runs the right test command for
+//! whichever project type lives at the workspace root, then boils the wall
+//! of test-runner output down into a structured pass/fail summary instead of
+//! handing the model raw text it tends to misread.
+//!
+//! Like `code_review.rs`, the actual command execution happens in
+//! `SessionService`'s dispatch loop (it already owns the `ToolBroker` handle
+//! needed to run a terminal command); this module only owns the request
+//! shape, framework detection and the pure parsing/formatting logic so all
+//! of it can be unit tested without any of that plumbing.
+
+use std::path::Path;
+
+use async_trait::async_trait;
+
+use crate::agentic::tool::{errors::ToolError, input::ToolInput, output::ToolOutput, r#type::Tool};
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RunTestsRequestPartial {
+    test_filter: Option<String>,
+    framework_hint: Option<String>,
+}
+
+impl RunTestsRequestPartial {
+    pub fn new(test_filter: Option<String>, framework_hint: Option<String>) -> Self {
+        Self {
+            test_filter,
+            framework_hint,
+        }
+    }
+
+    pub fn test_filter(&self) -> Option<&str> {
+        self.test_filter.as_deref()
+    }
+
+    pub fn framework_hint(&self) -> Option<&str> {
+        self.framework_hint.as_deref()
+    }
+
+    pub fn to_string(&self) -> String {
+        let test_filter = self
+            .test_filter
+            .as_deref()
+            .map(|test_filter| format!("\n<test_filter>\n{test_filter}\n</test_filter>"))
+            .unwrap_or_default();
+        let framework_hint = self
+            .framework_hint
+            .as_deref()
+            .map(|framework_hint| format!("\n<framework_hint>\n{framework_hint}\n</framework_hint>"))
+            .unwrap_or_default();
+        format!("<run_tests>{test_filter}{framework_hint}\n</run_tests>")
+    }
+}
+
+/// The test frameworks we know how to detect and drive. Anything else falls
+/// back to `Unknown`, which skips the structured parsing step entirely.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestFramework {
+    Cargo,
+    Npm,
+    Pytest,
+    Unknown,
+}
+
+impl std::fmt::Display for TestFramework {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Cargo => write!(f, "cargo"),
+            Self::Npm => write!(f, "npm"),
+            Self::Pytest => write!(f, "pytest"),
+            Self::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// Detects which test framework a workspace root belongs to. An explicit
+/// `framework_hint` from the model always wins over the manifest sniff below
+/// (useful for a Rust crate vendored inside a JS monorepo, or vice versa).
+pub fn detect_test_framework(root_directory: &Path, framework_hint: Option<&str>) -> TestFramework {
+    if let Some(hint) = framework_hint {
+        match hint.trim().to_lowercase().as_str() {
+            "cargo" | "rust" => return TestFramework::Cargo,
+            "npm" | "node" | "yarn" | "jest" => return TestFramework::Npm,
+            "pytest" | "python" => return TestFramework::Pytest,
+            _ => {}
+        }
+    }
+    if root_directory.join("Cargo.toml").exists() {
+        TestFramework::Cargo
+    } else if root_directory.join("package.json").exists() {
+        TestFramework::Npm
+    } else if root_directory.join("pyproject.toml").exists() {
+        TestFramework::Pytest
+    } else {
+        TestFramework::Unknown
+    }
+}
+
+/// The shell command to run for a detected framework, scoped down to
+/// `test_filter` when one was given. `None` for `Unknown` since there is no
+/// sensible command to fall back to.
+pub fn build_test_command(framework: TestFramework, test_filter: Option<&str>) -> Option<String> {
+    match framework {
+        TestFramework::Cargo => Some(match test_filter {
+            Some(test_filter) => format!("cargo test {test_filter}"),
+            None => "cargo test".to_owned(),
+        }),
+        TestFramework::Npm => Some(match test_filter {
+            Some(test_filter) => format!("npm test -- {test_filter}"),
+            None => "npm test".to_owned(),
+        }),
+        TestFramework::Pytest => Some(match test_filter {
+            Some(test_filter) => format!("pytest -k {test_filter}"),
+            None => "pytest".to_owned(),
+        }),
+        TestFramework::Unknown => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailingTest {
+    name: String,
+    first_failure_line: String,
+}
+
+impl FailingTest {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn first_failure_line(&self) -> &str {
+        &self.first_failure_line
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TestRunSummary {
+    passed: usize,
+    failed: usize,
+    ignored: usize,
+    failing_tests: Vec<FailingTest>,
+}
+
+impl TestRunSummary {
+    pub fn passed(&self) -> usize {
+        self.passed
+    }
+
+    pub fn failed(&self) -> usize {
+        self.failed
+    }
+
+    pub fn ignored(&self) -> usize {
+        self.ignored
+    }
+
+    pub fn failing_tests(&self) -> &[FailingTest] {
+        &self.failing_tests
+    }
+}
+
+fn extract_count(summary_line: &str, label: &str) -> Option<usize> {
+    regex::Regex::new(&format!(r"(\d+) {label}"))
+        .ok()?
+        .captures(summary_line)?
+        .get(1)?
+        .as_str()
+        .parse()
+        .ok()
+}
+
+/// `cargo test` prints one `test result: ...` summary line per binary (unit
+/// tests, each integration test file, doctests, ...); we add them up rather
+/// than only reading the last one, then pair each `FAILED` line up with the
+/// first line of its failure block (`---- test_name stdout ----` followed by
+/// the panic message) for a compact one-line-per-failure report.
+fn parse_cargo_test_output(raw_output: &str) -> Option<TestRunSummary> {
+    let summary_lines: Vec<&str> = raw_output
+        .lines()
+        .filter(|line| line.trim_start().starts_with("test result:"))
+        .collect();
+    if summary_lines.is_empty() {
+        return None;
+    }
+    let mut summary = TestRunSummary::default();
+    for summary_line in &summary_lines {
+        summary.passed += extract_count(summary_line, "passed").unwrap_or(0);
+        summary.failed += extract_count(summary_line, "failed").unwrap_or(0);
+        summary.ignored += extract_count(summary_line, "ignored").unwrap_or(0);
+    }
+    summary.failing_tests = raw_output
+        .lines()
+        .filter_map(|line| {
+            let trimmed = line.trim_start().strip_prefix("test ")?;
+            trimmed.strip_suffix(" ... FAILED")
+        })
+        .map(|name| FailingTest {
+            name: name.to_owned(),
+            first_failure_line: find_cargo_failure_detail(raw_output, name),
+        })
+        .collect();
+    Some(summary)
+}
+
+fn find_cargo_failure_detail(raw_output: &str, test_name: &str) -> String {
+    let marker = format!("---- {test_name} stdout ----");
+    raw_output
+        .lines()
+        .skip_while(|line| *line != marker)
+        .nth(1)
+        .unwrap_or_default()
+        .trim()
+        .to_owned()
+}
+
+/// pytest's short summary section (`-ra`/default failure output) prints one
+/// `FAILED path::test - Reason` line per failure and a final summary line
+/// like `2 failed, 5 passed, 1 skipped in 0.34s`.
+fn parse_pytest_output(raw_output: &str) -> Option<TestRunSummary> {
+    let summary_line = raw_output
+        .lines()
+        .rev()
+        .find(|line| line.contains(" in ") && (line.contains("passed") || line.contains("failed")))?;
+    let mut summary = TestRunSummary {
+        passed: extract_count(summary_line, "passed").unwrap_or(0),
+        failed: extract_count(summary_line, "failed").unwrap_or(0),
+        ignored: extract_count(summary_line, "skipped").unwrap_or(0),
+        failing_tests: vec![],
+    };
+    summary.failing_tests = raw_output
+        .lines()
+        .filter_map(|line| line.strip_prefix("FAILED "))
+        .map(|rest| match rest.split_once(" - ") {
+            Some((name, reason)) => FailingTest {
+                name: name.to_owned(),
+                first_failure_line: reason.to_owned(),
+            },
+            None => FailingTest {
+                name: rest.to_owned(),
+                first_failure_line: String::new(),
+            },
+        })
+        .collect();
+    Some(summary)
+}
+
+/// jest's default reporter prints a `Tests: N failed, M passed, K total` line
+/// and one `✕ test name` line under each failing suite.
+fn parse_npm_test_output(raw_output: &str) -> Option<TestRunSummary> {
+    let summary_line = raw_output
+        .lines()
+        .find(|line| line.trim_start().starts_with("Tests:"))?;
+    let summary = TestRunSummary {
+        passed: extract_count(summary_line, "passed").unwrap_or(0),
+        failed: extract_count(summary_line, "failed").unwrap_or(0),
+        ignored: extract_count(summary_line, "skipped").unwrap_or(0),
+        failing_tests: raw_output
+            .lines()
+            .filter_map(|line| line.trim_start().strip_prefix("\u{2715} "))
+            .map(|name| FailingTest {
+                name: name.trim().to_owned(),
+                first_failure_line: String::new(),
+            })
+            .collect(),
+    };
+    Some(summary)
+}
+
+/// Returns `None` (rather than an all-zero summary) when the output does not
+/// look like it came from the given framework at all, so the caller can fall
+/// back to showing the raw text with a note instead of reporting a bogus
+/// "0 passed, 0 failed".
+pub fn parse_test_output(framework: TestFramework, raw_output: &str) -> Option<TestRunSummary> {
+    match framework {
+        TestFramework::Cargo => parse_cargo_test_output(raw_output),
+        TestFramework::Npm => parse_npm_test_output(raw_output),
+        TestFramework::Pytest => parse_pytest_output(raw_output),
+        TestFramework::Unknown => None,
+    }
+}
+
+/// Renders the report we hand back to the model as a human message.
+pub fn format_report(
+    framework: TestFramework,
+    command: &str,
+    summary: Option<&TestRunSummary>,
+    raw_output: &str,
+) -> String {
+    match summary {
+        Some(summary) => {
+            let mut report = format!(
+                "Ran `{command}` ({framework}): {} passed, {} failed, {} ignored.",
+                summary.passed, summary.failed, summary.ignored
+            );
+            if !summary.failing_tests.is_empty() {
+                report.push_str("\n\nFailing tests:");
+                for failing_test in &summary.failing_tests {
+                    report.push_str(&format!(
+                        "\n- {}: {}",
+                        failing_test.name, failing_test.first_failure_line
+                    ));
+                }
+            }
+            report
+        }
+        None if framework == TestFramework::Unknown => format!(
+            "Could not detect a supported test framework (looked for Cargo.toml, package.json, pyproject.toml) at the workspace root, so no tests were run.\n\n{raw_output}"
+        ),
+        None => format!(
+            "Ran `{command}` ({framework}), but the output could not be parsed into a structured result; showing raw output instead.\n\n{raw_output}"
+        ),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RunTestsInput {
+    framework: TestFramework,
+    command: String,
+    raw_output: String,
+}
+
+impl RunTestsInput {
+    pub fn new(framework: TestFramework, command: String, raw_output: String) -> Self {
+        Self {
+            framework,
+            command,
+            raw_output,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RunTestsOutput {
+    formatted_report: String,
+}
+
+impl RunTestsOutput {
+    pub fn formatted_report(&self) -> &str {
+        &self.formatted_report
+    }
+}
+
+pub struct RunTestsTool {}
+
+impl RunTestsTool {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Tool for RunTestsTool {
+    async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
+        let context = input.is_run_tests()?;
+        let summary = parse_test_output(context.framework, &context.raw_output);
+        let formatted_report = format_report(
+            context.framework,
+            &context.command,
+            summary.as_ref(),
+            &context.raw_output,
+        );
+        Ok(ToolOutput::RunTests(RunTestsOutput { formatted_report }))
+    }
+
+    fn tool_description(&self) -> String {
+        r#"### run_tests
+Runs the test suite for whichever project type lives at the workspace root (Cargo, npm, or pytest), optionally scoped to a filter, and reports back a structured pass/fail count with the first line of each failure instead of the raw wall of test-runner output. Prefer this over `execute_command` when you just want to know whether the tests pass."#.to_owned()
+    }
+
+    fn tool_input_format(&self) -> String {
+        r#"Parameters:
+- test_filter: (optional) Restrict the run to tests matching this name or pattern.
+- framework_hint: (optional) Force a specific framework (`cargo`, `npm`, or `pytest`) instead of auto-detecting it from the workspace root.
+
+Usage:
+<run_tests>
+<test_filter>
+Optional filter here
+</test_filter>
+<framework_hint>
+Optional framework hint here
+</framework_hint>
+</run_tests>
+"#
+        .to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_test_framework_prefers_an_explicit_hint_over_the_manifest_sniff() {
+        let root = tempfile::tempdir().expect("tempdir to be created");
+        std::fs::write(root.path().join("Cargo.toml"), "[package]").expect("to write Cargo.toml");
+        assert_eq!(
+            detect_test_framework(root.path(), Some("pytest")),
+            TestFramework::Pytest
+        );
+    }
+
+    #[test]
+    fn detect_test_framework_finds_cargo_toml() {
+        let root = tempfile::tempdir().expect("tempdir to be created");
+        std::fs::write(root.path().join("Cargo.toml"), "[package]").expect("to write Cargo.toml");
+        assert_eq!(detect_test_framework(root.path(), None), TestFramework::Cargo);
+    }
+
+    #[test]
+    fn detect_test_framework_finds_package_json() {
+        let root = tempfile::tempdir().expect("tempdir to be created");
+        std::fs::write(root.path().join("package.json"), "{}").expect("to write package.json");
+        assert_eq!(detect_test_framework(root.path(), None), TestFramework::Npm);
+    }
+
+    #[test]
+    fn detect_test_framework_finds_pyproject_toml() {
+        let root = tempfile::tempdir().expect("tempdir to be created");
+        std::fs::write(root.path().join("pyproject.toml"), "[project]")
+            .expect("to write pyproject.toml");
+        assert_eq!(
+            detect_test_framework(root.path(), None),
+            TestFramework::Pytest
+        );
+    }
+
+    #[test]
+    fn detect_test_framework_falls_back_to_unknown_with_no_manifest() {
+        let root = tempfile::tempdir().expect("tempdir to be created");
+        assert_eq!(
+            detect_test_framework(root.path(), None),
+            TestFramework::Unknown
+        );
+    }
+
+    #[test]
+    fn build_test_command_returns_none_for_unknown_frameworks() {
+        assert_eq!(build_test_command(TestFramework::Unknown, None), None);
+    }
+
+    #[test]
+    fn build_test_command_scopes_cargo_to_the_filter() {
+        assert_eq!(
+            build_test_command(TestFramework::Cargo, Some("my_test")),
+            Some("cargo test my_test".to_owned())
+        );
+    }
+
+    #[test]
+    fn parse_cargo_test_output_sums_multiple_binaries_and_pairs_up_failures() {
+        let raw_output = r#"
+running 2 tests
+test foo::works ... ok
+test foo::broken ... FAILED
+
+failures:
+
+---- foo::broken stdout ----
+thread 'foo::broken' panicked at 'assertion failed', src/foo.rs:10:5
+
+test result: FAILED. 1 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out
+
+running 1 test
+test bar::works ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out
+"#;
+        let summary = parse_cargo_test_output(raw_output).expect("cargo output to parse");
+        assert_eq!(summary.passed(), 2);
+        assert_eq!(summary.failed(), 1);
+        assert_eq!(summary.ignored(), 0);
+        assert_eq!(summary.failing_tests().len(), 1);
+        assert_eq!(summary.failing_tests()[0].name(), "foo::broken");
+        assert!(summary.failing_tests()[0]
+            .first_failure_line()
+            .contains("panicked"));
+    }
+
+    #[test]
+    fn parse_cargo_test_output_returns_none_for_unrelated_text() {
+        assert_eq!(parse_cargo_test_output("hello world"), None);
+    }
+
+    #[test]
+    fn parse_pytest_output_reads_the_short_summary_and_failure_reasons() {
+        let raw_output = r#"
+FAILED tests/test_foo.py::test_bar - AssertionError: expected 1, got 2
+2 failed, 3 passed, 1 skipped in 0.12s
+"#;
+        let summary = parse_pytest_output(raw_output).expect("pytest output to parse");
+        assert_eq!(summary.passed(), 3);
+        assert_eq!(summary.failed(), 2);
+        assert_eq!(summary.ignored(), 1);
+        assert_eq!(summary.failing_tests().len(), 1);
+        assert_eq!(
+            summary.failing_tests()[0].first_failure_line(),
+            "AssertionError: expected 1, got 2"
+        );
+    }
+
+    #[test]
+    fn format_report_notes_when_an_unknown_framework_falls_back_to_raw_output() {
+        let report = format_report(TestFramework::Unknown, "cargo test", None, "raw text here");
+        assert!(report.contains("Could not detect a supported test framework"));
+        assert!(report.contains("raw text here"));
+    }
+}
@@ -3,14 +3,20 @@
 use std::{collections::HashMap, sync::Arc};
 
 use llm_client::broker::LLMBroker;
-use tokio::{io::AsyncWriteExt, sync::Mutex};
+use tokio::{
+    io::{AsyncWriteExt, BufWriter},
+    sync::Mutex,
+};
 use tokio_util::sync::CancellationToken;
 
 use crate::{
     agentic::{
         symbol::{
             errors::SymbolError,
-            events::{edit::SymbolToEdit, message_event::SymbolEventMessageProperties},
+            events::{
+                edit::{content_hash, SymbolToEditBuilder},
+                message_event::SymbolEventMessageProperties,
+            },
             identifier::SymbolIdentifier,
             manager::SymbolManager,
             scratch_pad::ScratchPadAgent,
@@ -19,40 +25,285 @@ use crate::{
         },
         tool::{
             broker::ToolBroker,
-            helpers::diff_recent_changes::DiffFileContent,
+            errors::ToolError,
+            helpers::{
+                diff_recent_changes::DiffFileContent,
+                diff_summary::{summarize_diff_if_too_large, DEFAULT_DIFF_SUMMARY_THRESHOLD_LINES},
+            },
             input::{ToolInput, ToolInputPartial},
             lsp::{
-                file_diagnostics::DiagnosticMap, open_file::OpenFileRequest,
-                search_file::SearchFileContentInput,
+                delete_file::DeleteFileInput, duplicate_symbol::DuplicateSymbolInput,
+                file_diagnostics::DiagnosticMap,
+                get_outline_nodes::GetOutlineNodesInput,
+                get_workspace_symbols::GetWorkspaceSymbolsInput,
+                list_files::ListFilesInput,
+                move_file::{rewrite_import_references, MoveFileInput},
+                open_file::{OpenFileRequest, OpenFileResponse}, search_file::SearchFileContentInput,
             },
-            plan::service::PlanService,
+            plan::{plan_impact::PlanImpactSummary, service::PlanService},
             r#type::{Tool, ToolType},
-            repo_map::generator::RepoMapGeneratorRequest,
-            session::{session::AgentToolUseOutput, tool_use_agent::ToolUseAgent},
+            repo_map::generator::{RepoMapGeneratorRequest, DEFAULT_REPO_MAP_TOKEN_LIMIT},
+            session::{
+                archiver::SessionArchiver,
+                code_review::{test_run_failed, CodeReviewInput},
+                file_watcher::{stale_files_note, WorkspaceFileWatcher},
+                find_symbol_definition::{
+                    CandidateResolution, FindSymbolDefinitionInput, FindSymbolDefinitionResolution,
+                    SymbolCandidate, resolve_candidate,
+                },
+                run_tests::{build_test_command, detect_test_framework, RunTestsInput},
+                session::AgentToolUseOutput,
+                show_diff::ShowDiffInput,
+                structured_tool_use::LLMBrokerStructuredToolCall,
+                summarize_context::SummarizeContextRequest,
+                tool_use_agent::ToolUseAgent,
+                workspace_roots::{WorkspaceRoot, WorkspaceRoots},
+            },
             terminal::terminal::TerminalInput,
         },
     },
     chunking::text_document::{Position, Range},
     repo::types::RepoRef,
-    user_context::types::UserContext,
+    user_context::types::{UserContext, VariableInformation},
 };
 
-use super::session::{AideAgentMode, Session};
+use super::session::{
+    AideAgentMode, ExchangeSearchMatch, MessageRole, OpenExchangesPolicy, Session,
+    SessionForkMetadata,
+};
+
+/// Bumped whenever `Session`'s on-disk shape changes in a way `#[serde(default)]`
+/// on the new field can't paper over by itself (a rename, a restructuring).
+/// `load_from_storage` reads the persisted value's `schema_version` and runs
+/// the matching entries of `SESSION_MIGRATIONS` to bring it up to this
+/// version before deserializing into `Session`.
+const CURRENT_SESSION_SCHEMA_VERSION: u32 = 2;
+
+/// How much of a serialized session `save_to_storage` writes per `write_all`
+/// call, so a session file which has grown very large (a long-running
+/// session with hundreds of exchanges) doesn't need to be handed to the
+/// kernel in a single multi-hundred-megabyte write.
+const SESSION_WRITE_CHUNK_BYTES: usize = 64 * 1024;
+
+/// How many times `tool_use_agentic` sends an `attempt_completion` back
+/// around the loop because tracked diagnostics were still outstanding,
+/// before giving up and accepting the completion anyway so a flaky
+/// diagnostics source can't wedge the agent forever.
+const MAX_COMPLETION_DIAGNOSTIC_RETRIES: usize = 2;
+
+/// Whether an `attempt_completion` should be rejected and sent back around
+/// the loop because diagnostics are still outstanding, rather than accepted.
+fn should_retry_completion_for_diagnostics(has_unresolved_diagnostics: bool, retries: usize) -> bool {
+    has_unresolved_diagnostics && retries < MAX_COMPLETION_DIAGNOSTIC_RETRIES
+}
+
+/// What `tool_use_agentic` should do next after a tool call comes back as
+/// `Err`, decided per [`ToolError`] variant rather than treating every
+/// failure the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ToolErrorAction {
+    /// Transient and worth trying again unchanged (e.g. the editor extension
+    /// dropped a single request).
+    Retry,
+    /// Not transient, but explainable to the model so it can adjust its next
+    /// call (e.g. it filled in the wrong tool input).
+    FeedbackToModel,
+    /// Nothing sensible to retry or explain; stop the loop rather than spin.
+    Abort,
+}
+
+/// How `tool_use_agentic` should react to `error`, and the message to show
+/// the user via [`crate::agentic::symbol::ui_event::UIEventWithID::tool_error`]
+/// so a tool failure is diagnosable instead of surfacing as a silent panic.
+fn categorize_tool_error(error: &ToolError) -> (ToolErrorAction, String) {
+    match error {
+        ToolError::ErrorCommunicatingWithEditor => (
+            ToolErrorAction::Retry,
+            "editor unreachable — is the extension running?".to_owned(),
+        ),
+        ToolError::RetriesExhausted => (
+            ToolErrorAction::Abort,
+            "gave up retrying this tool call after repeated failures".to_owned(),
+        ),
+        ToolError::SerdeConversionFailed => (
+            ToolErrorAction::FeedbackToModel,
+            "the tool response could not be parsed".to_owned(),
+        ),
+        ToolError::WrongToolInput(tool_type) => (
+            ToolErrorAction::FeedbackToModel,
+            format!("the input was not valid for {tool_type}"),
+        ),
+        other => (
+            ToolErrorAction::FeedbackToModel,
+            format!("the tool call failed: {other}"),
+        ),
+    }
+}
+
+/// `SESSION_MIGRATIONS[i]` upgrades a persisted session from schema version
+/// `i + 1` to `i + 2`. There is no entry for reaching version 1 since that
+/// was simply the on-disk shape before this versioning scheme existed.
+const SESSION_MIGRATIONS: &[fn(serde_json::Value) -> serde_json::Value] = &[migrate_v1_to_v2];
+
+/// v1 sessions (predating `schema_version` itself) already deserialize
+/// cleanly into the current `Session` since every field added since has
+/// `#[serde(default)]` - there is nothing to rewrite, this migration exists
+/// so the pipeline below has a real first entry to run.
+fn migrate_v1_to_v2(value: serde_json::Value) -> serde_json::Value {
+    value
+}
+
+/// Reads the `schema_version` a persisted session was written with (missing
+/// entirely means version 1, from before this field existed) and runs
+/// migrations up to `CURRENT_SESSION_SCHEMA_VERSION`, stripping the
+/// bookkeeping field back out so the result deserializes straight into
+/// `Session`.
+fn migrate_session_value(mut value: serde_json::Value, storage_path: &str) -> serde_json::Value {
+    let mut version = value
+        .get("schema_version")
+        .and_then(|version| version.as_u64())
+        .unwrap_or(1) as u32;
+    while version < CURRENT_SESSION_SCHEMA_VERSION {
+        let migration = SESSION_MIGRATIONS
+            .get((version - 1) as usize)
+            .unwrap_or_else(|| {
+                panic!(
+                    "no migration registered to take session {storage_path} from schema version {version} to {}",
+                    version + 1
+                )
+            });
+        value = migration(value);
+        version += 1;
+    }
+    if let Some(object) = value.as_object_mut() {
+        object.remove("schema_version");
+    }
+    value
+}
 
 /// The session service which takes care of creating the session and manages the storage
 pub struct SessionService {
     tool_box: Arc<ToolBox>,
     symbol_manager: Arc<SymbolManager>,
     running_exchanges: Arc<Mutex<HashMap<String, CancellationToken>>>,
+    // when set, sessions are persisted as indented JSON for easier manual
+    // inspection/diffing instead of the default compact form
+    pretty_print_sessions: bool,
+    // the most recently reported editor_url per session, so a mid-session
+    // window restart (a new port) can be picked up by requests which only
+    // have the session_id at hand, instead of the editor_url they were
+    // constructed with going stale
+    active_editor_urls: Arc<Mutex<HashMap<String, String>>>,
+    // one `Notify` per exchange currently paused inside
+    // `tool_use_agentic_with_interrupt`, waiting on the user to call
+    // `continue_agentic`
+    interrupt_notifiers: Arc<Mutex<HashMap<String, Arc<tokio::sync::Notify>>>>,
+    // exchanges a soft-stop has been requested for, checked at the top of
+    // each `tool_use_agentic` iteration; a hard `CancellationToken` aborts
+    // the in-flight tool immediately and can leave a half-applied edit
+    // behind, whereas this lets the current tool call finish first
+    soft_stop_requests: Arc<Mutex<std::collections::HashSet<String>>>,
+    // one optional `WorkspaceFileWatcher` per session, started the first
+    // time `tool_use_agentic` runs for that session_id; entirely best-effort,
+    // a session with no entry here (headless/eval runs, or a root_directory
+    // the watcher failed to attach to) just never gets a stale-files note
+    file_watchers: Arc<Mutex<HashMap<String, Arc<WorkspaceFileWatcher>>>>,
+    // one lock per session storage path, held for the full duration of
+    // `save_to_storage`'s read-merge-write so two genuinely concurrent savers
+    // (e.g. `plan_generation` and the hot-streak path) can't both read the
+    // same on-disk state, merge it in, and race to write - the second write
+    // clobbering whatever the first one added
+    save_locks: Arc<Mutex<HashMap<String, Arc<Mutex<()>>>>>,
 }
 
+// A per-session `cost_limit_guard(max_usd: f64)` was tried here (and reverted)
+// and is intentionally not present: `LLMBroker`/`llm_client` don't capture
+// per-call token usage or per-model pricing anywhere, so there was no real
+// `total_cost_usd` to compare against a limit - only a placeholder that would
+// always read zero. Landing the guard needs that accounting built out in
+// `llm_client` first; wiring a fake one here would silently do nothing while
+// looking like a working spend limit.
+
 impl SessionService {
     pub fn new(tool_box: Arc<ToolBox>, symbol_manager: Arc<SymbolManager>) -> Self {
         Self {
             tool_box,
             symbol_manager,
             running_exchanges: Arc::new(Mutex::new(HashMap::new())),
+            pretty_print_sessions: false,
+            active_editor_urls: Arc::new(Mutex::new(HashMap::new())),
+            interrupt_notifiers: Arc::new(Mutex::new(HashMap::new())),
+            soft_stop_requests: Arc::new(Mutex::new(std::collections::HashSet::new())),
+            file_watchers: Arc::new(Mutex::new(HashMap::new())),
+            save_locks: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Returns the lock guarding `storage_path`'s read-merge-write in
+    /// `save_to_storage`, creating one the first time this path is saved.
+    async fn save_lock_for_path(&self, storage_path: &str) -> Arc<Mutex<()>> {
+        let mut save_locks = self.save_locks.lock().await;
+        save_locks
+            .entry(storage_path.to_owned())
+            .or_insert_with(|| Arc::new(Mutex::new(())))
+            .clone()
+    }
+
+    /// Starts (or reuses) the `WorkspaceFileWatcher` for `session_id`, scoped
+    /// to `root_directory`. Watching is best-effort: if a watcher could not
+    /// be started, this session simply never gets a stale-files note.
+    async fn file_watcher_for_session(
+        &self,
+        session_id: &str,
+        root_directory: &str,
+    ) -> Option<Arc<WorkspaceFileWatcher>> {
+        let mut file_watchers = self.file_watchers.lock().await;
+        if let Some(watcher) = file_watchers.get(session_id) {
+            return Some(watcher.clone());
         }
+        let watcher = Arc::new(WorkspaceFileWatcher::start(std::path::Path::new(root_directory))?);
+        file_watchers.insert(session_id.to_owned(), watcher.clone());
+        Some(watcher)
+    }
+
+    /// Records `editor_url` as the current editor endpoint for `session_id`
+    /// and emits a UI event acknowledging the switch. Callers holding a
+    /// `SymbolEventMessageProperties` constructed against the old URL should
+    /// retarget it via `set_editor_url` before retrying any in-flight tool
+    /// call, since this only updates what future lookups see.
+    pub async fn update_editor_url(
+        &self,
+        session_id: &str,
+        exchange_id: &str,
+        editor_url: String,
+        ui_sender: &tokio::sync::mpsc::UnboundedSender<UIEventWithID>,
+    ) {
+        let previous_editor_url = {
+            let mut active_editor_urls = self.active_editor_urls.lock().await;
+            active_editor_urls.insert(session_id.to_owned(), editor_url.clone())
+        };
+        if let Some(previous_editor_url) = previous_editor_url {
+            if previous_editor_url != editor_url {
+                let _ = ui_sender.send(UIEventWithID::editor_url_switched(
+                    session_id.to_owned(),
+                    exchange_id.to_owned(),
+                    previous_editor_url,
+                    editor_url,
+                ));
+            }
+        }
+    }
+
+    /// The most recently reported editor_url for `session_id`, if the editor
+    /// has ever checked in for it via `update_editor_url`.
+    pub async fn editor_url_for_session(&self, session_id: &str) -> Option<String> {
+        let active_editor_urls = self.active_editor_urls.lock().await;
+        active_editor_urls.get(session_id).cloned()
+    }
+
+    pub fn with_pretty_print_sessions(mut self, pretty_print_sessions: bool) -> Self {
+        self.pretty_print_sessions = pretty_print_sessions;
+        self
     }
 
     async fn track_exchange(
@@ -66,6 +317,30 @@ impl SessionService {
         running_exchanges.insert(hash_id, cancellation_token);
     }
 
+    async fn untrack_exchange(&self, session_id: &str, exchange_id: &str) {
+        let hash_id = format!("{}-{}", session_id, exchange_id);
+        let mut running_exchanges = self.running_exchanges.lock().await;
+        running_exchanges.remove(&hash_id);
+    }
+
+    /// Asks the `tool_use_agentic` loop running `exchange_id` to wrap up
+    /// after its current tool call instead of aborting it outright. Checked
+    /// at the top of the next iteration; if the loop has already finished by
+    /// then this is a no-op.
+    pub async fn request_soft_stop(&self, session_id: &str, exchange_id: &str) {
+        let hash_id = format!("{}-{}", session_id, exchange_id);
+        let mut soft_stop_requests = self.soft_stop_requests.lock().await;
+        soft_stop_requests.insert(hash_id);
+    }
+
+    /// Checks whether a soft-stop was requested for `exchange_id`, clearing
+    /// it in the same step so it only fires once.
+    async fn take_soft_stop_request(&self, session_id: &str, exchange_id: &str) -> bool {
+        let hash_id = format!("{}-{}", session_id, exchange_id);
+        let mut soft_stop_requests = self.soft_stop_requests.lock().await;
+        soft_stop_requests.remove(&hash_id)
+    }
+
     pub async fn get_cancellation_token(
         &self,
         session_id: &str,
@@ -78,6 +353,60 @@ impl SessionService {
             .map(|cancellation_token| cancellation_token.clone())
     }
 
+    /// Registers (or reuses) the `Notify` a paused
+    /// `tool_use_agentic_with_interrupt` exchange is waiting on, so
+    /// `continue_agentic` can find it later.
+    async fn interrupt_notifier(&self, session_id: &str, exchange_id: &str) -> Arc<tokio::sync::Notify> {
+        let hash_id = format!("{}-{}", session_id, exchange_id);
+        let mut interrupt_notifiers = self.interrupt_notifiers.lock().await;
+        interrupt_notifiers
+            .entry(hash_id)
+            .or_insert_with(|| Arc::new(tokio::sync::Notify::new()))
+            .clone()
+    }
+
+    async fn clear_interrupt_notifier(&self, session_id: &str, exchange_id: &str) {
+        let hash_id = format!("{}-{}", session_id, exchange_id);
+        let mut interrupt_notifiers = self.interrupt_notifiers.lock().await;
+        interrupt_notifiers.remove(&hash_id);
+    }
+
+    /// Wakes a `tool_use_agentic_with_interrupt` loop which is currently
+    /// paused after `exchange_id`, letting it move on to the next tool call.
+    /// A no-op if the exchange is not currently paused (already resumed, or
+    /// never entered a pause).
+    pub async fn continue_agentic(&self, session_id: &str, exchange_id: &str) {
+        let hash_id = format!("{}-{}", session_id, exchange_id);
+        let interrupt_notifiers = self.interrupt_notifiers.lock().await;
+        if let Some(notifier) = interrupt_notifiers.get(&hash_id) {
+            notifier.notify_one();
+        }
+    }
+
+    /// Starts a background task which periodically sweeps `active_session_dir`
+    /// for sessions older than `max_age_days` and moves them to `archive_dir`.
+    /// The task runs for the lifetime of the process, so this should only be
+    /// called once per `SessionService`.
+    pub fn spawn_session_archiver(
+        &self,
+        active_session_dir: std::path::PathBuf,
+        archive_dir: std::path::PathBuf,
+        max_age_days: u64,
+        compress: bool,
+    ) {
+        tokio::spawn(async move {
+            let archiver = SessionArchiver::new(archive_dir, max_age_days, compress);
+            let mut sweep_interval =
+                tokio::time::interval(std::time::Duration::from_secs(24 * 60 * 60));
+            loop {
+                sweep_interval.tick().await;
+                if let Err(e) = archiver.archive_old_sessions(&active_session_dir).await {
+                    tracing::error!("failed to archive old sessions: {:?}", e);
+                }
+            }
+        });
+    }
+
     pub fn create_new_session_with_tools(
         &self,
         session_id: &str,
@@ -125,6 +454,7 @@ impl SessionService {
         repo_ref: RepoRef,
         agent_mode: AideAgentMode,
         mut message_properties: SymbolEventMessageProperties,
+        open_exchanges_policy: OpenExchangesPolicy,
     ) -> Result<(), SymbolError> {
         println!("session_service::human_message::start");
         let mut session = if let Ok(session) = self.load_from_storage(storage_path.to_owned()).await
@@ -153,8 +483,12 @@ impl SessionService {
             user_context,
             project_labels,
             repo_ref,
+            MessageRole::User,
         );
 
+        session = session
+            .accept_open_exchanges_if_any(open_exchanges_policy, message_properties.clone())?;
+
         let plan_exchange_id = self
             .tool_box
             .create_new_exchange(session_id.to_owned(), message_properties.clone())
@@ -339,6 +673,7 @@ impl SessionService {
         message_properties = message_properties
             .set_request_id(plan_exchange_id)
             .set_cancellation_token(cancellation_token);
+
         // now we can perform the plan generation over here
         session = session
             .perform_plan_generation(
@@ -359,12 +694,30 @@ impl SessionService {
         Ok(())
     }
 
+    /// Re-fetches the "these files will likely change" preview which was
+    /// computed and persisted alongside the plan once its steps finished
+    /// generating
+    pub async fn plan_impact(
+        &self,
+        plan_id: String,
+        plan_service: PlanService,
+    ) -> Result<PlanImpactSummary, SymbolError> {
+        let plan = plan_service
+            .load_plan_from_id(&plan_id)
+            .await
+            .map_err(|e| SymbolError::IOError(e))?;
+        plan.impact_summary()
+            .cloned()
+            .ok_or(SymbolError::PlanImpactSummaryNotFound(plan_id))
+    }
+
     /// TODO(skcd): Pick up the integration from here for the tool use
     pub async fn tool_use_agentic(
         &self,
         session_id: String,
         storage_path: String,
         user_message: String,
+        user_context: UserContext,
         exchange_id: String,
         all_files: Vec<String>,
         open_files: Vec<String>,
@@ -372,10 +725,41 @@ impl SessionService {
         project_labels: Vec<String>,
         repo_ref: RepoRef,
         root_directory: String,
+        // the other folders open alongside `root_directory` when the editor
+        // has a multi-root workspace open; empty for the common single-root
+        // case
+        additional_roots: Vec<WorkspaceRoot>,
         tool_box: Arc<ToolBox>,
         tool_broker: Arc<ToolBroker>,
         llm_broker: Arc<LLMBroker>,
         mut message_properties: SymbolEventMessageProperties,
+        report_unresolved_diagnostics: bool,
+        // when set, a command attached to `attempt_completion` is run before
+        // we accept the completion, and a non-zero looking result sends the
+        // agent back around the loop instead of finishing. Some users would
+        // rather get the instant completion and verify themselves, hence the
+        // flag instead of always doing this.
+        verify_completion_command: bool,
+        // when set, an `attempt_completion` is only accepted once diagnostics
+        // tracked on the session come back clean; otherwise they are fed
+        // back to the agent and the loop continues, up to
+        // `MAX_COMPLETION_DIAGNOSTIC_RETRIES` times.
+        verify_diagnostics_before_completion: bool,
+        // when set, the loop pauses after each complete LLM response (a
+        // chosen tool call) instead of executing it immediately, emitting
+        // `awaiting_user_continue` and waiting for `continue_agentic` to be
+        // called for this exchange. If nothing arrives before the duration
+        // elapses, the loop ends as though the agent had called
+        // `attempt_completion` itself.
+        pause_for_user_review: Option<std::time::Duration>,
+        // what to do with exchanges the user never explicitly reviewed if
+        // one is still open when this call starts
+        open_exchanges_policy: OpenExchangesPolicy,
+        // chat mode is a read-only capability gate: write-capable tool calls
+        // (edits, terminal commands, file moves/deletes) are rejected back
+        // to the model instead of being run, while read-only tools stay
+        // available so a chat reply can still ground itself in the codebase
+        agent_mode: AideAgentMode,
     ) -> Result<(), SymbolError> {
         println!("session_service::tool_use_agentic::start");
         let mut session = if let Ok(session) = self.load_from_storage(storage_path.to_owned()).await
@@ -402,10 +786,21 @@ impl SessionService {
                     ToolType::AttemptCompletion,
                     ToolType::RepoMapGeneration,
                     ToolType::TerminalCommand,
+                    ToolType::RunTests,
+                    ToolType::DeleteFile,
+                    ToolType::MoveFile,
+                    ToolType::SummarizeContext,
+                    ToolType::ShowDiff,
                 ],
             )
         };
 
+        // keep a copy around for validating file-mutating tool calls further
+        // down in the loop, since `root_directory` itself is moved into the
+        // tool use agent right below
+        let workspace_roots = WorkspaceRoots::new(root_directory.clone(), additional_roots.clone());
+        let root_directory_for_validation = root_directory.clone();
+
         // os can be passed over here safely since we can assume the sidecar is running
         // close to the vscode server
         // we should ideally get this information from the vscode-server side setting
@@ -414,23 +809,99 @@ impl SessionService {
             root_directory,
             std::env::consts::OS.to_owned(),
             shell.to_owned(),
-        );
+        )
+        .with_structured_tool_call_broker(Arc::new(LLMBrokerStructuredToolCall::new(
+            llm_broker.clone(),
+        )))
+        .with_additional_roots(additional_roots);
 
         session = session.human_message_tool_use(
             exchange_id.to_owned(),
             user_message,
+            user_context,
             all_files,
             open_files,
             shell,
         );
         let _ = self.save_to_storage(&session).await;
 
-        session = session.accept_open_exchanges_if_any(message_properties.clone());
+        session = session
+            .accept_open_exchanges_if_any(open_exchanges_policy, message_properties.clone())?;
         let mut human_message_ticker = 0;
+        let mut completion_diagnostic_retries = 0;
         // now that we have saved it we can start the loop over here and look out for the cancellation
         // token which will imply that we should end the current loop
         loop {
             let _ = self.save_to_storage(&session).await;
+
+            if let Some(file_watcher) = self
+                .file_watcher_for_session(&session_id, &root_directory_for_validation)
+                .await
+            {
+                let changed_files =
+                    file_watcher.take_changed_relevant_files(session.tracked_open_files());
+                if let Some(note) = stale_files_note(&changed_files) {
+                    human_message_ticker = human_message_ticker + 1;
+                    session = session.human_message(
+                        human_message_ticker.to_string(),
+                        note,
+                        UserContext::default(),
+                        vec![],
+                        repo_ref.clone(),
+                        MessageRole::User,
+                    );
+                }
+            }
+
+            if self.take_soft_stop_request(&session_id, &exchange_id).await {
+                let previous_messages = session.conversation_messages(tool_broker.clone()).await;
+                let closing_exchange_id = self
+                    .tool_box
+                    .create_new_exchange(session_id.to_owned(), message_properties.clone())
+                    .await?;
+                // reuse `SummarizeContextClient`: asking it to compress the
+                // conversation so far already produces exactly the "what was
+                // accomplished, what's still open" summary a soft-stop wants
+                let request = SummarizeContextRequest::new(
+                    previous_messages,
+                    session_id.to_owned(),
+                    closing_exchange_id.clone(),
+                    message_properties.cancellation_token(),
+                    message_properties.llm_properties().clone(),
+                );
+                let summary = tool_broker
+                    .invoke(ToolInput::SummarizeContext(request))
+                    .await
+                    .ok()
+                    .and_then(|output| output.get_summarize_context_response())
+                    .map(|response| response.summary().to_owned());
+                human_message_ticker = human_message_ticker + 1;
+                session = session.human_message(
+                    human_message_ticker.to_string(),
+                    match summary {
+                        Some(summary) => format!(
+                            "Stopped after the current step as requested. Summary of progress:\n{}",
+                            summary
+                        ),
+                        None => "Stopped after the current step as requested.".to_owned(),
+                    },
+                    UserContext::default(),
+                    vec![],
+                    repo_ref.clone(),
+                    MessageRole::ToolResult {
+                        tool: ToolType::SummarizeContext,
+                    },
+                );
+                let _ = message_properties.ui_sender().send(
+                    UIEventWithID::request_soft_stopped(
+                        session_id.to_owned(),
+                        closing_exchange_id,
+                    ),
+                );
+                let _ = self.save_to_storage(&session).await;
+                break;
+            }
+
             let tool_exchange_id = self
                 .tool_box
                 .create_new_exchange(session_id.to_owned(), message_properties.clone())
@@ -446,11 +917,9 @@ impl SessionService {
             self.track_exchange(&session_id, &tool_exchange_id, cancellation_token.clone())
                 .await;
 
+            let tool_exchange_id_for_diagnostics = tool_exchange_id.to_owned();
             let tool_use_output = dbg!(
                 session
-                    // the clone here is pretty bad but its the easiest and the sanest
-                    // way to keep things on the happy path
-                    .clone()
                     .get_tool_to_use(
                         tool_box.clone(),
                         tool_exchange_id,
@@ -462,11 +931,86 @@ impl SessionService {
             )?;
 
             match tool_use_output {
-                AgentToolUseOutput::Success((tool_input_partial, new_session)) => {
-                    // update our session
-                    session = new_session;
+                AgentToolUseOutput::Success((tool_input_partial, exchange, tracked_open_files)) => {
+                    // apply the delta: the new exchange plus the refreshed
+                    // open-files list, rather than swapping in a whole new
+                    // cloned session
+                    session = session.apply_tool_use_exchange(exchange, tracked_open_files);
                     // store to disk
                     let _ = self.save_to_storage(&session).await;
+
+                    // the model can emit a tool call which is well-formed
+                    // JSON/XML but semantically broken (an empty file path, a
+                    // regex which doesn't compile); catch that here instead
+                    // of letting it fail deep inside the tool, and feed the
+                    // validation error back so the model can correct itself
+                    if let Err(validation_error) = tool_input_partial.validate() {
+                        let tool_type = tool_input_partial.to_tool_type();
+                        println!("tool_input_partial::validation_failed::({})", &validation_error);
+                        human_message_ticker = human_message_ticker + 1;
+                        session = session.human_message(
+                            human_message_ticker.to_string(),
+                            format!(
+                                "Your tool call was not valid, please correct it and try again:\n{}",
+                                validation_error
+                            ),
+                            UserContext::default(),
+                            vec![],
+                            repo_ref.clone(),
+                            MessageRole::ToolResult { tool: tool_type },
+                        );
+                        continue;
+                    }
+
+                    // chat mode is read-only: a write-capable tool call is
+                    // rejected here, before it ever reaches a tool
+                    // invocation, with a polite note fed back so the model
+                    // can answer from what it already knows instead
+                    if let Err(mode_error) = tool_input_partial.validate_for_mode(&agent_mode) {
+                        let tool_type = tool_input_partial.to_tool_type();
+                        println!("tool_input_partial::mode_rejected::({})", &mode_error);
+                        human_message_ticker = human_message_ticker + 1;
+                        session = session.human_message(
+                            human_message_ticker.to_string(),
+                            format!(
+                                "{}\nPlease answer using the information already available instead.",
+                                mode_error
+                            ),
+                            UserContext::default(),
+                            vec![],
+                            repo_ref.clone(),
+                            MessageRole::ToolResult { tool: tool_type },
+                        );
+                        continue;
+                    }
+
+                    // for step-by-step review sessions, pause here (right
+                    // after a complete LLM response has been parsed into a
+                    // tool call, before we run it) until the user calls
+                    // `continue_agentic` for this exchange or the timeout
+                    // elapses, in which case we treat it the same as the
+                    // agent calling `attempt_completion` itself
+                    if let Some(timeout) = pause_for_user_review {
+                        let notifier = self
+                            .interrupt_notifier(&session_id, &tool_exchange_id_for_diagnostics)
+                            .await;
+                        let _ = message_properties.ui_sender().send(
+                            UIEventWithID::awaiting_user_continue(
+                                message_properties.root_request_id().to_owned(),
+                                tool_exchange_id_for_diagnostics.clone(),
+                                timeout.as_millis() as u64,
+                            ),
+                        );
+                        let timed_out = tokio::time::timeout(timeout, notifier.notified())
+                            .await
+                            .is_err();
+                        self.clear_interrupt_notifier(&session_id, &tool_exchange_id_for_diagnostics)
+                            .await;
+                        if timed_out {
+                            break;
+                        }
+                    }
+
                     // execute the partial tool input and get the final output here
                     match tool_input_partial {
                         ToolInputPartial::AskFollowupQuestions(followup_question) => {
@@ -478,16 +1022,196 @@ impl SessionService {
                         ToolInputPartial::AttemptCompletion(attempt_completion) => {
                             println!("LLM reached a stop condition");
                             println!("{:?}", &attempt_completion);
+                            let verification_command = if verify_completion_command {
+                                attempt_completion.command()
+                            } else {
+                                None
+                            };
+                            if let Some(command) = verification_command {
+                                let request =
+                                    TerminalInput::new(command, message_properties.editor_url());
+                                let tool_output =
+                                    match tool_broker.invoke(ToolInput::TerminalCommand(request.clone())).await {
+                                        Ok(output) => output,
+                                        Err(error) => {
+                                            let (action, message) = categorize_tool_error(&error);
+                                            let _ = message_properties.ui_sender().send(
+                                                UIEventWithID::tool_error(
+                                                    session_id.to_owned(),
+                                                    tool_exchange_id_for_diagnostics.clone(),
+                                                    message.clone(),
+                                                ),
+                                            );
+                                            match action {
+                                                ToolErrorAction::Retry => tool_broker
+                                                    .invoke(ToolInput::TerminalCommand(request))
+                                                    .await
+                                                    .expect("verification command retry to succeed"),
+                                                ToolErrorAction::FeedbackToModel => {
+                                                    human_message_ticker = human_message_ticker + 1;
+                                                    session = session.human_message(
+                                                        human_message_ticker.to_string(),
+                                                        format!(
+                                                            "The verification command could not be run ({message}), so the task is not complete yet.",
+                                                        ),
+                                                        UserContext::default(),
+                                                        vec![],
+                                                        repo_ref.clone(),
+                                                        MessageRole::ToolResult {
+                                                            tool: ToolType::TerminalCommand,
+                                                        },
+                                                    );
+                                                    continue;
+                                                }
+                                                ToolErrorAction::Abort => break,
+                                            }
+                                        }
+                                    };
+                                let output = tool_output
+                                    .terminal_command()
+                                    .expect("to work")
+                                    .output()
+                                    .to_owned();
+                                if test_run_failed(&output) {
+                                    human_message_ticker = human_message_ticker + 1;
+                                    session = session.human_message(
+                                        human_message_ticker.to_string(),
+                                        format!(
+                                            "The verification command failed, so the task is not complete yet. Output:\n{}",
+                                            output
+                                        ),
+                                        UserContext::default(),
+                                        vec![],
+                                        repo_ref.clone(),
+                                        MessageRole::ToolResult {
+                                            tool: ToolType::TerminalCommand,
+                                        },
+                                    );
+                                    continue;
+                                }
+                            }
+                            if verify_diagnostics_before_completion {
+                                let unresolved_diagnostics = session.get_unresolved_diagnostics();
+                                if should_retry_completion_for_diagnostics(
+                                    !unresolved_diagnostics.is_empty(),
+                                    completion_diagnostic_retries,
+                                ) {
+                                    let diagnostics_summary = unresolved_diagnostics
+                                        .into_iter()
+                                        .map(|diagnostic| {
+                                            format!(
+                                                "- {}: {}",
+                                                diagnostic.fs_file_path(),
+                                                diagnostic.message()
+                                            )
+                                        })
+                                        .collect::<Vec<_>>()
+                                        .join("\n");
+                                    completion_diagnostic_retries += 1;
+                                    human_message_ticker = human_message_ticker + 1;
+                                    session = session.human_message(
+                                        human_message_ticker.to_string(),
+                                        format!(
+                                            "The task is not complete yet, the following diagnostics are still outstanding:\n{}",
+                                            diagnostics_summary
+                                        ),
+                                        UserContext::default(),
+                                        vec![],
+                                        repo_ref.clone(),
+                                        MessageRole::ToolResult {
+                                            tool: ToolType::LSPDiagnostics,
+                                        },
+                                    );
+                                    continue;
+                                }
+                            }
                             break;
                         }
                         ToolInputPartial::CodeEditing(code_editing) => {
                             let fs_file_path = code_editing.fs_file_path().to_owned();
                             println!("Code editing: {}", fs_file_path);
-                            let file_contents = tool_box
-                                .file_open(fs_file_path.to_owned(), message_properties.clone())
-                                .await
-                                .expect("file_contents to work")
-                                .contents();
+                            if let Err(constraint_violation) = session
+                                .check_constraints(&fs_file_path, code_editing.instruction())
+                            {
+                                human_message_ticker = human_message_ticker + 1;
+                                session = session.human_message(
+                                    human_message_ticker.to_string(),
+                                    constraint_violation.to_string(),
+                                    UserContext::default(),
+                                    vec![],
+                                    repo_ref.clone(),
+                                    MessageRole::ToolResult {
+                                        tool: ToolType::CodeEditing,
+                                    },
+                                );
+                                continue;
+                            }
+                            let file_open_response = open_file_retrying_transport_errors(
+                                &tool_box,
+                                &fs_file_path,
+                                message_properties.clone(),
+                            )
+                            .await;
+                            let file_open_response = match file_open_response {
+                                Ok(response) if response.exists() => response,
+                                Ok(_does_not_exist) => {
+                                    let close_matches =
+                                        find_close_matching_paths(&tool_broker, &fs_file_path)
+                                            .await;
+                                    let corrective_message = if close_matches.is_empty() {
+                                        format!(
+                                            "{} does not exist. Use list_files or search_files to find the correct path before editing it.",
+                                            fs_file_path
+                                        )
+                                    } else {
+                                        format!(
+                                            "{} does not exist. Did you mean one of these?\n{}",
+                                            fs_file_path,
+                                            close_matches.join("\n")
+                                        )
+                                    };
+                                    human_message_ticker = human_message_ticker + 1;
+                                    session = session.human_message(
+                                        human_message_ticker.to_string(),
+                                        corrective_message,
+                                        UserContext::default(),
+                                        vec![],
+                                        repo_ref.clone(),
+                                        MessageRole::ToolResult {
+                                            tool: ToolType::CodeEditing,
+                                        },
+                                    );
+                                    continue;
+                                }
+                                Err(_communication_error) => {
+                                    let corrective_message = format!(
+                                        "Could not reach the editor to open {} after retrying, the edit was not performed. Try again.",
+                                        fs_file_path
+                                    );
+                                    human_message_ticker = human_message_ticker + 1;
+                                    session = session.human_message(
+                                        human_message_ticker.to_string(),
+                                        corrective_message,
+                                        UserContext::default(),
+                                        vec![],
+                                        repo_ref.clone(),
+                                        MessageRole::ToolResult {
+                                            tool: ToolType::CodeEditing,
+                                        },
+                                    );
+                                    continue;
+                                }
+                            };
+                            let file_contents = file_open_response.contents();
+                            // captured at read time so we can detect if the file is
+                            // changed externally (another process, the user typing)
+                            // before we apply the edit to it below
+                            let file_contents_hash_at_read = file_content_hash(&file_contents);
+
+                            // remembers what this file looked like the first time the
+                            // session touches it, so later edits can be diffed against
+                            // the whole session's changes instead of just the latest one
+                            session = session.track_edit_baseline(&fs_file_path, file_contents.clone());
 
                             let instruction = code_editing.instruction().to_owned();
 
@@ -497,27 +1221,59 @@ impl SessionService {
                                 .file_open(fs_file_path.to_owned(), message_properties.clone())
                                 .await;
 
+                            // re-check the file content right before applying the edit; if it
+                            // changed externally since we read it above, applying our edit on
+                            // top of it would silently clobber whatever changed it, so abort
+                            // and ask the agent to re-read the file instead
+                            let file_open_response_before_apply = open_file_retrying_transport_errors(
+                                &tool_box,
+                                &fs_file_path,
+                                message_properties.clone(),
+                            )
+                            .await;
+                            let file_changed_externally = match file_open_response_before_apply {
+                                Ok(response) => {
+                                    file_content_hash(&response.contents())
+                                        != file_contents_hash_at_read
+                                }
+                                Err(_communication_error) => false,
+                            };
+                            if file_changed_externally {
+                                let corrective_message = format!(
+                                    "{} changed externally after it was read, so the edit was not applied. Re-read the file and try again.",
+                                    fs_file_path
+                                );
+                                human_message_ticker = human_message_ticker + 1;
+                                session = session.human_message(
+                                    human_message_ticker.to_string(),
+                                    corrective_message,
+                                    UserContext::default(),
+                                    vec![],
+                                    repo_ref.clone(),
+                                    MessageRole::ToolResult {
+                                        tool: ToolType::CodeEditing,
+                                    },
+                                );
+                                continue;
+                            }
+
                             let default_range =
                             // very large end position
                                 Range::new(Position::new(0, 0, 0), Position::new(10_000, 0, 0));
 
-                            let symbol_to_edit = SymbolToEdit::new(
+                            let symbol_to_edit = SymbolToEditBuilder::new(
                                 fs_file_path.to_owned(),
                                 default_range,
                                 fs_file_path.to_owned(),
                                 vec![instruction.clone()],
-                                false,
-                                false, // is_new
-                                false,
                                 "".to_owned(),
-                                None,
-                                false,
-                                None,
-                                false,
-                                None,
-                                vec![], // previous_user_queries
-                                None,
-                            );
+                            )
+                            // the content this edit was planned against, so
+                            // `code_editing_with_search_and_replace` can
+                            // reject it if the file changed again in the gap
+                            // between this read and the edit actually running
+                            .expected_content_hash(Some(content_hash(&file_contents)))
+                            .build();
 
                             let symbol_identifier = SymbolIdentifier::new_symbol(&fs_file_path);
 
@@ -538,35 +1294,70 @@ impl SessionService {
                                 .expect("to work"); // big expectations but can also fail, we should handle it properly
 
                             // now that we have modified the file we can ask the editor for the git-diff of this file over here
-                            // and we also have the previous state over here
+                            // and we also have the previous state over here; prefer the
+                            // committed baseline over whatever the editor read right
+                            // before our edit, so the diff the agent sees isn't thrown
+                            // off by uncommitted changes it made earlier in the loop
                             let diff_changes = self
                                 .tool_box
                                 .recently_edited_files_with_content(
                                     vec![fs_file_path.to_owned()].into_iter().collect(),
-                                    match old_file_content {
-                                        Ok(old_file_content) => {
-                                            vec![DiffFileContent::new(
+                                    match DiffFileContent::from_git_index(&fs_file_path)
+                                        .or_else(|_| old_file_content.map(|old_file_content| {
+                                            DiffFileContent::new(
                                                 fs_file_path.to_owned(),
                                                 old_file_content.contents(),
-                                            )]
-                                        }
+                                            )
+                                        }))
+                                    {
+                                        Ok(diff_file_content) => vec![diff_file_content],
                                         Err(_) => vec![],
                                     },
                                     message_properties.clone(),
                                 )
                                 .await?;
 
+                            // the edit covered `default_range`, so any diagnostics we were
+                            // tracking in that span are no longer unresolved
+                            session = session.resolve_diagnostics_for_edit(
+                                &fs_file_path,
+                                &default_range,
+                            );
+
                             // we need to take the L1 level changes here since those are the ones we are interested in and then add
                             // that as a human message over here
                             human_message_ticker = human_message_ticker + 1;
-                            session = session.human_message(
-                                human_message_ticker.to_string(),
-                                format!(r#"I performed the edits which you asked for, here is the git diff for it:
-{}"#, diff_changes.l1_changes()),
-                                UserContext::default(),
-                                vec![],
-                                repo_ref.clone(),
-                            );
+                            let l1_changes = diff_changes.l1_changes();
+                            session = match summarize_diff_if_too_large(
+                                &l1_changes,
+                                DEFAULT_DIFF_SUMMARY_THRESHOLD_LINES,
+                            ) {
+                                Some(condensed_diff) => session.human_message_with_full_diff(
+                                    human_message_ticker.to_string(),
+                                    format!(r#"I performed the edits which you asked for, here is a condensed summary of the git diff for it (it was too large to show in full):
+{}
+The full diff is available if you need it, use the show_diff tool with fs_file_path set to {}."#, condensed_diff, fs_file_path),
+                                    UserContext::default(),
+                                    vec![],
+                                    repo_ref.clone(),
+                                    fs_file_path.to_owned(),
+                                    l1_changes.to_owned(),
+                                    MessageRole::ToolResult {
+                                        tool: ToolType::CodeEditing,
+                                    },
+                                ),
+                                None => session.human_message(
+                                    human_message_ticker.to_string(),
+                                    format!(r#"I performed the edits which you asked for, here is the git diff for it:
+{}"#, l1_changes),
+                                    UserContext::default(),
+                                    vec![],
+                                    repo_ref.clone(),
+                                    MessageRole::ToolResult {
+                                        tool: ToolType::CodeEditing,
+                                    },
+                                ),
+                            };
                             println!("response: {:?}", response);
                         }
                         ToolInputPartial::LSPDiagnostics(diagnostics) => {
@@ -589,6 +1380,11 @@ impl SessionService {
                                     acc
                                 });
 
+                            session = session.track_diagnostics(
+                                tool_exchange_id_for_diagnostics.clone(),
+                                &diagnostics_grouped_by_file,
+                            );
+
                             let formatted_diagnostics =
                                 PlanService::format_diagnostics(&diagnostics_grouped_by_file);
                             human_message_ticker = human_message_ticker + 1;
@@ -598,20 +1394,45 @@ impl SessionService {
                                 UserContext::default(),
                                 vec![],
                                 repo_ref.clone(),
+                                MessageRole::ToolResult {
+                                    tool: ToolType::LSPDiagnostics,
+                                },
                             );
                         }
                         ToolInputPartial::ListFiles(list_files) => {
                             println!("list files: {}", list_files.directory_path());
                             let input = ToolInput::ListFiles(list_files);
                             let response = tool_broker.invoke(input).await;
-                            let list_files_output = response
-                                .expect("to work")
-                                .get_list_files_directory()
-                                .expect("to work");
+                            let list_files_output =
+                                match response.expect("to work").checked_list_files_directory() {
+                                    Ok(list_files_output) => list_files_output,
+                                    Err(error) => {
+                                        human_message_ticker = human_message_ticker + 1;
+                                        session = session.human_message(
+                                            human_message_ticker.to_string(),
+                                            format!(
+                                                "The list files tool did not return the expected output ({error}), so the directory listing is not available.",
+                                            ),
+                                            UserContext::default(),
+                                            vec![],
+                                            repo_ref.clone(),
+                                            MessageRole::ToolResult {
+                                                tool: ToolType::ListFiles,
+                                            },
+                                        );
+                                        continue;
+                                    }
+                                };
+                            // render paths relative to the repo root in the prompt so we
+                            // don't waste tokens and leak machine-specific paths into the
+                            // transcript; execution itself still goes through absolute
+                            // paths (see `ListFilesInput`/`directory_path` above)
                             let response = list_files_output
                                 .files()
                                 .into_iter()
-                                .map(|file_path| file_path.to_string_lossy().to_string())
+                                .map(|file_path| {
+                                    repo_ref.to_relative_path(&file_path.to_string_lossy())
+                                })
                                 .collect::<Vec<_>>()
                                 .join("\n");
                             human_message_ticker = human_message_ticker + 1;
@@ -621,6 +1442,9 @@ impl SessionService {
                                 UserContext::default(),
                                 vec![],
                                 repo_ref.clone(),
+                                MessageRole::ToolResult {
+                                    tool: ToolType::ListFiles,
+                                },
                             );
                             println!("response: {:?}", response);
                         }
@@ -632,13 +1456,30 @@ impl SessionService {
                                 message_properties.editor_url(),
                             );
                             let input = ToolInput::OpenFile(request);
-                            let response = tool_broker
+                            let response = match tool_broker
                                 .invoke(input)
                                 .await
                                 .expect("to work")
-                                .get_file_open_response()
-                                .expect("to work")
-                                .to_string();
+                                .checked_file_open_response()
+                            {
+                                Ok(open_file_response) => open_file_response.to_string(),
+                                Err(error) => {
+                                    human_message_ticker = human_message_ticker + 1;
+                                    session = session.human_message(
+                                        human_message_ticker.to_string(),
+                                        format!(
+                                            "The open file tool did not return the expected output ({error}), so the file contents are not available.",
+                                        ),
+                                        UserContext::default(),
+                                        vec![],
+                                        repo_ref.clone(),
+                                        MessageRole::ToolResult {
+                                            tool: ToolType::OpenFile,
+                                        },
+                                    );
+                                    continue;
+                                }
+                            };
                             human_message_ticker = human_message_ticker + 1;
                             session = session.human_message(
                                 human_message_ticker.to_string(),
@@ -646,6 +1487,9 @@ impl SessionService {
                                 UserContext::default(),
                                 vec![],
                                 repo_ref.clone(),
+                                MessageRole::ToolResult {
+                                    tool: ToolType::OpenFile,
+                                },
                             );
                             println!("response: {:?}", response);
                         }
@@ -655,13 +1499,31 @@ impl SessionService {
                                 search_file.directory_path().to_owned(),
                                 search_file.regex_pattern().to_owned(),
                                 search_file.file_pattern().map(|s| s.to_owned()),
+                                search_file.exclude_pattern().map(|s| s.to_owned()),
                                 message_properties.editor_url(),
                             );
                             let input = ToolInput::SearchFileContentWithRegex(request);
                             let tool_response = tool_broker.invoke(input).await.expect("to work");
-                            let response = tool_response
-                                .get_search_file_content_with_regex()
-                                .expect("to work");
+                            let response =
+                                match tool_response.checked_search_file_content_with_regex() {
+                                    Ok(response) => response,
+                                    Err(error) => {
+                                        human_message_ticker = human_message_ticker + 1;
+                                        session = session.human_message(
+                                            human_message_ticker.to_string(),
+                                            format!(
+                                                "The search tool did not return the expected output ({error}), so the search results are not available.",
+                                            ),
+                                            UserContext::default(),
+                                            vec![],
+                                            repo_ref.clone(),
+                                            MessageRole::ToolResult {
+                                                tool: ToolType::SearchFileContentWithRegex,
+                                            },
+                                        );
+                                        continue;
+                                    }
+                                };
                             let response = response.response();
                             human_message_ticker = human_message_ticker + 1;
                             session = session.human_message(
@@ -670,6 +1532,9 @@ impl SessionService {
                                 UserContext::default(),
                                 vec![],
                                 repo_ref.clone(),
+                                MessageRole::ToolResult {
+                                    tool: ToolType::SearchFileContentWithRegex,
+                                },
                             );
                             println!("response: {:?}", response);
                         }
@@ -680,12 +1545,26 @@ impl SessionService {
                                 TerminalInput::new(command, message_properties.editor_url());
                             let input = ToolInput::TerminalCommand(request);
                             let tool_output = tool_broker.invoke(input).await;
-                            let output = tool_output
-                                .expect("to work")
-                                .terminal_command()
-                                .expect("to work")
-                                .output()
-                                .to_owned();
+                            let output = match tool_output.expect("to work").checked_terminal_command()
+                            {
+                                Ok(terminal_output) => terminal_output.output().to_owned(),
+                                Err(error) => {
+                                    human_message_ticker = human_message_ticker + 1;
+                                    session = session.human_message(
+                                        human_message_ticker.to_string(),
+                                        format!(
+                                            "The terminal command tool did not return the expected output ({error}), so the command output is not available.",
+                                        ),
+                                        UserContext::default(),
+                                        vec![],
+                                        repo_ref.clone(),
+                                        MessageRole::ToolResult {
+                                            tool: ToolType::TerminalCommand,
+                                        },
+                                    );
+                                    continue;
+                                }
+                            };
                             human_message_ticker = human_message_ticker + 1;
                             session = session.human_message(
                                 human_message_ticker.to_string(),
@@ -693,6 +1572,9 @@ impl SessionService {
                                 UserContext::default(),
                                 vec![],
                                 repo_ref.clone(),
+                                MessageRole::ToolResult {
+                                    tool: ToolType::TerminalCommand,
+                                },
                             );
                             println!("response: {:?}", output);
                         }
@@ -704,15 +1586,31 @@ impl SessionService {
                             let request =
                                 ToolInput::RepoMapGeneration(RepoMapGeneratorRequest::new(
                                     repo_map_request.directory_path().to_owned(),
-                                    3000,
+                                    DEFAULT_REPO_MAP_TOKEN_LIMIT,
                                 ));
                             let tool_output = tool_broker.invoke(request).await;
-                            let repo_map_str = tool_output
-                                .expect("to work")
-                                .repo_map_generator_response()
+                            let repo_map_str = match tool_output
                                 .expect("to work")
-                                .repo_map()
-                                .to_owned();
+                                .checked_repo_map_generator_response()
+                            {
+                                Ok(repo_map_response) => repo_map_response.repo_map().to_owned(),
+                                Err(error) => {
+                                    human_message_ticker = human_message_ticker + 1;
+                                    session = session.human_message(
+                                        human_message_ticker.to_string(),
+                                        format!(
+                                            "The repo map tool did not return the expected output ({error}), so the repo map is not available.",
+                                        ),
+                                        UserContext::default(),
+                                        vec![],
+                                        repo_ref.clone(),
+                                        MessageRole::ToolResult {
+                                            tool: ToolType::RepoMapGeneration,
+                                        },
+                                    );
+                                    continue;
+                                }
+                            };
 
                             human_message_ticker = human_message_ticker + 1;
                             session = session.human_message(
@@ -721,12 +1619,612 @@ impl SessionService {
                                 UserContext::default(),
                                 vec![],
                                 repo_ref.clone(),
+                                MessageRole::ToolResult {
+                                    tool: ToolType::RepoMapGeneration,
+                                },
                             );
                             println!("response: {:?}", repo_map_str);
                         }
+                        ToolInputPartial::DeleteFile(delete_file) => {
+                            println!("delete file: {}", delete_file.fs_file_path());
+                            if !path_contained_within(delete_file.fs_file_path(), &workspace_roots) {
+                                let response = format!(
+                                    "Cannot delete {} since it lies outside the workspace root {}",
+                                    delete_file.fs_file_path(),
+                                    &root_directory_for_validation
+                                );
+                                human_message_ticker = human_message_ticker + 1;
+                                session = session.human_message(
+                                    human_message_ticker.to_string(),
+                                    response.clone(),
+                                    UserContext::default(),
+                                    vec![],
+                                    repo_ref.clone(),
+                                    MessageRole::ToolResult {
+                                        tool: ToolType::DeleteFile,
+                                    },
+                                );
+                                println!("response: {:?}", response);
+                                continue;
+                            }
+                            let request = DeleteFileInput::new(
+                                delete_file.fs_file_path().to_owned(),
+                                message_properties.editor_url(),
+                            );
+                            let input = ToolInput::DeleteFile(request);
+                            let tool_output = tool_broker.invoke(input).await;
+                            let delete_response = tool_output
+                                .expect("to work")
+                                .get_file_delete_response()
+                                .expect("to work");
+                            let deleted = delete_response.is_deleted();
+                            let response = if deleted {
+                                match delete_response.previous_content() {
+                                    Some(previous_content) => format!(
+                                        "Deleted {}\nPrevious content (for undo purposes):\n{}",
+                                        delete_file.fs_file_path(),
+                                        previous_content
+                                    ),
+                                    None => format!("Deleted {}", delete_file.fs_file_path()),
+                                }
+                            } else {
+                                format!("Failed to delete {}", delete_file.fs_file_path())
+                            };
+                            human_message_ticker = human_message_ticker + 1;
+                            session = session.human_message(
+                                human_message_ticker.to_string(),
+                                response.clone(),
+                                UserContext::default(),
+                                vec![],
+                                repo_ref.clone(),
+                                MessageRole::ToolResult {
+                                    tool: ToolType::DeleteFile,
+                                },
+                            );
+                            println!("response: {:?}", response);
+                        }
+                        ToolInputPartial::MoveFile(move_file) => {
+                            println!(
+                                "move file: {} -> {}",
+                                move_file.fs_file_path(),
+                                move_file.new_fs_file_path()
+                            );
+                            if !path_contained_within(move_file.fs_file_path(), &workspace_roots)
+                                || !path_contained_within(move_file.new_fs_file_path(), &workspace_roots)
+                            {
+                                let response = format!(
+                                    "Cannot move {} to {} since one of the paths lies outside the workspace root {}",
+                                    move_file.fs_file_path(),
+                                    move_file.new_fs_file_path(),
+                                    &root_directory_for_validation
+                                );
+                                human_message_ticker = human_message_ticker + 1;
+                                session = session.human_message(
+                                    human_message_ticker.to_string(),
+                                    response.clone(),
+                                    UserContext::default(),
+                                    vec![],
+                                    repo_ref.clone(),
+                                    MessageRole::ToolResult {
+                                        tool: ToolType::MoveFile,
+                                    },
+                                );
+                                println!("response: {:?}", response);
+                                continue;
+                            }
+                            let request = MoveFileInput::new(
+                                move_file.fs_file_path().to_owned(),
+                                move_file.new_fs_file_path().to_owned(),
+                                message_properties.editor_url(),
+                            );
+                            let input = ToolInput::MoveFile(request);
+                            let tool_output = tool_broker.invoke(input).await;
+                            let moved = tool_output
+                                .expect("to work")
+                                .get_file_move_response()
+                                .expect("to work")
+                                .is_moved();
+                            let response = if moved {
+                                format!(
+                                    "Moved {} to {}",
+                                    move_file.fs_file_path(),
+                                    move_file.new_fs_file_path()
+                                )
+                            } else {
+                                format!(
+                                    "Failed to move {} to {}",
+                                    move_file.fs_file_path(),
+                                    move_file.new_fs_file_path()
+                                )
+                            };
+                            human_message_ticker = human_message_ticker + 1;
+                            session = session.human_message(
+                                human_message_ticker.to_string(),
+                                response.clone(),
+                                UserContext::default(),
+                                vec![],
+                                repo_ref.clone(),
+                                MessageRole::ToolResult {
+                                    tool: ToolType::MoveFile,
+                                },
+                            );
+                            println!("response: {:?}", response);
+
+                            // moving a file can leave other files pointing at
+                            // its old path via imports, so rewrite any
+                            // import-style references we can find under the
+                            // workspace root to point at the new path
+                            if moved {
+                                match rewrite_import_references(
+                                    &root_directory_for_validation,
+                                    move_file.fs_file_path(),
+                                    move_file.new_fs_file_path(),
+                                )
+                                .await
+                                {
+                                    Ok(updated_files) if !updated_files.is_empty() => {
+                                        let import_rewrite_message = format!(
+                                            "Updated import references to {} in the following files:\n{}",
+                                            move_file.fs_file_path(),
+                                            updated_files.join("\n")
+                                        );
+                                        human_message_ticker = human_message_ticker + 1;
+                                        session = session.human_message(
+                                            human_message_ticker.to_string(),
+                                            import_rewrite_message.clone(),
+                                            UserContext::default(),
+                                            vec![],
+                                            repo_ref.clone(),
+                                            MessageRole::ToolResult {
+                                                tool: ToolType::MoveFile,
+                                            },
+                                        );
+                                        println!("response: {:?}", import_rewrite_message);
+                                    }
+                                    Ok(_) => {}
+                                    Err(e) => {
+                                        println!(
+                                            "failed to rewrite import references for {}: {:?}",
+                                            move_file.fs_file_path(),
+                                            e
+                                        );
+                                    }
+                                }
+                            }
+                        }
+                        ToolInputPartial::DuplicateSymbol(duplicate_symbol) => {
+                            println!(
+                                "duplicate symbol: {} in {} as {}",
+                                duplicate_symbol.symbol_name(),
+                                duplicate_symbol.fs_file_path(),
+                                duplicate_symbol.new_name()
+                            );
+                            if !path_contained_within(duplicate_symbol.fs_file_path(), &workspace_roots) {
+                                let response = format!(
+                                    "Cannot duplicate a symbol in {} since it lies outside the workspace root {}",
+                                    duplicate_symbol.fs_file_path(),
+                                    &root_directory_for_validation
+                                );
+                                human_message_ticker = human_message_ticker + 1;
+                                session = session.human_message(
+                                    human_message_ticker.to_string(),
+                                    response.clone(),
+                                    UserContext::default(),
+                                    vec![],
+                                    repo_ref.clone(),
+                                    MessageRole::ToolResult {
+                                        tool: ToolType::DuplicateSymbol,
+                                    },
+                                );
+                                println!("response: {:?}", response);
+                                continue;
+                            }
+                            let request = DuplicateSymbolInput::new(
+                                duplicate_symbol.fs_file_path().to_owned(),
+                                duplicate_symbol.symbol_name().to_owned(),
+                                duplicate_symbol.new_name().to_owned(),
+                                message_properties.editor_url(),
+                            );
+                            let input = ToolInput::DuplicateSymbol(request);
+                            let tool_output = tool_broker.invoke(input).await;
+                            let new_symbol_range = tool_output
+                                .expect("to work")
+                                .get_duplicate_symbol_response()
+                                .expect("to work")
+                                .new_symbol_range()
+                                .to_owned();
+                            let response = format!(
+                                "Duplicated {} to {} in {}, new symbol at lines {}-{}",
+                                duplicate_symbol.symbol_name(),
+                                duplicate_symbol.new_name(),
+                                duplicate_symbol.fs_file_path(),
+                                new_symbol_range.start_position().line(),
+                                new_symbol_range.end_position().line(),
+                            );
+                            human_message_ticker = human_message_ticker + 1;
+                            session = session.human_message(
+                                human_message_ticker.to_string(),
+                                response.clone(),
+                                UserContext::default(),
+                                vec![],
+                                repo_ref.clone(),
+                                MessageRole::ToolResult {
+                                    tool: ToolType::DuplicateSymbol,
+                                },
+                            );
+                            println!("response: {:?}", response);
+                        }
+                        ToolInputPartial::SummarizeContext(_) => {
+                            println!("summarize session context");
+                            const KEEP_RECENT_EXCHANGES: usize = 4;
+                            let previous_messages =
+                                session.conversation_messages(tool_broker.clone()).await;
+                            let request = SummarizeContextRequest::new(
+                                previous_messages,
+                                session_id.to_owned(),
+                                tool_exchange_id_for_diagnostics.clone(),
+                                message_properties.cancellation_token(),
+                                message_properties.llm_properties().clone(),
+                            );
+                            let input = ToolInput::SummarizeContext(request);
+                            let tool_output = tool_broker.invoke(input).await;
+                            let summary = tool_output
+                                .expect("to work")
+                                .get_summarize_context_response()
+                                .expect("to work")
+                                .summary()
+                                .to_owned();
+                            let exchanges_before_compaction = session.exchanges();
+                            let summarized_exchanges =
+                                exchanges_before_compaction.saturating_sub(KEEP_RECENT_EXCHANGES);
+                            session = session.compact_with_summary(
+                                summary,
+                                KEEP_RECENT_EXCHANGES,
+                                tool_exchange_id_for_diagnostics.clone(),
+                            );
+                            let _ = message_properties.ui_sender().send(
+                                UIEventWithID::context_compacted(
+                                    session_id.to_owned(),
+                                    tool_exchange_id_for_diagnostics.clone(),
+                                    summarized_exchanges,
+                                    session.exchanges(),
+                                ),
+                            );
+                            let retained_exchanges = session.exchanges();
+                            human_message_ticker = human_message_ticker + 1;
+                            session = session.human_message(
+                                human_message_ticker.to_string(),
+                                format!(
+                                    "Compacted the conversation: {} earlier exchange(s) summarized, {} exchange(s) retained.",
+                                    summarized_exchanges, retained_exchanges,
+                                ),
+                                UserContext::default(),
+                                vec![],
+                                repo_ref.clone(),
+                                MessageRole::ToolResult {
+                                    tool: ToolType::SummarizeContext,
+                                },
+                            );
+                        }
+                        ToolInputPartial::ShowDiff(show_diff) => {
+                            let full_diff = session
+                                .find_full_diff_for_file(show_diff.fs_file_path())
+                                .map(|diff| diff.to_owned());
+                            let input = ToolInput::ShowDiff(ShowDiffInput::new(
+                                show_diff.fs_file_path().to_owned(),
+                                full_diff,
+                            ));
+                            let tool_output = tool_broker.invoke(input).await;
+                            let response = tool_output
+                                .expect("to work")
+                                .get_show_diff_response()
+                                .expect("to work")
+                                .formatted_diff()
+                                .to_owned();
+                            human_message_ticker = human_message_ticker + 1;
+                            session = session.human_message(
+                                human_message_ticker.to_string(),
+                                response,
+                                UserContext::default(),
+                                vec![],
+                                repo_ref.clone(),
+                                MessageRole::ToolResult {
+                                    tool: ToolType::ShowDiff,
+                                },
+                            );
+                        }
+                        ToolInputPartial::CodeReview(code_review_request) => {
+                            let diagnostics_output = tool_box
+                                .grab_workspace_diagnostics(message_properties.clone())
+                                .await
+                                .expect("diagnostics gathering to never fail");
+                            let diagnostics_count = diagnostics_output.0.len();
+                            let test_output = match code_review_request.test_command() {
+                                Some(test_command) => {
+                                    let request = TerminalInput::new(
+                                        test_command.to_owned(),
+                                        message_properties.editor_url(),
+                                    );
+                                    let output = tool_broker
+                                        .invoke(ToolInput::TerminalCommand(request))
+                                        .await
+                                        .expect("to work")
+                                        .terminal_command()
+                                        .expect("to work")
+                                        .output()
+                                        .to_owned();
+                                    Some(output)
+                                }
+                                None => None,
+                            };
+                            let diff = tool_box
+                                .get_git_diff(repo_ref.name())
+                                .await
+                                .expect("git diff to never fail")
+                                .new_version()
+                                .to_owned();
+                            let input = ToolInput::CodeReview(CodeReviewInput::new(
+                                diagnostics_count,
+                                test_output,
+                                diff,
+                            ));
+                            let response = tool_broker
+                                .invoke(input)
+                                .await
+                                .expect("to work")
+                                .get_code_review_response()
+                                .expect("to work")
+                                .formatted_report()
+                                .to_owned();
+                            human_message_ticker = human_message_ticker + 1;
+                            session = session.human_message(
+                                human_message_ticker.to_string(),
+                                response,
+                                UserContext::default(),
+                                vec![],
+                                repo_ref.clone(),
+                                MessageRole::ToolResult {
+                                    tool: ToolType::CodeReview,
+                                },
+                            );
+                        }
+                        ToolInputPartial::RunTests(run_tests_request) => {
+                            let framework = detect_test_framework(
+                                std::path::Path::new(&root_directory_for_validation),
+                                run_tests_request.framework_hint(),
+                            );
+                            let response =
+                                match build_test_command(framework, run_tests_request.test_filter())
+                                {
+                                    Some(command) => {
+                                        let request = TerminalInput::new(
+                                            command.clone(),
+                                            message_properties.editor_url(),
+                                        );
+                                        let raw_output = tool_broker
+                                            .invoke(ToolInput::TerminalCommand(request))
+                                            .await
+                                            .expect("to work")
+                                            .terminal_command()
+                                            .expect("to work")
+                                            .output()
+                                            .to_owned();
+                                        let input = ToolInput::RunTests(RunTestsInput::new(
+                                            framework, command, raw_output,
+                                        ));
+                                        tool_broker
+                                            .invoke(input)
+                                            .await
+                                            .expect("to work")
+                                            .get_run_tests_response()
+                                            .expect("to work")
+                                            .formatted_report()
+                                            .to_owned()
+                                    }
+                                    None => "Could not detect a supported test framework (looked for Cargo.toml, package.json, pyproject.toml) at the workspace root, so no tests were run.".to_owned(),
+                                };
+                            human_message_ticker = human_message_ticker + 1;
+                            session = session.human_message(
+                                human_message_ticker.to_string(),
+                                response,
+                                UserContext::default(),
+                                vec![],
+                                repo_ref.clone(),
+                                MessageRole::ToolResult {
+                                    tool: ToolType::RunTests,
+                                },
+                            );
+                        }
+                        ToolInputPartial::FindSymbolDefinition(find_symbol_definition) => {
+                            let symbol_name = find_symbol_definition.symbol_name().to_owned();
+                            let candidates = tool_box
+                                .grep_symbols_in_ide(&symbol_name, message_properties.clone())
+                                .await
+                                .map(|response| {
+                                    response
+                                        .locations()
+                                        .iter()
+                                        .map(|location| {
+                                            SymbolCandidate::new(
+                                                location.fs_file_path().to_owned(),
+                                                location.range().to_owned(),
+                                            )
+                                        })
+                                        .collect::<Vec<_>>()
+                                })
+                                .unwrap_or_default();
+                            let resolution = match resolve_candidate(
+                                find_symbol_definition.from_file(),
+                                &candidates,
+                            ) {
+                                CandidateResolution::NotFound => FindSymbolDefinitionResolution::NotFound,
+                                CandidateResolution::Ambiguous(candidates) => {
+                                    FindSymbolDefinitionResolution::Ambiguous(candidates)
+                                }
+                                CandidateResolution::Unique(candidate) => {
+                                    let definition = tool_box
+                                        .go_to_definition(
+                                            candidate.fs_file_path(),
+                                            candidate.range().start_position(),
+                                            message_properties.clone(),
+                                        )
+                                        .await
+                                        .ok()
+                                        .and_then(|response| {
+                                            response.definitions().into_iter().next()
+                                        });
+                                    match definition {
+                                        Some(definition) => {
+                                            let snippet = tool_broker
+                                                .invoke(ToolInput::OpenFile(OpenFileRequest::with_range(
+                                                    definition.file_path().to_owned(),
+                                                    message_properties.editor_url(),
+                                                    (
+                                                        definition.range().start_line(),
+                                                        definition.range().end_line(),
+                                                    ),
+                                                )))
+                                                .await
+                                                .ok()
+                                                .and_then(|output| output.get_file_open_response())
+                                                .map(|response| response.contents())
+                                                .unwrap_or_default();
+                                            FindSymbolDefinitionResolution::Resolved {
+                                                fs_file_path: definition.file_path().to_owned(),
+                                                range: definition.range().to_owned(),
+                                                snippet,
+                                            }
+                                        }
+                                        None => FindSymbolDefinitionResolution::NotFound,
+                                    }
+                                }
+                            };
+                            let input = ToolInput::FindSymbolDefinition(FindSymbolDefinitionInput::new(
+                                symbol_name,
+                                resolution,
+                            ));
+                            let response = tool_broker
+                                .invoke(input)
+                                .await
+                                .expect("to work")
+                                .get_find_symbol_definition_response()
+                                .expect("to work")
+                                .formatted_report()
+                                .to_owned();
+                            human_message_ticker = human_message_ticker + 1;
+                            session = session.human_message(
+                                human_message_ticker.to_string(),
+                                response,
+                                UserContext::default(),
+                                vec![],
+                                repo_ref.clone(),
+                                MessageRole::ToolResult {
+                                    tool: ToolType::FindSymbolDefinition,
+                                },
+                            );
+                        }
+                        ToolInputPartial::GetOutlineNodes(get_outline_nodes) => {
+                            println!("get outline nodes: {}", get_outline_nodes.fs_file_path());
+                            let request = GetOutlineNodesInput::new(
+                                get_outline_nodes.fs_file_path().to_owned(),
+                                message_properties.editor_url(),
+                            );
+                            let response = tool_broker
+                                .invoke(ToolInput::GetOutlineNodes(request))
+                                .await
+                                .expect("to work")
+                                .get_outline_nodes_output()
+                                .expect("to work");
+                            let response = response
+                                .nodes()
+                                .iter()
+                                .map(|node| {
+                                    format!(
+                                        "{} ({}) lines {}-{}",
+                                        node.name(),
+                                        node.kind(),
+                                        node.start_line(),
+                                        node.end_line()
+                                    )
+                                })
+                                .collect::<Vec<_>>()
+                                .join("\n");
+                            human_message_ticker = human_message_ticker + 1;
+                            session = session.human_message(
+                                human_message_ticker.to_string(),
+                                response.clone(),
+                                UserContext::default(),
+                                vec![],
+                                repo_ref.clone(),
+                                MessageRole::ToolResult {
+                                    tool: ToolType::GetOutlineNodes,
+                                },
+                            );
+                            println!("response: {:?}", response);
+                        }
+                        ToolInputPartial::GetWorkspaceSymbols(get_workspace_symbols) => {
+                            println!(
+                                "get workspace symbols: {}",
+                                get_workspace_symbols.query()
+                            );
+                            let request = GetWorkspaceSymbolsInput::new(
+                                get_workspace_symbols.query().to_owned(),
+                                get_workspace_symbols.max_results(),
+                                message_properties.editor_url(),
+                            );
+                            let response = tool_broker
+                                .invoke(ToolInput::GetWorkspaceSymbols(request))
+                                .await
+                                .expect("to work")
+                                .get_workspace_symbols_response()
+                                .expect("to work");
+                            let response = response
+                                .symbols()
+                                .iter()
+                                .map(|symbol| {
+                                    format!(
+                                        "{} ({}) {}:{}-{}",
+                                        symbol.name(),
+                                        symbol.kind(),
+                                        symbol.fs_file_path(),
+                                        symbol.range().start_line(),
+                                        symbol.range().end_line()
+                                    )
+                                })
+                                .collect::<Vec<_>>()
+                                .join("\n");
+                            human_message_ticker = human_message_ticker + 1;
+                            session = session.human_message(
+                                human_message_ticker.to_string(),
+                                response.clone(),
+                                UserContext::default(),
+                                vec![],
+                                repo_ref.clone(),
+                                MessageRole::ToolResult {
+                                    tool: ToolType::GetWorkspaceSymbols,
+                                },
+                            );
+                            println!("response: {:?}", response);
+                        }
                     };
                 }
-                AgentToolUseOutput::Cancelled => {}
+                AgentToolUseOutput::Cancelled => {
+                    session = session
+                        .set_tool_use_exchange_as_cancelled(&tool_exchange_id_for_diagnostics);
+                    let _ = self.save_to_storage(&session).await;
+                    let _ = message_properties.ui_sender().send(
+                        UIEventWithID::request_cancelled(
+                            session_id.to_owned(),
+                            tool_exchange_id_for_diagnostics.clone(),
+                        ),
+                    );
+                    self.untrack_exchange(&session_id, &tool_exchange_id_for_diagnostics)
+                        .await;
+                    // a cancelled inference means there is no tool call to act
+                    // on, so bail out of the loop instead of immediately
+                    // firing another (expensive) LLM call
+                    break;
+                }
                 AgentToolUseOutput::Failed(failed_to_parse_output) => {
                     let human_message = format!(
                         r#"Your output was incorrect, please give me the output in the correct format:
@@ -734,19 +2232,112 @@ impl SessionService {
                         failed_to_parse_output
                     );
                     human_message_ticker = human_message_ticker + 1;
+                    // this isn't the output of a specific tool the agent
+                    // asked for, it's us pointing out that its last tool
+                    // call itself was malformed, so there's no ToolType to
+                    // tag it with
                     session = session.human_message(
                         human_message_ticker.to_string(),
                         human_message,
                         UserContext::default(),
                         vec![],
                         repo_ref.clone(),
+                        MessageRole::User,
                     );
                 }
             }
         }
+
+        if report_unresolved_diagnostics {
+            let unresolved_diagnostics = session.get_unresolved_diagnostics();
+            if !unresolved_diagnostics.is_empty() {
+                let diagnostics_summary = unresolved_diagnostics
+                    .into_iter()
+                    .map(|diagnostic| {
+                        format!("- {}: {}", diagnostic.fs_file_path(), diagnostic.message())
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                human_message_ticker = human_message_ticker + 1;
+                session = session.human_message(
+                    human_message_ticker.to_string(),
+                    format!(
+                        r#"The following diagnostics were reported during this session but no edit touched their location, so they are likely still unresolved:
+{}"#,
+                        diagnostics_summary
+                    ),
+                    UserContext::default(),
+                    vec![],
+                    repo_ref.clone(),
+                    MessageRole::ToolResult {
+                        tool: ToolType::LSPDiagnostics,
+                    },
+                );
+                let _ = self.save_to_storage(&session).await;
+            }
+        }
+
         Ok(())
     }
 
+    /// Same as [`Self::tool_use_agentic`], except the loop pauses after each
+    /// complete LLM response for the user to review before the chosen tool
+    /// actually runs. Call `continue_agentic(session_id, exchange_id)` with
+    /// the exchange id of the tool call the editor is currently showing the
+    /// user to let the loop proceed; if nothing arrives within `timeout` the
+    /// loop ends as though the agent had called `attempt_completion`.
+    pub async fn tool_use_agentic_with_interrupt(
+        &self,
+        session_id: String,
+        storage_path: String,
+        user_message: String,
+        user_context: UserContext,
+        exchange_id: String,
+        all_files: Vec<String>,
+        open_files: Vec<String>,
+        shell: String,
+        project_labels: Vec<String>,
+        repo_ref: RepoRef,
+        root_directory: String,
+        additional_roots: Vec<WorkspaceRoot>,
+        tool_box: Arc<ToolBox>,
+        tool_broker: Arc<ToolBroker>,
+        llm_broker: Arc<LLMBroker>,
+        message_properties: SymbolEventMessageProperties,
+        report_unresolved_diagnostics: bool,
+        verify_completion_command: bool,
+        verify_diagnostics_before_completion: bool,
+        timeout: std::time::Duration,
+        open_exchanges_policy: OpenExchangesPolicy,
+        agent_mode: AideAgentMode,
+    ) -> Result<(), SymbolError> {
+        self.tool_use_agentic(
+            session_id,
+            storage_path,
+            user_message,
+            user_context,
+            exchange_id,
+            all_files,
+            open_files,
+            shell,
+            project_labels,
+            repo_ref,
+            root_directory,
+            additional_roots,
+            tool_box,
+            tool_broker,
+            llm_broker,
+            message_properties,
+            report_unresolved_diagnostics,
+            verify_completion_command,
+            verify_diagnostics_before_completion,
+            Some(timeout),
+            open_exchanges_policy,
+            agent_mode,
+        )
+        .await
+    }
+
     pub async fn code_edit_agentic(
         &self,
         session_id: String,
@@ -760,6 +2351,7 @@ impl SessionService {
         root_directory: String,
         codebase_search: bool,
         mut message_properties: SymbolEventMessageProperties,
+        open_exchanges_policy: OpenExchangesPolicy,
     ) -> Result<(), SymbolError> {
         println!("session_service::code_edit::agentic::start");
         let mut session = if let Ok(session) = self.load_from_storage(storage_path.to_owned()).await
@@ -782,7 +2374,8 @@ impl SessionService {
         // add an exchange that we are going to perform anchored edits
         session = session.agentic_edit(exchange_id, edit_request, user_context, codebase_search);
 
-        session = session.accept_open_exchanges_if_any(message_properties.clone());
+        session = session
+            .accept_open_exchanges_if_any(open_exchanges_policy, message_properties.clone())?;
         let edit_exchange_id = self
             .tool_box
             .create_new_exchange(session_id.to_owned(), message_properties.clone())
@@ -817,6 +2410,7 @@ impl SessionService {
         project_labels: Vec<String>,
         repo_ref: RepoRef,
         mut message_properties: SymbolEventMessageProperties,
+        open_exchanges_policy: OpenExchangesPolicy,
     ) -> Result<(), SymbolError> {
         println!("session_service::code_edit::anchored::start");
         let mut session = if let Ok(session) = self.load_from_storage(storage_path.to_owned()).await
@@ -836,10 +2430,10 @@ impl SessionService {
             )
         };
 
-        let selection_variable = user_context.variables.iter().find(|variable| {
-            variable.is_selection()
-                && !(variable.start_position.line() == 0 && variable.end_position.line() == 0)
-        });
+        let selection_variable = user_context
+            .variables
+            .iter()
+            .find(|variable| is_valid_anchored_selection(variable));
         if selection_variable.is_none() {
             return Ok(());
         }
@@ -861,7 +2455,8 @@ impl SessionService {
             .content_in_range(&selection_range)
             .unwrap_or(selection_variable.content.to_owned());
 
-        session = session.accept_open_exchanges_if_any(message_properties.clone());
+        session = session
+            .accept_open_exchanges_if_any(open_exchanges_policy, message_properties.clone())?;
         let edit_exchange_id = self
             .tool_box
             .create_new_exchange(session_id.to_owned(), message_properties.clone())
@@ -900,6 +2495,50 @@ impl SessionService {
         Ok(())
     }
 
+    /// Deep-copies the session at `storage_path`, truncated (inclusively) at
+    /// `at_exchange_id`, into a brand new session at `new_storage_path` so a
+    /// user can try an alternative path without disturbing the original.
+    ///
+    /// Any plan storage belonging to the copied exchanges is duplicated too, so
+    /// the fork remains independently undo-able and does not silently share
+    /// state with its parent.
+    pub async fn fork_session(
+        &self,
+        storage_path: String,
+        at_exchange_id: String,
+        new_session_id: String,
+        new_storage_path: String,
+        plan_service: PlanService,
+    ) -> Result<SessionForkMetadata, SymbolError> {
+        let session = self.load_from_storage(storage_path).await?;
+        let parent_session_id = session.session_id().to_owned();
+        let (forked_session, exchange_id_mapping) =
+            session.fork(new_session_id.clone(), new_storage_path.clone(), &at_exchange_id);
+
+        for (old_exchange_id, new_exchange_id) in exchange_id_mapping.iter() {
+            let old_plan_id = plan_service.generate_unique_plan_id(&parent_session_id, old_exchange_id);
+            let old_plan_path = plan_service.plan_storage_directory().join(&old_plan_id);
+            if let Ok(plan) = plan_service.load_plan(&old_plan_path.to_string_lossy()).await {
+                let new_plan_id = plan_service.generate_unique_plan_id(&new_session_id, new_exchange_id);
+                let new_plan_path = plan_service.plan_storage_directory().join(&new_plan_id);
+                let forked_plan =
+                    plan.with_id_and_storage_path(new_plan_id, new_plan_path.to_string_lossy().into_owned());
+                let _ = plan_service
+                    .save_plan(&forked_plan, &new_plan_path.to_string_lossy())
+                    .await;
+            }
+        }
+
+        self.save_to_storage(&forked_session).await?;
+
+        Ok(SessionForkMetadata::new(
+            forked_session.session_id().to_owned(),
+            forked_session.storage_path().to_owned(),
+            parent_session_id,
+            at_exchange_id,
+        ))
+    }
+
     pub async fn handle_session_undo(
         &self,
         exchange_id: &str,
@@ -1012,25 +2651,443 @@ impl SessionService {
         Ok(send_cancellation_signal)
     }
 
+    /// Deletes session files under `dir` whose last-modified time is older
+    /// than `older_than`, always keeping the `keep_min` most recently
+    /// modified ones regardless of age. Files which don't parse as a
+    /// `Session` are left alone - we should never delete something we can't
+    /// confirm is one of ours. Returns the paths of every file that was
+    /// pruned.
+    pub async fn prune_old_sessions(
+        dir: &std::path::Path,
+        older_than: std::time::Duration,
+        keep_min: usize,
+    ) -> Result<Vec<String>, SymbolError> {
+        let now = std::time::SystemTime::now();
+
+        let mut sessions_by_age = vec![];
+        let mut entries = tokio::fs::read_dir(dir)
+            .await
+            .map_err(|e| SymbolError::IOError(e))?;
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(|e| SymbolError::IOError(e))?
+        {
+            let path = entry.path();
+            if !path.is_file() {
+                continue;
+            }
+            let Ok(content) = tokio::fs::read_to_string(&path).await else {
+                continue;
+            };
+            if serde_json::from_str::<Session>(&content).is_err() {
+                continue;
+            }
+            let Ok(metadata) = entry.metadata().await else {
+                continue;
+            };
+            let Ok(modified) = metadata.modified() else {
+                continue;
+            };
+            sessions_by_age.push((path, modified));
+        }
+
+        // newest first, so the first `keep_min` entries are always kept
+        sessions_by_age.sort_by(|(_, left_modified), (_, right_modified)| {
+            right_modified.cmp(left_modified)
+        });
+
+        let mut pruned = vec![];
+        for (path, modified) in sessions_by_age.into_iter().skip(keep_min) {
+            let age = now
+                .duration_since(modified)
+                .unwrap_or(std::time::Duration::ZERO);
+            if age <= older_than {
+                continue;
+            }
+            tokio::fs::remove_file(&path)
+                .await
+                .map_err(|e| SymbolError::IOError(e))?;
+            pruned.push(path.to_string_lossy().into_owned());
+        }
+
+        Ok(pruned)
+    }
+
     async fn load_from_storage(&self, storage_path: String) -> Result<Session, SymbolError> {
         let content = tokio::fs::read_to_string(storage_path.to_owned())
             .await
             .map_err(|e| SymbolError::IOError(e))?;
 
-        let session: Session = serde_json::from_str(&content).expect(&format!(
+        let value: serde_json::Value = serde_json::from_str(&content)
+            .expect(&format!("converting to json is okay: {storage_path}"));
+        let migrated = migrate_session_value(value, &storage_path);
+        let session: Session = serde_json::from_value(migrated).expect(&format!(
             "converting to session from json is okay: {storage_path}"
         ));
+        if let Err(duplicate_ids) = session.validate_exchange_ids() {
+            println!(
+                "session_service::load_from_storage::invalid_exchange_ids::storage_path({})::({:?})",
+                &storage_path, duplicate_ids
+            );
+            return session.recalculate_exchange_ids();
+        }
         Ok(session)
     }
 
+    /// Looks up a session on disk and searches its exchanges for `query`,
+    /// e.g. to answer "where in this session did the agent change the retry
+    /// logic" without having to eyeball the raw JSON.
+    pub async fn search_session(
+        &self,
+        storage_path: String,
+        query: String,
+    ) -> Result<Vec<ExchangeSearchMatch>, SymbolError> {
+        let session = self.load_from_storage(storage_path).await?;
+        Ok(session.search_exchanges(&query))
+    }
+
+    /// Writes `session` to its storage path, first merging in whatever is
+    /// already on disk. Two tasks working off the same session (e.g.
+    /// `plan_generation` and the hot-streak path) each do their own
+    /// load -> mutate -> save; without this, whichever one saves last simply
+    /// overwrites the other's exchange out of existence. Re-reading the
+    /// on-disk copy here and merging it in (rather than tracking whether it
+    /// "advanced" since our own load) means a concurrent save is always
+    /// folded in, whether or not we can prove one happened.
     async fn save_to_storage(&self, session: &Session) -> Result<(), SymbolError> {
-        let serialized = serde_json::to_string(session).unwrap();
-        let mut file = tokio::fs::File::create(session.storage_path())
-            .await
-            .map_err(|e| SymbolError::IOError(e))?;
-        file.write_all(serialized.as_bytes())
+        let save_lock = self.save_lock_for_path(session.storage_path()).await;
+        let _guard = save_lock.lock().await;
+
+        let session = match self.load_from_storage(session.storage_path().to_owned()).await {
+            Ok(on_disk) => session.clone().merge(on_disk),
+            Err(_) => session.clone(),
+        };
+        let session = &session;
+        let mut value = serde_json::to_value(session).unwrap();
+        if let Some(object) = value.as_object_mut() {
+            object.insert(
+                "schema_version".to_owned(),
+                serde_json::Value::from(CURRENT_SESSION_SCHEMA_VERSION),
+            );
+        }
+        let serialized = if self.pretty_print_sessions {
+            serde_json::to_string_pretty(&value).unwrap()
+        } else {
+            serde_json::to_string(&value).unwrap()
+        };
+        let file = tokio::fs::File::create(session.storage_path())
             .await
             .map_err(|e| SymbolError::IOError(e))?;
+        let mut writer = BufWriter::new(file);
+        // stream the write out in fixed-size chunks rather than handing the
+        // whole (potentially very large) serialized session to a single
+        // write_all call
+        for chunk in serialized.as_bytes().chunks(SESSION_WRITE_CHUNK_BYTES) {
+            writer
+                .write_all(chunk)
+                .await
+                .map_err(|e| SymbolError::IOError(e))?;
+        }
+        writer.flush().await.map_err(|e| SymbolError::IOError(e))?;
         Ok(())
     }
 }
+
+/// Checks that `path` lives inside one of `workspace_roots`, so
+/// file-deleting/moving tool calls can't be pointed at paths outside the
+/// workspace we were given (including the additional roots of a multi-root
+/// workspace, not just the primary one).
+fn path_contained_within(path: &str, workspace_roots: &WorkspaceRoots) -> bool {
+    workspace_roots.contains_path(path)
+}
+
+/// Whether `candidate_name` is close enough to `file_name` (both already
+/// lowercased) to be worth suggesting as a correction, e.g. `session_v2`
+/// against a hallucinated `session`.
+fn is_close_matching_file_name(candidate_name: &str, file_name: &str) -> bool {
+    let candidate_name = candidate_name.to_lowercase();
+    candidate_name.contains(file_name) || file_name.contains(&candidate_name)
+}
+
+/// Whether `variable` is a real, usable selection for `code_edit_anchored`
+/// to anchor an edit on. Filters out: variables which aren't a selection at
+/// all, the editor's "nothing is selected" sentinel (start and end both on
+/// line 0), a missing file path, and a reversed range (end before start) -
+/// which the editor should never send us, but which would otherwise be fed
+/// straight into `Range::new` and confuse everything downstream.
+fn is_valid_anchored_selection(variable: &VariableInformation) -> bool {
+    variable.is_selection()
+        && !(variable.start_position.line() == 0 && variable.end_position.line() == 0)
+        && !variable.fs_file_path.is_empty()
+        && (variable.start_position.line(), variable.start_position.column())
+            <= (variable.end_position.line(), variable.end_position.column())
+}
+
+/// Opens `fs_file_path`, retrying once on a transport error (the editor was
+/// briefly unreachable) before giving up. A file which simply does not exist
+/// is not a transport error - the editor still answers, just with
+/// `exists: false` - so it comes back as `Ok` here and is handled by the
+/// caller instead of being retried.
+async fn open_file_retrying_transport_errors(
+    tool_box: &ToolBox,
+    fs_file_path: &str,
+    message_properties: SymbolEventMessageProperties,
+) -> Result<OpenFileResponse, SymbolError> {
+    match tool_box
+        .file_open(fs_file_path.to_owned(), message_properties.clone())
+        .await
+    {
+        Ok(response) => Ok(response),
+        Err(_first_error) => {
+            tool_box
+                .file_open(fs_file_path.to_owned(), message_properties)
+                .await
+        }
+    }
+}
+
+/// Hashes file content so we can cheaply tell whether a file changed
+/// between two reads without keeping the whole previous content around.
+fn file_content_hash(content: &str) -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Quick heuristic for "did the agent typo an existing path": lists the
+/// files in the hallucinated path's parent directory and keeps the ones
+/// whose name shares a substring with the file name the agent asked for, so
+/// we can point it at what it probably meant instead of just saying no.
+async fn find_close_matching_paths(tool_broker: &ToolBroker, fs_file_path: &str) -> Vec<String> {
+    let path = std::path::Path::new(fs_file_path);
+    let parent_directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_string_lossy().into_owned(),
+        _ => return vec![],
+    };
+    let file_name = match path.file_stem() {
+        Some(file_name) => file_name.to_string_lossy().to_lowercase(),
+        None => return vec![],
+    };
+    let request = ListFilesInput::new(parent_directory, false);
+    let sibling_files = match tool_broker
+        .invoke(ToolInput::ListFiles(request))
+        .await
+        .ok()
+        .and_then(|output| output.get_list_files_directory())
+    {
+        Some(output) => output.files().to_vec(),
+        None => return vec![],
+    };
+    sibling_files
+        .into_iter()
+        .filter(|candidate| {
+            candidate
+                .file_stem()
+                .map(|candidate_name| {
+                    is_close_matching_file_name(&candidate_name.to_string_lossy(), &file_name)
+                })
+                .unwrap_or(false)
+        })
+        .map(|candidate| candidate.to_string_lossy().into_owned())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        categorize_tool_error, file_content_hash, is_close_matching_file_name,
+        is_valid_anchored_selection, migrate_session_value, should_retry_completion_for_diagnostics,
+        SessionService, ToolErrorAction, MAX_COMPLETION_DIAGNOSTIC_RETRIES,
+    };
+    use crate::{
+        agentic::{
+            symbol::ui_event::UIEventWithID,
+            tool::{errors::ToolError, r#type::ToolType, session::session::Session},
+        },
+        chunking::text_document::{Position, Range},
+        repo::types::RepoRef,
+        user_context::types::{UserContext, VariableInformation},
+    };
+    use std::time::Duration;
+
+    fn selection_variable(start: Position, end: Position, fs_file_path: &str) -> VariableInformation {
+        VariableInformation::create_selection(
+            Range::new(start, end),
+            fs_file_path.to_owned(),
+            "selection".to_owned(),
+            "content".to_owned(),
+            "rust".to_owned(),
+        )
+    }
+
+    #[test]
+    fn test_migrate_v1_session_fixture_to_current_version() {
+        let modern_session = Session::new(
+            "sess-1".to_owned(),
+            vec![],
+            RepoRef::local(&std::env::temp_dir().to_string_lossy()).expect("local repo ref"),
+            "/tmp/sess-1".to_owned(),
+            UserContext::default(),
+            vec![ToolType::CodeEditing],
+        );
+        // a v1 fixture predates `schema_version` and every field added since
+        let mut v1_fixture = serde_json::to_value(&modern_session).unwrap();
+        let object = v1_fixture.as_object_mut().expect("session serializes to an object");
+        object.remove("schema_version");
+        object.remove("unresolved_diagnostics");
+        object.remove("parent_session_id");
+        object.remove("forked_from_exchange_id");
+
+        let migrated = migrate_session_value(v1_fixture, "sess-1-fixture");
+        let session: Session =
+            serde_json::from_value(migrated).expect("v1 fixture should migrate cleanly");
+
+        assert_eq!(session.session_id(), "sess-1");
+    }
+
+    #[test]
+    fn test_is_close_matching_file_name() {
+        assert!(is_close_matching_file_name("session", "session_v2"));
+        assert!(is_close_matching_file_name("session_v2", "session"));
+        assert!(!is_close_matching_file_name("broker", "session"));
+    }
+
+    #[test]
+    fn is_valid_anchored_selection_accepts_a_normal_selection() {
+        let selection = selection_variable(
+            Position::new(4, 0, 0),
+            Position::new(6, 10, 0),
+            "foo.rs",
+        );
+        assert!(is_valid_anchored_selection(&selection));
+    }
+
+    #[test]
+    fn is_valid_anchored_selection_rejects_the_no_selection_sentinel() {
+        let selection = selection_variable(Position::new(0, 0, 0), Position::new(0, 0, 0), "foo.rs");
+        assert!(!is_valid_anchored_selection(&selection));
+    }
+
+    #[test]
+    fn is_valid_anchored_selection_rejects_a_missing_file_path() {
+        let selection = selection_variable(Position::new(1, 0, 0), Position::new(2, 0, 0), "");
+        assert!(!is_valid_anchored_selection(&selection));
+    }
+
+    #[test]
+    fn is_valid_anchored_selection_rejects_a_reversed_range() {
+        let selection = selection_variable(
+            Position::new(6, 0, 0),
+            Position::new(4, 0, 0),
+            "foo.rs",
+        );
+        assert!(!is_valid_anchored_selection(&selection));
+    }
+
+    #[test]
+    fn is_valid_anchored_selection_rejects_non_selection_variables() {
+        let file_variable = VariableInformation::create_file(
+            Range::new(Position::new(1, 0, 0), Position::new(2, 0, 0)),
+            "foo.rs".to_owned(),
+            "foo.rs".to_owned(),
+            "content".to_owned(),
+            "rust".to_owned(),
+        );
+        assert!(!is_valid_anchored_selection(&file_variable));
+    }
+
+    fn write_fixture_session(dir: &std::path::Path, session_id: &str, age: Duration) {
+        let storage_path = dir.join(session_id).to_string_lossy().into_owned();
+        let session = Session::new(
+            session_id.to_owned(),
+            vec![],
+            RepoRef::local(&std::env::temp_dir().to_string_lossy()).expect("local repo ref"),
+            storage_path.clone(),
+            UserContext::default(),
+            vec![ToolType::CodeEditing],
+        );
+        std::fs::write(&storage_path, serde_json::to_string(&session).unwrap())
+            .expect("fixture session should write to disk");
+
+        let modified = std::time::SystemTime::now() - age;
+        let file = std::fs::File::open(&storage_path).expect("fixture session should exist");
+        file.set_modified(modified)
+            .expect("fixture session mtime should be adjustable");
+    }
+
+    #[tokio::test]
+    async fn test_prune_old_sessions_keeps_recent_and_min_count() {
+        let dir = tempfile::tempdir().expect("tempdir should be creatable");
+
+        // two old sessions and two recent ones
+        write_fixture_session(dir.path(), "ancient", Duration::from_secs(10 * 24 * 60 * 60));
+        write_fixture_session(dir.path(), "old", Duration::from_secs(8 * 24 * 60 * 60));
+        write_fixture_session(dir.path(), "recent", Duration::from_secs(60));
+        write_fixture_session(dir.path(), "fresh", Duration::from_secs(1));
+
+        // a file which isn't a session at all should be left alone
+        std::fs::write(dir.path().join("not_a_session.json"), "not json").unwrap();
+
+        let pruned = SessionService::prune_old_sessions(
+            dir.path(),
+            Duration::from_secs(7 * 24 * 60 * 60),
+            3,
+        )
+        .await
+        .expect("pruning should succeed");
+
+        assert_eq!(pruned.len(), 1);
+        assert!(pruned[0].ends_with("ancient"));
+        assert!(!dir.path().join("ancient").exists());
+        // "old" is past the threshold too, but keep_min = 3 covers it since
+        // it's the third most recently modified session on disk
+        assert!(dir.path().join("old").exists());
+        assert!(dir.path().join("recent").exists());
+        assert!(dir.path().join("fresh").exists());
+        assert!(dir.path().join("not_a_session.json").exists());
+    }
+
+    #[test]
+    fn should_retry_completion_for_diagnostics_continues_until_the_limit_then_gives_up() {
+        // outstanding diagnostics on the first attempt: retry
+        assert!(should_retry_completion_for_diagnostics(true, 0));
+        // outstanding diagnostics but we've already hit the retry limit: accept
+        assert!(!should_retry_completion_for_diagnostics(
+            true,
+            MAX_COMPLETION_DIAGNOSTIC_RETRIES
+        ));
+        // nothing outstanding: accept immediately regardless of retry count
+        assert!(!should_retry_completion_for_diagnostics(false, 0));
+    }
+
+    #[test]
+    fn file_content_hash_detects_external_modification() {
+        let original = "fn main() {}\n";
+        let hash_at_read = file_content_hash(original);
+
+        // the file is unchanged, so re-hashing it just before applying an
+        // edit should agree with the hash captured at read time
+        assert_eq!(file_content_hash(original), hash_at_read);
+
+        // someone else edits the file in between
+        let changed_externally = "fn main() { println!(\"hi\"); }\n";
+        assert_ne!(file_content_hash(changed_externally), hash_at_read);
+    }
+
+    #[test]
+    fn error_communicating_with_editor_is_retried_with_an_actionable_message() {
+        let (action, message) = categorize_tool_error(&ToolError::ErrorCommunicatingWithEditor);
+        assert_eq!(action, ToolErrorAction::Retry);
+        assert_eq!(message, "editor unreachable — is the extension running?");
+
+        let ui_event = UIEventWithID::tool_error(
+            "session-id".to_owned(),
+            "exchange-id".to_owned(),
+            message,
+        );
+        let serialized = serde_json::to_string(&ui_event).expect("ui event to serialize");
+        assert!(serialized.contains("editor unreachable"));
+    }
+}
@@ -21,7 +21,7 @@ use crate::{
             tool_box::ToolBox,
             tool_properties::ToolProperties,
             types::SymbolEventRequest,
-            ui_event::UIEventWithID,
+            ui_event::{AutoResolvedExchange, UIEventWithID},
         },
         tool::{
             broker::ToolBroker,
@@ -29,6 +29,7 @@ use crate::{
             lsp::file_diagnostics::DiagnosticMap,
             plan::{
                 generator::{Step, StepSenderEvent},
+                plan_impact::{PlanImpactedFile, PlanImpactSummary},
                 service::PlanService,
             },
             r#type::{Tool, ToolType},
@@ -45,9 +46,44 @@ use super::{
     tool_use_agent::{ToolUseAgent, ToolUseAgentInput, ToolUseAgentOutput},
 };
 
+/// An invariant the agent must not violate while editing, checked by
+/// [`Session::check_constraints`] before any `CodeEditing` tool call is
+/// executed.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
+pub enum EditConstraint {
+    /// No file matching this glob (matched against the file's path) may be edited.
+    NeverEditFile(String),
+    /// No symbol whose name matches this string may be edited. Since the
+    /// generic `CodeEditing` tool only carries a free-form instruction and
+    /// not a resolved symbol name, this is checked against the instruction
+    /// text as a best-effort heuristic.
+    NeverEditSymbol(String),
+    /// Edits to this file are allowed but must not change its public API
+    /// surface. We have no way to diff the public API surface of a file
+    /// before an edit is applied, so this is conservatively enforced by
+    /// rejecting edits to the file entirely.
+    PreservePublicAPI(String),
+}
+
+impl std::fmt::Display for EditConstraint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NeverEditFile(glob) => write!(f, "never edit files matching `{glob}`"),
+            Self::NeverEditSymbol(name) => write!(f, "never edit the symbol `{name}`"),
+            Self::PreservePublicAPI(fs_file_path) => {
+                write!(f, "preserve the public API of `{fs_file_path}`")
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum AgentToolUseOutput {
-    Success((ToolInputPartial, Session)),
+    /// The new exchange to append and the freshly refreshed open-files list
+    /// to apply, rather than a whole cloned `Session` — `get_tool_to_use`
+    /// only borrows the session it read from, so this is all the caller
+    /// needs to bring it up to date.
+    Success((ToolInputPartial, Exchange, Vec<String>)),
     Failed(String),
     Cancelled,
 }
@@ -60,6 +96,14 @@ pub enum AideAgentMode {
     Chat = 3,
 }
 
+impl Default for AideAgentMode {
+    /// The historical behavior for endpoints which never asked for a mode:
+    /// full tool use, unrestricted.
+    fn default() -> Self {
+        AideAgentMode::Edit
+    }
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum AideEditMode {
     Anchored = 1,
@@ -86,6 +130,27 @@ impl Default for ExchangeState {
     }
 }
 
+/// What `Session::accept_open_exchanges_if_any` should do when a new request
+/// arrives while an earlier exchange is still open (never explicitly
+/// accepted or rejected by the user). Defaults to `AutoAccept` since that
+/// was this codebase's only prior behavior.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum OpenExchangesPolicy {
+    /// Accept the open exchanges and keep going - the historical behavior.
+    AutoAccept,
+    /// Reject the open exchanges (e.g. discard their edits) and keep going.
+    AutoReject,
+    /// Refuse the new request instead of silently resolving exchanges the
+    /// user hasn't reviewed yet.
+    Block,
+}
+
+impl Default for OpenExchangesPolicy {
+    fn default() -> Self {
+        OpenExchangesPolicy::AutoAccept
+    }
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum ExchangeType {
     HumanChat(ExchangeTypeHuman),
@@ -134,12 +199,140 @@ pub struct ExchangeTypeEdit {
     exchange_type: AideEditMode,
 }
 
+/// The full diff for a file edit whose summary shown to the agent was
+/// condensed because it was too large. Kept around on the exchange so the
+/// `show_diff` tool can hand it back later without us having to recompute it.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ExchangeFullDiff {
+    fs_file_path: String,
+    diff: String,
+}
+
+/// Where an [`EditSuggestion`] fed into [`Session::apply_edit_suggestion`]
+/// came from, so the transcript stays honest about the fact that a human
+/// didn't type the underlying instruction.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum EditSource {
+    Human,
+    External { tool_name: String },
+}
+
+/// An edit external tooling (CI bots, security scanners, ...) wants to
+/// inject into a session without going through the LLM pipeline, via
+/// [`Session::apply_edit_suggestion`].
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct EditSuggestion {
+    fs_file_path: String,
+    range: Range,
+    new_content: String,
+    reason: String,
+    source: EditSource,
+}
+
+impl EditSuggestion {
+    pub fn new(
+        fs_file_path: String,
+        range: Range,
+        new_content: String,
+        reason: String,
+        source: EditSource,
+    ) -> Self {
+        Self {
+            fs_file_path,
+            range,
+            new_content,
+            reason,
+            source,
+        }
+    }
+}
+
+/// Which part of an exchange `Session::search_exchanges` found `query` in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExchangeSearchField {
+    HumanMessage,
+    AgentReply,
+    ToolInput,
+    Diff,
+}
+
+/// A single hit from `Session::search_exchanges`.
+#[derive(Debug, Clone)]
+pub struct ExchangeSearchMatch {
+    exchange_id: String,
+    field: ExchangeSearchField,
+    snippet: String,
+}
+
+impl ExchangeSearchMatch {
+    pub fn exchange_id(&self) -> &str {
+        &self.exchange_id
+    }
+
+    pub fn field(&self) -> &ExchangeSearchField {
+        &self.field
+    }
+
+    pub fn snippet(&self) -> &str {
+        &self.snippet
+    }
+}
+
+/// Case-insensitive substring search over `haystack` for `query_lower`
+/// (already lowercased by the caller so we are not repeatedly lowercasing it
+/// once per field), returning a short window of context around the match
+/// rather than the whole (possibly huge) field. `None` when there's no hit,
+/// so a caller scanning many fields on an exchange never has to materialize
+/// anything for the fields which don't match.
+fn matching_snippet(haystack: &str, query_lower: &str, context_chars: usize) -> Option<String> {
+    if query_lower.is_empty() {
+        return None;
+    }
+    let haystack_lower = haystack.to_lowercase();
+    let match_start = haystack_lower.find(query_lower)?;
+    let match_end = match_start + query_lower.len();
+    let snippet_start = haystack_lower[..match_start]
+        .char_indices()
+        .rev()
+        .nth(context_chars)
+        .map(|(byte_index, _)| byte_index)
+        .unwrap_or(0);
+    let snippet_end = haystack_lower[match_end..]
+        .char_indices()
+        .nth(context_chars)
+        .map(|(byte_index, _)| match_end + byte_index)
+        .unwrap_or(haystack_lower.len());
+    Some(haystack[snippet_start..snippet_end].trim().to_owned())
+}
+
+/// Who (or what) a `HumanChat` exchange actually came from. Historically the
+/// tool-use loop fabricated a "human" message for every tool result it fed
+/// back to the model, which made transcripts read as if the user had said
+/// all of that. `ToolResult` lets us keep reusing the same exchange shape for
+/// that feedback while still being honest about where it came from.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum MessageRole {
+    User,
+    ToolResult { tool: ToolType },
+    Assistant,
+}
+
+impl Default for MessageRole {
+    fn default() -> Self {
+        MessageRole::User
+    }
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ExchangeTypeHuman {
     query: String,
     user_context: UserContext,
     project_labels: Vec<String>,
     repo_ref: RepoRef,
+    #[serde(default)]
+    full_diff: Option<ExchangeFullDiff>,
+    #[serde(default)]
+    role: MessageRole,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -169,6 +362,11 @@ pub struct ExchangeReplyAgentTool {
     // for now, I am leaving things here until I can come up with a proper API for that
     tool_input_partial: ToolInputPartial,
     thinking: String,
+    /// How many prompt tokens the provider served from cache for the LLM call
+    /// which produced this tool use, if the provider reports it. `None` for
+    /// providers which don't support prompt caching or didn't hit the cache.
+    #[serde(default)]
+    cache_hit_tokens: Option<u32>,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -219,12 +417,14 @@ impl ExchangeTypeAgent {
         tool_type: ToolType,
         thinking: String,
         parent_exchange_id: String,
+        cache_hit_tokens: Option<u32>,
     ) -> Self {
         Self {
             reply: ExchangeReplyAgent::Tool(ExchangeReplyAgentTool {
                 tool_type,
                 tool_input_partial,
                 thinking,
+                cache_hit_tokens,
             }),
             parent_exchange_id,
         }
@@ -237,14 +437,21 @@ impl ExchangeTypeHuman {
         user_context: UserContext,
         project_labels: Vec<String>,
         repo_ref: RepoRef,
+        role: MessageRole,
     ) -> Self {
         Self {
             query,
             user_context,
             project_labels,
             repo_ref,
+            full_diff: None,
+            role,
         }
     }
+
+    pub fn role(&self) -> &MessageRole {
+        &self.role
+    }
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -253,6 +460,28 @@ pub struct Exchange {
     exchange_type: ExchangeType,
     #[serde(default)]
     exchange_state: ExchangeState,
+    /// Why `exchange_state` ended up what it is, when that wasn't a plain
+    /// user click - e.g. "auto-accepted: a new message arrived while this
+    /// was still open". `None` for exchanges the user resolved themselves.
+    #[serde(default)]
+    resolution_reason: Option<String>,
+    /// Wall-clock time of the last write to this exchange (creation or a
+    /// state change), used to order exchanges from concurrent sessions and
+    /// to pick a winner when the same exchange id was mutated by both.
+    /// Sessions persisted before this field existed deserialize it as `0`,
+    /// which sorts them before anything merge ever has to reason about.
+    #[serde(default)]
+    updated_at_ms: u64,
+}
+
+/// Milliseconds since the Unix epoch, used to order exchanges written by
+/// concurrent tasks (e.g. `plan_generation` and the hot-streak path) working
+/// off the same session.
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or_default()
 }
 
 impl Exchange {
@@ -260,12 +489,24 @@ impl Exchange {
         &self.exchange_id
     }
 
+    /// The exchange this one is a reply to, if any. Human, plan and edit
+    /// exchanges start a new causal chain (they have no parent), while agent
+    /// replies (chat, plan, edits, tool use) always point back at the
+    /// exchange which prompted them.
+    fn parent_exchange_id(&self) -> Option<&str> {
+        match &self.exchange_type {
+            ExchangeType::AgentChat(agent_chat) => Some(&agent_chat.parent_exchange_id),
+            ExchangeType::HumanChat(_) | ExchangeType::Edit(_) | ExchangeType::Plan(_) => None,
+        }
+    }
+
     fn human_chat(
         exchange_id: String,
         query: String,
         user_context: UserContext,
         project_labels: Vec<String>,
         repo_ref: RepoRef,
+        role: MessageRole,
     ) -> Self {
         Self {
             exchange_id,
@@ -274,9 +515,87 @@ impl Exchange {
                 user_context,
                 project_labels,
                 repo_ref,
+                role,
             )),
             exchange_state: ExchangeState::UserMessage,
+resolution_reason: None,
+            updated_at_ms: now_ms(),
+        }
+    }
+
+    /// Whether this exchange is tool feedback fed back into the conversation
+    /// rather than something the user actually typed.
+    pub fn is_tool_result(&self) -> bool {
+        matches!(
+            &self.exchange_type,
+            ExchangeType::HumanChat(human_chat) if matches!(human_chat.role, MessageRole::ToolResult { .. })
+        )
+    }
+
+    /// Stashes the full diff for `fs_file_path` on this exchange, a no-op if
+    /// the exchange isn't a human chat message (the only kind we ever attach
+    /// a condensed diff summary to).
+    fn attach_full_diff(&mut self, fs_file_path: String, diff: String) {
+        if let ExchangeType::HumanChat(human_chat) = &mut self.exchange_type {
+            human_chat.full_diff = Some(ExchangeFullDiff { fs_file_path, diff });
+        }
+    }
+
+    /// Scans this exchange's own fields for `query_lower` (already
+    /// lowercased by the caller), one field at a time rather than joining
+    /// everything into a single string first, so a session full of large
+    /// diffs doesn't force us to hold a giant concatenated copy in memory
+    /// just to search it.
+    fn search(&self, query_lower: &str) -> Vec<ExchangeSearchMatch> {
+        let mut matches = vec![];
+        let mut check = |field: ExchangeSearchField, text: &str| {
+            if let Some(snippet) = matching_snippet(text, query_lower, 40) {
+                matches.push(ExchangeSearchMatch {
+                    exchange_id: self.exchange_id.to_owned(),
+                    field,
+                    snippet,
+                });
+            }
+        };
+        match &self.exchange_type {
+            ExchangeType::HumanChat(human_chat) => {
+                check(ExchangeSearchField::HumanMessage, &human_chat.query);
+                if let Some(full_diff) = &human_chat.full_diff {
+                    check(ExchangeSearchField::Diff, &full_diff.diff);
+                }
+            }
+            ExchangeType::AgentChat(agent_chat) => match &agent_chat.reply {
+                ExchangeReplyAgent::Chat(chat) => {
+                    check(ExchangeSearchField::AgentReply, &chat.reply)
+                }
+                ExchangeReplyAgent::Edit(edit) => {
+                    check(ExchangeSearchField::Diff, &edit.edits_made_diff)
+                }
+                ExchangeReplyAgent::Tool(tool) => {
+                    check(ExchangeSearchField::ToolInput, &tool.tool_input_partial.to_string());
+                    check(ExchangeSearchField::AgentReply, &tool.thinking);
+                }
+                ExchangeReplyAgent::Plan(plan) => {
+                    for step in &plan.plan_steps {
+                        check(ExchangeSearchField::AgentReply, &step.title);
+                        check(ExchangeSearchField::AgentReply, &step.changes);
+                    }
+                }
+            },
+            ExchangeType::Edit(edit) => match &edit.information {
+                ExchangeEditInformation::Agentic(agentic) => {
+                    check(ExchangeSearchField::HumanMessage, &agentic.query)
+                }
+                ExchangeEditInformation::Anchored(anchored) => {
+                    check(ExchangeSearchField::HumanMessage, &anchored.query);
+                    check(ExchangeSearchField::HumanMessage, &anchored.fs_file_path);
+                }
+            },
+            ExchangeType::Plan(plan) => {
+                check(ExchangeSearchField::HumanMessage, &plan.query);
+            }
         }
+        matches
     }
 
     fn plan_request(exchange_id: String, query: String, user_context: UserContext) -> Self {
@@ -288,6 +607,8 @@ impl Exchange {
                 user_context,
             }),
             exchange_state: ExchangeState::UserMessage,
+resolution_reason: None,
+            updated_at_ms: now_ms(),
         }
     }
 
@@ -308,6 +629,8 @@ impl Exchange {
                 exchange_type: AideEditMode::Agentic,
             }),
             exchange_state: ExchangeState::UserMessage,
+resolution_reason: None,
+            updated_at_ms: now_ms(),
         }
     }
 
@@ -332,6 +655,8 @@ impl Exchange {
                 exchange_type: AideEditMode::Anchored,
             }),
             exchange_state: ExchangeState::UserMessage,
+resolution_reason: None,
+            updated_at_ms: now_ms(),
         }
     }
 
@@ -343,6 +668,8 @@ impl Exchange {
                 parent_exchange_id,
             )),
             exchange_state: ExchangeState::Running,
+resolution_reason: None,
+            updated_at_ms: now_ms(),
         }
     }
 
@@ -354,6 +681,8 @@ impl Exchange {
                 parent_exchange_id,
             )),
             exchange_state: ExchangeState::Running,
+resolution_reason: None,
+            updated_at_ms: now_ms(),
         }
     }
 
@@ -369,6 +698,8 @@ impl Exchange {
                 parent_exchange_id,
             )),
             exchange_state: ExchangeState::Running,
+resolution_reason: None,
+            updated_at_ms: now_ms(),
         }
     }
 
@@ -378,6 +709,7 @@ impl Exchange {
         tool_input: ToolInputPartial,
         tool_type: ToolType,
         thinking: String,
+        cache_hit_tokens: Option<u32>,
     ) -> Self {
         Self {
             exchange_id,
@@ -386,8 +718,11 @@ impl Exchange {
                 tool_type,
                 thinking,
                 parent_exchange_id,
+                cache_hit_tokens,
             )),
             exchange_state: ExchangeState::Running,
+resolution_reason: None,
+            updated_at_ms: now_ms(),
         }
     }
 
@@ -397,6 +732,12 @@ impl Exchange {
         } else {
             self.exchange_state = ExchangeState::Rejected;
         }
+        self.updated_at_ms = now_ms();
+        self
+    }
+
+    fn with_resolution_reason(mut self, resolution_reason: String) -> Self {
+        self.resolution_reason = Some(resolution_reason);
         self
     }
 
@@ -406,6 +747,15 @@ impl Exchange {
             && matches!(self.exchange_type, ExchangeType::AgentChat(_))
     }
 
+    /// Check if this is an agent reply carrying an edit outcome (as opposed
+    /// to a chat, plan or tool-use reply).
+    fn is_edit_reply(&self) -> bool {
+        matches!(
+            &self.exchange_type,
+            ExchangeType::AgentChat(agent_chat) if matches!(agent_chat.reply, ExchangeReplyAgent::Edit(_))
+        )
+    }
+
     /// Check if this is agent reply
     fn is_agent_work(&self) -> bool {
         matches!(self.exchange_type, ExchangeType::AgentChat(_))
@@ -423,6 +773,7 @@ impl Exchange {
 
     fn set_exchange_as_cancelled(&mut self) {
         self.exchange_state = ExchangeState::Cancelled;
+        self.updated_at_ms = now_ms();
     }
 
     /// Convert the exchange to a session chat message so we can send it over
@@ -435,7 +786,13 @@ impl Exchange {
             ExchangeType::HumanChat(ref chat_message) => {
                 // TODO(skcd): Figure out caching etc later on
                 let prompt = chat_message.query.to_owned();
-                SessionChatMessage::user(prompt)
+                match chat_message.role {
+                    MessageRole::User | MessageRole::Assistant => SessionChatMessage::user(prompt),
+                    // tool-derived content is data, not a user instruction,
+                    // so it gets tagged separately and wrapped/escaped
+                    // before it's ever turned into an LLM message
+                    MessageRole::ToolResult { .. } => SessionChatMessage::tool_output(prompt),
+                }
             }
             ExchangeType::AgentChat(ref chat_message) => {
                 // This completely breaks we have to figure out how to covert
@@ -562,6 +919,112 @@ impl Exchange {
     }
 }
 
+/// Mirrors the LSP diagnostic severity levels. We do not yet plumb the
+/// severity through from the editor's diagnostics response, so diagnostics
+/// tracked on the session currently default to `Error`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+/// A diagnostic which was surfaced to the agent (via an `LSPDiagnostics`
+/// tool call) but which no subsequent `CodeEditing` tool call has touched.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct UnresolvedDiagnostic {
+    fs_file_path: String,
+    message: String,
+    severity: DiagnosticSeverity,
+    range: Range,
+    referenced_in_exchange: Option<String>,
+}
+
+impl UnresolvedDiagnostic {
+    pub fn fs_file_path(&self) -> &str {
+        &self.fs_file_path
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn severity(&self) -> &DiagnosticSeverity {
+        &self.severity
+    }
+
+    pub fn referenced_in_exchange(&self) -> Option<&str> {
+        self.referenced_in_exchange.as_deref()
+    }
+}
+
+/// A roll-up of effectiveness metrics for a session, returned by
+/// [`Session::statistics`].
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SessionStatistics {
+    exchange_count: usize,
+    diagnostic_resolution_rate: Option<f32>,
+}
+
+impl SessionStatistics {
+    pub fn new(exchange_count: usize, diagnostic_resolution_rate: Option<f32>) -> Self {
+        Self {
+            exchange_count,
+            diagnostic_resolution_rate,
+        }
+    }
+
+    pub fn exchange_count(&self) -> usize {
+        self.exchange_count
+    }
+
+    pub fn diagnostic_resolution_rate(&self) -> Option<f32> {
+        self.diagnostic_resolution_rate
+    }
+}
+
+/// Returned by `SessionService::fork_session`, describing where the fork
+/// landed and what it was forked from.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SessionForkMetadata {
+    session_id: String,
+    storage_path: String,
+    parent_session_id: String,
+    forked_from_exchange_id: String,
+}
+
+impl SessionForkMetadata {
+    pub fn new(
+        session_id: String,
+        storage_path: String,
+        parent_session_id: String,
+        forked_from_exchange_id: String,
+    ) -> Self {
+        Self {
+            session_id,
+            storage_path,
+            parent_session_id,
+            forked_from_exchange_id,
+        }
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+}
+
+/// One file's worth of [`Session::diff_summary`], coalescing every edit this
+/// session made to that file since its baseline into a single hunk/line
+/// count so a review pane can render one row per touched file.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FileChangeSummary {
+    pub fs_file_path: String,
+    pub hunks: usize,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Session {
     session_id: String,
@@ -571,6 +1034,39 @@ pub struct Session {
     storage_path: String,
     global_running_user_context: UserContext,
     tools: Vec<ToolType>,
+    #[serde(default)]
+    unresolved_diagnostics: Vec<UnresolvedDiagnostic>,
+    /// Set when this session was created by `fork`, pointing at the session it
+    /// was forked from.
+    #[serde(default)]
+    parent_session_id: Option<String>,
+    /// The exchange in the parent session at which the fork happened.
+    #[serde(default)]
+    forked_from_exchange_id: Option<String>,
+    /// The editor's open-files list as of the most recent
+    /// [`Session::human_message_tool_use`] call, kept around so the tool-use
+    /// loop can remind the model which files are open without re-sending the
+    /// whole editor state on every single message.
+    #[serde(default)]
+    tracked_open_files: Vec<String>,
+    /// The content each edited file had the first time this session touched
+    /// it, keyed by file path, so [`Session::cumulative_diff_since_baseline`]
+    /// can show the full scope of the session's changes rather than just the
+    /// most recent edit.
+    #[serde(default)]
+    file_edit_baselines: HashMap<String, String>,
+    /// The total diagnostic count observed each time an `LSPDiagnostics` tool
+    /// response was recorded via [`Session::track_diagnostics`], in order, so
+    /// [`Session::get_diagnostic_resolution_rate`] can compare the first
+    /// snapshot against the most recent one. Unlike `unresolved_diagnostics`
+    /// this is append-only and never shrinks as diagnostics get resolved.
+    #[serde(default)]
+    diagnostic_snapshots: Vec<usize>,
+    /// Invariants the agent must not violate, checked by
+    /// [`Session::check_constraints`] before any `CodeEditing` tool call is
+    /// executed. See [`EditConstraint`].
+    #[serde(default)]
+    constraints: Vec<EditConstraint>,
 }
 
 impl Session {
@@ -590,9 +1086,520 @@ impl Session {
             storage_path,
             global_running_user_context,
             tools,
+            unresolved_diagnostics: vec![],
+            parent_session_id: None,
+            forked_from_exchange_id: None,
+            tracked_open_files: vec![],
+            file_edit_baselines: HashMap::new(),
+            diagnostic_snapshots: vec![],
+            constraints: vec![],
         }
     }
 
+    /// Registers a constraint the agent must not violate. Consumed by
+    /// [`Session::check_constraints`] before any `CodeEditing` tool call.
+    pub fn add_constraint(mut self, constraint: EditConstraint) -> Self {
+        self.constraints.push(constraint);
+        self
+    }
+
+    /// Checks `fs_file_path`/`instruction` (the inputs to a pending
+    /// `CodeEditing` tool call) against every registered constraint,
+    /// returning the first one violated, if any.
+    pub fn check_constraints(
+        &self,
+        fs_file_path: &str,
+        instruction: &str,
+    ) -> Result<(), SymbolError> {
+        for constraint in self.constraints.iter() {
+            let violated = match constraint {
+                EditConstraint::NeverEditFile(glob) => {
+                    globset::Glob::new(glob)
+                        .map(|glob| glob.compile_matcher().is_match(fs_file_path))
+                        .unwrap_or(false)
+                }
+                EditConstraint::NeverEditSymbol(name) => instruction.contains(name.as_str()),
+                EditConstraint::PreservePublicAPI(protected_fs_file_path) => {
+                    protected_fs_file_path == fs_file_path
+                }
+            };
+            if violated {
+                return Err(SymbolError::ConstraintViolation(constraint.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn parent_session_id(&self) -> Option<&str> {
+        self.parent_session_id.as_deref()
+    }
+
+    /// The editor's open-files list as of the most recent
+    /// `human_message_tool_use` call. See the field's doc comment.
+    pub fn tracked_open_files(&self) -> &[String] {
+        &self.tracked_open_files
+    }
+
+    pub fn forked_from_exchange_id(&self) -> Option<&str> {
+        self.forked_from_exchange_id.as_deref()
+    }
+
+    /// Deep-copies this session truncated (inclusively) at `at_exchange_id` into
+    /// a new session rooted at `new_session_id`/`new_storage_path`.
+    ///
+    /// Exchange ids are remapped so the fork can evolve independently of the
+    /// parent (mirrors how plan ids are derived from `session_id`+`exchange_id`
+    /// elsewhere, so a fork never collides with its parent's ids), running
+    /// exchanges are marked cancelled since there is nothing left driving them
+    /// forward in the new session, and the parent lineage is recorded on the
+    /// result. The returned mapping of old exchange id -> new exchange id lets
+    /// the caller duplicate any out-of-band state (e.g. plan storage) which is
+    /// keyed by the old ids.
+    pub fn fork(
+        &self,
+        new_session_id: String,
+        new_storage_path: String,
+        at_exchange_id: &str,
+    ) -> (Session, Vec<(String, String)>) {
+        let truncated: Vec<Exchange> = match self
+            .exchanges
+            .iter()
+            .position(|exchange| exchange.exchange_id == at_exchange_id)
+        {
+            Some(index) => self.exchanges[..=index].to_vec(),
+            None => self.exchanges.clone(),
+        };
+
+        let exchange_id_mapping: Vec<(String, String)> = truncated
+            .iter()
+            .map(|exchange| {
+                (
+                    exchange.exchange_id.to_owned(),
+                    format!("{new_session_id}-{}", exchange.exchange_id),
+                )
+            })
+            .collect();
+
+        let remap = |exchange_id: &str| -> String {
+            exchange_id_mapping
+                .iter()
+                .find(|(old, _)| old == exchange_id)
+                .map(|(_, new)| new.to_owned())
+                .unwrap_or_else(|| exchange_id.to_owned())
+        };
+
+        let forked_exchanges = truncated
+            .into_iter()
+            .map(|mut exchange| {
+                exchange.exchange_id = remap(&exchange.exchange_id);
+                if let ExchangeType::AgentChat(ref mut agent_exchange) = exchange.exchange_type {
+                    agent_exchange.parent_exchange_id = remap(&agent_exchange.parent_exchange_id);
+                }
+                // the exchange can no longer be driven forward by whatever task
+                // was running against the parent session, so we can not leave it
+                // dangling in a running state in the fork
+                if matches!(exchange.exchange_state, ExchangeState::Running) {
+                    exchange.exchange_state = ExchangeState::Cancelled;
+                }
+                exchange
+            })
+            .collect();
+
+        let forked_session = Session {
+            session_id: new_session_id,
+            project_labels: self.project_labels.clone(),
+            repo_ref: self.repo_ref.clone(),
+            exchanges: forked_exchanges,
+            storage_path: new_storage_path,
+            global_running_user_context: self.global_running_user_context.clone(),
+            tools: self.tools.clone(),
+            unresolved_diagnostics: self.unresolved_diagnostics.clone(),
+            parent_session_id: Some(self.session_id.clone()),
+            forked_from_exchange_id: Some(at_exchange_id.to_owned()),
+            tracked_open_files: self.tracked_open_files.clone(),
+            file_edit_baselines: self.file_edit_baselines.clone(),
+            diagnostic_snapshots: self.diagnostic_snapshots.clone(),
+            constraints: self.constraints.clone(),
+        };
+
+        (forked_session, exchange_id_mapping)
+    }
+
+    /// Finds exchange ids which appear more than once, which should never
+    /// happen through normal use but can show up after someone hand-edits a
+    /// session file on disk. Returns one message per duplicated id.
+    pub fn validate_exchange_ids(&self) -> Result<(), Vec<String>> {
+        let mut seen = std::collections::HashSet::new();
+        let mut duplicates = vec![];
+        for exchange in self.exchanges.iter() {
+            if !seen.insert(exchange.exchange_id.clone()) {
+                duplicates.push(format!(
+                    "duplicate exchange_id `{}`",
+                    exchange.exchange_id
+                ));
+            }
+        }
+        if duplicates.is_empty() {
+            Ok(())
+        } else {
+            Err(duplicates)
+        }
+    }
+
+    /// Repairs a session whose exchanges contain duplicate ids (e.g. after a
+    /// manual edit) by suffixing every id after the first occurrence with
+    /// `-1`, `-2`, ... and rewriting `parent_exchange_id` references which
+    /// pointed at the id that got renamed. Mirrors the id-remapping done by
+    /// [`Session::fork`], but keyed off first-seen order instead of a
+    /// session-id prefix.
+    pub fn recalculate_exchange_ids(&self) -> Result<Session, SymbolError> {
+        let mut seen_counts: std::collections::HashMap<String, usize> =
+            std::collections::HashMap::new();
+        // maps an exchange's position in `self.exchanges` to the id it should
+        // be renamed to (only present for ids which needed renaming)
+        let mut renamed_ids: Vec<Option<String>> = vec![None; self.exchanges.len()];
+
+        for (index, exchange) in self.exchanges.iter().enumerate() {
+            let occurrence = seen_counts
+                .entry(exchange.exchange_id.clone())
+                .or_insert(0);
+            if *occurrence > 0 {
+                renamed_ids[index] = Some(format!("{}-{}", exchange.exchange_id, occurrence));
+            }
+            *occurrence += 1;
+        }
+
+        // maps every original (possibly duplicated) id to the id its *first*
+        // occurrence ended up with, so parent references keep pointing at the
+        // exchange they originally pointed at
+        let mut original_id_to_new_first_id: std::collections::HashMap<String, String> =
+            std::collections::HashMap::new();
+        for (index, exchange) in self.exchanges.iter().enumerate() {
+            original_id_to_new_first_id
+                .entry(exchange.exchange_id.clone())
+                .or_insert_with(|| {
+                    renamed_ids[index]
+                        .clone()
+                        .unwrap_or_else(|| exchange.exchange_id.clone())
+                });
+        }
+
+        let mut recalculated = self.clone();
+        for (index, exchange) in recalculated.exchanges.iter_mut().enumerate() {
+            if let ExchangeType::AgentChat(ref mut agent_exchange) = exchange.exchange_type {
+                if let Some(new_parent_id) =
+                    original_id_to_new_first_id.get(&agent_exchange.parent_exchange_id)
+                {
+                    agent_exchange.parent_exchange_id = new_parent_id.to_owned();
+                }
+            }
+            if let Some(new_id) = renamed_ids[index].take() {
+                exchange.exchange_id = new_id;
+            }
+        }
+
+        Ok(recalculated)
+    }
+
+    /// Unions the exchanges of `self` and `other`, keyed by `exchange_id`,
+    /// so two tasks (e.g. `plan_generation` and the hot-streak path) which
+    /// each did their own load -> mutate -> save against the same session
+    /// storage path don't clobber each other's exchange out of existence.
+    /// An id present in only one side is kept as-is; an id present in both
+    /// keeps whichever copy has the newer `updated_at_ms`. The merged
+    /// exchanges are then reordered chronologically by `updated_at_ms` (ties
+    /// broken by `self`'s original relative order, then `other`'s; an id
+    /// present in both with equal `updated_at_ms` keeps `other`'s copy, so
+    /// the result depends on argument order only through ties). Everything
+    /// else (session id, tools, tracked diagnostics, ...) is kept from
+    /// `self` - `other` is only a source of exchanges here.
+    pub fn merge(mut self, other: Session) -> Session {
+        let mut by_id: std::collections::HashMap<String, Exchange> = std::collections::HashMap::new();
+        let mut order: Vec<String> = vec![];
+        for exchange in self.exchanges.drain(..).chain(other.exchanges.into_iter()) {
+            match by_id.entry(exchange.exchange_id.clone()) {
+                std::collections::hash_map::Entry::Vacant(entry) => {
+                    order.push(exchange.exchange_id.clone());
+                    entry.insert(exchange);
+                }
+                std::collections::hash_map::Entry::Occupied(mut entry) => {
+                    if exchange.updated_at_ms >= entry.get().updated_at_ms {
+                        entry.insert(exchange);
+                    }
+                }
+            }
+        }
+        let mut merged: Vec<Exchange> = order
+            .into_iter()
+            .map(|exchange_id| by_id.remove(&exchange_id).expect("just inserted"))
+            .collect();
+        merged.sort_by_key(|exchange| exchange.updated_at_ms);
+        self.exchanges = merged;
+        self
+    }
+
+    /// Case-insensitive substring search over every exchange's human
+    /// messages, agent replies, tool inputs and diffs, e.g. for answering
+    /// "where in this session did the agent change the retry logic". Each
+    /// exchange is scanned field-by-field rather than flattened into one
+    /// giant string first, so memory use stays flat even for sessions with
+    /// large diffs.
+    pub fn search_exchanges(&self, query: &str) -> Vec<ExchangeSearchMatch> {
+        let query_lower = query.to_lowercase();
+        self.exchanges
+            .iter()
+            .flat_map(|exchange| exchange.search(&query_lower))
+            .collect()
+    }
+
+    /// Records diagnostics observed from an `LSPDiagnostics` tool response so
+    /// they can later be reported as unresolved if no `CodeEditing` call ever
+    /// touches the affected line range.
+    pub fn track_diagnostics(
+        mut self,
+        exchange_id: String,
+        diagnostics: &DiagnosticMap,
+    ) -> Session {
+        let mut diagnostic_count = 0;
+        for (fs_file_path, file_diagnostics) in diagnostics.iter() {
+            for diagnostic in file_diagnostics.iter() {
+                diagnostic_count += 1;
+                self.unresolved_diagnostics.push(UnresolvedDiagnostic {
+                    fs_file_path: fs_file_path.to_owned(),
+                    message: diagnostic.diagnostic_message().to_owned(),
+                    severity: DiagnosticSeverity::Error,
+                    range: diagnostic.range().clone(),
+                    referenced_in_exchange: Some(exchange_id.clone()),
+                });
+            }
+        }
+        self.diagnostic_snapshots.push(diagnostic_count);
+        self
+    }
+
+    /// The fraction of diagnostics the agent has cleared since its first
+    /// `LSPDiagnostics` tool response this session, comparing that first
+    /// snapshot's total diagnostic count against the most recent one. `1.0`
+    /// means every diagnostic seen at the start has since been resolved (or
+    /// there were none to begin with); `0.0` means no progress has been made.
+    /// Returns `None` if this session has no recorded `LSPDiagnostics`
+    /// response yet.
+    pub fn get_diagnostic_resolution_rate(&self) -> Option<f32> {
+        let initial_count = *self.diagnostic_snapshots.first()?;
+        let final_count = *self.diagnostic_snapshots.last()?;
+        if initial_count == 0 {
+            return Some(1.0);
+        }
+        Some(initial_count.saturating_sub(final_count) as f32 / initial_count as f32)
+    }
+
+    /// A snapshot of session-level effectiveness metrics, for surfacing to a
+    /// UI or logging pipeline that wants a single roll-up rather than digging
+    /// through exchanges itself.
+    pub fn statistics(&self) -> SessionStatistics {
+        SessionStatistics::new(self.exchanges.len(), self.get_diagnostic_resolution_rate())
+    }
+
+    /// Drops any tracked diagnostics for `fs_file_path` whose range overlaps
+    /// `edited_range`, since a `CodeEditing` call has since touched that part
+    /// of the file.
+    pub fn resolve_diagnostics_for_edit(
+        mut self,
+        fs_file_path: &str,
+        edited_range: &Range,
+    ) -> Session {
+        self.unresolved_diagnostics.retain(|diagnostic| {
+            !(diagnostic.fs_file_path == fs_file_path
+                && diagnostic.range.intersects_with_another_range(edited_range))
+        });
+        self
+    }
+
+    /// Returns the diagnostics which were surfaced to the agent but never
+    /// addressed by a subsequent edit to the affected lines.
+    pub fn get_unresolved_diagnostics(&self) -> Vec<&UnresolvedDiagnostic> {
+        self.unresolved_diagnostics.iter().collect()
+    }
+
+    /// Records `content` as the baseline for `fs_file_path` the first time
+    /// this session edits it. A no-op on every later call for the same file,
+    /// so the baseline always reflects the file as it was before this
+    /// session made any changes to it.
+    pub fn track_edit_baseline(mut self, fs_file_path: &str, content: String) -> Session {
+        self.file_edit_baselines
+            .entry(fs_file_path.to_owned())
+            .or_insert(content);
+        self
+    }
+
+    /// Diffs `current_content` against the content `fs_file_path` had the
+    /// first time this session edited it, so the agent can see the full
+    /// scope of its own changes rather than just the edit which just
+    /// happened. Returns `None` if the session has no recorded baseline for
+    /// this file, e.g. it hasn't been edited yet this session.
+    pub fn cumulative_diff_since_baseline(
+        &self,
+        fs_file_path: &str,
+        current_content: &str,
+    ) -> Option<String> {
+        let baseline = self.file_edit_baselines.get(fs_file_path)?;
+        let diff = similar::TextDiff::from_lines(baseline.as_str(), current_content);
+        Some(
+            diff.unified_diff()
+                .header(fs_file_path, fs_file_path)
+                .to_string(),
+        )
+    }
+
+    /// A per-file summary of everything this session has changed, for a UI
+    /// review pane that wants a single "review all changes" view rather than
+    /// digging through individual exchanges. Diffs `current_file_contents`
+    /// against this session's recorded baseline for each file (see
+    /// [`Session::track_edit_baseline`]) the same way
+    /// [`Session::cumulative_diff_since_baseline`] does, but for every
+    /// baselined file at once, so multiple edits to the same file are
+    /// naturally coalesced into one summary instead of listed per-edit.
+    /// Files with no baseline (never edited this session) or missing from
+    /// `current_file_contents` are skipped.
+    pub fn diff_summary(
+        &self,
+        current_file_contents: &HashMap<String, String>,
+    ) -> Vec<FileChangeSummary> {
+        self.file_edit_baselines
+            .iter()
+            .filter_map(|(fs_file_path, baseline)| {
+                let current_content = current_file_contents.get(fs_file_path)?;
+                let diff = similar::TextDiff::from_lines(baseline.as_str(), current_content.as_str());
+                let hunks = diff.unified_diff().iter_hunks().count();
+                if hunks == 0 {
+                    return None;
+                }
+                let (lines_added, lines_removed) = diff.iter_all_changes().fold(
+                    (0usize, 0usize),
+                    |(added, removed), change| match change.tag() {
+                        similar::ChangeTag::Insert => (added + 1, removed),
+                        similar::ChangeTag::Delete => (added, removed + 1),
+                        similar::ChangeTag::Equal => (added, removed),
+                    },
+                );
+                Some(FileChangeSummary {
+                    fs_file_path: fs_file_path.to_owned(),
+                    hunks,
+                    lines_added,
+                    lines_removed,
+                })
+            })
+            .collect()
+    }
+
+    /// A compact "state of the world" summary the tool-use loop prepends to
+    /// each iteration's prompt, so the model does not have to re-derive
+    /// which files it already touched from scrolling back through the
+    /// conversation. Regenerated fresh from `Session` state every call
+    /// rather than accumulated as a message, and trimmed to fit
+    /// `token_budget` (approximated as 4 characters per token) by dropping
+    /// whole sections lowest-priority first: the last terminal output, then
+    /// open files, then edited files. The diagnostics count is always kept.
+    pub fn state_of_the_world_header(&self, token_budget: usize) -> String {
+        self.state_of_the_world_header_with_open_files(token_budget, &self.tracked_open_files)
+    }
+
+    /// Same as [`Session::state_of_the_world_header`], but lets the caller
+    /// supply the open-files list instead of using `self.tracked_open_files`
+    /// — used by [`Session::get_tool_to_use`], which only has a borrowed
+    /// `Session` to work with and refreshes the open-files list itself
+    /// before the session gets a chance to store it.
+    fn state_of_the_world_header_with_open_files(
+        &self,
+        token_budget: usize,
+        tracked_open_files: &[String],
+    ) -> String {
+        let char_budget = token_budget.saturating_mul(4);
+
+        let diagnostics_section = format!(
+            "outstanding diagnostics: {}",
+            self.unresolved_diagnostics.len()
+        );
+
+        let mut edited_files: Vec<(String, i64)> = vec![];
+        for exchange in self.exchanges.iter() {
+            if let ExchangeType::HumanChat(human_chat) = &exchange.exchange_type {
+                if let Some(full_diff) = &human_chat.full_diff {
+                    let delta = full_diff.diff.lines().fold(0i64, |delta, line| {
+                        if line.starts_with("+++") || line.starts_with("---") {
+                            delta
+                        } else if line.starts_with('+') {
+                            delta + 1
+                        } else if line.starts_with('-') {
+                            delta - 1
+                        } else {
+                            delta
+                        }
+                    });
+                    edited_files.retain(|(fs_file_path, _)| fs_file_path != &full_diff.fs_file_path);
+                    edited_files.push((full_diff.fs_file_path.clone(), delta));
+                }
+            }
+        }
+        let edited_files_section = if edited_files.is_empty() {
+            "edited files: none".to_owned()
+        } else {
+            format!(
+                "edited files:\n{}",
+                edited_files
+                    .iter()
+                    .map(|(fs_file_path, delta)| format!("- {fs_file_path} ({delta:+})"))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            )
+        };
+
+        let open_files_section = if tracked_open_files.is_empty() {
+            "open files: none".to_owned()
+        } else {
+            format!("open files:\n{}", tracked_open_files.join("\n"))
+        };
+
+        let last_terminal_output = self.exchanges.iter().rev().find_map(|exchange| {
+            if let ExchangeType::HumanChat(human_chat) = &exchange.exchange_type {
+                if matches!(
+                    human_chat.role,
+                    MessageRole::ToolResult {
+                        tool: ToolType::TerminalCommand
+                    }
+                ) {
+                    return Some(human_chat.query.clone());
+                }
+            }
+            None
+        });
+        let terminal_section = match last_terminal_output {
+            Some(output) => format!(
+                "last terminal output:\n{}",
+                output.chars().take(400).collect::<String>()
+            ),
+            None => "last terminal output: none".to_owned(),
+        };
+
+        // highest to lowest priority
+        let mut sections = vec![
+            diagnostics_section,
+            edited_files_section,
+            open_files_section,
+            terminal_section,
+        ];
+        while sections.len() > 1
+            && sections.iter().map(|section| section.len()).sum::<usize>() > char_budget
+        {
+            sections.pop();
+        }
+
+        format!(
+            "<state_of_the_world>\n{}\n</state_of_the_world>",
+            sections.join("\n")
+        )
+    }
+
     pub fn session_id(&self) -> &str {
         &self.session_id
     }
@@ -617,6 +1624,25 @@ impl Session {
             .find(|exchange| &exchange.exchange_id == exchange_id)
     }
 
+    /// Follows the `parent_exchange_id` links starting at `exchange_id` all
+    /// the way back to the root of the chain (a human, plan or edit exchange
+    /// with no parent), and returns the exchanges in causal order (root
+    /// first). This is used to build LLM prompt history which respects the
+    /// logical thread of a conversation (plan iterations, hot streak replies)
+    /// rather than plain chronological order.
+    pub fn get_exchange_chain(&self, exchange_id: &str) -> Vec<&Exchange> {
+        let mut chain = vec![];
+        let mut current_exchange_id = Some(exchange_id.to_owned());
+        while let Some(exchange) =
+            current_exchange_id.and_then(|exchange_id| self.find_exchange_by_id(&exchange_id))
+        {
+            current_exchange_id = exchange.parent_exchange_id().map(|id| id.to_owned());
+            chain.push(exchange);
+        }
+        chain.reverse();
+        chain
+    }
+
     /// Finds the exchange we are interested in and mutates the previous queries
     /// and the current query
     pub fn plan_iteration(
@@ -683,6 +1709,23 @@ impl Session {
             .cloned()
     }
 
+    /// The `UserContext` attached to `exchange_id`, if its exchange type
+    /// carries one. `HumanChat`, `Plan` and `Edit` exchanges all carry their
+    /// own user context; `AgentChat` exchanges (chat replies, plan replies,
+    /// edit replies, tool-use ticker messages) are the agent's own output and
+    /// never do, so those return `None`.
+    pub fn user_context_for_exchange(&self, exchange_id: &str) -> Option<&UserContext> {
+        self.exchanges
+            .iter()
+            .find(|exchange| exchange.exchange_id == exchange_id)
+            .and_then(|exchange| match &exchange.exchange_type {
+                ExchangeType::HumanChat(human_chat) => Some(&human_chat.user_context),
+                ExchangeType::Plan(plan) => Some(&plan.user_context),
+                ExchangeType::Edit(edit) => Some(&edit.user_context),
+                ExchangeType::AgentChat(_) => None,
+            })
+    }
+
     pub fn agentic_edit(
         mut self,
         exchange_id: String,
@@ -726,6 +1769,7 @@ impl Session {
         mut self,
         exchange_id: String,
         human_message: String,
+        user_context: UserContext,
         all_files: Vec<String>,
         open_files: Vec<String>,
         _shell: String,
@@ -741,44 +1785,236 @@ impl Session {
 </editor_status>
 <user_query>
 {}
-</user_query>"#,
+</user_query>{}"#,
             all_files.join("\n"),
             open_files.join("\n"),
-            human_message
+            human_message,
+            user_context.attachments_xml(),
         );
+        self.tracked_open_files = open_files;
+        self.global_running_user_context = self
+            .global_running_user_context
+            .merge_user_context(user_context.clone());
+        let exchange = Exchange::human_chat(
+            exchange_id,
+            user_message,
+            user_context,
+            self.project_labels.to_vec(),
+            self.repo_ref.clone(),
+            MessageRole::User,
+        );
+        self.exchanges.push(exchange);
+        self
+    }
+
+    /// Pushes a `HumanChat` exchange tagged with `role`. Most callers pass
+    /// [`MessageRole::User`] for a genuine follow-up from the user, or
+    /// [`MessageRole::ToolResult`] when the tool-use loop is feeding a tool's
+    /// output back into the conversation for the model to react to.
+    pub fn human_message(
+        mut self,
+        exchange_id: String,
+        human_message: String,
+        user_context: UserContext,
+        project_labels: Vec<String>,
+        repo_ref: RepoRef,
+        role: MessageRole,
+    ) -> Session {
+        self.global_running_user_context = self
+            .global_running_user_context
+            .merge_user_context(user_context.clone());
         let exchange = Exchange::human_chat(
             exchange_id,
-            user_message,
-            UserContext::default(),
-            self.project_labels.to_vec(),
-            self.repo_ref.clone(),
+            human_message,
+            user_context,
+            project_labels,
+            repo_ref,
+            role,
         );
         self.exchanges.push(exchange);
         self
     }
 
-    pub fn human_message(
+    /// Same as [`Session::human_message`], but also stashes the full diff for
+    /// `fs_file_path` on the newly created exchange so a later `show_diff`
+    /// tool call can fetch it back via [`Session::find_full_diff_for_file`].
+    /// Used when the diff embedded in `human_message` itself has been
+    /// condensed for being too large to show in full.
+    pub fn human_message_with_full_diff(
         mut self,
         exchange_id: String,
         human_message: String,
         user_context: UserContext,
         project_labels: Vec<String>,
         repo_ref: RepoRef,
+        fs_file_path: String,
+        full_diff: String,
+        role: MessageRole,
     ) -> Session {
         self.global_running_user_context = self
             .global_running_user_context
             .merge_user_context(user_context.clone());
-        let exchange = Exchange::human_chat(
+        let mut exchange = Exchange::human_chat(
             exchange_id,
             human_message,
             user_context,
             project_labels,
             repo_ref,
+            role,
         );
+        exchange.attach_full_diff(fs_file_path, full_diff);
         self.exchanges.push(exchange);
         self
     }
 
+    /// Lets external tooling (CI bots, security scanners, ...) inject an
+    /// edit into a session without going through the LLM pipeline. Records a
+    /// synthetic exchange explaining where the edit came from and why,
+    /// applies it directly via the tool box, and appends the resulting diff
+    /// as an already-accepted edit exchange, so the session can simply be
+    /// continued from here as if the agent had made the edit itself.
+    pub async fn apply_edit_suggestion(
+        mut self,
+        exchange_id: String,
+        suggestion: EditSuggestion,
+        tool_box: Arc<ToolBox>,
+        message_properties: SymbolEventMessageProperties,
+    ) -> Result<Session, SymbolError> {
+        let source_description = match &suggestion.source {
+            EditSource::Human => "a human reviewer".to_owned(),
+            EditSource::External { tool_name } => format!("the {tool_name} tool"),
+        };
+        self.exchanges.push(Exchange::human_chat(
+            exchange_id.clone(),
+            format!(
+                "{source_description} suggested an edit to {}: {}",
+                suggestion.fs_file_path, suggestion.reason
+            ),
+            UserContext::default(),
+            self.project_labels.to_vec(),
+            self.repo_ref.clone(),
+            MessageRole::ToolResult {
+                tool: ToolType::CodeEditing,
+            },
+        ));
+
+        let old_content = tool_box
+            .file_open(suggestion.fs_file_path.clone(), message_properties.clone())
+            .await
+            .map(|response| response.contents())
+            .unwrap_or_default();
+
+        tool_box
+            .apply_edits_to_editor(
+                &suggestion.fs_file_path,
+                &suggestion.range,
+                &suggestion.new_content,
+                true,
+                message_properties.clone(),
+            )
+            .await?;
+
+        let diff = similar::TextDiff::from_lines(old_content.as_str(), suggestion.new_content.as_str())
+            .unified_diff()
+            .header(&suggestion.fs_file_path, &suggestion.fs_file_path)
+            .to_string();
+
+        let reply_exchange_id = format!("{exchange_id}-edit-suggestion");
+        let mut reply_exchange =
+            Exchange::agent_edits_reply(exchange_id, reply_exchange_id, diff)
+                .set_completion_status(true);
+        // the conversation history reads the reply's own `accepted` flag
+        if let ExchangeType::AgentChat(ExchangeTypeAgent {
+            reply: ExchangeReplyAgent::Edit(ref mut edit_reply),
+            ..
+        }) = reply_exchange.exchange_type
+        {
+            edit_reply.accepted = true;
+        }
+        self.exchanges.push(reply_exchange);
+
+        Ok(self)
+    }
+
+    /// The most recent agent edit reply whose exchange state is `Accepted`.
+    pub fn get_last_successful_edit(&self) -> Option<&Exchange> {
+        self.exchanges.iter().rev().find(|exchange| {
+            exchange.is_edit_reply() && matches!(exchange.exchange_state, ExchangeState::Accepted)
+        })
+    }
+
+    /// Symmetric to [`Session::get_last_successful_edit`], for the most
+    /// recent edit exchange that was rejected.
+    pub fn get_last_failed_edit(&self) -> Option<&Exchange> {
+        self.exchanges.iter().rev().find(|exchange| {
+            exchange.is_edit_reply() && matches!(exchange.exchange_state, ExchangeState::Rejected)
+        })
+    }
+
+    /// Looks back over the exchanges for the most recent full diff we stashed
+    /// for `fs_file_path`, used by the `show_diff` tool to fetch back a diff
+    /// which was condensed for being too large.
+    pub fn find_full_diff_for_file(&self, fs_file_path: &str) -> Option<&str> {
+        self.exchanges.iter().rev().find_map(|exchange| {
+            let ExchangeType::HumanChat(human_chat) = &exchange.exchange_type else {
+                return None;
+            };
+            let full_diff = human_chat.full_diff.as_ref()?;
+            if full_diff.fs_file_path == fs_file_path {
+                Some(full_diff.diff.as_str())
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Converts every exchange we have so far into the messages we would
+    /// send to an LLM, so callers outside this module (eg the `SummarizeContext`
+    /// tool dispatch) can build a completion request out of the session history
+    /// without reaching into `Exchange`'s private conversion logic.
+    pub async fn conversation_messages(
+        &self,
+        tool_broker: Arc<ToolBroker>,
+    ) -> Vec<SessionChatMessage> {
+        let mut converted_messages = vec![];
+        for exchange in self.exchanges.iter() {
+            converted_messages.push(exchange.to_conversation_message(tool_broker.clone()).await);
+        }
+        converted_messages
+    }
+
+    /// Replaces the older exchanges with a single synthetic exchange carrying
+    /// `summary`, keeping the most recent `keep_recent` exchanges untouched.
+    /// This is used by the `SummarizeContext` tool so a long-running agentic
+    /// session can compress its own history instead of relying on truncation.
+    pub fn compact_with_summary(
+        mut self,
+        summary: String,
+        keep_recent: usize,
+        summary_exchange_id: String,
+    ) -> Session {
+        let total = self.exchanges.len();
+        let keep_recent = keep_recent.min(total);
+        let recent_exchanges = self.exchanges.split_off(total - keep_recent);
+        let summary_exchange = Exchange::human_chat(
+            summary_exchange_id,
+            format!(
+                r#"The conversation so far has been summarized to save context space:
+{}"#,
+                summary
+            ),
+            UserContext::default(),
+            self.project_labels.to_vec(),
+            self.repo_ref.clone(),
+            MessageRole::ToolResult {
+                tool: ToolType::SummarizeContext,
+            },
+        );
+        self.exchanges = vec![summary_exchange];
+        self.exchanges.extend(recent_exchanges);
+        self
+    }
+
     fn last_exchange(&self) -> Option<&Exchange> {
         self.exchanges.last()
     }
@@ -851,6 +2087,8 @@ impl Session {
                                     parent_exchange_id,
                                 }),
                                 exchange_state: exchange.exchange_state,
+                                resolution_reason: exchange.resolution_reason,
+                                updated_at_ms: now_ms(),
                             }
                         }
                         _ => exchange,
@@ -925,7 +2163,7 @@ impl Session {
     }
 
     pub async fn get_tool_to_use(
-        mut self,
+        &self,
         tool_box: Arc<ToolBox>,
         exchange_id: String,
         parent_exchange_id: String,
@@ -935,6 +2173,24 @@ impl Session {
         // figure out what to do over here given the state of the session
         let mut converted_messages = vec![];
         for previous_message in self.exchanges.iter() {
+            if let ExchangeType::HumanChat(ref chat_message) = previous_message.exchange_type {
+                if let MessageRole::ToolResult { ref tool } = chat_message.role {
+                    if let Some(matched_pattern) =
+                        crate::agentic::tool::helpers::prompt_injection::detect_suspicious_instruction(
+                            &chat_message.query,
+                        )
+                    {
+                        let _ = message_properties.ui_sender().send(
+                            UIEventWithID::possible_prompt_injection_detected(
+                                message_properties.root_request_id().to_owned(),
+                                message_properties.request_id_str().to_owned(),
+                                tool.clone(),
+                                matched_pattern.to_owned(),
+                            ),
+                        );
+                    }
+                }
+            }
             converted_messages.push(
                 previous_message
                     .to_conversation_message(tool_box.tools().clone())
@@ -948,23 +2204,42 @@ impl Session {
             .grab_pending_subprocess_output(message_properties.clone())
             .await?;
 
+        // refresh our view of the open files from the editor, since the user
+        // may have opened or closed files since the session started (or
+        // since the last turn)
+        let open_files_from_editor = tool_box
+            .grab_open_files_from_editor(message_properties.clone())
+            .await?;
+        let tracked_open_files: Vec<String> = open_files_from_editor
+            .into_iter()
+            .map(|open_file| open_file.fs_file_path().to_owned())
+            .collect();
+
         // Now we can create the input for the tool use agent
+        let session_tool_types = self.tools.to_vec();
+        let state_of_the_world = self.state_of_the_world_header_with_open_files(
+            super::tool_use_agent::state_of_the_world_token_budget(
+                message_properties.llm_properties().llm(),
+            ),
+            &tracked_open_files,
+        );
         let tool_use_agent_input = ToolUseAgentInput::new(
             converted_messages,
-            self.tools
-                .to_vec()
-                .into_iter()
-                .filter_map(|tool_type| tool_box.tools().get_tool_description(&tool_type))
+            session_tool_types
+                .iter()
+                .filter_map(|tool_type| tool_box.tools().get_tool_description(tool_type))
                 .collect(),
+            session_tool_types,
             pending_spawned_process_output,
             message_properties.clone(),
-        );
+        )
+        .with_state_of_the_world(state_of_the_world);
 
         // now we can invoke the tool use agent over here and get the parsed input and store it
         let output = tool_use_agent.invoke(tool_use_agent_input).await;
         println!("tool_agent_output::({:?})", &output);
         match output {
-            Ok(ToolUseAgentOutput::Success((tool_input_partial, thinking))) => {
+            Ok(ToolUseAgentOutput::Success((tool_input_partial, thinking, cache_hit_tokens))) => {
                 // send over a UI event over here to inform the editor layer that we found a tool to use
                 let _ = message_properties
                     .ui_sender()
@@ -975,14 +2250,19 @@ impl Session {
                         thinking.to_owned(),
                     ));
                 let tool_type = tool_input_partial.to_tool_type();
-                self.exchanges.push(Exchange::agent_tool_use(
+                let exchange = Exchange::agent_tool_use(
                     parent_exchange_id,
                     exchange_id,
                     tool_input_partial.clone(),
                     tool_type,
                     thinking,
-                ));
-                Ok(AgentToolUseOutput::Success((tool_input_partial, self)))
+                    cache_hit_tokens,
+                );
+                Ok(AgentToolUseOutput::Success((
+                    tool_input_partial,
+                    exchange,
+                    tracked_open_files,
+                )))
             }
             Ok(ToolUseAgentOutput::Failure(input_string)) => {
                 Ok(AgentToolUseOutput::Failed(input_string))
@@ -991,6 +2271,18 @@ impl Session {
         }
     }
 
+    /// Applies the delta computed by a successful [`Session::get_tool_to_use`]
+    /// call: refreshes the tracked open files and appends the new exchange.
+    /// Kept as a separate step (rather than folded into `get_tool_to_use`
+    /// itself) since `get_tool_to_use` only borrows the session it reads
+    /// from, so the caller decides whether to apply the delta at all (a
+    /// failed or cancelled tool-use round leaves the session untouched).
+    pub fn apply_tool_use_exchange(mut self, exchange: Exchange, tracked_open_files: Vec<String>) -> Session {
+        self.tracked_open_files = tracked_open_files;
+        self.exchanges.push(exchange);
+        self
+    }
+
     /// This reacts to the last message and generates the reply for the user to handle
     ///
     /// we should have a way to sync this up with a queue based system so we react to events
@@ -1120,9 +2412,10 @@ impl Session {
     /// progress towards are current exchange
     pub fn accept_open_exchanges_if_any(
         mut self,
+        policy: OpenExchangesPolicy,
         message_properties: SymbolEventMessageProperties,
-    ) -> Self {
-        let exchanges_to_close = self
+    ) -> Result<Self, SymbolError> {
+        let exchanges_to_resolve = self
             .exchanges
             .iter()
             .filter_map(|exchange| {
@@ -1134,14 +2427,44 @@ impl Session {
             })
             .collect::<Vec<_>>();
 
-        exchanges_to_close.into_iter().for_each(|exchange_id| {
-            // mark the exchange as accepted
-            let _ = message_properties
-                .ui_sender()
-                .send(UIEventWithID::edits_accepted(
-                    self.session_id.to_owned(),
-                    exchange_id.to_owned(),
-                ));
+        if exchanges_to_resolve.is_empty() {
+            return Ok(self);
+        }
+
+        if let OpenExchangesPolicy::Block = policy {
+            return Err(SymbolError::OpenExchangesBlockRequest(exchanges_to_resolve));
+        }
+
+        let accepted = matches!(policy, OpenExchangesPolicy::AutoAccept);
+        let resolution_reason = if accepted {
+            "auto-accepted: a new message arrived while this exchange was still open"
+        } else {
+            "auto-rejected: a new message arrived while this exchange was still open"
+        };
+
+        let _ = message_properties
+            .ui_sender()
+            .send(UIEventWithID::exchanges_auto_resolved(
+                self.session_id.to_owned(),
+                message_properties.request_id_str().to_owned(),
+                exchanges_to_resolve
+                    .iter()
+                    .map(|exchange_id| {
+                        AutoResolvedExchange::new(
+                            exchange_id.to_owned(),
+                            resolution_reason.to_owned(),
+                        )
+                    })
+                    .collect(),
+            ));
+
+        exchanges_to_resolve.into_iter().for_each(|exchange_id| {
+            // mark the exchange as accepted or rejected
+            let _ = message_properties.ui_sender().send(if accepted {
+                UIEventWithID::edits_accepted(self.session_id.to_owned(), exchange_id.to_owned())
+            } else {
+                UIEventWithID::edits_rejected(self.session_id.to_owned(), exchange_id.to_owned())
+            });
             // mark the exchange as closed
             let _ = message_properties
                 .ui_sender()
@@ -1151,20 +2474,22 @@ impl Session {
                 ));
         });
 
-        // now update all our exchanges to accepted
+        // now update all our exchanges to their resolved state
         self.exchanges = self
             .exchanges
             .into_iter()
             .map(|exchange| {
                 if exchange.is_open() {
-                    exchange.set_completion_status(true)
+                    exchange
+                        .set_completion_status(accepted)
+                        .with_resolution_reason(resolution_reason.to_owned())
                 } else {
                     exchange
                 }
             })
             .collect();
 
-        self
+        Ok(self)
     }
 
     /// We have to map the plan revert exchange-id over here to be similar to
@@ -1303,6 +2628,8 @@ impl Session {
                     user_context: _,
                 }),
             exchange_state: _,
+            resolution_reason: _,
+            updated_at_ms: _,
         }) = exchange_in_focus
         {
             // take everything until the exchange id of the message we are supposed to
@@ -1355,6 +2682,16 @@ impl Session {
                 session_id.to_owned(),
                 exchange_id.to_owned(),
             ));
+            let _ = ui_sender.send(UIEventWithID::plan_generation_started(
+                session_id.to_owned(),
+                exchange_id.to_owned(),
+            ));
+
+            // plan_id and plan_storage_path get moved into the plan-generation
+            // task below, but we need them again afterwards to compute the
+            // impact preview, so keep our own copies
+            let plan_id_for_impact = plan_id.clone();
+            let plan_storage_path_for_impact = plan_storage_path.clone();
 
             let cloned_message_properties = message_properties.clone();
             let cloned_plan_service = plan_service.clone();
@@ -1475,6 +2812,18 @@ impl Session {
                             }
                         }
                         generated_steps.push(step.clone());
+                        // every plan step in this codebase is carried out as
+                        // a code edit, there's no other tool a step can
+                        // currently resolve to
+                        let _ = message_properties.ui_sender().send(
+                            UIEventWithID::plan_step_generated(
+                                self.session_id.to_owned(),
+                                exchange_id.clone(),
+                                generated_steps.len() - 1,
+                                step.description().to_owned(),
+                                ToolType::CodeEditing,
+                            ),
+                        );
                         let _ = edits_sender.send(Some(step)).await;
                     }
                     StepSenderEvent::NewStepTitle(title_found) => {
@@ -1512,6 +2861,13 @@ impl Session {
                             ));
                     }
                     StepSenderEvent::Done => {
+                        let _ = message_properties.ui_sender().send(
+                            UIEventWithID::plan_generation_completed(
+                                self.session_id.to_owned(),
+                                exchange_id.clone(),
+                                generated_steps.len(),
+                            ),
+                        );
                         let _ = edits_sender.send(None).await;
                         break;
                     }
@@ -1536,6 +2892,51 @@ impl Session {
             // since we generated something for the plan
             if !message_properties.cancellation_token().is_cancelled() {
                 println!("session::perform_plan_generation::cancellation_token::not_cancelled");
+
+                // consolidate a "these files will likely change" preview over
+                // the plan we just generated, so the editor can show it
+                // before the user approves execution
+                if let Ok(mut generated_plan) =
+                    plan_service.load_plan(&plan_storage_path_for_impact).await
+                {
+                    let mut impacted_files = vec![];
+                    let mut files_to_be_created = vec![];
+                    for fs_file_path in generated_plan.files_in_plan() {
+                        match tool_box
+                            .get_outline_nodes(&fs_file_path, message_properties.clone())
+                            .await
+                        {
+                            Some(outline_nodes) => {
+                                let symbol_ranges = outline_nodes
+                                    .into_iter()
+                                    .map(|outline_node| {
+                                        (outline_node.name().to_owned(), *outline_node.range())
+                                    })
+                                    .collect();
+                                impacted_files
+                                    .push(PlanImpactedFile::new(fs_file_path, symbol_ranges));
+                            }
+                            None => files_to_be_created.push(fs_file_path),
+                        }
+                    }
+                    let impact_summary = PlanImpactSummary::new(
+                        plan_id_for_impact,
+                        impacted_files,
+                        files_to_be_created,
+                    );
+                    generated_plan.set_impact_summary(impact_summary.clone());
+                    let _ = plan_service
+                        .save_plan(&generated_plan, &plan_storage_path_for_impact)
+                        .await;
+                    let _ = message_properties
+                        .ui_sender()
+                        .send(UIEventWithID::plan_impact_summary(
+                            message_properties.root_request_id().to_owned(),
+                            message_properties.request_id_str().to_owned(),
+                            impact_summary,
+                        ));
+                }
+
                 let _ = message_properties
                     .ui_sender()
                     .send(UIEventWithID::request_review(
@@ -1574,6 +2975,8 @@ impl Session {
                     ..
                 }),
             exchange_state: _,
+            resolution_reason: _,
+            updated_at_ms: _,
         }) = last_exchange
         {
             let edits_performed = scratch_pad_agent
@@ -1615,14 +3018,23 @@ impl Session {
                         ExchangeEditInformation::Anchored(ExchangeEditInformationAnchored {
                             query,
                             fs_file_path,
-                            range,
+                            mut range,
                             selection_context: _,
                         }),
                     ..
                 }),
             exchange_state: _,
+            resolution_reason: _,
+            updated_at_ms: _,
         }) = last_exchange
         {
+            // the range was captured when the anchor was set, but earlier
+            // exchanges in this session may have edited the file since then and
+            // shifted its bytes, so bring the byte offsets back in line with
+            // the file as it stands now before we anchor the edit on them
+            if let Ok(current_file_content) = tool_box.get_file_content(&fs_file_path).await {
+                range.recompute_bytes(&current_file_content);
+            }
             let mut converted_messages = vec![];
             for previous_message in self.exchanges.iter() {
                 converted_messages.push(
@@ -1729,6 +3141,8 @@ impl Session {
                         parent_exchange_id: _,
                     }),
                 exchange_state: _,
+                resolution_reason: _,
+                updated_at_ms: _,
             }) => {
                 // do something over here
                 let files_to_edit = plan_steps
@@ -1763,6 +3177,8 @@ impl Session {
                         ..
                     }),
                 exchange_state: _,
+                resolution_reason: _,
+                updated_at_ms: _,
             } => {
                 vec![fs_file_path.to_owned()]
             }
@@ -1943,6 +3359,20 @@ impl Session {
         self
     }
 
+    /// Marks the tool-use exchange `exchange_id` as cancelled, e.g. when the
+    /// LLM inference backing it was cancelled before a tool call could be
+    /// parsed out of it. Does nothing if the exchange can't be found or is
+    /// no longer running.
+    pub fn set_tool_use_exchange_as_cancelled(mut self, exchange_id: &str) -> Self {
+        if let Some(exchange) = self.find_exchange_by_id_mut(exchange_id) {
+            if matches!(exchange.exchange_state, ExchangeState::Running) {
+                exchange.exchange_state = ExchangeState::Cancelled;
+                exchange.updated_at_ms = now_ms();
+            }
+        }
+        self
+    }
+
     async fn save_to_storage(&self) -> Result<(), SymbolError> {
         let serialized = serde_json::to_string(self).unwrap();
         let mut file = tokio::fs::File::create(self.storage_path())
@@ -1954,3 +3384,498 @@ impl Session {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Session;
+    use crate::repo::types::RepoRef;
+    use crate::user_context::types::UserContext;
+
+    fn test_session(num_exchanges: usize) -> Session {
+        let mut session = Session::new(
+            "session-id".to_owned(),
+            vec![],
+            RepoRef::local("/tmp/test-repo").expect("local repo ref to parse"),
+            "/tmp/test-session-storage".to_owned(),
+            UserContext::default(),
+            vec![],
+        );
+        for index in 0..num_exchanges {
+            session = session.human_message(
+                index.to_string(),
+                format!("message {index}"),
+                UserContext::default(),
+                vec![],
+                RepoRef::local("/tmp/test-repo").expect("local repo ref to parse"),
+                super::MessageRole::User,
+            );
+        }
+        session
+    }
+
+    #[test]
+    fn compact_with_summary_reduces_exchange_count_and_keeps_recent_messages() {
+        let session = test_session(6);
+        let compacted = session.compact_with_summary(
+            "decided to use X, edited foo.rs, open question about Y".to_owned(),
+            2,
+            "summary-exchange".to_owned(),
+        );
+
+        // the 2 most recent exchanges plus the new summary exchange remain
+        assert_eq!(compacted.exchanges(), 3);
+
+        let remaining = &compacted.exchanges;
+        assert_eq!(remaining[0].exchange_id(), "summary-exchange");
+        match &remaining[0].exchange_type {
+            super::ExchangeType::HumanChat(chat) => {
+                assert!(chat.query.contains("decided to use X"));
+            }
+            _ => panic!("expected the summary to be inserted as a human chat exchange"),
+        }
+
+        // the latest messages are preserved untouched, in order
+        assert_eq!(remaining[1].exchange_id(), "4");
+        assert_eq!(remaining[2].exchange_id(), "5");
+    }
+
+    #[test]
+    fn matching_snippet_finds_case_insensitive_hit_with_context() {
+        let haystack = "we discussed retry logic in the http client a while back";
+        let snippet = super::matching_snippet(haystack, "RETRY LOGIC".to_lowercase().as_str(), 10);
+        assert!(snippet.is_some());
+        assert!(snippet.unwrap().to_lowercase().contains("retry logic"));
+    }
+
+    #[test]
+    fn matching_snippet_returns_none_when_absent() {
+        assert!(super::matching_snippet("nothing interesting here", "retry logic", 10).is_none());
+    }
+
+    #[test]
+    fn search_exchanges_finds_human_message_by_substring() {
+        let session = test_session(3);
+        let matches = session.search_exchanges("message 1");
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].exchange_id(), "1");
+        assert_eq!(matches[0].field(), &super::ExchangeSearchField::HumanMessage);
+    }
+
+    #[test]
+    fn search_exchanges_is_case_insensitive_and_empty_for_no_match() {
+        let session = test_session(2);
+        assert_eq!(session.search_exchanges("MESSAGE 0").len(), 1);
+        assert!(session.search_exchanges("no such text anywhere").is_empty());
+    }
+
+    #[test]
+    fn terminal_command_result_is_recorded_as_tool_result_not_user_message() {
+        use crate::agentic::tool::r#type::ToolType;
+
+        let session = test_session(1).human_message(
+            "1".to_owned(),
+            "total 0\ndrwxr-xr-x foo.rs".to_owned(),
+            UserContext::default(),
+            vec![],
+            RepoRef::local("/tmp/test-repo").expect("local repo ref to parse"),
+            super::MessageRole::ToolResult {
+                tool: ToolType::TerminalCommand,
+            },
+        );
+
+        let terminal_result_exchange = session.exchanges.last().expect("exchange to be present");
+        assert!(terminal_result_exchange.is_tool_result());
+        match &terminal_result_exchange.exchange_type {
+            super::ExchangeType::HumanChat(chat) => {
+                assert_eq!(
+                    chat.role(),
+                    &super::MessageRole::ToolResult {
+                        tool: ToolType::TerminalCommand
+                    }
+                );
+                assert_ne!(chat.role(), &super::MessageRole::User);
+            }
+            _ => panic!("expected a human chat exchange"),
+        }
+    }
+
+    #[test]
+    fn user_context_for_exchange_returns_context_for_human_chat_and_none_for_agent_chat() {
+        let mut context = UserContext::default();
+        context = context.add_variables(vec![]);
+        let mut session = test_session(1).plan(
+            "1".to_owned(),
+            "plan this out".to_owned(),
+            context.clone(),
+        );
+        session.exchanges.push(super::Exchange::agent_chat_reply(
+            "1".to_owned(),
+            "2".to_owned(),
+            "here is the plan".to_owned(),
+        ));
+
+        assert!(session.user_context_for_exchange("0").is_some());
+        assert!(session.user_context_for_exchange("1").is_some());
+        assert!(session.user_context_for_exchange("2").is_none());
+        assert!(session.user_context_for_exchange("does-not-exist").is_none());
+    }
+
+    #[test]
+    fn state_of_the_world_header_reports_diagnostics_edits_and_open_files() {
+        let session = test_session(1)
+            .human_message_with_full_diff(
+                "1".to_owned(),
+                "edited foo.rs".to_owned(),
+                UserContext::default(),
+                vec![],
+                RepoRef::local("/tmp/test-repo").expect("local repo ref to parse"),
+                "foo.rs".to_owned(),
+                "--- a/foo.rs\n+++ b/foo.rs\n+line one\n+line two\n-old line\n".to_owned(),
+                super::MessageRole::ToolResult {
+                    tool: crate::agentic::tool::r#type::ToolType::CodeEditing,
+                },
+            )
+            .human_message_tool_use(
+                "2".to_owned(),
+                "what's next".to_owned(),
+                UserContext::default(),
+                vec![],
+                vec!["foo.rs".to_owned(), "bar.rs".to_owned()],
+                "bash".to_owned(),
+            );
+
+        let header = session.state_of_the_world_header(10_000);
+        assert!(header.contains("outstanding diagnostics: 0"));
+        assert!(header.contains("foo.rs (+1)"));
+        assert!(header.contains("bar.rs"));
+    }
+
+    #[test]
+    fn state_of_the_world_header_drops_lowest_priority_sections_under_a_tight_budget() {
+        let session = test_session(1).human_message_tool_use(
+            "1".to_owned(),
+            "what's next".to_owned(),
+            UserContext::default(),
+            vec![],
+            vec!["a-quite-long-open-file-path.rs".to_owned()],
+            "bash".to_owned(),
+        );
+
+        let header = session.state_of_the_world_header(2);
+        assert!(header.contains("outstanding diagnostics"));
+        assert!(!header.contains("open files"));
+    }
+
+    #[test]
+    fn validate_exchange_ids_passes_for_unique_ids() {
+        let session = test_session(3);
+        assert!(session.validate_exchange_ids().is_ok());
+    }
+
+    #[test]
+    fn validate_exchange_ids_reports_duplicates() {
+        let mut session = test_session(1);
+        session.exchanges.push(session.exchanges[0].clone());
+
+        let duplicates = session
+            .validate_exchange_ids()
+            .expect_err("duplicate exchange_id should be detected");
+        assert_eq!(duplicates.len(), 1);
+        assert!(duplicates[0].contains("0"));
+    }
+
+    #[test]
+    fn recalculate_exchange_ids_renames_duplicates_and_keeps_parent_references_intact() {
+        let mut session = test_session(1);
+        // manual edit gone wrong: a second exchange ended up with the same id
+        session.exchanges.push(session.exchanges[0].clone());
+        // this reply was already pointing at the first "0" before the mixup
+        session
+            .exchanges
+            .push(super::Exchange::agent_chat_reply(
+                "0".to_owned(),
+                "1".to_owned(),
+                "reply".to_owned(),
+            ));
+
+        let recalculated = session
+            .recalculate_exchange_ids()
+            .expect("recalculation should succeed");
+
+        assert!(recalculated.validate_exchange_ids().is_ok());
+        assert_eq!(recalculated.exchanges[0].exchange_id(), "0");
+        assert_eq!(recalculated.exchanges[1].exchange_id(), "0-1");
+        // the reply's parent still points at the original first occurrence
+        match &recalculated.exchanges[2].exchange_type {
+            super::ExchangeType::AgentChat(agent_chat) => {
+                assert_eq!(agent_chat.parent_exchange_id, "0");
+            }
+            _ => panic!("expected an agent chat exchange"),
+        }
+    }
+
+    #[test]
+    fn cumulative_diff_since_baseline_reflects_two_edits_to_the_same_file() {
+        let session = test_session(0);
+
+        // nothing has touched foo.rs yet, so there is no baseline to diff against
+        assert!(session
+            .cumulative_diff_since_baseline("foo.rs", "fn foo() {}\n")
+            .is_none());
+
+        // first edit: only the baseline is recorded, the content itself hasn't
+        // "changed" from the session's point of view yet
+        let session = session.track_edit_baseline("foo.rs", "fn foo() {}\n".to_owned());
+        let after_first_edit = "fn foo() {\n    println!(\"one\");\n}\n";
+        let diff_after_first_edit = session
+            .cumulative_diff_since_baseline("foo.rs", after_first_edit)
+            .expect("baseline should be recorded after the first edit");
+        assert!(diff_after_first_edit.contains("println!(\"one\")"));
+
+        // second edit: track_edit_baseline is a no-op now, so the diff is still
+        // measured against the original content and shows both changes at once
+        let session = session.track_edit_baseline("foo.rs", after_first_edit.to_owned());
+        let after_second_edit =
+            "fn foo() {\n    println!(\"one\");\n    println!(\"two\");\n}\n";
+        let cumulative_diff = session
+            .cumulative_diff_since_baseline("foo.rs", after_second_edit)
+            .expect("baseline should still be recorded");
+        assert!(cumulative_diff.contains("println!(\"one\")"));
+        assert!(cumulative_diff.contains("println!(\"two\")"));
+    }
+
+    #[test]
+    fn diff_summary_coalesces_edits_and_lists_each_touched_file() {
+        let session = test_session(0)
+            .track_edit_baseline("foo.rs", "fn foo() {}\n".to_owned())
+            .track_edit_baseline("bar.rs", "fn bar() {}\n".to_owned())
+            .track_edit_baseline("baz.rs", "fn baz() {}\n".to_owned());
+
+        // foo.rs is edited twice; track_edit_baseline is a no-op past the
+        // first call, so the summary should still be measured cumulatively
+        // against the original content, not just the latest edit
+        let session = session.track_edit_baseline(
+            "foo.rs",
+            "fn foo() {\n    println!(\"one\");\n}\n".to_owned(),
+        );
+
+        let mut current_file_contents = std::collections::HashMap::new();
+        current_file_contents.insert(
+            "foo.rs".to_owned(),
+            "fn foo() {\n    println!(\"one\");\n    println!(\"two\");\n}\n".to_owned(),
+        );
+        current_file_contents.insert("bar.rs".to_owned(), "fn bar() {\n    // noop\n}\n".to_owned());
+        // baz.rs is left unchanged, so it should not show up in the summary
+        current_file_contents.insert("baz.rs".to_owned(), "fn baz() {}\n".to_owned());
+
+        let mut summary = session.diff_summary(&current_file_contents);
+        summary.sort_by(|a, b| a.fs_file_path.cmp(&b.fs_file_path));
+
+        assert_eq!(summary.len(), 2);
+
+        let bar_summary = &summary[0];
+        assert_eq!(bar_summary.fs_file_path, "bar.rs");
+        assert_eq!(bar_summary.lines_added, 3);
+        assert_eq!(bar_summary.lines_removed, 1);
+
+        let foo_summary = &summary[1];
+        assert_eq!(foo_summary.fs_file_path, "foo.rs");
+        assert_eq!(foo_summary.lines_added, 4);
+        assert_eq!(foo_summary.lines_removed, 1);
+    }
+
+    #[test]
+    fn apply_tool_use_exchange_appends_the_exchange_and_refreshes_open_files() {
+        use super::{Exchange, ToolInputPartial};
+        use crate::agentic::tool::lsp::open_file::OpenFileRequestPartial;
+
+        let session = test_session(1);
+        let exchange = Exchange::agent_tool_use(
+            "0".to_owned(),
+            "1".to_owned(),
+            ToolInputPartial::OpenFile(OpenFileRequestPartial::new("foo.rs".to_owned())),
+            super::ToolType::OpenFile,
+            "thinking out loud".to_owned(),
+            None,
+        );
+
+        let updated = session.apply_tool_use_exchange(exchange, vec!["foo.rs".to_owned()]);
+
+        assert_eq!(updated.exchanges(), 2);
+        assert_eq!(updated.tracked_open_files, vec!["foo.rs".to_owned()]);
+    }
+
+    fn diagnostics_for_count(count: usize) -> std::collections::HashMap<String, Vec<crate::agentic::symbol::events::lsp::LSPDiagnosticError>> {
+        let mut map = std::collections::HashMap::new();
+        map.insert(
+            "foo.rs".to_owned(),
+            (0..count)
+                .map(|index| {
+                    crate::agentic::symbol::events::lsp::LSPDiagnosticError::new(
+                        super::Range::default(),
+                        "fn foo() {}".to_owned(),
+                        "foo.rs".to_owned(),
+                        format!("unused variable {index}"),
+                        None,
+                        None,
+                    )
+                })
+                .collect(),
+        );
+        map
+    }
+
+    #[test]
+    fn diagnostic_resolution_rate_measures_progress_between_first_and_last_snapshot() {
+        let session = test_session(0)
+            .track_diagnostics("0".to_owned(), &diagnostics_for_count(4))
+            .track_diagnostics("1".to_owned(), &diagnostics_for_count(1));
+
+        assert_eq!(session.get_diagnostic_resolution_rate(), Some(0.75));
+        assert_eq!(session.statistics().diagnostic_resolution_rate(), Some(0.75));
+    }
+
+    #[test]
+    fn diagnostic_resolution_rate_is_none_without_any_lsp_response() {
+        let session = test_session(0);
+        assert_eq!(session.get_diagnostic_resolution_rate(), None);
+    }
+
+    #[test]
+    fn diagnostic_resolution_rate_is_full_when_no_diagnostics_were_ever_reported() {
+        let session = test_session(0).track_diagnostics("0".to_owned(), &diagnostics_for_count(0));
+        assert_eq!(session.get_diagnostic_resolution_rate(), Some(1.0));
+    }
+
+    fn append_human_message(session: Session, exchange_id: &str) -> Session {
+        session.human_message(
+            exchange_id.to_owned(),
+            format!("message {exchange_id}"),
+            UserContext::default(),
+            vec![],
+            RepoRef::local("/tmp/test-repo").expect("local repo ref to parse"),
+            super::MessageRole::User,
+        )
+    }
+
+    #[test]
+    fn merge_unions_exchanges_present_on_only_one_side() {
+        let base = test_session(0);
+        let ours = append_human_message(base.clone(), "ours");
+        let theirs = append_human_message(base, "theirs");
+
+        let merged = ours.merge(theirs);
+
+        let mut ids: Vec<&str> = merged
+            .exchanges
+            .iter()
+            .map(|exchange| exchange.exchange_id())
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec!["ours", "theirs"]);
+    }
+
+    #[test]
+    fn merge_keeps_the_more_recently_updated_copy_of_a_shared_exchange_id() {
+        let base = test_session(0);
+        let mut stale = append_human_message(base.clone(), "shared");
+        let mut fresh = append_human_message(base, "shared");
+        stale.find_exchange_by_id_mut("shared").unwrap().updated_at_ms = 1;
+        fresh.find_exchange_by_id_mut("shared").unwrap().updated_at_ms = 2;
+        fresh
+            .find_exchange_by_id_mut("shared")
+            .unwrap()
+            .resolution_reason = Some("won the merge".to_owned());
+
+        let merged = stale.merge(fresh);
+
+        assert_eq!(merged.exchanges.len(), 1);
+        assert_eq!(
+            merged.exchanges[0].resolution_reason,
+            Some("won the merge".to_owned())
+        );
+    }
+
+    // Two tasks (`a` and `b`) each start from the same on-disk session and
+    // interleave appending their own exchanges and saving (a save always
+    // merges with whatever is on disk at that point, mirroring
+    // `SessionService::save_to_storage`). However the two tasks' local
+    // copies and the appends interleave, the disk copy after every save
+    // should contain the union of every exchange either task has appended
+    // so far - nothing either task wrote is ever allowed to disappear.
+    #[test]
+    fn merge_of_interleaved_concurrent_appends_always_converges_to_the_union() {
+        use rand::Rng;
+
+        for trial in 0..200 {
+            let mut rng = rand::thread_rng();
+            let base = test_session(0);
+            let mut disk = base.clone();
+            let mut task_a = base.clone();
+            let mut task_b = base;
+
+            let mut expected_ids: Vec<String> = vec![];
+            let moves = 12;
+            for step in 0..moves {
+                let use_task_a = rng.gen_bool(0.5);
+                let exchange_id = format!("trial{trial}-{}-{step}", if use_task_a { "a" } else { "b" });
+                expected_ids.push(exchange_id.clone());
+                if use_task_a {
+                    task_a = append_human_message(task_a, &exchange_id);
+                    disk = task_a.clone().merge(disk);
+                } else {
+                    task_b = append_human_message(task_b, &exchange_id);
+                    disk = task_b.clone().merge(disk);
+                }
+            }
+
+            let mut disk_ids: Vec<String> = disk
+                .exchanges
+                .iter()
+                .map(|exchange| exchange.exchange_id().to_owned())
+                .collect();
+            disk_ids.sort();
+            expected_ids.sort();
+            assert_eq!(disk_ids, expected_ids, "trial {trial} lost an exchange during merge");
+        }
+    }
+
+    #[test]
+    fn check_constraints_rejects_edits_to_a_globbed_file() {
+        let session = test_session(0).add_constraint(super::EditConstraint::NeverEditFile(
+            "vendor/**".to_owned(),
+        ));
+        assert!(session
+            .check_constraints("vendor/lib/thing.rs", "rename the helper")
+            .is_err());
+        assert!(session
+            .check_constraints("src/lib/thing.rs", "rename the helper")
+            .is_ok());
+    }
+
+    #[test]
+    fn check_constraints_rejects_edits_mentioning_a_protected_symbol() {
+        let session = test_session(0).add_constraint(super::EditConstraint::NeverEditSymbol(
+            "parse_config".to_owned(),
+        ));
+        assert!(session
+            .check_constraints("src/config.rs", "refactor parse_config to take a Path")
+            .is_err());
+        assert!(session
+            .check_constraints("src/config.rs", "refactor write_config to take a Path")
+            .is_ok());
+    }
+
+    #[test]
+    fn check_constraints_rejects_any_edit_to_a_public_api_preserved_file() {
+        let session = test_session(0).add_constraint(super::EditConstraint::PreservePublicAPI(
+            "src/api.rs".to_owned(),
+        ));
+        assert!(session
+            .check_constraints("src/api.rs", "add a doc comment")
+            .is_err());
+        assert!(session
+            .check_constraints("src/internal.rs", "add a doc comment")
+            .is_ok());
+    }
+}
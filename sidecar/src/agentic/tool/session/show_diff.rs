@@ -0,0 +1,114 @@
+//! Lets the agent pull back the full diff for a file whose edit summary was
+//! condensed (because it was too large to show in full) as a human message.
+//! The actual lookup happens against the session's stored exchanges before
+//! this tool is invoked (it has no access to the session itself), so this
+//! module is only responsible for formatting whatever the caller found.
+
+use async_trait::async_trait;
+
+use crate::agentic::tool::{errors::ToolError, input::ToolInput, output::ToolOutput, r#type::Tool};
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ShowDiffRequestPartial {
+    fs_file_path: String,
+}
+
+impl ShowDiffRequestPartial {
+    pub fn new(fs_file_path: String) -> Self {
+        Self { fs_file_path }
+    }
+
+    pub fn fs_file_path(&self) -> &str {
+        &self.fs_file_path
+    }
+
+    pub fn to_string(&self) -> String {
+        format!(
+            r#"<show_diff>
+<fs_file_path>
+{}
+</fs_file_path>
+</show_diff>"#,
+            &self.fs_file_path
+        )
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ShowDiffInput {
+    fs_file_path: String,
+    // filled in by the session service after it looks the diff up on the
+    // exchange which stored it, `None` when we never condensed a diff for
+    // this file (or the agent asked about a file we have no record of)
+    full_diff: Option<String>,
+}
+
+impl ShowDiffInput {
+    pub fn new(fs_file_path: String, full_diff: Option<String>) -> Self {
+        Self {
+            fs_file_path,
+            full_diff,
+        }
+    }
+
+    pub fn fs_file_path(&self) -> &str {
+        &self.fs_file_path
+    }
+
+    pub fn full_diff(&self) -> Option<&str> {
+        self.full_diff.as_deref()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ShowDiffOutput {
+    formatted_diff: String,
+}
+
+impl ShowDiffOutput {
+    pub fn formatted_diff(&self) -> &str {
+        &self.formatted_diff
+    }
+}
+
+pub struct ShowDiffTool {}
+
+impl ShowDiffTool {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Tool for ShowDiffTool {
+    async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
+        let context = input.is_show_diff()?;
+        let formatted_diff = match context.full_diff() {
+            Some(full_diff) => format!("<full_diff>\n{}\n</full_diff>", full_diff),
+            None => format!(
+                "No condensed diff was recorded for {}, the edit summary you already have is the whole diff.",
+                context.fs_file_path()
+            ),
+        };
+        Ok(ToolOutput::ShowDiff(ShowDiffOutput { formatted_diff }))
+    }
+
+    fn tool_description(&self) -> String {
+        r#"### show_diff
+Fetches the full diff for a file whose edit summary was condensed because it was too large to show in full. Use this when you need to see the exact lines that were changed in a file rather than the hunk-header summary you were shown after an edit."#.to_owned()
+    }
+
+    fn tool_input_format(&self) -> String {
+        r#"Parameters:
+- fs_file_path: (required) The absolute path of the file whose full diff you want to see.
+
+Usage:
+<show_diff>
+<fs_file_path>
+File path here
+</fs_file_path>
+</show_diff>
+"#
+        .to_owned()
+    }
+}
@@ -0,0 +1,382 @@
+//! For models which support native function calling / JSON mode we would
+//! rather ask for a structured tool call directly instead of asking the
+//! model to emit our XML-ish tool use format and parsing that back out.
+//! This is a lot less brittle since we are no longer at the mercy of the
+//! model producing well formed, line-delimited XML.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use llm_client::{broker::LLMBroker, clients::types::LLMClientMessage};
+use tokio_util::sync::CancellationToken;
+
+use crate::agentic::{
+    symbol::{errors::SymbolError, identifier::LLMProperties},
+    tool::{
+        code_edit::types::CodeEditingPartialRequest,
+        helpers::cancellation_future::run_with_cancellation,
+        input::ToolInputPartial,
+        lsp::{
+            delete_file::DeleteFileInputPartial, duplicate_symbol::DuplicateSymbolInputPartial,
+            file_diagnostics::WorkspaceDiagnosticsPartial,
+            get_outline_nodes::GetOutlineNodesInputPartial,
+            get_workspace_symbols::GetWorkspaceSymbolsInputPartial, list_files::ListFilesInput,
+            move_file::MoveFileInputPartial, open_file::OpenFileRequestPartial,
+            search_file::SearchFileContentInputPartial,
+        },
+        r#type::ToolType,
+        repo_map::generator::RepoMapGeneratorRequestPartial,
+        session::{
+            ask_followup_question::AskFollowupQuestionsRequest,
+            attempt_completion::AttemptCompletionClientRequest,
+            code_review::CodeReviewRequestPartial,
+            find_symbol_definition::FindSymbolDefinitionRequestPartial,
+            run_tests::RunTestsRequestPartial,
+            show_diff::ShowDiffRequestPartial,
+            summarize_context::SummarizeContextInputPartial,
+        },
+        terminal::terminal::TerminalInputPartial,
+    },
+};
+
+/// The tag names here have to line up exactly with the XML tags the text
+/// parser understands (`ToolUseGenerator` in `tool_use_agent.rs`), since a
+/// structured call and a text based call for the same tool must end up
+/// producing an identical `ToolInputPartial`.
+const KNOWN_TOOL_SCHEMAS: &[(&str, ToolType, &[&str], &[&str])] = &[
+    ("code_edit_input", ToolType::CodeEditing, &["fs_file_path", "instruction"], &[]),
+    ("list_files", ToolType::ListFiles, &["directory_path", "recursive"], &[]),
+    (
+        "search_files",
+        ToolType::SearchFileContentWithRegex,
+        &["directory_path", "regex_pattern"],
+        &["file_pattern", "exclude_pattern"],
+    ),
+    ("read_file", ToolType::OpenFile, &["fs_file_path"], &[]),
+    ("get_diagnostics", ToolType::FileDiagnostics, &[], &[]),
+    ("execute_command", ToolType::TerminalCommand, &["command"], &[]),
+    (
+        "ask_followup_question",
+        ToolType::AskFollowupQuestions,
+        &["question"],
+        &[],
+    ),
+    (
+        "attempt_completion",
+        ToolType::AttemptCompletion,
+        &["result"],
+        &["command"],
+    ),
+    (
+        "repo_map_generation",
+        ToolType::RepoMapGeneration,
+        &["directory_path"],
+        &[],
+    ),
+    ("delete_file", ToolType::DeleteFile, &["fs_file_path"], &[]),
+    (
+        "move_file",
+        ToolType::MoveFile,
+        &["fs_file_path", "new_fs_file_path"],
+        &[],
+    ),
+    (
+        "summarize_session",
+        ToolType::SummarizeContext,
+        &[],
+        &[],
+    ),
+    ("show_diff", ToolType::ShowDiff, &["fs_file_path"], &[]),
+    ("code_review", ToolType::CodeReview, &[], &["command"]),
+    (
+        "get_outline_nodes",
+        ToolType::GetOutlineNodes,
+        &["fs_file_path"],
+        &[],
+    ),
+    (
+        "duplicate_symbol",
+        ToolType::DuplicateSymbol,
+        &["fs_file_path", "symbol_name", "new_name"],
+        &[],
+    ),
+    (
+        "find_symbol_definition",
+        ToolType::FindSymbolDefinition,
+        &["symbol_name"],
+        &["from_file"],
+    ),
+    (
+        "get_workspace_symbols",
+        ToolType::GetWorkspaceSymbols,
+        &["query", "max_results"],
+        &[],
+    ),
+    (
+        "run_tests",
+        ToolType::RunTests,
+        &[],
+        &["test_filter", "framework_hint"],
+    ),
+];
+
+/// Builds the JSON schema for whichever tools out of `tool_types` we know
+/// how to reconstruct a `ToolInputPartial` for. Tools we don't recognise are
+/// silently left out of the schema, the model simply won't be offered them
+/// through the structured path (the text parser has no tags for them
+/// either).
+pub fn tools_json_schema(tool_types: &[ToolType]) -> serde_json::Value {
+    let tools = KNOWN_TOOL_SCHEMAS
+        .iter()
+        .filter(|(_, tool_type, _, _)| tool_types.contains(tool_type))
+        .map(|(name, _, required, optional)| {
+            let mut properties = serde_json::Map::new();
+            for field in required.iter().chain(optional.iter()) {
+                properties.insert(
+                    field.to_string(),
+                    serde_json::json!({ "type": "string" }),
+                );
+            }
+            serde_json::json!({
+                "name": name,
+                "parameters": {
+                    "type": "object",
+                    "properties": properties,
+                    "required": required,
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    serde_json::json!({ "tools": tools })
+}
+
+fn string_field(parameters: &serde_json::Map<String, serde_json::Value>, field: &str) -> Option<String> {
+    parameters
+        .get(field)
+        .and_then(|value| value.as_str())
+        .map(|value| value.to_owned())
+}
+
+/// The reverse of `tools_json_schema`: given the tool name the model picked
+/// and the parameters it filled in, reconstructs the same `ToolInputPartial`
+/// the text parser would have produced for an equivalent XML tool call.
+pub fn parse_structured_tool_call(
+    tool_name: &str,
+    parameters: &serde_json::Map<String, serde_json::Value>,
+) -> Option<ToolInputPartial> {
+    match tool_name {
+        "code_edit_input" => Some(ToolInputPartial::CodeEditing(CodeEditingPartialRequest::new(
+            string_field(parameters, "fs_file_path")?,
+            string_field(parameters, "instruction")?,
+        ))),
+        "list_files" => Some(ToolInputPartial::ListFiles(ListFilesInput::new(
+            string_field(parameters, "directory_path")?,
+            parameters
+                .get("recursive")
+                // the schema types every field as a string, so accept "true"
+                // the same way the text parser does, as well as a JSON bool
+                .and_then(|value| {
+                    value
+                        .as_bool()
+                        .or_else(|| value.as_str().map(|value| value.trim().parse::<bool>().unwrap_or(false)))
+                })
+                .unwrap_or_default(),
+        ))),
+        "search_files" => Some(ToolInputPartial::SearchFileContentWithRegex(
+            SearchFileContentInputPartial::new(
+                string_field(parameters, "directory_path")?,
+                string_field(parameters, "regex_pattern")?,
+                string_field(parameters, "file_pattern"),
+            )
+            .with_exclude_pattern(string_field(parameters, "exclude_pattern")),
+        )),
+        "read_file" => Some(ToolInputPartial::OpenFile(OpenFileRequestPartial::new(
+            string_field(parameters, "fs_file_path")?,
+        ))),
+        "get_diagnostics" => Some(ToolInputPartial::LSPDiagnostics(
+            WorkspaceDiagnosticsPartial::new(),
+        )),
+        "execute_command" => Some(ToolInputPartial::TerminalCommand(TerminalInputPartial::new(
+            string_field(parameters, "command")?,
+        ))),
+        "ask_followup_question" => Some(ToolInputPartial::AskFollowupQuestions(
+            AskFollowupQuestionsRequest::new(string_field(parameters, "question")?),
+        )),
+        "attempt_completion" => Some(ToolInputPartial::AttemptCompletion(
+            AttemptCompletionClientRequest::new(
+                string_field(parameters, "result")?,
+                string_field(parameters, "command"),
+            ),
+        )),
+        "repo_map_generation" => Some(ToolInputPartial::RepoMapGeneration(
+            RepoMapGeneratorRequestPartial::new(string_field(parameters, "directory_path")?),
+        )),
+        "delete_file" => Some(ToolInputPartial::DeleteFile(DeleteFileInputPartial::new(
+            string_field(parameters, "fs_file_path")?,
+        ))),
+        "move_file" => Some(ToolInputPartial::MoveFile(MoveFileInputPartial::new(
+            string_field(parameters, "fs_file_path")?,
+            string_field(parameters, "new_fs_file_path")?,
+        ))),
+        "summarize_session" => Some(ToolInputPartial::SummarizeContext(
+            SummarizeContextInputPartial::new(),
+        )),
+        "show_diff" => Some(ToolInputPartial::ShowDiff(ShowDiffRequestPartial::new(
+            string_field(parameters, "fs_file_path")?,
+        ))),
+        "code_review" => Some(ToolInputPartial::CodeReview(CodeReviewRequestPartial::new(
+            string_field(parameters, "command"),
+        ))),
+        "get_outline_nodes" => Some(ToolInputPartial::GetOutlineNodes(
+            GetOutlineNodesInputPartial::new(string_field(parameters, "fs_file_path")?),
+        )),
+        "duplicate_symbol" => Some(ToolInputPartial::DuplicateSymbol(
+            DuplicateSymbolInputPartial::new(
+                string_field(parameters, "fs_file_path")?,
+                string_field(parameters, "symbol_name")?,
+                string_field(parameters, "new_name")?,
+            ),
+        )),
+        "find_symbol_definition" => Some(ToolInputPartial::FindSymbolDefinition(
+            FindSymbolDefinitionRequestPartial::new(
+                string_field(parameters, "symbol_name")?,
+                string_field(parameters, "from_file"),
+            ),
+        )),
+        "get_workspace_symbols" => Some(ToolInputPartial::GetWorkspaceSymbols(
+            GetWorkspaceSymbolsInputPartial::new(
+                string_field(parameters, "query")?,
+                parameters
+                    .get("max_results")
+                    .and_then(|value| value.as_u64())
+                    .unwrap_or(20) as usize,
+            ),
+        )),
+        "run_tests" => Some(ToolInputPartial::RunTests(RunTestsRequestPartial::new(
+            string_field(parameters, "test_filter"),
+            string_field(parameters, "framework_hint"),
+        ))),
+        _ => None,
+    }
+}
+
+/// Input for a single structured tool call request.
+pub struct StructuredToolCallInput {
+    system_message: String,
+    messages: Vec<LLMClientMessage>,
+    llm_properties: LLMProperties,
+    tools_schema: serde_json::Value,
+    cancellation_token: CancellationToken,
+}
+
+impl StructuredToolCallInput {
+    pub fn new(
+        system_message: String,
+        messages: Vec<LLMClientMessage>,
+        llm_properties: LLMProperties,
+        tools_schema: serde_json::Value,
+        cancellation_token: CancellationToken,
+    ) -> Self {
+        Self {
+            system_message,
+            messages,
+            llm_properties,
+            tools_schema,
+            cancellation_token,
+        }
+    }
+}
+
+/// Providers which understand structured function calling / JSON mode
+/// implement this instead of going through the free-form text parser.
+/// Returning `Ok(None)` signals "no structured call could be made" (e.g. the
+/// model declined to call a tool), at which point the caller should fall
+/// back to the text based parser.
+#[async_trait]
+pub trait StructuredToolCallBroker: Send + Sync {
+    async fn generate_structured_tool_call(
+        &self,
+        input: StructuredToolCallInput,
+    ) -> Result<Option<ToolInputPartial>, SymbolError>;
+}
+
+/// Default implementation which drives the request through the existing
+/// `LLMBroker`, asking the model to reply with nothing but a JSON object
+/// which matches the schema we hand it.
+pub struct LLMBrokerStructuredToolCall {
+    llm_broker: Arc<LLMBroker>,
+}
+
+impl LLMBrokerStructuredToolCall {
+    pub fn new(llm_broker: Arc<LLMBroker>) -> Self {
+        Self { llm_broker }
+    }
+}
+
+#[async_trait]
+impl StructuredToolCallBroker for LLMBrokerStructuredToolCall {
+    async fn generate_structured_tool_call(
+        &self,
+        input: StructuredToolCallInput,
+    ) -> Result<Option<ToolInputPartial>, SymbolError> {
+        let schema_instruction = format!(
+            r#"Respond with nothing but a single JSON object of the shape {{"tool": "<tool_name>", "parameters": {{...}}}} which invokes exactly one of the following tools, and does not include any other text:
+{}"#,
+            serde_json::to_string_pretty(&input.tools_schema).unwrap_or_default()
+        );
+        let system_message =
+            LLMClientMessage::system(format!("{}\n\n{}", input.system_message, schema_instruction));
+        let final_messages: Vec<_> = vec![system_message]
+            .into_iter()
+            .chain(input.messages)
+            .collect();
+
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let llm_broker = self.llm_broker.clone();
+        let llm_properties = input.llm_properties.clone();
+        let response = run_with_cancellation(
+            input.cancellation_token.clone(),
+            tokio::spawn(async move {
+                llm_broker
+                    .stream_completion(
+                        llm_properties.api_key().clone(),
+                        llm_client::clients::types::LLMClientCompletionRequest::new(
+                            llm_properties.llm().clone(),
+                            final_messages,
+                            0.2,
+                            None,
+                        ),
+                        llm_properties.provider().clone(),
+                        vec![("event_type".to_owned(), "structured_tool_use".to_owned())]
+                            .into_iter()
+                            .collect(),
+                        sender,
+                    )
+                    .await
+            }),
+        )
+        .await;
+
+        let response = match response {
+            Some(Ok(Ok(response))) => response,
+            _ => return Ok(None),
+        };
+
+        let parsed: Option<serde_json::Value> = serde_json::from_str(response.trim()).ok();
+        match parsed {
+            Some(serde_json::Value::Object(response_object)) => {
+                let tool_name = response_object.get("tool").and_then(|value| value.as_str());
+                let parameters = response_object
+                    .get("parameters")
+                    .and_then(|value| value.as_object());
+                match (tool_name, parameters) {
+                    (Some(tool_name), Some(parameters)) => {
+                        Ok(parse_structured_tool_call(tool_name, parameters))
+                    }
+                    _ => Ok(None),
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,189 @@
+//! Asks the LLM to compress the conversation so far into a short summary,
+//! which the session then substitutes for the older exchanges. This gives
+//! the agent a self-directed way to manage its own context window on long
+//! running sessions, instead of relying on automatic truncation.
+
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use llm_client::{
+    broker::LLMBroker,
+    clients::types::{LLMClientCompletionRequest, LLMClientMessage},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::agentic::{
+    symbol::identifier::LLMProperties,
+    tool::{errors::ToolError, input::ToolInput, output::ToolOutput, r#type::Tool},
+};
+
+use super::chat::{SessionChatMessage, SessionChatRole};
+
+/// The partial version of the request which the agent can invoke directly,
+/// before the conversation history and llm properties are filled in by the
+/// session layer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SummarizeContextInputPartial {}
+
+impl SummarizeContextInputPartial {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub fn to_string(&self) -> String {
+        r#"<summarize_session>
+</summarize_session>"#
+            .to_owned()
+    }
+}
+
+impl Default for SummarizeContextInputPartial {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SummarizeContextRequest {
+    previous_messages: Vec<SessionChatMessage>,
+    session_id: String,
+    exchange_id: String,
+    cancellation_token: tokio_util::sync::CancellationToken,
+    llm_properties: LLMProperties,
+}
+
+impl SummarizeContextRequest {
+    pub fn new(
+        previous_messages: Vec<SessionChatMessage>,
+        session_id: String,
+        exchange_id: String,
+        cancellation_token: tokio_util::sync::CancellationToken,
+        llm_properties: LLMProperties,
+    ) -> Self {
+        Self {
+            previous_messages,
+            session_id,
+            exchange_id,
+            cancellation_token,
+            llm_properties,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SummarizeContextResponse {
+    summary: String,
+}
+
+impl SummarizeContextResponse {
+    pub fn new(summary: String) -> Self {
+        Self { summary }
+    }
+
+    pub fn summary(&self) -> &str {
+        &self.summary
+    }
+}
+
+pub struct SummarizeContextClient {
+    llm_client: Arc<LLMBroker>,
+}
+
+impl SummarizeContextClient {
+    pub fn new(llm_client: Arc<LLMBroker>) -> Self {
+        Self { llm_client }
+    }
+
+    fn system_message(&self) -> LLMClientMessage {
+        LLMClientMessage::system(
+            r#"You are an expert software engineer summarizing an ongoing agentic coding session so it can continue with a smaller context window.
+Write a concise summary of the conversation so far covering:
+- the decisions which were made and why
+- the files which were changed and what changed in them
+- any open questions or follow up work which is still pending
+Only summarize, do not continue the task or suggest new edits. Keep the summary dense and skip pleasantries."#
+                .to_owned(),
+        )
+    }
+
+    fn user_messages(&self, context: &SummarizeContextRequest) -> Vec<LLMClientMessage> {
+        context
+            .previous_messages
+            .iter()
+            .map(|previous_message| match previous_message.role() {
+                SessionChatRole::User => {
+                    LLMClientMessage::user(previous_message.message().to_owned())
+                }
+                SessionChatRole::Assistant => {
+                    LLMClientMessage::assistant(previous_message.message().to_owned())
+                }
+                SessionChatRole::ToolOutput => LLMClientMessage::user(
+                    crate::agentic::tool::helpers::prompt_injection::wrap_untrusted_tool_output(
+                        previous_message.message(),
+                    ),
+                ),
+            })
+            .chain(std::iter::once(LLMClientMessage::user(
+                "Summarize the conversation so far as instructed.".to_owned(),
+            )))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Tool for SummarizeContextClient {
+    async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
+        let context = input.is_summarize_context()?;
+        let cancellation_token = context.cancellation_token.clone();
+        let session_id = context.session_id.to_owned();
+        let exchange_id = context.exchange_id.to_owned();
+        let llm_properties = context.llm_properties.clone();
+
+        let mut messages = vec![self.system_message()];
+        messages.extend(self.user_messages(&context));
+
+        let request =
+            LLMClientCompletionRequest::new(llm_properties.llm().clone(), messages, 0.2, None);
+
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let response = crate::agentic::tool::helpers::cancellation_future::run_with_cancellation(
+            cancellation_token,
+            self.llm_client.stream_completion(
+                llm_properties.api_key().clone(),
+                request,
+                llm_properties.provider().clone(),
+                vec![
+                    ("event_type".to_owned(), "summarize_session".to_owned()),
+                    ("root_id".to_owned(), session_id),
+                    ("exchange_id".to_owned(), exchange_id),
+                ]
+                .into_iter()
+                .collect(),
+                sender,
+            ),
+        )
+        .await;
+
+        match response {
+            Some(Ok(summary)) => Ok(ToolOutput::summarize_context(
+                SummarizeContextResponse::new(summary),
+            )),
+            _ => Err(ToolError::RetriesExhausted),
+        }
+    }
+
+    fn tool_description(&self) -> String {
+        r#"### summarize_session
+Request to summarize the conversation so far and replace the older turns with the summary, keeping only the summary and the most recent turns.
+Use this on very long running sessions when the context is getting large and you want to keep going without losing track of the decisions made, files changed, and open questions so far."#
+            .to_owned()
+    }
+
+    fn tool_input_format(&self) -> String {
+        r#"Usage:
+<summarize_session>
+</summarize_session>
+"#
+        .to_owned()
+    }
+}
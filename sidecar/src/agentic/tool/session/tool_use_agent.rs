@@ -18,7 +18,10 @@ use crate::agentic::{
         helpers::cancellation_future::run_with_cancellation,
         input::ToolInputPartial,
         lsp::{
-            file_diagnostics::WorkspaceDiagnosticsPartial, list_files::ListFilesInput,
+            delete_file::DeleteFileInputPartial,
+            duplicate_symbol::DuplicateSymbolInputPartial,
+            file_diagnostics::WorkspaceDiagnosticsPartial,
+            list_files::ListFilesInput, move_file::MoveFileInputPartial,
             open_file::OpenFileRequestPartial, search_file::SearchFileContentInputPartial,
         },
         r#type::ToolType,
@@ -31,36 +34,91 @@ use crate::agentic::{
 use super::{
     ask_followup_question::AskFollowupQuestionsRequest,
     attempt_completion::AttemptCompletionClientRequest, chat::SessionChatMessage,
+    code_review::CodeReviewRequestPartial,
+    find_symbol_definition::FindSymbolDefinitionRequestPartial, show_diff::ShowDiffRequestPartial,
+    structured_tool_use::{tools_json_schema, StructuredToolCallBroker, StructuredToolCallInput},
+    summarize_context::SummarizeContextInputPartial,
+    workspace_roots::WorkspaceRoot,
 };
 
+/// Default token budget handed to `Session::state_of_the_world_header` when
+/// assembling each tool-use iteration's prompt.
+pub const STATE_OF_THE_WORLD_TOKEN_BUDGET: usize = 400;
+
+/// Works out how much of the state-of-the-world header a given model can
+/// afford, scaling with the model's context window instead of always
+/// spending the same fixed budget - a model with a much larger context
+/// window (eg. Gemini's 1M tokens) can afford a much richer header than one
+/// with an 8k window, without ever dropping below the historical default.
+pub fn state_of_the_world_token_budget(llm_type: &llm_client::clients::types::LLMType) -> usize {
+    (llm_type.context_window() / 100).max(STATE_OF_THE_WORLD_TOKEN_BUDGET)
+}
+
+/// Whether a stream that was cut off mid-way through a tool decision is
+/// worth resuming with a continuation call rather than either giving up
+/// immediately or discarding the partial answer and retrying from scratch.
+/// We only ever try this once per `invoke` call, only for the network-level
+/// errors that a retry can plausibly fix, and only when we actually have
+/// something to resume from.
+fn should_attempt_continuation(
+    stream_error: &llm_client::clients::types::LLMClientError,
+    already_attempted_continuation: bool,
+    partial_response: &str,
+) -> bool {
+    !already_attempted_continuation
+        && !partial_response.is_empty()
+        && matches!(
+            stream_error,
+            llm_client::clients::types::LLMClientError::ReqwestError(_)
+        )
+}
+
 #[derive(Clone)]
 pub struct ToolUseAgentInput {
     // pass in the messages
     session_messages: Vec<SessionChatMessage>,
     tool_descriptions: Vec<String>,
+    // kept alongside `tool_descriptions` so the structured tool call path
+    // can build a JSON schema without having to re-derive tool types from
+    // free-form description strings
+    tool_types: Vec<ToolType>,
     pending_spawned_process_output: Option<String>,
     symbol_event_message_properties: SymbolEventMessageProperties,
+    // regenerated fresh by the caller on every iteration from `Session`
+    // state (edited files, open files, diagnostics, last terminal output)
+    // rather than accumulated as a message
+    state_of_the_world: Option<String>,
 }
 
 impl ToolUseAgentInput {
     pub fn new(
         session_messages: Vec<SessionChatMessage>,
         tool_descriptions: Vec<String>,
+        tool_types: Vec<ToolType>,
         pending_spawned_process_output: Option<String>,
         symbol_event_message_properties: SymbolEventMessageProperties,
     ) -> Self {
         Self {
             session_messages,
             tool_descriptions,
+            tool_types,
             pending_spawned_process_output,
             symbol_event_message_properties,
+            state_of_the_world: None,
         }
     }
+
+    pub fn with_state_of_the_world(mut self, state_of_the_world: String) -> Self {
+        self.state_of_the_world = Some(state_of_the_world);
+        self
+    }
 }
 
 #[derive(Debug)]
 pub enum ToolUseAgentOutput {
-    Success((ToolInputPartial, String)),
+    // (tool input, thinking, prompt cache hit tokens for this turn if the
+    // provider reports them)
+    Success((ToolInputPartial, String, Option<u32>)),
     Failure(String),
 }
 
@@ -70,6 +128,14 @@ pub struct ToolUseAgent {
     working_directory: String,
     operating_system: String,
     shell: String,
+    // when present and the model backing this request supports native
+    // function calling, we ask for a structured tool call first instead of
+    // going straight to the XML-ish text parser
+    structured_tool_call_broker: Option<Arc<dyn StructuredToolCallBroker>>,
+    // the other folders open alongside `working_directory` in a multi-root
+    // workspace, so the agent knows to prefix paths with a root name instead
+    // of assuming everything lives under the primary root
+    additional_roots: Vec<WorkspaceRoot>,
 }
 
 impl ToolUseAgent {
@@ -84,14 +150,40 @@ impl ToolUseAgent {
             working_directory,
             operating_system,
             shell,
+            structured_tool_call_broker: None,
+            additional_roots: Vec::new(),
         }
     }
 
+    pub fn with_structured_tool_call_broker(
+        mut self,
+        structured_tool_call_broker: Arc<dyn StructuredToolCallBroker>,
+    ) -> Self {
+        self.structured_tool_call_broker = Some(structured_tool_call_broker);
+        self
+    }
+
+    pub fn with_additional_roots(mut self, additional_roots: Vec<WorkspaceRoot>) -> Self {
+        self.additional_roots = additional_roots;
+        self
+    }
+
     fn system_message(&self, context: &ToolUseAgentInput) -> String {
         let tool_descriptions = context.tool_descriptions.join("\n");
         let working_directory = self.working_directory.to_owned();
         let operating_system = self.operating_system.to_owned();
         let default_shell = self.shell.to_owned();
+        let additional_roots_section = match crate::agentic::tool::session::workspace_roots::WorkspaceRoots::new(
+            self.working_directory.clone(),
+            self.additional_roots.clone(),
+        )
+        .render_additional_roots()
+        {
+            Some(rendered) => format!(
+                "\n- This is a multi-root workspace. In addition to the current working directory ({working_directory}), the following roots are also open, so file paths under them must be passed in full rather than assumed to live under {working_directory}:\n{rendered}",
+            ),
+            None => String::new(),
+        };
         format!(
             r#"You are SOTA-agent, a highly skilled state of the art agentic software engineer with extensive knowledge in all programming languages, frameworks, design patterns, and best practices. You are always correct and through with your changes.
 ====
@@ -163,7 +255,7 @@ CAPABILITIES
 
 RULES
 
-- Your current working directory is: {working_directory}
+- Your current working directory is: {working_directory}{additional_roots_section}
 - You cannot \`cd\` into a different directory to complete a task. You are stuck operating from '{working_directory}', so be sure to pass in the correct 'path' parameter when using tools that require a path.
 - Do not use the ~ character or $HOME to refer to the home directory.
 - If you have executed some terminal commands before which are long running, the user will show you that output in <executed_terminal_output></executed_terminal_output> section. This way you can stay on top of long running commands or in case you missed the output from before.
@@ -215,12 +307,17 @@ You accomplish a given task iteratively, breaking it down into clear steps and w
     ) -> Result<ToolUseAgentOutput, SymbolError> {
         // Now over here we want to trigger the tool agent recursively and also parse out the output as required
         // this will involve some kind of magic because for each tool type we want to be sure about how we are parsing the output but it should not be too hard to make that happen
-        let system_message = LLMClientMessage::system(self.system_message(&input));
+        let system_message_text = self.system_message(&input);
+        // the system prompt never changes between loop iterations, so it's
+        // always worth marking as a cache point
+        let system_message = LLMClientMessage::system(system_message_text.clone()).cache_point();
         // grab the previous messages as well
         let llm_properties = input
             .symbol_event_message_properties
             .llm_properties()
             .clone();
+        let cancellation_token = input.symbol_event_message_properties.cancellation_token();
+        let tool_types = input.tool_types.clone();
         let mut previous_messages = input
             .session_messages
             .into_iter()
@@ -233,17 +330,35 @@ You accomplish a given task iteratively, breaking it down into clear steps and w
                     SessionChatRole::Assistant => {
                         LLMClientMessage::assistant(session_message.message().to_owned())
                     }
+                    SessionChatRole::ToolOutput => LLMClientMessage::user(
+                        crate::agentic::tool::helpers::prompt_injection::wrap_untrusted_tool_output(
+                            session_message.message(),
+                        ),
+                    ),
                 }
             })
             .collect::<Vec<_>>();
 
         // we want to modify 2 things here, the last user message and the one before
-        // should be cached as well
-        previous_messages.last_mut().map(|previous_message| {
-            if previous_message.is_human_message() {
-                previous_message.is_cache_point();
-            }
-        });
+        // should be cached as well, so everything except the freshly appended
+        // tail of the conversation is served from the provider's prompt cache
+        // on the next iteration of this loop
+        let cacheable_from_the_end = if previous_messages
+            .last()
+            .map(|message| message.is_human_message())
+            .unwrap_or_default()
+        {
+            2
+        } else {
+            0
+        };
+        let previous_messages_len = previous_messages.len();
+        for message in previous_messages
+            .iter_mut()
+            .skip(previous_messages_len.saturating_sub(cacheable_from_the_end))
+        {
+            message.set_cache_point();
+        }
         if previous_messages
             .last()
             .map(|last_message| last_message.is_human_message())
@@ -257,6 +372,9 @@ You accomplish a given task iteratively, breaking it down into clear steps and w
                     pending_spawned_process_output
                 )));
             }
+            if let Some(state_of_the_world) = input.state_of_the_world {
+                previous_messages.push(LLMClientMessage::user(state_of_the_world));
+            }
         }
         let root_request_id = input
             .symbol_event_message_properties
@@ -264,121 +382,215 @@ You accomplish a given task iteratively, breaking it down into clear steps and w
             .to_owned();
         let ui_sender = input.symbol_event_message_properties.ui_sender();
         let exchange_id = input.symbol_event_message_properties.request_id_str();
-        let final_messages: Vec<_> = vec![system_message]
+
+        // for models which support native function calling we would rather
+        // ask for a structured tool call up front than risk a reparse loop
+        // on free-form XML-ish output
+        if llm_properties.llm().supports_native_tool_calling() {
+            if let Some(structured_tool_call_broker) = self.structured_tool_call_broker.as_ref() {
+                let structured_input = StructuredToolCallInput::new(
+                    system_message_text.clone(),
+                    previous_messages.clone(),
+                    llm_properties.clone(),
+                    tools_json_schema(&tool_types),
+                    cancellation_token.clone(),
+                );
+                match structured_tool_call_broker
+                    .generate_structured_tool_call(structured_input)
+                    .await
+                {
+                    Ok(Some(tool_input_partial)) => {
+                        return Ok(ToolUseAgentOutput::Success((
+                            tool_input_partial,
+                            "".to_owned(),
+                            // native tool calling does not go through the
+                            // streaming loop below, so there is no cache-hit
+                            // count to report here
+                            None,
+                        )));
+                    }
+                    // the broker couldn't make a structured call (e.g. the
+                    // model declined to use a tool) so fall back to the text
+                    // parser below
+                    Ok(None) => {}
+                    // an actual failure (including cancellation) is not the
+                    // same as "no structured call available" and should not
+                    // trigger a fallback to a second, real LLM call
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+
+        let mut final_messages: Vec<_> = vec![system_message]
             .into_iter()
             .chain(previous_messages)
             .collect();
 
-        let cancellation_token = input.symbol_event_message_properties.cancellation_token();
+        // when a stream gets interrupted partway through (a network blip) we
+        // seed the next attempt's generator with whatever we had already
+        // parsed out, so a continuation reply from the provider is stitched
+        // onto the tokens we already paid for instead of starting the tool
+        // decision over from scratch. We only ever try this once - if the
+        // continuation attempt also fails we fall back to whatever partial
+        // output we have.
+        let mut seed_text: Option<String> = None;
+        let mut attempted_continuation = false;
 
-        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
-        let cloned_llm_client = self.llm_client.clone();
-        let cloned_root_request_id = root_request_id.to_owned();
-        let response = run_with_cancellation(
-            cancellation_token.clone(),
-            tokio::spawn(async move {
-                cloned_llm_client
-                    .stream_completion(
-                        llm_properties.api_key().clone(),
-                        LLMClientCompletionRequest::new(
-                            llm_properties.llm().clone(),
-                            final_messages,
-                            0.2,
-                            None,
-                        ),
-                        llm_properties.provider().clone(),
-                        vec![
-                            ("event_type".to_owned(), "tool_use".to_owned()),
-                            ("root_id".to_owned(), cloned_root_request_id),
-                        ]
-                        .into_iter()
-                        .collect(),
-                        sender,
-                    )
-                    .await
-            }),
-        );
-
-        let mut delta_receiver = tokio_stream::wrappers::UnboundedReceiverStream::new(receiver);
-        let (tool_update_sender, tool_update_receiver) = tokio::sync::mpsc::unbounded_channel();
-        let mut tool_use_generator = ToolUseGenerator::new(tool_update_sender);
+        loop {
+            let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+            let cloned_llm_client = self.llm_client.clone();
+            let cloned_root_request_id = root_request_id.to_owned();
+            let cloned_llm_properties = llm_properties.clone();
+            let cloned_messages = final_messages.clone();
+            let response = run_with_cancellation(
+                cancellation_token.clone(),
+                tokio::spawn(async move {
+                    cloned_llm_client
+                        .stream_completion(
+                            cloned_llm_properties.api_key().clone(),
+                            LLMClientCompletionRequest::new(
+                                cloned_llm_properties.llm().clone(),
+                                cloned_messages,
+                                0.2,
+                                None,
+                            ),
+                            cloned_llm_properties.provider().clone(),
+                            vec![
+                                ("event_type".to_owned(), "tool_use".to_owned()),
+                                ("root_id".to_owned(), cloned_root_request_id),
+                            ]
+                            .into_iter()
+                            .collect(),
+                            sender,
+                        )
+                        .await
+                }),
+            );
 
-        // run this in a background thread for now
-        let cloned_cancellation_token = cancellation_token.clone();
-        let delta_updater_task = tokio::spawn(async move {
-            while let Some(Some(stream_msg)) =
-                run_with_cancellation(cloned_cancellation_token.clone(), delta_receiver.next())
-                    .await
-            {
-                let delta = stream_msg.delta();
-                if let Some(delta) = delta {
-                    tool_use_generator.add_delta(delta);
-                }
+            let mut delta_receiver =
+                tokio_stream::wrappers::UnboundedReceiverStream::new(receiver);
+            let (tool_update_sender, tool_update_receiver) = tokio::sync::mpsc::unbounded_channel();
+            let mut tool_use_generator = ToolUseGenerator::new(tool_update_sender);
+            if let Some(seed) = seed_text.take() {
+                tool_use_generator.add_delta(&seed);
             }
-            // for forcing a flush, we append a \n on our own to the answer up until now
-            // so that there are no remaining lines
-            tool_use_generator.flush_answer();
-            let thinking_for_tool = tool_use_generator.thinking;
-            let tool_input_partial = tool_use_generator.tool_input_partial;
-            let complete_response = tool_use_generator.answer_up_until_now;
-            (thinking_for_tool, tool_input_partial, complete_response)
-        });
 
-        // now take the tool_receiver and try sending them over as a ui_sender
-        // event
-        let mut tool_update_receiver =
-            tokio_stream::wrappers::UnboundedReceiverStream::new(tool_update_receiver);
-        while let Some(Some(tool_update)) =
-            run_with_cancellation(cancellation_token.clone(), tool_update_receiver.next()).await
-        {
-            match tool_update {
-                ToolBlockEvent::ThinkingFull(thinking_up_until_now) => {
-                    let _ = ui_sender.clone().send(UIEventWithID::tool_thinking(
-                        root_request_id.to_owned(),
-                        exchange_id.to_owned(),
-                        thinking_up_until_now,
-                    ));
-                }
-                ToolBlockEvent::NoToolFound(full_output) => {
-                    let _ = ui_sender.clone().send(UIEventWithID::tool_not_found(
-                        root_request_id.to_owned(),
-                        exchange_id.to_owned(),
-                        full_output,
-                    ));
-                }
-                ToolBlockEvent::ToolFound(tool_found) => {
-                    let _ = ui_sender.clone().send(UIEventWithID::tool_found(
-                        root_request_id.to_owned(),
-                        exchange_id.to_owned(),
-                        tool_found,
-                    ));
+            // run this in a background thread for now
+            let cloned_cancellation_token = cancellation_token.clone();
+            let delta_updater_task = tokio::spawn(async move {
+                let mut cache_hit_tokens = None;
+                while let Some(Some(stream_msg)) =
+                    run_with_cancellation(cloned_cancellation_token.clone(), delta_receiver.next())
+                        .await
+                {
+                    if let Some(hit_tokens) = stream_msg.cache_hit_tokens() {
+                        cache_hit_tokens = Some(hit_tokens);
+                    }
+                    let delta = stream_msg.delta();
+                    if let Some(delta) = delta {
+                        tool_use_generator.add_delta(delta);
+                    }
                 }
-                ToolBlockEvent::ToolParameters(tool_parameters_update) => {
-                    let _ = ui_sender.clone().send(UIEventWithID::tool_parameter_found(
-                        root_request_id.to_owned(),
-                        exchange_id.to_owned(),
-                        tool_parameters_update,
-                    ));
+                // for forcing a flush, we append a \n on our own to the answer up until now
+                // so that there are no remaining lines
+                tool_use_generator.flush_answer();
+                let thinking_for_tool = tool_use_generator.thinking;
+                let tool_input_partial = tool_use_generator.tool_input_partial;
+                let complete_response = tool_use_generator.answer_up_until_now;
+                (
+                    thinking_for_tool,
+                    tool_input_partial,
+                    complete_response,
+                    cache_hit_tokens,
+                )
+            });
+
+            // now take the tool_receiver and try sending them over as a ui_sender
+            // event
+            let mut tool_update_receiver =
+                tokio_stream::wrappers::UnboundedReceiverStream::new(tool_update_receiver);
+            while let Some(Some(tool_update)) =
+                run_with_cancellation(cancellation_token.clone(), tool_update_receiver.next())
+                    .await
+            {
+                match tool_update {
+                    ToolBlockEvent::ThinkingFull(thinking_up_until_now) => {
+                        let _ = ui_sender.clone().send(UIEventWithID::tool_thinking(
+                            root_request_id.to_owned(),
+                            exchange_id.to_owned(),
+                            thinking_up_until_now,
+                        ));
+                    }
+                    ToolBlockEvent::NoToolFound(full_output) => {
+                        let _ = ui_sender.clone().send(UIEventWithID::tool_not_found(
+                            root_request_id.to_owned(),
+                            exchange_id.to_owned(),
+                            full_output,
+                        ));
+                    }
+                    ToolBlockEvent::ToolFound(tool_found) => {
+                        let _ = ui_sender.clone().send(UIEventWithID::tool_found(
+                            root_request_id.to_owned(),
+                            exchange_id.to_owned(),
+                            tool_found,
+                        ));
+                    }
+                    ToolBlockEvent::ToolParameters(tool_parameters_update) => {
+                        let _ = ui_sender.clone().send(UIEventWithID::tool_parameter_found(
+                            root_request_id.to_owned(),
+                            exchange_id.to_owned(),
+                            tool_parameters_update,
+                        ));
+                    }
                 }
             }
-        }
 
-        if let Ok((thinking_for_tool, tool_input_partial, complete_response)) =
-            delta_updater_task.await
-        {
-            let final_output = match tool_input_partial {
-                Some(tool_input_partial) => Ok(ToolUseAgentOutput::Success((
-                    tool_input_partial,
-                    thinking_for_tool,
-                ))),
-                None => Ok(ToolUseAgentOutput::Failure(complete_response)),
+            let Ok((thinking_for_tool, tool_input_partial, complete_response, cache_hit_tokens)) =
+                delta_updater_task.await
+            else {
+                return Err(SymbolError::CancelledResponseStream);
             };
+
             match response.await {
-                Some(_) => final_output,
-                None => Err(SymbolError::CancelledResponseStream),
+                // the request was interrupted partway (e.g. a dropped
+                // connection); if we have not already tried a continuation
+                // and there is something worth resuming from, retry with the
+                // partial answer folded back in as an assistant prefix
+                // instead of throwing the whole attempt away
+                Some(Ok(Err(stream_error))) => {
+                    if should_attempt_continuation(
+                        &stream_error,
+                        attempted_continuation,
+                        &complete_response,
+                    ) {
+                        attempted_continuation = true;
+                        final_messages.push(LLMClientMessage::assistant(
+                            complete_response.to_owned(),
+                        ));
+                        seed_text = Some(complete_response);
+                        continue;
+                    }
+                    return match tool_input_partial {
+                        Some(tool_input_partial) => Ok(ToolUseAgentOutput::Success((
+                            tool_input_partial,
+                            thinking_for_tool,
+                            cache_hit_tokens,
+                        ))),
+                        None => Ok(ToolUseAgentOutput::Failure(complete_response)),
+                    };
+                }
+                Some(_) => {
+                    return match tool_input_partial {
+                        Some(tool_input_partial) => Ok(ToolUseAgentOutput::Success((
+                            tool_input_partial,
+                            thinking_for_tool,
+                            cache_hit_tokens,
+                        ))),
+                        None => Ok(ToolUseAgentOutput::Failure(complete_response)),
+                    };
+                }
+                None => return Err(SymbolError::CancelledResponseStream),
             }
-        } else {
-            Err(SymbolError::CancelledResponseStream)
         }
     }
 }
@@ -395,14 +607,19 @@ enum ToolBlockStatus {
     ToolFound,
     // these are all the different attributes of the tool input
     FilePathFound,
+    NewFilePathFound,
+    SymbolNameFound,
+    NewNameFound,
     InstructionFound,
     DirectoryPathFound,
     RecursiveFound,
     RegexPatternFound,
     FilePatternFound,
+    ExcludePatternFound,
     CommandFound,
     QuestionFound,
     ResultFound,
+    FromFileFound,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -428,14 +645,19 @@ struct ToolUseGenerator {
     thinking: String,
     tool_type_possible: Option<ToolType>,
     fs_file_path: Option<String>,
+    new_fs_file_path: Option<String>,
+    symbol_name: Option<String>,
+    new_name: Option<String>,
     instruction: Option<String>,
     directory_path: Option<String>,
     recursive: Option<bool>,
     regex_pattern_found: Option<String>,
     file_pattern: Option<String>,
+    exclude_pattern: Option<String>,
     command: Option<String>,
     question: Option<String>,
     result: Option<String>,
+    from_file: Option<String>,
     tool_input_partial: Option<ToolInputPartial>,
     sender: tokio::sync::mpsc::UnboundedSender<ToolBlockEvent>,
 }
@@ -449,14 +671,19 @@ impl ToolUseGenerator {
             thinking: "".to_owned(),
             tool_type_possible: None,
             fs_file_path: None,
+            new_fs_file_path: None,
+            symbol_name: None,
+            new_name: None,
             instruction: None,
             directory_path: None,
             recursive: None,
             regex_pattern_found: None,
             file_pattern: None,
+            exclude_pattern: None,
             command: None,
             question: None,
             result: None,
+            from_file: None,
             tool_input_partial: None,
             sender,
         }
@@ -578,11 +805,61 @@ impl ToolUseGenerator {
                             .send(ToolBlockEvent::ToolFound(ToolType::RepoMapGeneration));
                         // these are the ending condition over here
                         // we grab all the fields which are required and then return them back over here
+                    } else if answer_line_at_index == "<delete_file>" {
+                        self.tool_block_status = ToolBlockStatus::ToolFound;
+                        self.tool_type_possible = Some(ToolType::DeleteFile);
+                        let _ = self
+                            .sender
+                            .send(ToolBlockEvent::ToolFound(ToolType::DeleteFile));
+                    } else if answer_line_at_index == "<move_file>" {
+                        self.tool_block_status = ToolBlockStatus::ToolFound;
+                        self.tool_type_possible = Some(ToolType::MoveFile);
+                        let _ = self
+                            .sender
+                            .send(ToolBlockEvent::ToolFound(ToolType::MoveFile));
+                    } else if answer_line_at_index == "<duplicate_symbol>" {
+                        self.tool_block_status = ToolBlockStatus::ToolFound;
+                        self.tool_type_possible = Some(ToolType::DuplicateSymbol);
+                        let _ = self
+                            .sender
+                            .send(ToolBlockEvent::ToolFound(ToolType::DuplicateSymbol));
+                    } else if answer_line_at_index == "<summarize_session>" {
+                        self.tool_block_status = ToolBlockStatus::ToolFound;
+                        self.tool_type_possible = Some(ToolType::SummarizeContext);
+                        let _ = self
+                            .sender
+                            .send(ToolBlockEvent::ToolFound(ToolType::SummarizeContext));
+                    } else if answer_line_at_index == "<show_diff>" {
+                        self.tool_block_status = ToolBlockStatus::ToolFound;
+                        self.tool_type_possible = Some(ToolType::ShowDiff);
+                        let _ = self
+                            .sender
+                            .send(ToolBlockEvent::ToolFound(ToolType::ShowDiff));
+                    } else if answer_line_at_index == "<code_review>" {
+                        self.tool_block_status = ToolBlockStatus::ToolFound;
+                        self.tool_type_possible = Some(ToolType::CodeReview);
+                        let _ = self
+                            .sender
+                            .send(ToolBlockEvent::ToolFound(ToolType::CodeReview));
+                    } else if answer_line_at_index == "<find_symbol_definition>" {
+                        self.tool_block_status = ToolBlockStatus::ToolFound;
+                        self.tool_type_possible = Some(ToolType::FindSymbolDefinition);
+                        let _ = self
+                            .sender
+                            .send(ToolBlockEvent::ToolFound(ToolType::FindSymbolDefinition));
                     }
                 }
                 ToolBlockStatus::ToolFound => {
                     if answer_line_at_index == "<fs_file_path>" {
                         self.tool_block_status = ToolBlockStatus::FilePathFound;
+                    } else if answer_line_at_index == "<new_fs_file_path>" {
+                        self.tool_block_status = ToolBlockStatus::NewFilePathFound;
+                    } else if answer_line_at_index == "<symbol_name>" {
+                        self.tool_block_status = ToolBlockStatus::SymbolNameFound;
+                    } else if answer_line_at_index == "<from_file>" {
+                        self.tool_block_status = ToolBlockStatus::FromFileFound;
+                    } else if answer_line_at_index == "<new_name>" {
+                        self.tool_block_status = ToolBlockStatus::NewNameFound;
                     } else if answer_line_at_index == "<instruction>" {
                         self.tool_block_status = ToolBlockStatus::InstructionFound;
                     } else if answer_line_at_index == "<directory_path>" {
@@ -593,6 +870,8 @@ impl ToolUseGenerator {
                         self.tool_block_status = ToolBlockStatus::RegexPatternFound;
                     } else if answer_line_at_index == "<file_pattern>" {
                         self.tool_block_status = ToolBlockStatus::FilePatternFound;
+                    } else if answer_line_at_index == "<exclude_pattern>" {
+                        self.tool_block_status = ToolBlockStatus::ExcludePatternFound;
                     } else if answer_line_at_index == "<command>" {
                         self.tool_block_status = ToolBlockStatus::CommandFound;
                     } else if answer_line_at_index == "<question>" {
@@ -612,7 +891,8 @@ impl ToolUseGenerator {
                                             directory_path,
                                             regex_pattern,
                                             self.file_pattern.clone(),
-                                        ),
+                                        )
+                                        .with_exclude_pattern(self.exclude_pattern.clone()),
                                     ));
                             }
                             _ => {}
@@ -657,6 +937,12 @@ impl ToolUseGenerator {
                             WorkspaceDiagnosticsPartial::new(),
                         ));
                         self.tool_type_possible = None;
+                    } else if answer_line_at_index == "</summarize_session>" {
+                        self.tool_block_status = ToolBlockStatus::NoBlock;
+                        self.tool_input_partial = Some(ToolInputPartial::SummarizeContext(
+                            SummarizeContextInputPartial::new(),
+                        ));
+                        self.tool_type_possible = None;
                     } else if answer_line_at_index == "</execute_command>" {
                         self.tool_block_status = ToolBlockStatus::NoBlock;
                         match self.command.clone() {
@@ -707,6 +993,85 @@ impl ToolUseGenerator {
                             _ => {}
                         }
                         self.tool_type_possible = None;
+                    } else if answer_line_at_index == "</delete_file>" {
+                        self.tool_block_status = ToolBlockStatus::NoBlock;
+                        match self.fs_file_path.clone() {
+                            Some(fs_file_path) => {
+                                self.tool_input_partial = Some(ToolInputPartial::DeleteFile(
+                                    DeleteFileInputPartial::new(fs_file_path),
+                                ));
+                            }
+                            _ => {}
+                        }
+                        self.tool_type_possible = None;
+                    } else if answer_line_at_index == "</show_diff>" {
+                        self.tool_block_status = ToolBlockStatus::NoBlock;
+                        match self.fs_file_path.clone() {
+                            Some(fs_file_path) => {
+                                self.tool_input_partial = Some(ToolInputPartial::ShowDiff(
+                                    ShowDiffRequestPartial::new(fs_file_path),
+                                ));
+                            }
+                            _ => {}
+                        }
+                        self.tool_type_possible = None;
+                    } else if answer_line_at_index == "</code_review>" {
+                        self.tool_block_status = ToolBlockStatus::NoBlock;
+                        // the test command is optional, so unlike the other
+                        // tools we always produce a `ToolInputPartial` here
+                        // even when `self.command` was never set
+                        self.tool_input_partial = Some(ToolInputPartial::CodeReview(
+                            CodeReviewRequestPartial::new(self.command.clone()),
+                        ));
+                        self.tool_type_possible = None;
+                    } else if answer_line_at_index == "</move_file>" {
+                        self.tool_block_status = ToolBlockStatus::NoBlock;
+                        match (self.fs_file_path.clone(), self.new_fs_file_path.clone()) {
+                            (Some(fs_file_path), Some(new_fs_file_path)) => {
+                                self.tool_input_partial = Some(ToolInputPartial::MoveFile(
+                                    MoveFileInputPartial::new(fs_file_path, new_fs_file_path),
+                                ));
+                            }
+                            _ => {}
+                        }
+                        self.tool_type_possible = None;
+                    } else if answer_line_at_index == "</duplicate_symbol>" {
+                        self.tool_block_status = ToolBlockStatus::NoBlock;
+                        match (
+                            self.fs_file_path.clone(),
+                            self.symbol_name.clone(),
+                            self.new_name.clone(),
+                        ) {
+                            (Some(fs_file_path), Some(symbol_name), Some(new_name)) => {
+                                self.tool_input_partial = Some(ToolInputPartial::DuplicateSymbol(
+                                    DuplicateSymbolInputPartial::new(
+                                        fs_file_path,
+                                        symbol_name,
+                                        new_name,
+                                    ),
+                                ));
+                            }
+                            _ => {}
+                        }
+                        self.tool_type_possible = None;
+                    } else if answer_line_at_index == "</find_symbol_definition>" {
+                        self.tool_block_status = ToolBlockStatus::NoBlock;
+                        // `from_file` is optional, so a `ToolInputPartial` is
+                        // produced whenever `symbol_name` was set, even when
+                        // `from_file` never was
+                        match self.symbol_name.clone() {
+                            Some(symbol_name) => {
+                                self.tool_input_partial =
+                                    Some(ToolInputPartial::FindSymbolDefinition(
+                                        FindSymbolDefinitionRequestPartial::new(
+                                            symbol_name,
+                                            self.from_file.clone(),
+                                        ),
+                                    ));
+                            }
+                            None => {}
+                        }
+                        self.tool_type_possible = None;
                     }
                 }
                 ToolBlockStatus::FilePathFound => {
@@ -723,6 +1088,62 @@ impl ToolUseGenerator {
                             }));
                     }
                 }
+                ToolBlockStatus::NewFilePathFound => {
+                    if answer_line_at_index == "</new_fs_file_path>" {
+                        self.tool_block_status = ToolBlockStatus::ToolFound;
+                    } else {
+                        self.new_fs_file_path = Some(answer_line_at_index.to_owned());
+                        let _ = self
+                            .sender
+                            .send(ToolBlockEvent::ToolParameters(ToolParameters {
+                                field_name: "new_fs_file_path".to_owned(),
+                                field_content_up_until_now: answer_line_at_index.to_owned(),
+                                field_content_delta: answer_line_at_index.to_owned(),
+                            }));
+                    }
+                }
+                ToolBlockStatus::SymbolNameFound => {
+                    if answer_line_at_index == "</symbol_name>" {
+                        self.tool_block_status = ToolBlockStatus::ToolFound;
+                    } else {
+                        self.symbol_name = Some(answer_line_at_index.to_owned());
+                        let _ = self
+                            .sender
+                            .send(ToolBlockEvent::ToolParameters(ToolParameters {
+                                field_name: "symbol_name".to_owned(),
+                                field_content_up_until_now: answer_line_at_index.to_owned(),
+                                field_content_delta: answer_line_at_index.to_owned(),
+                            }));
+                    }
+                }
+                ToolBlockStatus::FromFileFound => {
+                    if answer_line_at_index == "</from_file>" {
+                        self.tool_block_status = ToolBlockStatus::ToolFound;
+                    } else {
+                        self.from_file = Some(answer_line_at_index.to_owned());
+                        let _ = self
+                            .sender
+                            .send(ToolBlockEvent::ToolParameters(ToolParameters {
+                                field_name: "from_file".to_owned(),
+                                field_content_up_until_now: answer_line_at_index.to_owned(),
+                                field_content_delta: answer_line_at_index.to_owned(),
+                            }));
+                    }
+                }
+                ToolBlockStatus::NewNameFound => {
+                    if answer_line_at_index == "</new_name>" {
+                        self.tool_block_status = ToolBlockStatus::ToolFound;
+                    } else {
+                        self.new_name = Some(answer_line_at_index.to_owned());
+                        let _ = self
+                            .sender
+                            .send(ToolBlockEvent::ToolParameters(ToolParameters {
+                                field_name: "new_name".to_owned(),
+                                field_content_up_until_now: answer_line_at_index.to_owned(),
+                                field_content_delta: answer_line_at_index.to_owned(),
+                            }));
+                    }
+                }
                 ToolBlockStatus::InstructionFound => {
                     if answer_line_at_index == "</instruction>" {
                         self.tool_block_status = ToolBlockStatus::ToolFound;
@@ -816,6 +1237,20 @@ impl ToolUseGenerator {
                             }));
                     }
                 }
+                ToolBlockStatus::ExcludePatternFound => {
+                    if answer_line_at_index == "</exclude_pattern>" {
+                        self.tool_block_status = ToolBlockStatus::ToolFound;
+                    } else {
+                        self.exclude_pattern = Some(answer_line_at_index.to_owned());
+                        let _ = self
+                            .sender
+                            .send(ToolBlockEvent::ToolParameters(ToolParameters {
+                                field_name: "exclude_pattern".to_owned(),
+                                field_content_up_until_now: answer_line_at_index.to_owned(),
+                                field_content_delta: answer_line_at_index.to_owned(),
+                            }));
+                    }
+                }
                 ToolBlockStatus::CommandFound => {
                     if answer_line_at_index == "</command>" {
                         self.tool_block_status = ToolBlockStatus::ToolFound;
@@ -916,7 +1351,203 @@ fn get_last_newline_line_number(s: &str) -> Option<usize> {
 
 #[cfg(test)]
 mod tests {
-    use super::ToolUseGenerator;
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use llm_client::{
+        broker::LLMBroker,
+        clients::types::{LLMClientError, LLMType},
+        config::LLMBrokerConfiguration,
+        provider::{LLMProvider, LLMProviderAPIKeys, OpenAIProvider},
+    };
+
+    use crate::agentic::{
+        symbol::{
+            errors::SymbolError,
+            events::{input::SymbolEventRequestId, message_event::SymbolEventMessageProperties},
+            identifier::LLMProperties,
+        },
+        tool::{
+            input::ToolInputPartial, lsp::open_file::OpenFileRequestPartial, r#type::ToolType,
+        },
+    };
+
+    use super::{
+        should_attempt_continuation, ToolUseAgent, ToolUseAgentInput, ToolUseAgentOutput,
+        ToolUseGenerator,
+    };
+    use crate::agentic::tool::session::{
+        chat::SessionChatMessage,
+        structured_tool_use::{StructuredToolCallBroker, StructuredToolCallInput},
+    };
+
+    struct MockStructuredToolCallBroker {
+        tool_input_partial: ToolInputPartial,
+    }
+
+    #[async_trait]
+    impl StructuredToolCallBroker for MockStructuredToolCallBroker {
+        async fn generate_structured_tool_call(
+            &self,
+            _input: StructuredToolCallInput,
+        ) -> Result<Option<ToolInputPartial>, SymbolError> {
+            Ok(Some(self.tool_input_partial.clone()))
+        }
+    }
+
+    #[tokio::test]
+    async fn structured_tool_call_bypasses_text_parser() {
+        let llm_broker = LLMBroker::new(LLMBrokerConfiguration::new(std::env::temp_dir()))
+            .await
+            .expect("llm broker should initialise against a scratch data dir");
+        let tool_agent = ToolUseAgent::new(
+            Arc::new(llm_broker),
+            "/tmp".to_owned(),
+            "linux".to_owned(),
+            "bash".to_owned(),
+        )
+        .with_structured_tool_call_broker(Arc::new(MockStructuredToolCallBroker {
+            tool_input_partial: ToolInputPartial::OpenFile(OpenFileRequestPartial::new(
+                "src/main.rs".to_owned(),
+            )),
+        }));
+
+        let (ui_sender, _ui_receiver) = tokio::sync::mpsc::unbounded_channel();
+        let symbol_event_message_properties = SymbolEventMessageProperties::new(
+            SymbolEventRequestId::new("test_request".to_owned(), "test_root_request".to_owned()),
+            ui_sender,
+            "".to_owned(),
+            tokio_util::sync::CancellationToken::new(),
+            LLMProperties::new(
+                LLMType::Gpt4O,
+                LLMProvider::OpenAI,
+                LLMProviderAPIKeys::OpenAI(OpenAIProvider::new("".to_owned())),
+            ),
+        );
+
+        let input = ToolUseAgentInput::new(
+            vec![SessionChatMessage::user("read main.rs".to_owned())],
+            vec!["read_file: reads a file".to_owned()],
+            vec![ToolType::OpenFile],
+            None,
+            symbol_event_message_properties,
+        );
+
+        let output = tool_agent
+            .invoke(input)
+            .await
+            .expect("mock broker call should not fail");
+        match output {
+            ToolUseAgentOutput::Success((ToolInputPartial::OpenFile(open_file), _, _)) => {
+                assert_eq!(open_file.fs_file_path(), "src/main.rs");
+            }
+            ToolUseAgentOutput::Success((other, _, _)) => {
+                panic!("expected an OpenFile tool call from the mock broker, got {other:?}");
+            }
+            ToolUseAgentOutput::Failure(failure) => {
+                panic!("expected structured tool call to succeed, got failure: {failure}");
+            }
+        }
+    }
+
+    struct CancellingStructuredToolCallBroker {
+        call_count: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl StructuredToolCallBroker for CancellingStructuredToolCallBroker {
+        async fn generate_structured_tool_call(
+            &self,
+            _input: StructuredToolCallInput,
+        ) -> Result<Option<ToolInputPartial>, SymbolError> {
+            self.call_count
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Err(SymbolError::CancelledResponseStream)
+        }
+    }
+
+    #[tokio::test]
+    async fn cancelled_inference_does_not_fall_back_to_a_second_llm_call() {
+        let llm_broker = LLMBroker::new(LLMBrokerConfiguration::new(std::env::temp_dir()))
+            .await
+            .expect("llm broker should initialise against a scratch data dir");
+        let cancelling_broker = Arc::new(CancellingStructuredToolCallBroker {
+            call_count: std::sync::atomic::AtomicUsize::new(0),
+        });
+        let tool_agent = ToolUseAgent::new(
+            Arc::new(llm_broker),
+            "/tmp".to_owned(),
+            "linux".to_owned(),
+            "bash".to_owned(),
+        )
+        .with_structured_tool_call_broker(cancelling_broker.clone());
+
+        let (ui_sender, _ui_receiver) = tokio::sync::mpsc::unbounded_channel();
+        let symbol_event_message_properties = SymbolEventMessageProperties::new(
+            SymbolEventRequestId::new("test_request".to_owned(), "test_root_request".to_owned()),
+            ui_sender,
+            "".to_owned(),
+            tokio_util::sync::CancellationToken::new(),
+            LLMProperties::new(
+                LLMType::Gpt4O,
+                LLMProvider::OpenAI,
+                LLMProviderAPIKeys::OpenAI(OpenAIProvider::new("".to_owned())),
+            ),
+        );
+
+        let input = ToolUseAgentInput::new(
+            vec![SessionChatMessage::user("read main.rs".to_owned())],
+            vec!["read_file: reads a file".to_owned()],
+            vec![ToolType::OpenFile],
+            None,
+            symbol_event_message_properties,
+        );
+
+        let output = tool_agent.invoke(input).await;
+        assert!(
+            matches!(output, Err(SymbolError::CancelledResponseStream)),
+            "expected the cancellation to surface as an error instead of falling through to the text parser, got {output:?}"
+        );
+        assert_eq!(
+            cancelling_broker
+                .call_count
+                .load(std::sync::atomic::Ordering::SeqCst),
+            1,
+            "the structured broker should only be asked once, no retry via a second LLM call"
+        );
+    }
+
+    #[tokio::test]
+    async fn should_attempt_continuation_only_retries_recoverable_network_errors_once() {
+        // any unroutable address gives us a real reqwest::Error without touching the network
+        let reqwest_error = reqwest::Client::new()
+            .get("http://127.0.0.1:0")
+            .send()
+            .await
+            .expect_err("connecting to port 0 should always fail");
+        let network_error = LLMClientError::ReqwestError(reqwest_error);
+
+        assert!(
+            should_attempt_continuation(&network_error, false, "partial answer"),
+            "a fresh network error with partial output to resume from should be retried"
+        );
+        assert!(
+            !should_attempt_continuation(&network_error, true, "partial answer"),
+            "we only ever attempt the continuation once per invoke() call"
+        );
+        assert!(
+            !should_attempt_continuation(&network_error, false, ""),
+            "there is nothing to resume from, so a continuation would just repeat the prompt"
+        );
+        assert!(
+            !should_attempt_continuation(
+                &LLMClientError::FailedToGetResponse,
+                false,
+                "partial answer"
+            ),
+            "non-network errors are not something a continuation retry can fix"
+        );
+    }
 
     #[test]
     fn test_make_tool_parsing_work() {
@@ -943,4 +1574,38 @@ trait\s+Tool\s*\{
         let tool_use_possible = tool_use_generator.tool_input_partial;
         assert!(tool_use_possible.is_some());
     }
+
+    #[test]
+    fn search_files_parses_exclude_pattern_alongside_file_pattern() {
+        let input = r#"<thinking>
+Let me search for it, skipping the test files this time.
+</thinking>
+
+<search_files>
+<directory_path>
+/Users/skcd/test_repo/sidecar
+</directory_path>
+<regex_pattern>
+trait\s+Tool\s*\{
+</regex_pattern>
+<file_pattern>
+*.rs
+</file_pattern>
+<exclude_pattern>
+*.test.rs
+</exclude_pattern>
+</search_files>"#;
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let mut tool_use_generator = ToolUseGenerator::new(sender);
+        tool_use_generator.add_delta(&input);
+        tool_use_generator.flush_answer();
+
+        match tool_use_generator.tool_input_partial {
+            Some(ToolInputPartial::SearchFileContentWithRegex(search_file)) => {
+                assert_eq!(search_file.file_pattern(), Some("*.rs"));
+                assert_eq!(search_file.exclude_pattern(), Some("*.test.rs"));
+            }
+            other => panic!("expected a search_files tool call, got {:?}", other),
+        }
+    }
 }
@@ -0,0 +1,111 @@
+//! A VS Code multi-root workspace opens several folders under one window,
+//! each with its own path and an optional display name. `RepoRef` and
+//! `root_directory` elsewhere in this crate assume a single folder, which is
+//! fine for the common case but breaks path containment checks and prompt
+//! rendering once a monorepo is opened with more than one root. This module
+//! is a minimal, additive layer on top of that: it lets a session carry the
+//! extra roots the primary `root_directory` doesn't know about, so path
+//! validation can check all of them and the agent's prompt can disclose
+//! their names instead of only ever seeing the first one.
+//!
+//! This deliberately does not change how `ListFiles`, `RepoMapGeneration` or
+//! `SearchFileContentWithRegex` are invoked: each already accepts an
+//! explicit `directory_path`/`fs_file_path`, so once the agent knows the
+//! other roots exist (via the prompt) it can already point those tools at
+//! them. Rearchitecting `RepoRef` itself to be multi-root would ripple
+//! through the indexing/state layer far beyond what this change calls for.
+
+use serde::{Deserialize, Serialize};
+
+/// One additional folder in a multi-root workspace, named the way VS Code
+/// names it in the workspace file (falling back to the last path segment
+/// when the caller doesn't have a nicer name to give it).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceRoot {
+    name: String,
+    path: String,
+}
+
+impl WorkspaceRoot {
+    pub fn new(name: String, path: String) -> Self {
+        Self { name, path }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+/// The primary `root_directory` a session was started with, plus whatever
+/// extra roots the multi-root workspace has open. Path containment checks
+/// and prompt rendering go through here instead of comparing against the
+/// primary root directly, so they stay correct once more than one root is
+/// in play.
+#[derive(Debug, Clone)]
+pub struct WorkspaceRoots {
+    primary: String,
+    additional: Vec<WorkspaceRoot>,
+}
+
+impl WorkspaceRoots {
+    pub fn new(primary: String, additional: Vec<WorkspaceRoot>) -> Self {
+        Self { primary, additional }
+    }
+
+    pub fn primary(&self) -> &str {
+        &self.primary
+    }
+
+    /// Whether `path` lives under the primary root or any additional one.
+    pub fn contains_path(&self, path: &str) -> bool {
+        std::iter::once(self.primary.as_str())
+            .chain(self.additional.iter().map(WorkspaceRoot::path))
+            .any(|root| std::path::Path::new(path).starts_with(std::path::Path::new(root)))
+    }
+
+    /// Renders the additional roots for inclusion in the agent's system
+    /// prompt, or `None` when there aren't any (the common single-root
+    /// case), so callers can skip the section entirely.
+    pub fn render_additional_roots(&self) -> Option<String> {
+        if self.additional.is_empty() {
+            return None;
+        }
+        Some(
+            self.additional
+                .iter()
+                .map(|root| format!("- {}: {}", root.name(), root.path()))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contains_path_checks_every_root() {
+        let roots = WorkspaceRoots::new(
+            "/repo/backend".to_owned(),
+            vec![WorkspaceRoot::new(
+                "frontend".to_owned(),
+                "/repo/frontend".to_owned(),
+            )],
+        );
+
+        assert!(roots.contains_path("/repo/backend/src/main.rs"));
+        assert!(roots.contains_path("/repo/frontend/src/index.ts"));
+        assert!(!roots.contains_path("/etc/passwd"));
+    }
+
+    #[test]
+    fn render_additional_roots_is_none_for_single_root_workspace() {
+        let roots = WorkspaceRoots::new("/repo".to_owned(), vec![]);
+        assert_eq!(roots.render_additional_roots(), None);
+    }
+}
@@ -111,8 +111,19 @@ pub enum ToolType {
     StepGenerator,
     // Create a new file
     CreateFile,
+    // Delete a file
+    DeleteFile,
+    // Move or rename a file
+    MoveFile,
+    // Summarize the conversation so far to free up context
+    SummarizeContext,
+    // Fetch back the full diff for a file whose edit summary was condensed
+    ShowDiff,
     // File diagnostics
     FileDiagnostics,
+    // Gathers diagnostics, an optional test run, and a diff summary in one
+    // shot and reports a clean/needs-work verdict
+    CodeReview,
     // Add steps to the plan
     PlanStepAdd,
     // Go to previous word at a position
@@ -141,6 +152,24 @@ pub enum ToolType {
     RepoMapGeneration,
     // Sub-process spawned pending output
     SubProcessSpawnedPendingOutput,
+    // Get the symbol map (outline nodes) for a file
+    GetOutlineNodes,
+    // Ask the editor which files are currently open in buffers
+    ListOpenFiles,
+    // Extract a selection into its own function using the editor's
+    // built-in refactoring
+    ExtractFunction,
+    // Duplicate a symbol under a new name, adjacent to the original
+    DuplicateSymbol,
+    // Evaluate a constant expression without running the full test suite
+    InlineValue,
+    // Search for a symbol and resolve it to its definition in one step
+    FindSymbolDefinition,
+    // Search for symbols across the whole workspace by name prefix
+    GetWorkspaceSymbols,
+    // Run the project's test suite and parse the output into a structured
+    // pass/fail result
+    RunTests,
 }
 
 impl std::fmt::Display for ToolType {
@@ -217,6 +246,11 @@ impl std::fmt::Display for ToolType {
             ToolType::PlanUpdater => write!(f, "Plan Updater"),
             ToolType::StepGenerator => write!(f, "Step generator"),
             ToolType::CreateFile => write!(f, "Create File"),
+            ToolType::DeleteFile => write!(f, "Delete File"),
+            ToolType::MoveFile => write!(f, "Move File"),
+            ToolType::SummarizeContext => write!(f, "Summarize context"),
+            ToolType::ShowDiff => write!(f, "Show diff"),
+            ToolType::CodeReview => write!(f, "Code review"),
             ToolType::FileDiagnostics => write!(f, "File Diagnostics"),
             ToolType::PlanStepAdd => write!(f, "Plan step add"),
             ToolType::GoToPreviousWordRange => write!(f, "Go to previous word range"),
@@ -237,6 +271,14 @@ impl std::fmt::Display for ToolType {
             ToolType::SubProcessSpawnedPendingOutput => {
                 write!(f, "Sub process spawned pending output")
             }
+            ToolType::GetOutlineNodes => write!(f, "Get outline nodes"),
+            ToolType::ListOpenFiles => write!(f, "List open files"),
+            ToolType::ExtractFunction => write!(f, "Extract Function"),
+            ToolType::DuplicateSymbol => write!(f, "Duplicate Symbol"),
+            ToolType::InlineValue => write!(f, "Inline Value"),
+            ToolType::FindSymbolDefinition => write!(f, "Find symbol definition"),
+            ToolType::GetWorkspaceSymbols => write!(f, "Get workspace symbols"),
+            ToolType::RunTests => write!(f, "Run tests"),
         }
     }
 }
@@ -23,7 +23,7 @@ use crate::{
     agentic::{
         symbol::{identifier::LLMProperties, manager::SymbolManager, tool_box::ToolBox},
         tool::{
-            broker::{ToolBroker, ToolBrokerConfiguration},
+            broker::{ToolBroker, ToolBrokerConfiguration, DEFAULT_LLM_REQUESTS_PER_SECOND},
             code_edit::models::broker::CodeEditBroker,
             session::service::SessionService,
         },
@@ -96,7 +96,10 @@ impl Application {
             symbol_tracker.clone(),
             language_parsing.clone(),
             // do not apply the edits directly
-            ToolBrokerConfiguration::new(None, false),
+            ToolBrokerConfiguration::new(None, false).with_llm_rate_limit(
+                LLMProvider::OpenAI.to_string(),
+                DEFAULT_LLM_REQUESTS_PER_SECOND,
+            ),
             LLMProperties::new(
                 LLMType::Gpt4O,
                 LLMProvider::OpenAI,
@@ -20,7 +20,7 @@ use sidecar::{
             manager::SymbolManager,
         },
         tool::{
-            broker::{ToolBroker, ToolBrokerConfiguration},
+            broker::{ToolBroker, ToolBrokerConfiguration, DEFAULT_LLM_REQUESTS_PER_SECOND},
             code_edit::models::broker::CodeEditBroker,
         },
     },
@@ -72,7 +72,10 @@ async fn main() {
         symbol_broker.clone(),
         Arc::new(TSLanguageParsing::init()),
         // for our testing workflow we want to apply the edits directly
-        ToolBrokerConfiguration::new(None, true),
+        ToolBrokerConfiguration::new(None, true).with_llm_rate_limit(
+            LLMProvider::OpenAI.to_string(),
+            DEFAULT_LLM_REQUESTS_PER_SECOND,
+        ),
         LLMProperties::new(
             LLMType::Gpt4O,
             LLMProvider::OpenAI,
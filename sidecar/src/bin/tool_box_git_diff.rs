@@ -10,7 +10,7 @@ use sidecar::{
     agentic::{
         symbol::{identifier::LLMProperties, tool_box::ToolBox},
         tool::{
-            broker::{ToolBroker, ToolBrokerConfiguration},
+            broker::{ToolBroker, ToolBrokerConfiguration, DEFAULT_LLM_REQUESTS_PER_SECOND},
             code_edit::models::broker::CodeEditBroker,
         },
     },
@@ -40,7 +40,10 @@ async fn main() {
         Arc::new(CodeEditBroker::new()),
         symbol_broker.clone(),
         Arc::new(TSLanguageParsing::init()),
-        ToolBrokerConfiguration::new(None, true),
+        ToolBrokerConfiguration::new(None, true).with_llm_rate_limit(
+            LLMProvider::GoogleAIStudio.to_string(),
+            DEFAULT_LLM_REQUESTS_PER_SECOND,
+        ),
         LLMProperties::new(
             LLMType::GeminiPro,
             LLMProvider::GoogleAIStudio,
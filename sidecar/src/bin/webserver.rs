@@ -243,6 +243,10 @@ fn agentic_router() -> Router {
             "/cancel_running_event",
             post(sidecar::webserver::agentic::cancel_running_exchange),
         )
+        .route(
+            "/soft_stop_running_event",
+            post(sidecar::webserver::agentic::soft_stop_running_exchange),
+        )
         .route(
             "/user_feedback_on_exchange",
             post(sidecar::webserver::agentic::user_feedback_on_exchange),
@@ -118,6 +118,23 @@ impl Position {
         self.byte_offset = byte_offset;
     }
 
+    /// Recalculates `byte_offset` from `line`/`character` against
+    /// `file_content`. `character` is a byte offset within its line (as
+    /// tree-sitter reports it), so this walks lines by byte length rather
+    /// than by `char` count, which keeps it correct for multibyte UTF-8.
+    fn recompute_byte_offset(&mut self, file_content: &str) {
+        let mut byte_offset = 0usize;
+        for (line_number, line) in file_content.split('\n').enumerate() {
+            if line_number == self.line {
+                byte_offset += self.character.min(line.len());
+                break;
+            }
+            // +1 accounts for the '\n' consumed by split but not included in `line`
+            byte_offset += line.len() + 1;
+        }
+        self.byte_offset = byte_offset;
+    }
+
     pub fn from_byte(byte: usize, line_end_indices: &[u32]) -> Self {
         let line = line_end_indices
             .iter()
@@ -203,6 +220,15 @@ impl Range {
         self.end_position.set_byte_offset(byte);
     }
 
+    /// Recalculates both endpoints' byte offsets from their line/column
+    /// against `file_content`, useful when a `Range` was captured before an
+    /// edit shifted the underlying bytes and only its byte offsets (not its
+    /// line/column) are now stale.
+    pub fn recompute_bytes(&mut self, file_content: &str) {
+        self.start_position.recompute_byte_offset(file_content);
+        self.end_position.recompute_byte_offset(file_content);
+    }
+
     pub fn start_position(&self) -> Position {
         self.start_position.clone()
     }
@@ -739,3 +765,28 @@ impl OutlineForRange {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Position, Range};
+
+    #[test]
+    fn test_recompute_bytes_after_edit_with_multibyte_utf8() {
+        // "héllo" -> 'é' is 2 bytes in UTF-8, so byte offsets on this line
+        // diverge from character counts
+        let original_content = "héllo\nworld\n";
+        let range = Range::new(Position::new(1, 0, 7), Position::new(1, 5, 12));
+        assert_eq!(&original_content[7..12], "world");
+
+        // an edit earlier in the file pushed everything after line 0 forward
+        // by a few bytes, so the stored byte offsets above are now stale even
+        // though line/column still correctly point at "world"
+        let edited_content = "héllo, there\nworld\n";
+        let mut recomputed = range;
+        recomputed.recompute_bytes(edited_content);
+
+        assert_eq!(recomputed.start_byte(), 14);
+        assert_eq!(recomputed.end_byte(), 19);
+        assert_eq!(&edited_content[14..19], "world");
+    }
+}
@@ -99,6 +99,64 @@ impl RepoRef {
                 .into(),
         }
     }
+
+    /// Renders `absolute_path` relative to this repo's root, for use in
+    /// prompts where the full absolute path would waste tokens and leak
+    /// machine-specific paths into the transcript. Falls back to
+    /// `absolute_path` unchanged if it isn't actually inside the repo root
+    /// (e.g. a path on a different Windows drive), so the caller never loses
+    /// information.
+    ///
+    /// Paths are compared with separators normalized rather than through
+    /// `std::path::Path`, since a repo root can be a Windows-style path
+    /// (`C:\Users\...`) regardless of which platform sidecar itself runs on.
+    pub fn to_relative_path(&self, absolute_path: &str) -> String {
+        let root = normalize_path_separators(&self.name);
+        let root_with_trailing_slash = if root.ends_with('/') {
+            root
+        } else {
+            format!("{root}/")
+        };
+        let candidate = normalize_path_separators(absolute_path);
+        match candidate.strip_prefix(&root_with_trailing_slash) {
+            Some(relative) if !relative.is_empty() => relative.to_owned(),
+            _ => absolute_path.to_owned(),
+        }
+    }
+
+    /// The inverse of [`RepoRef::to_relative_path`]: joins `path` onto this
+    /// repo's root if `path` is relative, otherwise returns it unchanged.
+    /// Used to translate paths the model wrote out relative to the repo (in
+    /// SEARCH/REPLACE file lines or tool arguments) back into absolute paths
+    /// before they're used for actual file IO.
+    pub fn to_absolute_path(&self, path: &str) -> String {
+        if is_absolute_path(path) {
+            return path.to_owned();
+        }
+        let root = self.name.trim_end_matches(['/', '\\']);
+        let separator = if root.contains('\\') && !root.contains('/') {
+            '\\'
+        } else {
+            '/'
+        };
+        format!("{root}{separator}{path}")
+    }
+}
+
+/// Rewrites `\` to `/` so path comparisons are agnostic to whether the path
+/// came from a Windows or Unix-style repo root.
+fn normalize_path_separators(path: &str) -> String {
+    path.replace('\\', "/")
+}
+
+/// Whether `path` is absolute on either Unix (`/foo`) or Windows
+/// (`C:\foo`, `C:/foo`), independent of the platform sidecar runs on.
+fn is_absolute_path(path: &str) -> bool {
+    if path.starts_with('/') || path.starts_with('\\') {
+        return true;
+    }
+    let bytes = path.as_bytes();
+    bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
 }
 
 impl<P: AsRef<Path>> From<&P> for RepoRef {
@@ -259,4 +317,64 @@ mod tests {
         let repo_ref = RepoRef::from_str("local/c:\\Users\\someone\\pifuhd");
         assert!(repo_ref.is_ok());
     }
+
+    #[test]
+    fn to_relative_path_strips_repo_root_with_spaces() {
+        let repo_ref = RepoRef::local("/home/dev/my project").expect("local repo ref to parse");
+        assert_eq!(
+            repo_ref.to_relative_path("/home/dev/my project/src/main.rs"),
+            "src/main.rs"
+        );
+    }
+
+    #[test]
+    fn to_relative_path_strips_windows_drive_repo_root() {
+        let repo_ref = RepoRef::local("c:\\Users\\someone\\pifuhd").expect("local repo ref to parse");
+        assert_eq!(
+            repo_ref.to_relative_path("c:\\Users\\someone\\pifuhd\\src\\lib.rs"),
+            "src/lib.rs"
+        );
+    }
+
+    #[test]
+    fn to_relative_path_leaves_paths_outside_the_repo_root_unchanged() {
+        let repo_ref = RepoRef::local("/home/dev/my project").expect("local repo ref to parse");
+        assert_eq!(
+            repo_ref.to_relative_path("/home/dev/other project/src/main.rs"),
+            "/home/dev/other project/src/main.rs"
+        );
+    }
+
+    #[test]
+    fn to_absolute_path_joins_relative_path_onto_repo_root() {
+        let repo_ref = RepoRef::local("/home/dev/my project").expect("local repo ref to parse");
+        assert_eq!(
+            repo_ref.to_absolute_path("src/main.rs"),
+            "/home/dev/my project/src/main.rs"
+        );
+    }
+
+    #[test]
+    fn to_absolute_path_joins_relative_path_onto_windows_drive_repo_root() {
+        let repo_ref = RepoRef::local("c:\\Users\\someone\\pifuhd").expect("local repo ref to parse");
+        assert_eq!(
+            repo_ref.to_absolute_path("src\\lib.rs"),
+            "c:\\Users\\someone\\pifuhd\\src\\lib.rs"
+        );
+    }
+
+    #[test]
+    fn to_absolute_path_leaves_already_absolute_paths_unchanged() {
+        let repo_ref = RepoRef::local("/home/dev/my project").expect("local repo ref to parse");
+        assert_eq!(
+            repo_ref.to_absolute_path("/etc/hosts"),
+            "/etc/hosts"
+        );
+        let windows_repo_ref =
+            RepoRef::local("c:\\Users\\someone\\pifuhd").expect("local repo ref to parse");
+        assert_eq!(
+            windows_repo_ref.to_absolute_path("d:\\other\\file.rs"),
+            "d:\\other\\file.rs"
+        );
+    }
 }
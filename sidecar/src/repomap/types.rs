@@ -233,3 +233,57 @@ impl RepoMap {
         context.format()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_fixture_fn(dir: &std::path::Path, name: &str) -> String {
+        let file_path = dir.join(format!("{}.rs", name));
+        std::fs::write(
+            &file_path,
+            format!(
+                "pub fn {name}(input: usize) -> usize {{\n    input + 1\n}}\n",
+                name = name
+            ),
+        )
+        .expect("write fixture file");
+        file_path.to_string_lossy().to_string()
+    }
+
+    #[tokio::test]
+    async fn find_best_tree_drops_whole_tags_instead_of_truncating_mid_symbol() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let file_paths = (0..8)
+            .map(|index| write_fixture_fn(dir.path(), &format!("function_{index}")))
+            .collect::<Vec<_>>();
+
+        let tag_index = TagIndex::from_files(dir.path(), file_paths).await;
+        let ranked_tags = TagAnalyzer::new(&tag_index).get_ranked_tags();
+        assert!(
+            ranked_tags.len() > 1,
+            "fixture should produce more than one tag to rank"
+        );
+
+        // budget large enough for at least one whole function definition but
+        // far too small to fit every function in the fixture set
+        let small_budget = 40;
+        let repo_map = RepoMap::new();
+        let tree = repo_map.find_best_tree(ranked_tags, small_budget);
+
+        assert!(
+            repo_map.get_token_count(&tree) <= small_budget,
+            "tree should respect the token budget it was asked to fit within"
+        );
+
+        for line in tree.lines() {
+            if let Some(fn_name_start) = line.find("pub fn function_") {
+                let signature = &line[fn_name_start..];
+                assert!(
+                    signature.contains('('),
+                    "included function signatures should never be cut off mid-symbol: {signature}"
+                );
+            }
+        }
+    }
+}
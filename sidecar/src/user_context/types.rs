@@ -14,8 +14,21 @@ use super::helpers::{guess_content, ProbableFileKind};
 pub enum UserContextError {
     #[error("Unable to read from path: {0}")]
     UnableToReadFromPath(String),
+
+    #[error("User context is {actual_bytes} bytes, exceeding the {max_bytes} byte limit")]
+    ContextTooLarge {
+        actual_bytes: usize,
+        max_bytes: usize,
+    },
 }
 
+/// Default ceiling passed to [`UserContext::validate`]/[`UserContext::truncate_to`]
+/// before a [`UserContext`] is folded into an LLM request. Editor-supplied
+/// context (open files, variables) has no upstream size limit of its own, so
+/// this is what actually keeps an oversized selection from turning into an
+/// opaque provider-side failure downstream.
+pub const DEFAULT_MAX_CONTEXT_BYTES: usize = 400_000;
+
 #[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
 pub enum VariableType {
     File,
@@ -187,6 +200,82 @@ Code Symbol
     }
 }
 
+/// Content the user pasted or dropped into the chat directly, as opposed to
+/// a selection or file pulled in from the workspace via [`VariableInformation`]
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub enum Attachment {
+    /// A free-text snippet, eg a pasted error log or stack trace, with an
+    /// optional label the user (or the editor) gave it
+    Snippet {
+        label: Option<String>,
+        content: String,
+    },
+    /// A reference to an image the user attached, either as a path the editor
+    /// can resolve or as an inline base64 payload (or both, since the path is
+    /// also used for the transcript even when we already have the bytes)
+    Image {
+        path: Option<String>,
+        base64: Option<String>,
+        media_type: Option<String>,
+    },
+}
+
+impl Attachment {
+    pub fn snippet(label: Option<String>, content: String) -> Self {
+        Attachment::Snippet { label, content }
+    }
+
+    pub fn image(path: Option<String>, base64: Option<String>, media_type: Option<String>) -> Self {
+        Attachment::Image {
+            path,
+            base64,
+            media_type,
+        }
+    }
+
+    pub fn is_image(&self) -> bool {
+        matches!(self, Attachment::Image { .. })
+    }
+
+    pub fn image_base64(&self) -> Option<&str> {
+        match self {
+            Attachment::Image { base64, .. } => base64.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub fn image_media_type(&self) -> &str {
+        match self {
+            Attachment::Image { media_type, .. } => {
+                media_type.as_deref().unwrap_or("image/png")
+            }
+            _ => "image/png",
+        }
+    }
+
+    /// Renders the attachment for the `<attachments>` prompt section. Images
+    /// are not rendered here since they are sent to vision-capable LLMs via
+    /// the broker message API instead, not inlined into the text prompt
+    pub fn to_xml(&self) -> Option<String> {
+        match self {
+            Attachment::Snippet { label, content } => {
+                let label = label.clone().unwrap_or_else(|| "Attachment".to_owned());
+                Some(format!(
+                    r#"<attachment_item>
+<label>
+{label}
+</label>
+<content>
+{content}
+</content>
+</attachment_item>"#
+                ))
+            }
+            Attachment::Image { .. } => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct FileContentValue {
     pub file_path: String,
@@ -232,6 +321,9 @@ pub struct UserContext {
     // These paths will be absolute and need to be used to get the
     // context of the folders here, we will output it properly
     folder_paths: Vec<String>,
+    // Snippets and images the user pasted or dropped into the chat directly
+    #[serde(default)]
+    attachments: Vec<Attachment>,
     // These are all hacks for now, we will move them to proper strucutre later on
     is_plan_generation: bool,
     is_plan_execution_until: Option<usize>,
@@ -253,6 +345,7 @@ impl UserContext {
             file_content_map,
             terminal_selection,
             folder_paths,
+            attachments: vec![],
             is_plan_generation: false,
             is_plan_execution_until: None,
             is_plan_append: false,
@@ -265,6 +358,39 @@ impl UserContext {
         self
     }
 
+    pub fn add_attachments(mut self, attachments: Vec<Attachment>) -> Self {
+        self.attachments.extend(attachments);
+        self
+    }
+
+    pub fn attachments(&self) -> &[Attachment] {
+        &self.attachments
+    }
+
+    /// Renders the free-text attachments (pasted snippets) into a standalone
+    /// `<attachments>` block, for callers which build up the prompt as a
+    /// plain string rather than going through [`UserContext::to_xml`]
+    pub fn attachments_xml(&self) -> String {
+        let attachments_prompt = self
+            .attachments
+            .iter()
+            .filter_map(|attachment| attachment.to_xml())
+            .collect::<Vec<_>>()
+            .join("\n");
+        if attachments_prompt.is_empty() {
+            String::new()
+        } else {
+            format!("\n<attachments>\n{attachments_prompt}\n</attachments>")
+        }
+    }
+
+    /// Image attachments, handed to the caller so they can be passed through
+    /// to a vision-capable LLM via the broker message API instead of being
+    /// inlined into the text prompt
+    pub fn image_attachments(&self) -> Vec<&Attachment> {
+        self.attachments.iter().filter(|a| a.is_image()).collect()
+    }
+
     /// If we are in any part of the plan generation flow over here
     pub fn is_plan_generation_flow(&self) -> bool {
         self.is_plan_append()
@@ -307,8 +433,110 @@ impl UserContext {
         self.folder_paths.to_vec()
     }
 
+    /// Splits `file_content_map` into windows of at most `chunk_size` files,
+    /// each carried by its own `UserContext` which otherwise shares the
+    /// variables, attachments, terminal selection and folder paths of the
+    /// original. Used by callers who want to fan a wide search over a large
+    /// context out into several smaller, concurrent prompts instead of one
+    /// giant one. Contexts with nothing to chunk are returned unchanged as a
+    /// single-element vec.
+    pub fn chunk_by_files(&self, chunk_size: usize) -> Vec<UserContext> {
+        if self.file_content_map.is_empty() || chunk_size == 0 {
+            return vec![self.clone()];
+        }
+        self.file_content_map
+            .chunks(chunk_size)
+            .map(|files_chunk| UserContext {
+                variables: self.variables.clone(),
+                file_content_map: files_chunk.to_vec(),
+                terminal_selection: self.terminal_selection.clone(),
+                folder_paths: self.folder_paths.clone(),
+                attachments: self.attachments.clone(),
+                is_plan_generation: self.is_plan_generation,
+                is_plan_execution_until: self.is_plan_execution_until,
+                is_plan_append: self.is_plan_append,
+                is_plan_drop_from: self.is_plan_drop_from,
+            })
+            .collect()
+    }
+
     pub fn is_empty(&self) -> bool {
-        self.variables.is_empty() && self.terminal_selection.is_none()
+        self.variables.is_empty()
+            && self.terminal_selection.is_none()
+            && self.attachments.is_empty()
+    }
+
+    /// Approximates how much prompt space this context will take up by
+    /// summing the byte length of every piece of free text it carries.
+    pub fn size_in_bytes(&self) -> usize {
+        let variables_size: usize = self.variables.iter().map(|v| v.content.len()).sum();
+        let file_content_size: usize = self
+            .file_content_map
+            .iter()
+            .map(|file_content| file_content.file_content.len())
+            .sum();
+        let terminal_selection_size = self
+            .terminal_selection
+            .as_ref()
+            .map(|selection| selection.len())
+            .unwrap_or(0);
+        let attachments_size: usize = self
+            .attachments
+            .iter()
+            .filter_map(|attachment| attachment.to_xml())
+            .map(|xml| xml.len())
+            .sum();
+        variables_size + file_content_size + terminal_selection_size + attachments_size
+    }
+
+    /// Rejects a context which is too large to safely fold into an LLM
+    /// request, per [`UserContext::size_in_bytes`].
+    pub fn validate(&self, max_bytes: usize) -> Result<(), UserContextError> {
+        let actual_bytes = self.size_in_bytes();
+        if actual_bytes > max_bytes {
+            Err(UserContextError::ContextTooLarge {
+                actual_bytes,
+                max_bytes,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Trims this context down to `max_bytes`, dropping the largest,
+    /// least-important content first: whole files pulled in from the
+    /// workspace (`file_content_map`), then non-selection variables. Active
+    /// selections are never dropped, since that's the part of the context
+    /// the user is most likely to be directly asking about - the result can
+    /// still be over `max_bytes` if the selection alone exceeds it.
+    pub fn truncate_to(mut self, max_bytes: usize) -> Self {
+        if self.size_in_bytes() <= max_bytes {
+            return self;
+        }
+
+        self.file_content_map
+            .sort_by_key(|file_content| file_content.file_content.len());
+        while self.size_in_bytes() > max_bytes && !self.file_content_map.is_empty() {
+            self.file_content_map.pop();
+        }
+
+        if self.size_in_bytes() <= max_bytes {
+            return self;
+        }
+
+        let (selections, mut droppable): (Vec<_>, Vec<_>) = self
+            .variables
+            .drain(..)
+            .partition(|variable| variable.variable_type.selection());
+        let selections_len = selections.len();
+        droppable.sort_by_key(|variable| variable.content.len());
+        self.variables = selections;
+        self.variables.extend(droppable);
+        while self.size_in_bytes() > max_bytes && self.variables.len() > selections_len {
+            self.variables.pop();
+        }
+
+        self
     }
 
     pub fn file_paths(&self) -> Vec<String> {
@@ -419,6 +647,19 @@ impl UserContext {
         .into_iter()
         .collect::<Result<Vec<_>, UserContextError>>()?
         .join("\n");
+        // text attachments (pasted snippets) get their own section, images are
+        // not rendered here since they go to vision-capable LLMs separately
+        let attachments_prompt = self
+            .attachments
+            .iter()
+            .filter_map(|attachment| attachment.to_xml())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let attachments_prompt = if attachments_prompt.is_empty() {
+            String::new()
+        } else {
+            format!("\n<attachments>\n{attachments_prompt}\n</attachments>")
+        };
         // Now we create the xml string for this
         let mut final_string = "<selection>\n".to_owned();
         final_string.push_str(&variable_prompt);
@@ -427,6 +668,7 @@ impl UserContext {
         // final_string.push_str("\n");
         // final_string.push_str(&file_prompt);
         final_string.push_str("\n</selection>");
+        final_string.push_str(&attachments_prompt);
         Ok(final_string)
     }
 
@@ -495,8 +737,52 @@ impl UserContext {
             })
             .collect::<Vec<_>>();
         new_user_context.variables.extend(variables_to_select);
+        new_user_context.attachments.extend(self.attachments);
         new_user_context
     }
+
+    /// Unions the context gathered from two sources, eg an editor selection
+    /// plus the currently open file, into a single `UserContext`. Variables
+    /// are deduplicated by their unique identifier (`fs_file_path` + range)
+    /// and `file_content_map` entries are deduplicated by file path, with
+    /// `self` taking priority over `other` on a collision. Attachments and
+    /// folder paths from both sides are kept.
+    ///
+    /// This is distinct from [`UserContext::merge_user_context`], which
+    /// treats `self` as a stale copy to be refreshed by a newer
+    /// `new_user_context`; `merge` instead treats both sides as equally
+    /// fresh context which just happens to come from different places.
+    pub fn merge(mut self, other: UserContext) -> Self {
+        let existing_identifiers = self
+            .variables
+            .iter()
+            .map(|variable| variable.unique_identifier())
+            .collect::<HashSet<_>>();
+        self.variables.extend(other.variables.into_iter().filter(|variable| {
+            !existing_identifiers.contains(&variable.unique_identifier())
+        }));
+
+        let existing_file_paths = self
+            .file_content_map
+            .iter()
+            .map(|file_content| file_content.file_path.clone())
+            .collect::<HashSet<_>>();
+        self.file_content_map.extend(
+            other
+                .file_content_map
+                .into_iter()
+                .filter(|file_content| !existing_file_paths.contains(&file_content.file_path)),
+        );
+
+        self.terminal_selection = self.terminal_selection.or(other.terminal_selection);
+        for folder_path in other.folder_paths {
+            if !self.folder_paths.contains(&folder_path) {
+                self.folder_paths.push(folder_path);
+            }
+        }
+        self.attachments.extend(other.attachments);
+        self
+    }
 }
 
 #[async_recursion]
@@ -597,3 +883,61 @@ pub async fn read_folder_selection(
     output.push_str("</file_content>\n</folder>\n</selection_item>");
     Ok(output)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{UserContext, VariableInformation};
+    use crate::chunking::text_document::{Position, Range};
+
+    fn range() -> Range {
+        Range::new(Position::new(0, 0, 0), Position::new(1, 0, 0))
+    }
+
+    #[test]
+    fn truncate_to_drops_large_file_variables_but_keeps_the_selection() {
+        let selection = VariableInformation::create_selection(
+            range(),
+            "src/lib.rs".to_owned(),
+            "selection".to_owned(),
+            "fn active_selection() {}".to_owned(),
+            "rust".to_owned(),
+        );
+        let large_variable = VariableInformation::create_file(
+            range(),
+            "src/huge_file.rs".to_owned(),
+            "huge_file".to_owned(),
+            "x".repeat(1_000),
+            "rust".to_owned(),
+        );
+        let context = UserContext::new(
+            vec![selection.clone(), large_variable],
+            vec![],
+            None,
+            vec![],
+        );
+        assert!(context.validate(100).is_err());
+
+        let truncated = context.truncate_to(100);
+        assert!(truncated.size_in_bytes() <= 100);
+        assert_eq!(truncated.variables.len(), 1);
+        assert!(truncated.variables[0].variable_type.selection());
+        assert_eq!(truncated.variables[0].content, selection.content);
+    }
+
+    #[test]
+    fn validate_accepts_a_context_within_the_limit() {
+        let context = UserContext::new(
+            vec![VariableInformation::create_selection(
+                range(),
+                "src/lib.rs".to_owned(),
+                "selection".to_owned(),
+                "fn small() {}".to_owned(),
+                "rust".to_owned(),
+            )],
+            vec![],
+            None,
+            vec![],
+        );
+        assert!(context.validate(1_000).is_ok());
+    }
+}
@@ -31,7 +31,7 @@ use crate::agentic::symbol::toolbox::helpers::SymbolChangeSet;
 use crate::agentic::symbol::ui_event::{RelevantReference, UIEventWithID};
 use crate::agentic::tool::lsp::open_file::OpenFileResponse;
 use crate::agentic::tool::plan::service::PlanService;
-use crate::agentic::tool::session::session::AideAgentMode;
+use crate::agentic::tool::session::session::{AideAgentMode, OpenExchangesPolicy};
 use crate::chunking::text_document::Range;
 use crate::repo::types::RepoRef;
 use crate::webserver::plan::{
@@ -843,6 +843,41 @@ pub struct AgenticCancelRunningExchangeResponse {
 
 impl ApiResponse for AgenticCancelRunningExchangeResponse {}
 
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AgenticSoftStopRunningExchange {
+    exchange_id: String,
+    session_id: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AgenticSoftStopRunningExchangeResponse {
+    success: bool,
+}
+
+impl ApiResponse for AgenticSoftStopRunningExchangeResponse {}
+
+/// Unlike `cancel_running_exchange`, this does not abort the in-flight tool
+/// call - it just asks the loop to wrap up after the tool it is currently
+/// running instead of requesting another one, so it can't leave a
+/// half-applied edit behind. The closing summary and the
+/// `ExecutionExchangeStateEvent::SoftStopped` UI event are emitted by the
+/// running loop itself once it notices the request, over the stream that
+/// request is already using - this endpoint only needs to flip the flag.
+pub async fn soft_stop_running_exchange(
+    Extension(app): Extension<Application>,
+    Json(AgenticSoftStopRunningExchange {
+        exchange_id,
+        session_id,
+    }): Json<AgenticSoftStopRunningExchange>,
+) -> Result<impl IntoResponse> {
+    app.session_service
+        .request_soft_stop(&session_id, &exchange_id)
+        .await;
+    Ok(json_result(AgenticSoftStopRunningExchangeResponse {
+        success: true,
+    }))
+}
+
 /// TODO(skcd): Figure out how to cancel a running request properly over here
 pub async fn cancel_running_exchange(
     Extension(app): Extension<Application>,
@@ -971,9 +1006,14 @@ pub struct AgentSessionChatRequest {
     query: String,
     user_context: UserContext,
     // The mode in which we want to reply to the exchanges
-    // agent_mode: AideAgentMode,
+    #[serde(default)]
+    agent_mode: AideAgentMode,
     repo_ref: RepoRef,
     root_directory: String,
+    // the other folders open alongside `root_directory` in a multi-root
+    // workspace; empty for the common single-root case
+    #[serde(default)]
+    additional_roots: Vec<crate::agentic::tool::session::workspace_roots::WorkspaceRoot>,
     project_labels: Vec<String>,
     #[serde(default)]
     codebase_search: bool,
@@ -982,6 +1022,18 @@ pub struct AgentSessionChatRequest {
     all_files: Vec<String>,
     open_files: Vec<String>,
     shell: String,
+    #[serde(default)]
+    verify_completion_command: bool,
+    // when set, an `attempt_completion` is only accepted once diagnostics
+    // tracked on edited files come back clean, feeding remaining errors back
+    // to the agent instead, up to a retry limit
+    #[serde(default)]
+    verify_diagnostics_before_completion: bool,
+    // what to do with exchanges the user never explicitly reviewed if one is
+    // still open when this request arrives - defaults to the historical
+    // behavior of silently accepting them
+    #[serde(default)]
+    open_exchanges_policy: OpenExchangesPolicy,
 }
 
 /// Handles the agent session and either creates it or appends to it
@@ -995,16 +1047,20 @@ pub async fn agent_session_chat(
         editor_url,
         query,
         user_context,
-        // agent_mode,
+        agent_mode: _,
         repo_ref,
         project_labels,
         root_directory: _root_directory,
+        additional_roots: _additional_roots,
         codebase_search: _codebase_search,
         access_token,
         model_configuration,
         all_files: _all_files,
         open_files: _open_files,
         shell: _shell,
+        verify_completion_command: _verify_completion_command,
+        verify_diagnostics_before_completion: _verify_diagnostics_before_completion,
+        open_exchanges_policy,
     }): Json<AgentSessionChatRequest>,
 ) -> Result<impl IntoResponse> {
     let llm_provider = model_configuration
@@ -1023,6 +1079,9 @@ pub async fn agent_session_chat(
     );
     let cancellation_token = tokio_util::sync::CancellationToken::new();
     let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+    app.session_service
+        .update_editor_url(&session_id, &exchange_id, editor_url.clone(), &sender)
+        .await;
     let message_properties = SymbolEventMessageProperties::new(
         SymbolEventRequestId::new(exchange_id.to_owned(), session_id.to_string()),
         sender.clone(),
@@ -1048,6 +1107,7 @@ pub async fn agent_session_chat(
                 repo_ref,
                 agent_mode,
                 message_properties,
+                open_exchanges_policy,
             )
             .await;
     });
@@ -1104,16 +1164,20 @@ pub async fn agent_session_edit_anchored(
         editor_url,
         query,
         user_context,
-        // agent_mode,
+        agent_mode: _,
         repo_ref,
         project_labels,
         root_directory: _root_directory,
+        additional_roots: _additional_roots,
         codebase_search: _codebase_search,
         access_token,
         model_configuration,
         open_files: _open_files,
         all_files: _all_files,
         shell: _shell,
+        verify_completion_command: _verify_completion_command,
+        verify_diagnostics_before_completion: _verify_diagnostics_before_completion,
+        open_exchanges_policy,
     }): Json<AgentSessionChatRequest>,
 ) -> Result<impl IntoResponse> {
     let llm_provider = model_configuration
@@ -1166,6 +1230,7 @@ pub async fn agent_session_edit_anchored(
                 project_labels,
                 repo_ref,
                 message_properties,
+                open_exchanges_policy,
             )
             .await;
     });
@@ -1224,16 +1289,20 @@ pub async fn agent_session_edit_agentic(
         editor_url,
         query,
         user_context,
-        // agent_mode,
+        agent_mode: _,
         repo_ref,
         project_labels,
         root_directory,
+        additional_roots: _additional_roots,
         codebase_search,
         access_token,
         model_configuration,
         all_files: _all_files,
         open_files: _open_files,
         shell: _shell,
+        verify_completion_command: _verify_completion_command,
+        verify_diagnostics_before_completion: _verify_diagnostics_before_completion,
+        open_exchanges_policy,
     }): Json<AgentSessionChatRequest>,
 ) -> Result<impl IntoResponse> {
     let llm_provider = model_configuration
@@ -1288,6 +1357,7 @@ pub async fn agent_session_edit_agentic(
                 root_directory,
                 codebase_search,
                 message_properties,
+                open_exchanges_policy,
             )
             .await;
         println!("tokio::spawn::code_edit_agentic::finished");
@@ -1344,17 +1414,21 @@ pub async fn agent_tool_use(
         exchange_id,
         editor_url,
         query,
-        user_context: _user_context,
-        // agent_mode,
+        user_context,
+        agent_mode,
         repo_ref,
         project_labels,
         root_directory,
+        additional_roots,
         codebase_search: _codebase_search,
         access_token,
         model_configuration,
         all_files,
         open_files,
         shell,
+        verify_completion_command,
+        verify_diagnostics_before_completion,
+        open_exchanges_policy,
     }): Json<AgentSessionChatRequest>,
 ) -> Result<impl IntoResponse> {
     let llm_provider = model_configuration
@@ -1394,6 +1468,7 @@ pub async fn agent_tool_use(
                 cloned_session_id,
                 session_storage_path,
                 query,
+                user_context,
                 exchange_id,
                 all_files,
                 open_files,
@@ -1401,10 +1476,17 @@ pub async fn agent_tool_use(
                 project_labels,
                 repo_ref,
                 root_directory,
+                additional_roots,
                 tool_box,
                 tool_broker,
                 llm_broker,
                 message_properties,
+                true,
+                verify_completion_command,
+                verify_diagnostics_before_completion,
+                None,
+                open_exchanges_policy,
+                agent_mode,
             )
             .await;
         println!("tokio::spawn::tool_use::iteration::finished");
@@ -1456,16 +1538,20 @@ pub async fn agent_session_plan_iterate(
         editor_url,
         query,
         user_context,
-        // agent_mode,
+        agent_mode: _,
         repo_ref,
         project_labels,
         root_directory,
+        additional_roots: _additional_roots,
         codebase_search,
         access_token,
         model_configuration,
         all_files: _all_files,
         open_files: _open_files,
         shell: _shell,
+        verify_completion_command: _verify_completion_command,
+        verify_diagnostics_before_completion: _verify_diagnostics_before_completion,
+        open_exchanges_policy: _open_exchanges_policy,
     }): Json<AgentSessionChatRequest>,
 ) -> Result<impl IntoResponse> {
     let llm_provider = model_configuration
@@ -1577,16 +1663,20 @@ pub async fn agent_session_plan(
         editor_url,
         query,
         user_context,
-        // agent_mode,
+        agent_mode: _,
         repo_ref,
         project_labels,
         root_directory,
+        additional_roots: _additional_roots,
         codebase_search,
         access_token,
         model_configuration,
         all_files: _all_files,
         open_files: _open_files,
         shell: _shell,
+        verify_completion_command: _verify_completion_command,
+        verify_diagnostics_before_completion: _verify_diagnostics_before_completion,
+        open_exchanges_policy: _open_exchanges_policy,
     }): Json<AgentSessionChatRequest>,
 ) -> Result<impl IntoResponse> {
     let llm_provider = model_configuration